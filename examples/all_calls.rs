@@ -98,65 +98,28 @@ async fn delete_test_bucket(auth_data: &AuthorizeAccountOk, test_bucket_name: &B
     .await
     .expect("Listing test bucket failed");
     for bucket in buckets.buckets() {
-        print!("Deleting files in test bucket ... ");
-        delete_all_files_in_bucket(auth_data, bucket).await;
-        println!("Done");
-        print!("Deleting test bucket ... ");
-        b2_delete_bucket(
-            auth_data.api_url(),
-            auth_data.authorization_token(),
-            auth_data.account_id(),
-            bucket.bucket_id(),
-        )
-        .await
-        .expect("Could not delete test bucket");
+        print!("Deleting files and test bucket ... ");
+        let request = DeleteAllFileVersionsRequest::builder()
+            .bucket_id(bucket.bucket_id())
+            .delete_bucket(true)
+            .build();
+        let deleted = b2_delete_all_file_versions(auth_data, &request)
+            .await
+            .expect("Could not list files");
+        assert!(
+            deleted.is_complete(),
+            "Could not delete all files: {:?}",
+            deleted
+        );
+        assert!(
+            matches!(deleted.deleted_bucket(), Some(Ok(_))),
+            "Could not delete test bucket"
+        );
         println!("done");
     }
     println!("Listing test bucket ... done");
 }
 
-async fn delete_all_files_in_bucket(auth_data: &AuthorizeAccountOk, bucket: &Bucket) {
-    let mut start_file_name = None;
-    let mut start_file_id = None;
-    loop {
-        //loops until all files were deleted
-        let file_version_request = ListFileVersionsRequest::new(
-            bucket.bucket_id(),
-            start_file_name.as_ref(),
-            start_file_id.as_ref(),
-            Some(MaxFileCount::try_from(1000).unwrap()),
-            None,
-            None,
-        );
-        let files = b2_list_file_versions(
-            auth_data.api_url(),
-            auth_data.authorization_token(),
-            &file_version_request,
-        )
-        .await
-        .expect("Could not list files");
-        for file in files.files() {
-            if let Some(file_id) = file.file_id() {
-                let delete_request =
-                    DeleteFileVersionRequest::new(file.file_name(), file_id, Some(true));
-                b2_delete_file_version(
-                    auth_data.api_url(),
-                    auth_data.authorization_token(),
-                    &delete_request,
-                )
-                .await
-                .expect("Could not delete file version");
-            }
-        }
-        if let Some(filename) = files.next_file_name() {
-            start_file_name = Some(filename.to_owned());
-            start_file_id = files.next_file_id().cloned();
-        } else {
-            break;
-        }
-    }
-}
-
 //cleanup after the test / before creating keys
 async fn clean_up<'a>(
     root_authorization_data: &'a AuthorizeAccountOk,
@@ -175,7 +138,7 @@ async fn create_test_bucket(
     let params = CreateBucketRequest::builder()
         .account_id(root_authorization_data.account_id())
         .bucket_name(test_bucket_name)
-        .bucket_type(BucketType::AllPrivate)
+        .bucket_type(SettableBucketType::AllPrivate)
         .file_lock_enabled(true) // used for [update_file_legal_hold]
         .build();
     let res = b2_create_bucket(
@@ -248,7 +211,7 @@ async fn upload_file(test_key_auth: &AuthorizeAccountOk, test_bucket: &Bucket) -
     let upload_file_params = UploadFileParameters::builder()
         .file_name(&UPLOAD_FILE_NAME)
         .content_length(UPLOAD_FILE_CONTENTS.len() as u64)
-        .content_sha1(&UPLOAD_FILE_CONTENTS_SHA1)
+        .content_sha1(&*UPLOAD_FILE_CONTENTS_SHA1)
         .build();
 
     let res = b2_upload_file(
@@ -348,7 +311,7 @@ async fn build_large_file(
         )
         .await
         .expect("Could not list unfinished files");
-        assert_eq!(&None, unfinished_files.next_file_name(), "although only one unfinished file should exist, unfinished files returned a next file name");
+        assert!(unfinished_files.next_file_id().is_none(), "although only one unfinished file should exist, unfinished files returned a next file id");
         assert_eq!(
             1,
             unfinished_files.files().len(),
@@ -619,7 +582,7 @@ async fn update_file_legal_hold(test_key_auth: &AuthorizeAccountOk, file: &FileI
     println!("done");
 }
 
-const FILE_RETENTION_DURATION: TimeStamp = 1000 * 60 * 60; //1h after upload
+const FILE_RETENTION_DURATION: Duration = Duration::from_secs(60 * 60); //1h after upload
 /// sets and unsets file legal hold
 async fn update_file_retention(test_key_auth: &AuthorizeAccountOk, file: &FileInformation) {
     let file_id = file.file_id().expect("File did not have a fileId");
@@ -627,7 +590,7 @@ async fn update_file_retention(test_key_auth: &AuthorizeAccountOk, file: &FileIn
     {
         let new_retention = FileRetention::new(
             FileRetentionMode::Governance,
-            file.upload_timestamp() + FILE_RETENTION_DURATION,
+            *file.upload_timestamp() + FILE_RETENTION_DURATION,
         );
         let request = UpdateFileRetentionRequest::builder()
             .file_id(file_id)
@@ -731,8 +694,8 @@ async fn server_side_encryption_c_calls(test_key_auth: &AuthorizeAccountOk, test
         let upload_file_params = UploadFileParameters::builder()
             .file_name(&UPLOAD_FILE_NAME_SSEC)
             .content_length(UPLOAD_FILE_CONTENTS.len() as u64)
-            .content_sha1(&UPLOAD_FILE_CONTENTS_SHA1)
-            .server_side_encryption(&server_side_encryption)
+            .content_sha1(&*UPLOAD_FILE_CONTENTS_SHA1)
+            .server_side_encryption(server_side_encryption)
             .build();
 
         b2_upload_file(
@@ -753,8 +716,8 @@ async fn server_side_encryption_c_calls(test_key_auth: &AuthorizeAccountOk, test
         let parameters = CopyFileRequest::builder()
             .source_file_id(uploaded_file_id)
             .file_name(&COPY_FILE_NAME_SSEC)
-            .source_server_side_encryption(&server_side_encryption)
-            .destination_server_side_encryption(&server_side_encryption)
+            .source_server_side_encryption(server_side_encryption)
+            .destination_server_side_encryption(server_side_encryption)
             .build();
         b2_copy_file(
             test_key_auth.api_url(),
@@ -774,7 +737,7 @@ async fn server_side_encryption_c_calls(test_key_auth: &AuthorizeAccountOk, test
                     .file_id()
                     .expect("File copied with SSE-C did not have a file_id"),
             )
-            .server_side_encryption(&server_side_encryption)
+            .server_side_encryption(server_side_encryption)
             .build();
         let download = b2_download_file_by_id(
             test_key_auth.download_url(),
@@ -798,7 +761,7 @@ async fn server_side_encryption_c_calls(test_key_auth: &AuthorizeAccountOk, test
             .bucket_name(test_bucket.bucket_name())
             .file_name(copied_file.file_name())
             .authorization(test_key_auth.authorization_token())
-            .server_side_encryption(&server_side_encryption)
+            .server_side_encryption(server_side_encryption)
             .build();
         let download = b2_download_file_by_name(test_key_auth.download_url(), &download_params)
             .await
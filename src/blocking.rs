@@ -0,0 +1,501 @@
+//! Synchronous versions of the [crate::v2] calls, for code that does not run inside of an async runtime.
+//!
+//! The calls are executed on an internal tokio runtime with a single worker thread, so they must not be called
+//! from within an async context (this panics, same as [tokio::runtime::Runtime::block_on]).
+//! Downloads are returned as [BlockingResponse], which implements [std::io::Read].
+use std::{future::Future, io::Read, time::Duration};
+
+use bytes::Bytes;
+use lazy_static::lazy_static;
+use reqwest::Body;
+use serde::Serialize;
+
+use crate::v2::{
+    self,
+    errors::{
+        AuthorizeError, B2ApiError, CopyError, CreateBucketError, DeleteFileVersionError,
+        DownloadFileError, GenericB2Error, GetDownloadAuthorizationError, GetFileInfoError,
+        GetUploadUrlError, LargeFileError, ListFileNamesError, ListFileVersionsError,
+        UpdateBucketError, UpdateFileLockError, UploadFileError, UploadPartError,
+    },
+    AccountId, ApiUrl, ApplicationKeyIdRef, ApplicationKeyRef, AuthorizationToken,
+    AuthorizeAccountOk, Bucket, BucketId, CancelFileOk, CancelUnfinishedLargeFilesOk,
+    CancelUnfinishedLargeFilesRequest, CopyFileRequest, CopyPartRequest, CreateBucketRequest,
+    CreateKeyRequest, CreatedKeyInformation, DeleteAllFileVersionsOk, DeleteAllFileVersionsRequest,
+    DeleteFileVersionOk, DeleteFileVersionRequest, DownloadAuthorizationToken,
+    DownloadFileByNameRequest, DownloadParams, DownloadUrl, FileId, FileInformation, FileName,
+    FileNamePrefix, FileProtection, GetDownloadAuthorizationOk, GetDownloadAuthorizationRequest,
+    HeadFileOk, KeyInformation, KeyManagerError, KeyName, KeyTemplate, ListBucketsOk,
+    ListBucketsRequest, ListFileNamesOk, ListFileNamesRequest, ListFileVersionsOk,
+    ListFileVersionsRequest, ListKeysOk, ListKeysRequest, ListPartsOk, ListPartsRequest,
+    ListUnfinishedLargeFilesOk, ListUnfinishedLargeFilesRequest, PresignDownloadRequest,
+    PresignDownloadsRequest, RotateKeyOk, Sha1DigestRef, StartLargeFileParameters,
+    UpdateBucketRequest, UpdateFileLegalHoldOk, UpdateFileLegalHoldRequest, UpdateFileLocksOk,
+    UpdateFileLocksRequest, UpdateFileRetentionOk, UpdateFileRetentionRequest,
+    UploadFileParameters, UploadParameters, UploadPartOk, UploadPartParameters,
+    UploadPartUrlParameters, ValidKeyLifeTimeInSeconds,
+};
+
+lazy_static! {
+    // the worker thread keeps driving the pooled connections of this runtime between calls
+    static ref RUNTIME: tokio::runtime::Runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+        .expect("Could not create runtime for blocking calls");
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    RUNTIME.block_on(future)
+}
+
+/// Response of a download, the body is read synchronously
+#[derive(Debug)]
+pub struct BlockingResponse {
+    response: reqwest::Response,
+    /// part of the last chunk, that was not read yet
+    buffer: Bytes,
+}
+
+impl BlockingResponse {
+    fn new(response: reqwest::Response) -> Self {
+        Self {
+            response,
+            buffer: Bytes::new(),
+        }
+    }
+
+    /// Get the blocking response's status.
+    pub fn status(&self) -> http::StatusCode {
+        self.response.status()
+    }
+
+    /// Get a reference to the blocking response's headers.
+    pub fn headers(&self) -> &http::HeaderMap {
+        self.response.headers()
+    }
+
+    /// Get the blocking response's content length, if known.
+    pub fn content_length(&self) -> Option<u64> {
+        crate::transport::response_content_length(&self.response)
+    }
+}
+
+impl Read for BlockingResponse {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.buffer.is_empty() {
+            match block_on(self.response.chunk()) {
+                Ok(Some(chunk)) => self.buffer = chunk,
+                Ok(None) => return Ok(0),
+                Err(e) => return Err(std::io::Error::other(e)),
+            }
+        }
+        let len = buf.len().min(self.buffer.len());
+        buf[..len].copy_from_slice(&self.buffer.split_to(len));
+        Ok(len)
+    }
+}
+
+/// creates a function, that blocks on the [crate::v2] function of the same name
+macro_rules! blocking_fn {
+    ($name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty) => {
+        #[doc = concat!("Blocking version of [crate::v2::", stringify!($name), "]")]
+        pub fn $name($($arg: $ty),*) -> $ret {
+            block_on(v2::$name($($arg),*))
+        }
+    };
+}
+
+blocking_fn!(b2_authorize_account(
+    application_key_id: ApplicationKeyIdRef<'_>,
+    application_key: ApplicationKeyRef<'_>,
+) -> Result<AuthorizeAccountOk, AuthorizeError>);
+
+blocking_fn!(b2_cancel_large_file(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    file_id: &FileId,
+) -> Result<CancelFileOk, GenericB2Error>);
+
+blocking_fn!(b2_delete_all_file_versions(
+    authorization: &AuthorizeAccountOk,
+    request: &DeleteAllFileVersionsRequest<'_>,
+) -> Result<DeleteAllFileVersionsOk, B2ApiError>);
+
+blocking_fn!(b2_update_file_locks(
+    authorization: &AuthorizeAccountOk,
+    request: &UpdateFileLocksRequest<'_>,
+) -> Result<UpdateFileLocksOk, B2ApiError>);
+
+#[cfg(feature = "b2_unstable")]
+blocking_fn!(b2_rotate_customer_key(
+    authorization: &AuthorizeAccountOk,
+    request: &v2::RotateCustomerKeyRequest<'_>,
+) -> Result<v2::RotateCustomerKeyOk, B2ApiError>);
+
+blocking_fn!(b2_list_protected_file_versions(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    bucket_id: &BucketId,
+    prefix: Option<&FileNamePrefix>,
+) -> Result<Vec<(FileInformation, FileProtection)>, B2ApiError>);
+
+blocking_fn!(b2_cancel_unfinished_large_files(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request: &CancelUnfinishedLargeFilesRequest<'_>,
+) -> Result<CancelUnfinishedLargeFilesOk, GenericB2Error>);
+
+blocking_fn!(b2_copy_file(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request: &CopyFileRequest<'_>,
+) -> Result<FileInformation, CopyError>);
+
+blocking_fn!(b2_copy_part(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request: &CopyPartRequest<'_>,
+) -> Result<UploadPartOk, CopyError>);
+
+blocking_fn!(b2_create_bucket(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request: &CreateBucketRequest<'_>,
+) -> Result<Bucket, CreateBucketError>);
+
+blocking_fn!(b2_create_key(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request: &CreateKeyRequest<'_>,
+) -> Result<CreatedKeyInformation, GenericB2Error>);
+
+blocking_fn!(b2_delete_bucket(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    account_id: &AccountId,
+    bucket_id: &BucketId,
+) -> Result<Bucket, GenericB2Error>);
+
+blocking_fn!(b2_delete_file_version(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request: &DeleteFileVersionRequest<'_>,
+) -> Result<DeleteFileVersionOk, DeleteFileVersionError>);
+
+blocking_fn!(b2_delete_key(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    application_key_id: ApplicationKeyIdRef<'_>,
+) -> Result<KeyInformation, GenericB2Error>);
+
+/// Blocking version of [crate::v2::b2_download_file_by_id]
+pub fn b2_download_file_by_id(
+    download_url: &DownloadUrl,
+    authorization_token: Option<&AuthorizationToken>,
+    params: &DownloadParams<'_>,
+) -> Result<BlockingResponse, DownloadFileError> {
+    block_on(v2::b2_download_file_by_id(
+        download_url,
+        authorization_token,
+        params,
+    ))
+    .map(BlockingResponse::new)
+}
+
+/// Blocking version of [crate::v2::b2_download_file_by_name]
+pub fn b2_download_file_by_name<AuthToken>(
+    download_url: &DownloadUrl,
+    request: &DownloadFileByNameRequest<'_, AuthToken>,
+) -> Result<BlockingResponse, DownloadFileError>
+where
+    AuthToken: DownloadAuthorizationToken + Serialize,
+{
+    block_on(v2::b2_download_file_by_name(download_url, request)).map(BlockingResponse::new)
+}
+
+blocking_fn!(b2_finish_large_file(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    file_id: &FileId,
+    part_sha1s: &[Sha1DigestRef<'_>],
+) -> Result<FileInformation, LargeFileError>);
+
+blocking_fn!(b2_get_download_authorization(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request_data: &GetDownloadAuthorizationRequest<'_>,
+) -> Result<GetDownloadAuthorizationOk, GetDownloadAuthorizationError>);
+
+blocking_fn!(b2_get_file_info(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    file_id: &FileId,
+) -> Result<FileInformation, GetFileInfoError>);
+
+blocking_fn!(b2_get_upload_part_url(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    file_id: &FileId,
+) -> Result<UploadPartUrlParameters, GetUploadUrlError>);
+
+blocking_fn!(b2_get_upload_url(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    bucket_id: &BucketId,
+) -> Result<UploadParameters, GetUploadUrlError>);
+
+/// Blocking version of [crate::v2::b2_head_file_by_name]
+pub fn b2_head_file_by_name<AuthToken>(
+    download_url: &DownloadUrl,
+    request: &DownloadFileByNameRequest<'_, AuthToken>,
+) -> Result<HeadFileOk, DownloadFileError>
+where
+    AuthToken: DownloadAuthorizationToken + Serialize,
+{
+    block_on(v2::b2_head_file_by_name(download_url, request))
+}
+
+blocking_fn!(b2_head_file_by_id(
+    download_url: &DownloadUrl,
+    authorization_token: Option<&AuthorizationToken>,
+    params: &DownloadParams<'_>,
+) -> Result<HeadFileOk, DownloadFileError>);
+
+blocking_fn!(b2_hide_file(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    bucket_id: &BucketId,
+    file_name: &FileName,
+) -> Result<FileInformation, GetFileInfoError>);
+
+blocking_fn!(b2_list_buckets(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request_body: &ListBucketsRequest<'_>,
+) -> Result<ListBucketsOk, GenericB2Error>);
+
+blocking_fn!(b2_list_file_names(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request_body: &ListFileNamesRequest<'_>,
+) -> Result<ListFileNamesOk, ListFileNamesError>);
+
+blocking_fn!(b2_list_file_versions(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request_body: &ListFileVersionsRequest<'_>,
+) -> Result<ListFileVersionsOk, ListFileVersionsError>);
+
+blocking_fn!(b2_list_keys(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    params: &ListKeysRequest<'_>,
+) -> Result<ListKeysOk, GenericB2Error>);
+
+blocking_fn!(b2_list_parts(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request_parameters: &ListPartsRequest<'_>,
+) -> Result<ListPartsOk, GenericB2Error>);
+
+blocking_fn!(b2_list_unfinished_large_files(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request_parameters: &ListUnfinishedLargeFilesRequest<'_>,
+) -> Result<ListUnfinishedLargeFilesOk, GenericB2Error>);
+
+blocking_fn!(b2_start_large_file(
+    api_url: &ApiUrl,
+    authorization: &AuthorizationToken,
+    params: &StartLargeFileParameters<'_>,
+) -> Result<FileInformation, LargeFileError>);
+
+blocking_fn!(b2_update_bucket(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request: &UpdateBucketRequest<'_>,
+) -> Result<Bucket, UpdateBucketError>);
+
+blocking_fn!(b2_update_file_legal_hold(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request: &UpdateFileLegalHoldRequest<'_>,
+) -> Result<UpdateFileLegalHoldOk, UpdateFileLockError>);
+
+blocking_fn!(b2_update_file_retention(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request: &UpdateFileRetentionRequest<'_>,
+) -> Result<UpdateFileRetentionOk, UpdateFileLockError>);
+
+/// Blocking version of [crate::v2::b2_upload_file]
+pub fn b2_upload_file<T: Into<Body>>(
+    uploader_params: &mut UploadParameters,
+    upload_file_params: &UploadFileParameters<'_>,
+    file_contents: T,
+) -> Result<FileInformation, UploadFileError> {
+    block_on(v2::b2_upload_file(
+        uploader_params,
+        upload_file_params,
+        file_contents,
+    ))
+}
+
+/// Blocking version of [crate::v2::b2_upload_part]
+pub fn b2_upload_part<T: Into<Body>>(
+    uploader_params: &mut UploadPartUrlParameters,
+    upload_part_params: &UploadPartParameters<'_>,
+    file_contents: T,
+) -> Result<UploadPartOk, UploadPartError> {
+    block_on(v2::b2_upload_part(
+        uploader_params,
+        upload_part_params,
+        file_contents,
+    ))
+}
+
+blocking_fn!(presign_download(
+    api_url: &ApiUrl,
+    download_url: &DownloadUrl,
+    authorization_token: &AuthorizationToken,
+    request: &PresignDownloadRequest<'_>,
+) -> Result<String, GetDownloadAuthorizationError>);
+
+blocking_fn!(presign_downloads(
+    api_url: &ApiUrl,
+    download_url: &DownloadUrl,
+    authorization_token: &AuthorizationToken,
+    request: &PresignDownloadsRequest<'_>,
+) -> Result<Vec<String>, GetDownloadAuthorizationError>);
+
+/// Blocking version of [crate::v2::KeyManager]
+#[derive(Debug, Clone, Copy)]
+pub struct KeyManager<'a>(v2::KeyManager<'a>);
+
+impl<'a> KeyManager<'a> {
+    pub fn new(authorization: &'a AuthorizeAccountOk) -> Self {
+        Self(v2::KeyManager::new(authorization))
+    }
+
+    /// Blocking version of [crate::v2::KeyManager::create_key]
+    pub fn create_key(
+        &self,
+        key_name: &KeyName,
+        template: &KeyTemplate,
+    ) -> Result<CreatedKeyInformation, GenericB2Error> {
+        block_on(self.0.create_key(key_name, template))
+    }
+
+    /// Blocking version of [crate::v2::KeyManager::list_keys]
+    pub fn list_keys(&self) -> Result<Vec<KeyInformation>, GenericB2Error> {
+        block_on(self.0.list_keys())
+    }
+
+    /// Blocking version of [crate::v2::KeyManager::expiring_keys]
+    pub fn expiring_keys(&self, duration: Duration) -> Result<Vec<KeyInformation>, GenericB2Error> {
+        block_on(self.0.expiring_keys(duration))
+    }
+
+    /// Blocking version of [crate::v2::KeyManager::orphaned_keys]
+    pub fn orphaned_keys(&self) -> Result<Vec<KeyInformation>, KeyManagerError> {
+        block_on(self.0.orphaned_keys())
+    }
+
+    /// Blocking version of [crate::v2::KeyManager::rotate_key]
+    pub fn rotate_key(
+        &self,
+        key: &KeyInformation,
+        valid_duration: Option<ValidKeyLifeTimeInSeconds>,
+    ) -> Result<RotateKeyOk, KeyManagerError> {
+        block_on(self.0.rotate_key(key, valid_duration))
+    }
+
+    /// Blocking version of [crate::v2::KeyManager::delete_key]
+    pub fn delete_key(&self, key: &KeyInformation) -> Result<KeyInformation, GenericB2Error> {
+        block_on(self.0.delete_key(key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::v2::test::mock_server::*;
+
+    #[test]
+    fn test_blocking_calls() {
+        // the mock server runs on its own runtime, the blocking calls must not run inside of one
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock_server = runtime.block_on(async {
+            let mock_server = B2MockServer::start().await;
+            mock_server.register_default_list_file_names_handler().await;
+            mock_server
+        });
+        let res = b2_list_file_names(
+            &ApiUrl(mock_server.uri()),
+            &AuthorizationToken(FAKE_AUTHORIZATION_TOKEN.into()),
+            &ListFileNamesRequest::builder()
+                .bucket_id(&FAKE_BUCKET_ID.to_owned().try_into().unwrap())
+                .build(),
+        );
+        assert!(res.is_ok());
+
+        let download_server = runtime.block_on(async {
+            let mock_server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(path("/b2api/v2/b2_download_file_by_id"))
+                .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![42u8; 100_000]))
+                .mount(&mock_server)
+                .await;
+            mock_server
+        });
+        let file_id: FileId = "file_id".to_owned().try_into().unwrap();
+        let mut response = b2_download_file_by_id(
+            &serde_json::from_value(serde_json::Value::String(download_server.uri())).unwrap(),
+            None,
+            &DownloadParams::builder().file_id(&file_id).build(),
+        )
+        .unwrap();
+        let mut content = Vec::new();
+        response.read_to_end(&mut content).unwrap();
+        assert_eq!(vec![42u8; 100_000], content);
+    }
+
+    #[test]
+    fn test_blocking_key_manager() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock_server = runtime.block_on(async {
+            let mock_server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/b2api/v2/b2_list_keys"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "keys": [{
+                        "accountId": FAKE_ACCOUNT_ID,
+                        "applicationKeyId": "k1",
+                        "bucketId": null,
+                        "capabilities": ["listFiles"],
+                        "expirationTimestamp": null,
+                        "keyName": "customer-1",
+                        "namePrefix": null,
+                        "options": ["s3"],
+                    }],
+                    "nextApplicationKeyId": null,
+                })))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+            mock_server
+        });
+        let authorization = fake_authorization(&mock_server.uri(), &["listKeys"]);
+        let keys = KeyManager::new(&authorization).list_keys().unwrap();
+        assert_eq!(1, keys.len());
+        assert_eq!("customer-1", keys[0].key_name().as_str());
+    }
+}
@@ -0,0 +1,1427 @@
+//! Client side envelope encryption of file contents, so B2 never sees the plaintext or a key.
+//!
+//! Every file is encrypted with its own random data key, which is wrapped (encrypted) with a [MasterKey] of the
+//! application. The content is split into chunks of `chunk_size` bytes, each of them is encrypted and authenticated
+//! on its own with AES-256-GCM or ChaCha20-Poly1305 (see [Cipher]). The nonce of a chunk is a random per file prefix
+//! followed by the index of the chunk, the last chunk is marked as such, so chunks can neither be reordered nor cut
+//! off. Each encrypted chunk is 16 bytes longer than its plaintext.
+//!
+//! The wrapped data key, the nonce prefix, the chunk size, the cipher and the id of the master key are stored in the
+//! file info of the file (see [FileEncryption::file_info]):
+//! * uploads: encrypt the content with [FileEncryption::encrypt] and pass the file info to
+//!   [crate::v2::UploadFileParameters]. The length and sha1 of the upload are the ones of the encrypted content.
+//! * large files: pass the file info (converted with `serde_json::to_value`) to [crate::v2::b2_start_large_file],
+//!   which needs the `b2_unstable` feature, and encrypt each part with [FileEncryption::encrypt_part]. All parts except the last one must be a multiple of the chunk size long.
+//! * downloads: read the file info (e.g. from [crate::v2::HeadFileOk::file_info]) with
+//!   [FileEncryption::from_file_info] and decrypt the content with [FileEncryption::decrypt]. To read a range of the
+//!   plaintext, download the chunks containing it with the range of [FileEncryption::encrypted_range] and decrypt
+//!   only those with [FileEncryption::decrypt_range].
+//!
+//! Contents too large to be held in memory are encrypted and decrypted while they are streamed:
+//! [b2_upload_encrypted_file] and [b2_upload_encrypted_part] encrypt a stream of plaintext while it is uploaded,
+//! sending the sha1 of the encrypted content at its end. [b2_download_decrypted_file_by_id] and
+//! [b2_download_decrypted_file_by_name] return a stream of the plaintext of a file or an [EncryptedRange] of it.
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm,
+};
+use bytes::Bytes;
+use chacha20poly1305::ChaCha20Poly1305;
+use futures_util::Stream;
+use serde::Serialize;
+use typed_builder::TypedBuilder;
+use zeroize::Zeroize;
+
+use crate::{
+    transport::{Body, BoxError},
+    v2::{
+        b2_download_file_by_id, b2_download_file_by_name, b2_upload_file, b2_upload_part,
+        errors::{DownloadFileError, UploadFileError, UploadPartError},
+        AuthorizationToken, ContentSha1, ContentTypeRef, DownloadAuthorizationToken,
+        DownloadFileByNameRequest, DownloadParams, DownloadUrl, FileInformation, FileName,
+        InvalidData, PartNumber, UploadFileParameters, UploadParameters, UploadPartOk,
+        UploadPartParameters, UploadPartUrlParameters, CONTENT_TYPE_AUTO,
+    },
+};
+
+pub const CIPHER_FILE_INFO: &str = "cse_cipher";
+pub const KEY_ID_FILE_INFO: &str = "cse_key_id";
+pub const WRAPPED_KEY_FILE_INFO: &str = "cse_wrapped_key";
+pub const NONCE_PREFIX_FILE_INFO: &str = "cse_nonce_prefix";
+pub const CHUNK_SIZE_FILE_INFO: &str = "cse_chunk_size";
+
+/// chunk size, if not set otherwise
+pub const DEFAULT_CHUNK_SIZE: u32 = 64 * 1024;
+
+const KEY_BYTES: usize = 32;
+const NONCE_BYTES: usize = 12;
+const NONCE_PREFIX_BYTES: usize = 8;
+/// length of the authentication tag added to each chunk
+const TAG_BYTES: u64 = 16;
+/// length of the hex encoded sha1 sent at the end of streamed uploads
+const SHA1_HEX_BYTES: u64 = 40;
+
+/// Authenticated encryption used for the chunks and to wrap the data key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cipher {
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl Cipher {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Cipher::Aes256Gcm => "AES-256-GCM",
+            Cipher::ChaCha20Poly1305 => "ChaCha20-Poly1305",
+        }
+    }
+}
+
+impl Display for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Cipher {
+    type Err = ClientEncryptionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "AES-256-GCM" => Ok(Cipher::Aes256Gcm),
+            "ChaCha20-Poly1305" => Ok(Cipher::ChaCha20Poly1305),
+            other => Err(ClientEncryptionError::InvalidFileInfo {
+                key: CIPHER_FILE_INFO,
+                message: format!("unknown cipher {}", other),
+            }),
+        }
+    }
+}
+
+/// [Cipher] with its key
+enum Aead256 {
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(Box<ChaCha20Poly1305>),
+}
+
+impl Aead256 {
+    fn new(cipher: Cipher, key: &[u8; KEY_BYTES]) -> Self {
+        match cipher {
+            Cipher::Aes256Gcm => Self::Aes256Gcm(Box::new(Aes256Gcm::new(key.into()))),
+            Cipher::ChaCha20Poly1305 => {
+                Self::ChaCha20Poly1305(Box::new(ChaCha20Poly1305::new(key.into())))
+            }
+        }
+    }
+
+    fn seal(&self, nonce: &[u8; NONCE_BYTES], msg: &[u8], aad: &[u8]) -> Vec<u8> {
+        let payload = Payload { msg, aad };
+        // unwraps are safe, encryption only fails for messages larger than 64 GB
+        match self {
+            Self::Aes256Gcm(aead) => aead.encrypt(nonce.into(), payload).unwrap(),
+            Self::ChaCha20Poly1305(aead) => aead.encrypt(nonce.into(), payload).unwrap(),
+        }
+    }
+
+    fn open(
+        &self,
+        nonce: &[u8; NONCE_BYTES],
+        msg: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, ClientEncryptionError> {
+        let payload = Payload { msg, aad };
+        match self {
+            Self::Aes256Gcm(aead) => aead.decrypt(nonce.into(), payload),
+            Self::ChaCha20Poly1305(aead) => aead.decrypt(nonce.into(), payload),
+        }
+        .map_err(|_| ClientEncryptionError::Authentication)
+    }
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], ClientEncryptionError> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(ClientEncryptionError::Random)?;
+    Ok(bytes)
+}
+
+/// Key of the application, that wraps the data keys of the files
+///
+/// The key is overwritten with zeros when it is dropped and never printed. Its id is stored with each file, to find
+/// the key again when the file is downloaded, so keys can be rotated without re-encrypting the files.
+#[derive(Clone)]
+pub struct MasterKey {
+    key_id: String,
+    key: [u8; KEY_BYTES],
+}
+
+impl MasterKey {
+    pub fn new(key_id: impl Into<String>, key: [u8; KEY_BYTES]) -> Self {
+        Self {
+            key_id: key_id.into(),
+            key,
+        }
+    }
+
+    /// New random key from the random number generator of the operating system
+    pub fn generate(key_id: impl Into<String>) -> Result<Self, ClientEncryptionError> {
+        Ok(Self::new(key_id, random_bytes()?))
+    }
+
+    /// Get a reference to the master key's id.
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+}
+
+impl Drop for MasterKey {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+impl std::fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MasterKey")
+            .field("key_id", &self.key_id)
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Range of an encrypted file, that contains a range of the plaintext, see [FileEncryption::encrypted_range]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedRange {
+    start: u64,
+    end: u64,
+    first_chunk: u64,
+    chunk_count: u64,
+    skip: usize,
+    length: usize,
+}
+
+impl EncryptedRange {
+    /// Get the encrypted range's first byte.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Get the encrypted range's end (exclusive).
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// range header to download the encrypted range, e.g. with [crate::v2::DownloadParams]
+    pub fn to_header(&self) -> headers::Range {
+        // unwrap is safe, the range is never empty as every file has at least one chunk
+        headers::Range::bytes(self.start..self.end).unwrap()
+    }
+}
+
+/// Encryption of a single file: the unwrapped data key and the parameters stored in the file info
+///
+/// Clones share the data key, which is overwritten with zeros when the last of them is dropped.
+#[derive(Clone)]
+pub struct FileEncryption {
+    cipher: Cipher,
+    aead: Arc<Aead256>,
+    key_id: String,
+    wrapped_key: Vec<u8>,
+    nonce_prefix: [u8; NONCE_PREFIX_BYTES],
+    chunk_size: u32,
+}
+
+impl FileEncryption {
+    /// Encryption of a new file with a new random data key, wrapped with `master_key`
+    pub fn new(
+        master_key: &MasterKey,
+        cipher: Cipher,
+        chunk_size: u32,
+    ) -> Result<Self, ClientEncryptionError> {
+        if chunk_size == 0 {
+            return Err(ClientEncryptionError::InvalidChunkSize);
+        }
+        let mut data_key: [u8; KEY_BYTES] = random_bytes()?;
+        let nonce_prefix = random_bytes()?;
+        let key_nonce = random_bytes()?;
+        let aad = wrapping_aad(cipher, chunk_size, &nonce_prefix);
+        let mut wrapped_key = key_nonce.to_vec();
+        wrapped_key.extend(Aead256::new(cipher, &master_key.key).seal(&key_nonce, &data_key, &aad));
+        let aead = Arc::new(Aead256::new(cipher, &data_key));
+        data_key.zeroize();
+        Ok(Self {
+            cipher,
+            aead,
+            key_id: master_key.key_id.clone(),
+            wrapped_key,
+            nonce_prefix,
+            chunk_size,
+        })
+    }
+
+    /// Encryption of an existing file, from its file info and the master key with the id stored in it
+    pub fn from_file_info(
+        file_info: &HashMap<String, String>,
+        master_keys: &[MasterKey],
+    ) -> Result<Self, ClientEncryptionError> {
+        let get = |key: &'static str| {
+            file_info
+                .get(key)
+                .ok_or(ClientEncryptionError::MissingFileInfo { key })
+        };
+        let invalid = |key: &'static str, message: String| ClientEncryptionError::InvalidFileInfo {
+            key,
+            message,
+        };
+        let cipher: Cipher = get(CIPHER_FILE_INFO)?.parse()?;
+        let key_id = get(KEY_ID_FILE_INFO)?;
+        let master_key = master_keys
+            .iter()
+            .find(|master_key| &master_key.key_id == key_id)
+            .ok_or_else(|| ClientEncryptionError::UnknownKey {
+                key_id: key_id.to_owned(),
+            })?;
+        let chunk_size = get(CHUNK_SIZE_FILE_INFO)?
+            .parse::<u32>()
+            .map_err(|e| invalid(CHUNK_SIZE_FILE_INFO, e.to_string()))?;
+        if chunk_size == 0 {
+            return Err(ClientEncryptionError::InvalidChunkSize);
+        }
+        let nonce_prefix: [u8; NONCE_PREFIX_BYTES] = base64::decode(get(NONCE_PREFIX_FILE_INFO)?)
+            .map_err(|e| invalid(NONCE_PREFIX_FILE_INFO, e.to_string()))?
+            .try_into()
+            .map_err(|_| invalid(NONCE_PREFIX_FILE_INFO, "invalid length".to_owned()))?;
+        let wrapped_key = base64::decode(get(WRAPPED_KEY_FILE_INFO)?)
+            .map_err(|e| invalid(WRAPPED_KEY_FILE_INFO, e.to_string()))?;
+        if wrapped_key.len() < NONCE_BYTES {
+            return Err(invalid(WRAPPED_KEY_FILE_INFO, "invalid length".to_owned()));
+        }
+        let (key_nonce, encrypted_key) = wrapped_key.split_at(NONCE_BYTES);
+        // unwrap is safe, the nonce was split off with the right length
+        let key_nonce: &[u8; NONCE_BYTES] = key_nonce.try_into().unwrap();
+        let aad = wrapping_aad(cipher, chunk_size, &nonce_prefix);
+        let mut data_key =
+            Aead256::new(cipher, &master_key.key).open(key_nonce, encrypted_key, &aad)?;
+        let aead = <&[u8; KEY_BYTES]>::try_from(data_key.as_slice())
+            .map(|data_key| Arc::new(Aead256::new(cipher, data_key)))
+            .map_err(|_| invalid(WRAPPED_KEY_FILE_INFO, "invalid key length".to_owned()));
+        data_key.zeroize();
+        Ok(Self {
+            cipher,
+            aead: aead?,
+            key_id: key_id.to_owned(),
+            wrapped_key,
+            nonce_prefix,
+            chunk_size,
+        })
+    }
+
+    /// File info to be stored with the encrypted file
+    pub fn file_info(&self) -> HashMap<String, String> {
+        HashMap::from([
+            (CIPHER_FILE_INFO.to_owned(), self.cipher.to_string()),
+            (KEY_ID_FILE_INFO.to_owned(), self.key_id.clone()),
+            (
+                WRAPPED_KEY_FILE_INFO.to_owned(),
+                base64::encode(&self.wrapped_key),
+            ),
+            (
+                NONCE_PREFIX_FILE_INFO.to_owned(),
+                base64::encode(self.nonce_prefix),
+            ),
+            (CHUNK_SIZE_FILE_INFO.to_owned(), self.chunk_size.to_string()),
+        ])
+    }
+
+    /// Get the file encryption's cipher.
+    pub fn cipher(&self) -> Cipher {
+        self.cipher
+    }
+
+    /// Get a reference to the id of the master key, that wraps the data key.
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Get the file encryption's chunk size.
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    fn encrypted_chunk_size(&self) -> u64 {
+        self.chunk_size as u64 + TAG_BYTES
+    }
+
+    /// length of the encrypted content, e.g. the content length of the upload
+    pub fn encrypted_length(&self, plaintext_length: u64) -> u64 {
+        let chunk_count = plaintext_length.div_ceil(self.chunk_size as u64).max(1);
+        plaintext_length + chunk_count * TAG_BYTES
+    }
+
+    /// length of the plaintext of an encrypted file
+    pub fn plaintext_length(&self, encrypted_length: u64) -> Result<u64, ClientEncryptionError> {
+        let chunk_count = encrypted_length.div_ceil(self.encrypted_chunk_size());
+        let last_chunk =
+            encrypted_length.saturating_sub((chunk_count.max(1) - 1) * self.encrypted_chunk_size());
+        if chunk_count == 0
+            || last_chunk < TAG_BYTES
+            || (chunk_count > 1 && last_chunk == TAG_BYTES)
+        {
+            return Err(ClientEncryptionError::InvalidLength { encrypted_length });
+        }
+        Ok(encrypted_length - chunk_count * TAG_BYTES)
+    }
+
+    fn nonce(&self, chunk: u64) -> Result<[u8; NONCE_BYTES], ClientEncryptionError> {
+        let chunk = u32::try_from(chunk).map_err(|_| ClientEncryptionError::InvalidPart {
+            message: "too many chunks, use a larger chunk size".to_owned(),
+        })?;
+        let mut nonce = [0u8; NONCE_BYTES];
+        nonce[..NONCE_PREFIX_BYTES].copy_from_slice(&self.nonce_prefix);
+        nonce[NONCE_PREFIX_BYTES..].copy_from_slice(&chunk.to_be_bytes());
+        Ok(nonce)
+    }
+
+    /// Encrypts the content of a whole file
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, ClientEncryptionError> {
+        self.encrypt_part(0, plaintext, true)
+    }
+
+    /// Encrypts a part of a file starting at `offset` of the plaintext, e.g. a part of a large file
+    ///
+    /// The offset must be a multiple of the chunk size, as must be the length of every part but the last one.
+    pub fn encrypt_part(
+        &self,
+        offset: u64,
+        plaintext: &[u8],
+        is_last: bool,
+    ) -> Result<Vec<u8>, ClientEncryptionError> {
+        self.check_part(offset, plaintext.len() as u64, is_last)?;
+        let chunk_size = self.chunk_size as usize;
+        let first_chunk = offset / chunk_size as u64;
+        let chunks: Vec<&[u8]> = if plaintext.is_empty() {
+            // an empty file consists of a single empty chunk
+            vec![plaintext]
+        } else {
+            plaintext.chunks(chunk_size).collect()
+        };
+        let mut encrypted = Vec::with_capacity(plaintext.len() + chunks.len() * TAG_BYTES as usize);
+        for (index, chunk) in chunks.iter().enumerate() {
+            let chunk_is_last = is_last && index + 1 == chunks.len();
+            let nonce = self.nonce(first_chunk + index as u64)?;
+            encrypted.extend(self.aead.seal(&nonce, chunk, &[chunk_is_last as u8]));
+        }
+        Ok(encrypted)
+    }
+
+    /// checks that a part starts and (unless it is the last one) ends at a chunk boundary
+    fn check_part(
+        &self,
+        offset: u64,
+        length: u64,
+        is_last: bool,
+    ) -> Result<(), ClientEncryptionError> {
+        let chunk_size = self.chunk_size as u64;
+        if !offset.is_multiple_of(chunk_size) {
+            return Err(ClientEncryptionError::InvalidPart {
+                message: format!("offset {} is not a multiple of the chunk size", offset),
+            });
+        }
+        if !is_last && !length.is_multiple_of(chunk_size) {
+            return Err(ClientEncryptionError::InvalidPart {
+                message: format!(
+                    "length {} of a part other than the last one is not a multiple of the chunk size",
+                    length
+                ),
+            });
+        }
+        if is_last && length == 0 && offset > 0 {
+            return Err(ClientEncryptionError::InvalidPart {
+                message: "the last part is empty".to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Encrypts a stream of `plaintext_length` bytes of plaintext starting at `offset`, like [Self::encrypt_part]
+    ///
+    /// The stream fails with [ClientEncryptionError::UnexpectedLength], if the plaintext is shorter or longer.
+    pub fn encrypt_stream<S, E>(
+        &self,
+        offset: u64,
+        plaintext_length: u64,
+        is_last: bool,
+        plaintext: S,
+    ) -> Result<EncryptStream<S>, ClientEncryptionError>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: Into<BoxError>,
+    {
+        self.check_part(offset, plaintext_length, is_last)?;
+        Ok(EncryptStream {
+            encryption: self.clone(),
+            plaintext: Box::pin(plaintext),
+            buffer: Vec::new(),
+            next_chunk: offset / self.chunk_size as u64,
+            sealed_chunks: 0,
+            plaintext_length,
+            remaining: plaintext_length,
+            is_last,
+            done: false,
+        })
+    }
+
+    /// Decrypts a stream of the encrypted content of a whole file or, if `range` is set, of an [EncryptedRange]
+    /// (downloaded with [EncryptedRange::to_header])
+    pub fn decrypt_stream<S, E>(
+        &self,
+        range: Option<&EncryptedRange>,
+        encrypted: S,
+    ) -> DecryptStream<S>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: Into<BoxError>,
+    {
+        DecryptStream {
+            encryption: self.clone(),
+            encrypted: Box::pin(encrypted),
+            buffer: Vec::new(),
+            next_chunk: range.map_or(0, |range| range.first_chunk),
+            received: 0,
+            skip: range.map_or(0, |range| range.skip),
+            remaining: range.map(|range| range.length),
+            range: range.cloned(),
+            done: false,
+        }
+    }
+
+    /// Decrypts the content of a whole file
+    pub fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>, ClientEncryptionError> {
+        let encrypted_length = encrypted.len() as u64;
+        let range = self.encrypted_range(
+            0..self.plaintext_length(encrypted_length)?,
+            encrypted_length,
+        )?;
+        self.decrypt_range(&range, encrypted)
+    }
+
+    /// Range of the encrypted file with the chunks containing `plaintext_range`
+    pub fn encrypted_range(
+        &self,
+        plaintext_range: std::ops::Range<u64>,
+        encrypted_length: u64,
+    ) -> Result<EncryptedRange, ClientEncryptionError> {
+        let plaintext_length = self.plaintext_length(encrypted_length)?;
+        if plaintext_range.start > plaintext_range.end || plaintext_range.end > plaintext_length {
+            return Err(ClientEncryptionError::InvalidRange {
+                start: plaintext_range.start,
+                end: plaintext_range.end,
+                plaintext_length,
+            });
+        }
+        let chunk_size = self.chunk_size as u64;
+        let chunk_count = encrypted_length.div_ceil(self.encrypted_chunk_size());
+        let first_chunk = (plaintext_range.start / chunk_size).min(chunk_count - 1);
+        let last_chunk = (plaintext_range.end.saturating_sub(1) / chunk_size)
+            .clamp(first_chunk, chunk_count - 1);
+        Ok(EncryptedRange {
+            start: first_chunk * self.encrypted_chunk_size(),
+            end: ((last_chunk + 1) * self.encrypted_chunk_size()).min(encrypted_length),
+            first_chunk,
+            chunk_count,
+            skip: (plaintext_range.start - first_chunk * chunk_size) as usize,
+            length: (plaintext_range.end - plaintext_range.start) as usize,
+        })
+    }
+
+    /// Decrypts the chunks of an [EncryptedRange] (downloaded with [EncryptedRange::to_header]), returns only the
+    /// plaintext of the range it was created for
+    pub fn decrypt_range(
+        &self,
+        range: &EncryptedRange,
+        encrypted: &[u8],
+    ) -> Result<Vec<u8>, ClientEncryptionError> {
+        if encrypted.len() as u64 != range.end - range.start {
+            return Err(ClientEncryptionError::InvalidLength {
+                encrypted_length: encrypted.len() as u64,
+            });
+        }
+        let mut plaintext = Vec::with_capacity(encrypted.len());
+        for (index, chunk) in encrypted
+            .chunks(self.encrypted_chunk_size() as usize)
+            .enumerate()
+        {
+            let chunk_index = range.first_chunk + index as u64;
+            let is_last = chunk_index + 1 == range.chunk_count;
+            let nonce = self.nonce(chunk_index)?;
+            plaintext.extend(self.aead.open(&nonce, chunk, &[is_last as u8])?);
+        }
+        plaintext.drain(..range.skip);
+        plaintext.truncate(range.length);
+        Ok(plaintext)
+    }
+}
+
+impl std::fmt::Debug for FileEncryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileEncryption")
+            .field("cipher", &self.cipher)
+            .field("key_id", &self.key_id)
+            .field("chunk_size", &self.chunk_size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Stream of the encrypted chunks of a stream of plaintext, see [FileEncryption::encrypt_stream]
+pub struct EncryptStream<S> {
+    encryption: FileEncryption,
+    plaintext: Pin<Box<S>>,
+    buffer: Vec<u8>,
+    next_chunk: u64,
+    sealed_chunks: u64,
+    plaintext_length: u64,
+    remaining: u64,
+    is_last: bool,
+    done: bool,
+}
+
+impl<S> EncryptStream<S> {
+    fn seal(&mut self, length: usize, is_last: bool) -> Result<Bytes, ClientEncryptionError> {
+        let nonce = self.encryption.nonce(self.next_chunk)?;
+        let chunk: Vec<u8> = self.buffer.drain(..length).collect();
+        self.next_chunk += 1;
+        self.sealed_chunks += 1;
+        Ok(self
+            .encryption
+            .aead
+            .seal(&nonce, &chunk, &[is_last as u8])
+            .into())
+    }
+}
+
+impl<S, E> Stream for EncryptStream<S>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Into<BoxError>,
+{
+    type Item = Result<Bytes, ClientEncryptionError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let chunk_size = this.encryption.chunk_size as usize;
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+            // the last chunk is only sealed at the end of the plaintext, as it is marked as such
+            if this.buffer.len() > chunk_size
+                || (this.buffer.len() == chunk_size && this.remaining > 0)
+            {
+                let chunk = this.seal(chunk_size, false);
+                this.done = chunk.is_err();
+                return Poll::Ready(Some(chunk));
+            }
+            match this.plaintext.as_mut().poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(bytes))) => {
+                    if bytes.len() as u64 > this.remaining {
+                        this.done = true;
+                        return Poll::Ready(Some(Err(ClientEncryptionError::UnexpectedLength {
+                            expected: this.plaintext_length,
+                            actual: this.plaintext_length - this.remaining + bytes.len() as u64,
+                        })));
+                    }
+                    this.remaining -= bytes.len() as u64;
+                    this.buffer.extend_from_slice(&bytes);
+                }
+                Poll::Ready(Some(Err(error))) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(ClientEncryptionError::Stream(error.into()))));
+                }
+                Poll::Ready(None) => {
+                    this.done = true;
+                    if this.remaining > 0 {
+                        return Poll::Ready(Some(Err(ClientEncryptionError::UnexpectedLength {
+                            expected: this.plaintext_length,
+                            actual: this.plaintext_length - this.remaining,
+                        })));
+                    }
+                    // an empty file consists of a single empty chunk
+                    if this.buffer.is_empty() && this.sealed_chunks > 0 {
+                        return Poll::Ready(None);
+                    }
+                    let is_last = this.is_last;
+                    return Poll::Ready(Some(this.seal(this.buffer.len(), is_last)));
+                }
+            }
+        }
+    }
+}
+
+/// Stream of the plaintext of a stream of encrypted chunks, see [FileEncryption::decrypt_stream]
+pub struct DecryptStream<S> {
+    encryption: FileEncryption,
+    encrypted: Pin<Box<S>>,
+    range: Option<EncryptedRange>,
+    buffer: Vec<u8>,
+    next_chunk: u64,
+    received: u64,
+    skip: usize,
+    remaining: Option<usize>,
+    done: bool,
+}
+
+impl<S> DecryptStream<S> {
+    fn open(&mut self, length: usize, at_end: bool) -> Result<Bytes, ClientEncryptionError> {
+        let is_last = match &self.range {
+            Some(range) => self.next_chunk + 1 == range.chunk_count,
+            None => at_end,
+        };
+        let nonce = self.encryption.nonce(self.next_chunk)?;
+        let chunk: Vec<u8> = self.buffer.drain(..length).collect();
+        self.next_chunk += 1;
+        let mut plaintext = self
+            .encryption
+            .aead
+            .open(&nonce, &chunk, &[is_last as u8])?;
+        let skip = self.skip.min(plaintext.len());
+        plaintext.drain(..skip);
+        self.skip -= skip;
+        if let Some(remaining) = self.remaining.as_mut() {
+            plaintext.truncate(*remaining);
+            *remaining -= plaintext.len();
+        }
+        Ok(plaintext.into())
+    }
+}
+
+impl<S, E> Stream for DecryptStream<S>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Into<BoxError>,
+{
+    type Item = Result<Bytes, ClientEncryptionError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let chunk_size = this.encryption.encrypted_chunk_size() as usize;
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+            // the last chunk is only opened at the end of the content, as it is marked as such
+            if this.buffer.len() > chunk_size {
+                let plaintext = this.open(chunk_size, false);
+                this.done = plaintext.is_err();
+                return Poll::Ready(Some(plaintext));
+            }
+            match this.encrypted.as_mut().poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(bytes))) => {
+                    this.received += bytes.len() as u64;
+                    this.buffer.extend_from_slice(&bytes);
+                }
+                Poll::Ready(Some(Err(error))) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(ClientEncryptionError::Stream(error.into()))));
+                }
+                Poll::Ready(None) => {
+                    this.done = true;
+                    let expected = this.range.as_ref().map(|range| range.end - range.start);
+                    if this.received == 0
+                        || expected.is_some_and(|expected| expected != this.received)
+                    {
+                        return Poll::Ready(Some(Err(ClientEncryptionError::InvalidLength {
+                            encrypted_length: this.received,
+                        })));
+                    }
+                    return Poll::Ready(Some(this.open(this.buffer.len(), true)));
+                }
+            }
+        }
+    }
+}
+
+/// appends the hex encoded sha1 of the content to it, as expected by B2 for [ContentSha1::HexDigitsAtEnd]
+struct Sha1AtEnd<S> {
+    content: S,
+    sha1: Option<sha1::Sha1>,
+}
+
+impl<S> Sha1AtEnd<S> {
+    fn new(content: S) -> Self {
+        Self {
+            content,
+            sha1: Some(sha1::Sha1::new()),
+        }
+    }
+}
+
+impl<S> Stream for Sha1AtEnd<S>
+where
+    S: Stream<Item = Result<Bytes, ClientEncryptionError>> + Unpin,
+{
+    type Item = Result<Bytes, ClientEncryptionError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let sha1 = match this.sha1.as_mut() {
+            Some(sha1) => sha1,
+            None => return Poll::Ready(None),
+        };
+        match Pin::new(&mut this.content).poll_next(cx) {
+            Poll::Ready(Some(Ok(bytes))) => {
+                sha1.update(&bytes);
+                Poll::Ready(Some(Ok(bytes)))
+            }
+            Poll::Ready(Some(Err(error))) => {
+                this.sha1 = None;
+                Poll::Ready(Some(Err(error)))
+            }
+            Poll::Ready(None) => {
+                let digest = sha1.digest().to_string();
+                this.sha1 = None;
+                Poll::Ready(Some(Ok(digest.into())))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Upload of a file encrypted while it is streamed, see [b2_upload_encrypted_file]
+#[derive(Debug, TypedBuilder)]
+pub struct EncryptedUploadRequest<'s> {
+    file_name: &'s FileName,
+
+    /// content type of the file, if not set "b2/x-auto" will be sent, causing backblaze to determine the type
+    #[builder(default = &CONTENT_TYPE_AUTO)]
+    content_type: ContentTypeRef<'s>,
+
+    /// length of the plaintext
+    content_length: u64,
+
+    /// custom file info, stored along with the file info of the encryption
+    #[builder(default, setter(strip_option))]
+    file_info: Option<&'s HashMap<String, String>>,
+}
+
+/// Part of a large file encrypted while it is streamed, see [b2_upload_encrypted_part]
+#[derive(Debug, TypedBuilder)]
+pub struct EncryptedPartRequest {
+    part_number: PartNumber,
+
+    /// offset of the part in the plaintext of the file, a multiple of the chunk size
+    offset: u64,
+
+    /// length of the plaintext of the part, a multiple of the chunk size unless it is the last part
+    content_length: u64,
+
+    is_last: bool,
+}
+
+/// error of a request which cannot be encrypted, returned before it is sent
+fn invalid_request(error: ClientEncryptionError) -> crate::v2::Error {
+    crate::v2::Error::InvalidData(InvalidData::new(error.to_string()))
+}
+
+/// Uploads a file with [b2_upload_file], encrypting the stream of plaintext while it is sent
+///
+/// The file info of the encryption is stored with the file. The sha1 of the encrypted content is sent at its end.
+pub async fn b2_upload_encrypted_file<S, E>(
+    uploader_params: &mut UploadParameters,
+    request: &EncryptedUploadRequest<'_>,
+    encryption: &FileEncryption,
+    plaintext: S,
+) -> Result<FileInformation, UploadFileError>
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + Sync + 'static,
+    E: Into<BoxError> + 'static,
+{
+    let encrypted = encryption
+        .encrypt_stream(0, request.content_length, true, plaintext)
+        .map_err(|error| UploadFileError::Unexpected {
+            raw_error: invalid_request(error),
+            retry: None,
+        })?;
+    let mut file_info = request.file_info.cloned().unwrap_or_default();
+    file_info.extend(encryption.file_info());
+    let params = UploadFileParameters::builder()
+        .file_name(request.file_name)
+        .content_type(request.content_type)
+        .content_length(encryption.encrypted_length(request.content_length) + SHA1_HEX_BYTES)
+        .content_sha1(ContentSha1::HexDigitsAtEnd)
+        .file_info(&file_info)
+        .build();
+    let body = Body::wrap_stream(Sha1AtEnd::new(encrypted));
+    b2_upload_file(uploader_params, &params, body).await
+}
+
+/// Uploads a part of a large file with [b2_upload_part], encrypting the stream of plaintext while it is sent
+///
+/// The sha1 of the encrypted part is sent at its end and returned in [UploadPartOk::content_sha1].
+pub async fn b2_upload_encrypted_part<S, E>(
+    uploader_params: &mut UploadPartUrlParameters,
+    request: &EncryptedPartRequest,
+    encryption: &FileEncryption,
+    plaintext: S,
+) -> Result<UploadPartOk, UploadPartError>
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + Sync + 'static,
+    E: Into<BoxError> + 'static,
+{
+    let encrypted = encryption
+        .encrypt_stream(
+            request.offset,
+            request.content_length,
+            request.is_last,
+            plaintext,
+        )
+        .map_err(|error| UploadPartError::Unexpected {
+            raw_error: invalid_request(error),
+            retry: None,
+        })?;
+    let chunk_count = request
+        .content_length
+        .div_ceil(encryption.chunk_size as u64)
+        .max(1);
+    let params = UploadPartParameters::builder()
+        .part_number(request.part_number)
+        .content_length(request.content_length + chunk_count * TAG_BYTES + SHA1_HEX_BYTES)
+        .content_sha1(ContentSha1::HexDigitsAtEnd)
+        .build();
+    let body = Body::wrap_stream(Sha1AtEnd::new(encrypted));
+    b2_upload_part(uploader_params, &params, body).await
+}
+
+/// checks that the range of the download matches the encrypted range to be decrypted
+fn check_range(has_range: bool, range: Option<&EncryptedRange>) -> Result<(), DownloadFileError> {
+    if has_range != range.is_some() {
+        return Err(DownloadFileError::Unexpected {
+            raw_error: crate::v2::Error::InvalidData(InvalidData::new(
+                "The range of the download must be the one of the encrypted range".to_owned(),
+            )),
+            retry: None,
+        });
+    }
+    Ok(())
+}
+
+/// Downloads a file with [b2_download_file_by_id] and decrypts it while it is streamed
+///
+/// To read a range of the plaintext, request [EncryptedRange::to_header] in `params` and pass the range.
+pub async fn b2_download_decrypted_file_by_id(
+    download_url: &DownloadUrl,
+    authorization_token: Option<&AuthorizationToken>,
+    params: &DownloadParams<'_>,
+    encryption: &FileEncryption,
+    range: Option<&EncryptedRange>,
+) -> Result<DecryptStream<impl Stream<Item = reqwest::Result<Bytes>>>, DownloadFileError> {
+    check_range(params.has_range(), range)?;
+    let resp = b2_download_file_by_id(download_url, authorization_token, params).await?;
+    Ok(encryption.decrypt_stream(range, resp.bytes_stream()))
+}
+
+/// Downloads a file with [b2_download_file_by_name] and decrypts it while it is streamed
+///
+/// To read a range of the plaintext, request [EncryptedRange::to_header] in `request` and pass the range.
+pub async fn b2_download_decrypted_file_by_name<AuthToken>(
+    download_url: &DownloadUrl,
+    request: &DownloadFileByNameRequest<'_, AuthToken>,
+    encryption: &FileEncryption,
+    range: Option<&EncryptedRange>,
+) -> Result<DecryptStream<impl Stream<Item = reqwest::Result<Bytes>>>, DownloadFileError>
+where
+    AuthToken: DownloadAuthorizationToken + Serialize,
+{
+    check_range(request.has_range(), range)?;
+    let resp = b2_download_file_by_name(download_url, request).await?;
+    Ok(encryption.decrypt_stream(range, resp.bytes_stream()))
+}
+
+/// binds the parameters stored next to the wrapped key to it, so they cannot be changed unnoticed
+fn wrapping_aad(
+    cipher: Cipher,
+    chunk_size: u32,
+    nonce_prefix: &[u8; NONCE_PREFIX_BYTES],
+) -> Vec<u8> {
+    format!("{};{};{}", cipher, chunk_size, base64::encode(nonce_prefix)).into_bytes()
+}
+
+#[derive(Debug)]
+pub enum ClientEncryptionError {
+    /// The random number generator of the operating system failed
+    Random(getrandom::Error),
+    /// None of the master keys has the id stored with the file
+    UnknownKey {
+        key_id: String,
+    },
+    /// The file is not encrypted or its file info is incomplete
+    MissingFileInfo {
+        key: &'static str,
+    },
+    InvalidFileInfo {
+        key: &'static str,
+        message: String,
+    },
+    /// The content or the file info was changed or the master key is wrong
+    Authentication,
+    InvalidChunkSize,
+    /// The part of a large file does not start or end at a chunk boundary
+    InvalidPart {
+        message: String,
+    },
+    /// The length of the encrypted content does not match its chunks
+    InvalidLength {
+        encrypted_length: u64,
+    },
+    InvalidRange {
+        start: u64,
+        end: u64,
+        plaintext_length: u64,
+    },
+    /// The stream of plaintext is shorter or longer than its length
+    UnexpectedLength {
+        expected: u64,
+        actual: u64,
+    },
+    /// Reading the plaintext or downloading the encrypted content failed
+    Stream(BoxError),
+}
+
+impl Display for ClientEncryptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientEncryptionError::Random(error) => {
+                write!(f, "Could not generate a key: {}", error)
+            }
+            ClientEncryptionError::UnknownKey { key_id } => {
+                write!(f, "Unknown master key {}", key_id)
+            }
+            ClientEncryptionError::MissingFileInfo { key } => {
+                write!(f, "File info {} is missing", key)
+            }
+            ClientEncryptionError::InvalidFileInfo { key, message } => {
+                write!(f, "Invalid file info {}: {}", key, message)
+            }
+            ClientEncryptionError::Authentication => write!(
+                f,
+                "Decryption failed, the content was changed or the key is wrong"
+            ),
+            ClientEncryptionError::InvalidChunkSize => write!(f, "The chunk size must not be 0"),
+            ClientEncryptionError::InvalidPart { message } => {
+                write!(f, "Invalid part: {}", message)
+            }
+            ClientEncryptionError::InvalidLength { encrypted_length } => write!(
+                f,
+                "Invalid length {} of the encrypted content",
+                encrypted_length
+            ),
+            ClientEncryptionError::InvalidRange {
+                start,
+                end,
+                plaintext_length,
+            } => write!(
+                f,
+                "Invalid range {}..{} of a file of {} bytes",
+                start, end, plaintext_length
+            ),
+            ClientEncryptionError::UnexpectedLength { expected, actual } => write!(
+                f,
+                "Expected {} bytes of plaintext, but got {}",
+                expected, actual
+            ),
+            ClientEncryptionError::Stream(error) => write!(f, "Streaming failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ClientEncryptionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientEncryptionError::Random(error) => Some(error),
+            ClientEncryptionError::Stream(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use serde_json::json;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::v2::test::mock_server;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let master_key = MasterKey::generate("key_1").unwrap();
+        let plaintext: Vec<u8> = (0..100u8).collect();
+        for cipher in [Cipher::Aes256Gcm, Cipher::ChaCha20Poly1305] {
+            let encryption = FileEncryption::new(&master_key, cipher, 16).unwrap();
+            let encrypted = encryption.encrypt(&plaintext).unwrap();
+            assert_eq!(100 + 7 * 16, encrypted.len());
+            assert_eq!(encryption.encrypted_length(100), encrypted.len() as u64);
+            assert_eq!(
+                100,
+                encryption.plaintext_length(encrypted.len() as u64).unwrap()
+            );
+
+            // parts of a large file
+            let mut parts = encryption.encrypt_part(0, &plaintext[..32], false).unwrap();
+            parts.extend(encryption.encrypt_part(32, &plaintext[32..], true).unwrap());
+            assert_eq!(encrypted, parts);
+            assert!(matches!(
+                encryption.encrypt_part(0, &plaintext[..30], false),
+                Err(ClientEncryptionError::InvalidPart { .. })
+            ));
+
+            let file_info = encryption.file_info();
+            let decryption =
+                FileEncryption::from_file_info(&file_info, std::slice::from_ref(&master_key))
+                    .unwrap();
+            assert_eq!(plaintext, decryption.decrypt(&encrypted).unwrap());
+
+            let range = decryption
+                .encrypted_range(20..50, encrypted.len() as u64)
+                .unwrap();
+            assert_eq!((32, 128), (range.start(), range.end()));
+            let chunks = &encrypted[range.start() as usize..range.end() as usize];
+            assert_eq!(
+                plaintext[20..50].to_vec(),
+                decryption.decrypt_range(&range, chunks).unwrap()
+            );
+
+            let empty = encryption.encrypt(&[]).unwrap();
+            assert_eq!(16, empty.len());
+            assert!(decryption.decrypt(&empty).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_tampering() {
+        let master_key = MasterKey::generate("key_1").unwrap();
+        let encryption = FileEncryption::new(&master_key, Cipher::Aes256Gcm, 16).unwrap();
+        let plaintext = [7u8; 64];
+        let encrypted = encryption.encrypt(&plaintext).unwrap();
+
+        let mut changed = encrypted.clone();
+        changed[40] ^= 1;
+        assert!(matches!(
+            encryption.decrypt(&changed),
+            Err(ClientEncryptionError::Authentication)
+        ));
+        // cutting off the last chunk is detected, as the new last chunk is not marked as such
+        assert!(matches!(
+            encryption.decrypt(&encrypted[..96]),
+            Err(ClientEncryptionError::Authentication)
+        ));
+
+        let mut file_info = encryption.file_info();
+        file_info.insert(CHUNK_SIZE_FILE_INFO.to_owned(), "32".to_owned());
+        assert!(matches!(
+            FileEncryption::from_file_info(&file_info, std::slice::from_ref(&master_key)),
+            Err(ClientEncryptionError::Authentication)
+        ));
+        let other_key = MasterKey::new("key_1", [0u8; 32]);
+        assert!(matches!(
+            FileEncryption::from_file_info(&encryption.file_info(), &[other_key]),
+            Err(ClientEncryptionError::Authentication)
+        ));
+        assert!(matches!(
+            FileEncryption::from_file_info(&encryption.file_info(), &[]),
+            Err(ClientEncryptionError::UnknownKey { .. })
+        ));
+        assert!(!format!("{:?}", master_key).contains(&format!("{:?}", master_key.key)));
+    }
+
+    fn plaintext_stream(
+        plaintext: &[u8],
+        splits: &[usize],
+    ) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send + Sync + 'static {
+        let mut parts = Vec::new();
+        let mut start = 0;
+        for &end in splits.iter().chain(std::iter::once(&plaintext.len())) {
+            parts.push(Ok(Bytes::copy_from_slice(&plaintext[start..end])));
+            start = end;
+        }
+        futures_util::stream::iter(parts)
+    }
+
+    async fn collect<S: Stream<Item = Result<Bytes, ClientEncryptionError>>>(
+        stream: S,
+    ) -> Result<Vec<u8>, ClientEncryptionError> {
+        use futures_util::TryStreamExt;
+        let chunks: Vec<Bytes> = stream.try_collect().await?;
+        Ok(chunks.concat())
+    }
+
+    #[tokio::test]
+    async fn test_streams() {
+        let master_key = MasterKey::generate("key_1").unwrap();
+        let encryption = FileEncryption::new(&master_key, Cipher::ChaCha20Poly1305, 16).unwrap();
+        let plaintext: Vec<u8> = (0..100u8).collect();
+        let encrypted = collect(
+            encryption
+                .encrypt_stream(0, 100, true, plaintext_stream(&plaintext, &[7, 16, 57]))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(encryption.encrypt(&plaintext).unwrap(), encrypted);
+        let part = collect(
+            encryption
+                .encrypt_stream(0, 32, false, plaintext_stream(&plaintext[..32], &[16]))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            encryption.encrypt_part(0, &plaintext[..32], false).unwrap(),
+            part
+        );
+        let empty = collect(
+            encryption
+                .encrypt_stream(0, 0, true, plaintext_stream(&[], &[]))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(encryption.encrypt(&[]).unwrap(), empty);
+
+        for length in [99, 101] {
+            let res = collect(
+                encryption
+                    .encrypt_stream(0, length, true, plaintext_stream(&plaintext, &[50]))
+                    .unwrap(),
+            )
+            .await;
+            assert!(matches!(
+                res,
+                Err(ClientEncryptionError::UnexpectedLength { expected, .. }) if expected == length
+            ));
+        }
+        assert!(matches!(
+            encryption.encrypt_stream(0, 30, false, plaintext_stream(&plaintext[..30], &[])),
+            Err(ClientEncryptionError::InvalidPart { .. })
+        ));
+
+        let decrypted = collect(
+            encryption.decrypt_stream(None, plaintext_stream(&encrypted, &[1, 40, 41, 100])),
+        )
+        .await
+        .unwrap();
+        assert_eq!(plaintext, decrypted);
+        let range = encryption
+            .encrypted_range(20..50, encrypted.len() as u64)
+            .unwrap();
+        let chunks = &encrypted[range.start() as usize..range.end() as usize];
+        let decrypted =
+            collect(encryption.decrypt_stream(Some(&range), plaintext_stream(chunks, &[5])))
+                .await
+                .unwrap();
+        assert_eq!(plaintext[20..50].to_vec(), decrypted);
+        // cutting off the last chunk is detected
+        assert!(matches!(
+            collect(encryption.decrypt_stream(None, plaintext_stream(&encrypted[..96], &[]))).await,
+            Err(ClientEncryptionError::Authentication)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_upload_encrypted_file() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/upload"))
+            .and(header("X-Bz-Content-Sha1", "hex_digits_at_end"))
+            .and(header("X-Bz-Info-author", "me"))
+            .and(header("X-Bz-Info-cse_key_id", "key_1"))
+            // 100 bytes of plaintext in 7 chunks, followed by the sha1
+            .and(header("Content-Length", "252"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(mock_server::file_version(
+                    "f1",
+                    "secret.bin",
+                    "upload",
+                    None,
+                    None,
+                )),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let mut upload: UploadParameters = serde_json::from_value(json!({
+            "bucketId": mock_server::FAKE_BUCKET_ID,
+            "uploadUrl": format!("{}/upload", mock_server.uri()),
+            "authorizationToken": mock_server::FAKE_AUTHORIZATION_TOKEN,
+        }))
+        .unwrap();
+
+        let master_key = MasterKey::generate("key_1").unwrap();
+        let encryption = FileEncryption::new(&master_key, Cipher::Aes256Gcm, 16).unwrap();
+        let plaintext: Vec<u8> = (0..100u8).collect();
+        let file_name = "secret.bin".to_owned().try_into().unwrap();
+        let file_info = HashMap::from([("author".to_owned(), "me".to_owned())]);
+        let request = EncryptedUploadRequest::builder()
+            .file_name(&file_name)
+            .content_length(100)
+            .file_info(&file_info)
+            .build();
+        let file = b2_upload_encrypted_file(
+            &mut upload,
+            &request,
+            &encryption,
+            plaintext_stream(&plaintext, &[10, 60]),
+        )
+        .await
+        .unwrap();
+        assert_eq!("f1", file.file_id().unwrap().as_str());
+
+        let requests = mock_server.received_requests().await.unwrap();
+        let body = &requests[0].body;
+        let (encrypted, sha1) = body.split_at(body.len() - SHA1_HEX_BYTES as usize);
+        assert_eq!(252, body.len());
+        assert_eq!(
+            sha1::Sha1::from(encrypted).digest().to_string().as_bytes(),
+            sha1
+        );
+        assert_eq!(plaintext, encryption.decrypt(encrypted).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_upload_encrypted_part() {
+        let master_key = MasterKey::generate("key_1").unwrap();
+        let encryption = FileEncryption::new(&master_key, Cipher::Aes256Gcm, 16).unwrap();
+        let plaintext: Vec<u8> = (0..100u8).collect();
+        let encrypted_part = encryption
+            .encrypt_part(32, &plaintext[32..64], false)
+            .unwrap();
+        let sha1 = sha1::Sha1::from(&encrypted_part).digest().to_string();
+        let mut body = encrypted_part.clone();
+        body.extend(sha1.as_bytes());
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/upload_part"))
+            .and(header("X-Bz-Part-Number", "2"))
+            .and(header("X-Bz-Content-Sha1", "hex_digits_at_end"))
+            .and(wiremock::matchers::body_bytes(body))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "fileId": "f1",
+                "partNumber": 2,
+                "contentLength": encrypted_part.len(),
+                "contentSha1": sha1,
+                "uploadTimestamp": 1000,
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let mut upload: UploadPartUrlParameters = serde_json::from_value(json!({
+            "fileId": "f1",
+            "uploadUrl": format!("{}/upload_part", mock_server.uri()),
+            "authorizationToken": mock_server::FAKE_AUTHORIZATION_TOKEN,
+        }))
+        .unwrap();
+        let request = EncryptedPartRequest::builder()
+            .part_number(2.try_into().unwrap())
+            .offset(32)
+            .content_length(32)
+            .is_last(false)
+            .build();
+        let part = b2_upload_encrypted_part(
+            &mut upload,
+            &request,
+            &encryption,
+            plaintext_stream(&plaintext[32..64], &[20]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(sha1, String::from(part.content_sha1().clone()));
+    }
+
+    #[tokio::test]
+    async fn test_download_decrypted_file() {
+        let master_key = MasterKey::generate("key_1").unwrap();
+        let encryption = FileEncryption::new(&master_key, Cipher::Aes256Gcm, 16).unwrap();
+        let plaintext: Vec<u8> = (0..100u8).collect();
+        let encrypted = encryption.encrypt(&plaintext).unwrap();
+        let range = encryption
+            .encrypted_range(20..50, encrypted.len() as u64)
+            .unwrap();
+        let range_header = range.to_header();
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/b2api/v2/b2_download_file_by_id"))
+            .and(header("Range", "bytes=32-127"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .set_body_bytes(&encrypted[range.start() as usize..range.end() as usize]),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/file/bucket/secret.bin"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(encrypted.clone()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let download_url: DownloadUrl =
+            serde_json::from_value(serde_json::Value::String(mock_server.uri())).unwrap();
+        let token = AuthorizationToken(mock_server::FAKE_AUTHORIZATION_TOKEN.to_owned());
+
+        let file_id = "f1".to_owned().try_into().unwrap();
+        let params = DownloadParams::builder()
+            .file_id(&file_id)
+            .range(&range_header)
+            .build();
+        let stream = b2_download_decrypted_file_by_id(
+            &download_url,
+            Some(&token),
+            &params,
+            &encryption,
+            Some(&range),
+        )
+        .await
+        .unwrap();
+        assert_eq!(plaintext[20..50].to_vec(), collect(stream).await.unwrap());
+
+        let bucket_name = "bucket".to_owned().try_into().unwrap();
+        let file_name = "secret.bin".to_owned().try_into().unwrap();
+        let request = DownloadFileByNameRequest::builder()
+            .bucket_name(&bucket_name)
+            .file_name(&file_name)
+            .authorization(&token)
+            .build();
+        let stream = b2_download_decrypted_file_by_name(&download_url, &request, &encryption, None)
+            .await
+            .unwrap();
+        assert_eq!(plaintext, collect(stream).await.unwrap());
+
+        // a range without the matching download range is rejected before the download
+        let res =
+            b2_download_decrypted_file_by_name(&download_url, &request, &encryption, Some(&range))
+                .await;
+        assert!(matches!(
+            res,
+            Err(DownloadFileError::Unexpected {
+                raw_error: crate::v2::Error::InvalidData(_),
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,492 @@
+//! Loading application keys from the environment, profile files and the account store of the b2 command line tool,
+//! and caching the result of [b2_authorize_account] on disk.
+//!
+//! [CredentialsLoader] tries the sources in this order, the first one containing a key is used:
+//! 1. the environment variables `B2_APPLICATION_KEY_ID` and `B2_APPLICATION_KEY`
+//! 2. a named profile of a TOML or YAML file (selected by the file extension), e.g.
+//!    ```toml
+//!    [default]
+//!    application_key_id = "0014aa9865d6f00000000000b"
+//!    application_key = "K001..."
+//!    ```
+//! 3. the SQLite database of the [b2 command line tool](https://github.com/Backblaze/B2_Command_Line_Tool),
+//!    `~/.b2_account_info` or the file set in `B2_ACCOUNT_INFO`
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use typed_builder::TypedBuilder;
+
+use crate::v2::{
+    b2_authorize_account, errors::AuthorizeError, ApplicationKey, ApplicationKeyId,
+    AuthorizeAccountOk,
+};
+
+pub const APPLICATION_KEY_ID_VARIABLE: &str = "B2_APPLICATION_KEY_ID";
+pub const APPLICATION_KEY_VARIABLE: &str = "B2_APPLICATION_KEY";
+/// overrides the path of the b2 command line tool's account store
+pub const ACCOUNT_INFO_VARIABLE: &str = "B2_ACCOUNT_INFO";
+
+/// An application key with its id
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Credentials {
+    application_key_id: ApplicationKeyId,
+    application_key: ApplicationKey,
+}
+
+impl Credentials {
+    pub fn new(application_key_id: ApplicationKeyId, application_key: ApplicationKey) -> Self {
+        Self {
+            application_key_id,
+            application_key,
+        }
+    }
+
+    fn from_strings(application_key_id: String, application_key: String) -> Self {
+        // unwraps are safe, conversion of these values cannot fail
+        Self::new(
+            application_key_id.try_into().unwrap(),
+            application_key.try_into().unwrap(),
+        )
+    }
+
+    /// Credentials from `B2_APPLICATION_KEY_ID` and `B2_APPLICATION_KEY`, `None` if neither is set
+    pub fn from_env() -> Result<Option<Self>, CredentialsError> {
+        let key_id = std::env::var(APPLICATION_KEY_ID_VARIABLE).ok();
+        let key = std::env::var(APPLICATION_KEY_VARIABLE).ok();
+        match (key_id, key) {
+            (Some(key_id), Some(key)) => Ok(Some(Self::from_strings(key_id, key))),
+            (None, None) => Ok(None),
+            (None, Some(_)) => Err(CredentialsError::MissingEnvironmentVariable {
+                name: APPLICATION_KEY_ID_VARIABLE,
+            }),
+            (Some(_), None) => Err(CredentialsError::MissingEnvironmentVariable {
+                name: APPLICATION_KEY_VARIABLE,
+            }),
+        }
+    }
+
+    /// Credentials of `profile` in a TOML (`.toml`) or YAML (`.yaml`, `.yml`) file
+    pub fn from_profile_file(path: &Path, profile: &str) -> Result<Self, CredentialsError> {
+        let content = std::fs::read_to_string(path).map_err(|error| CredentialsError::Io {
+            path: path.to_owned(),
+            error,
+        })?;
+        let invalid = |message: String| CredentialsError::InvalidProfileFile {
+            path: path.to_owned(),
+            message,
+        };
+        let mut profiles: HashMap<String, Credentials> =
+            match path.extension().and_then(|extension| extension.to_str()) {
+                Some("toml") => toml::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+                Some("yaml") | Some("yml") => {
+                    serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?
+                }
+                _ => {
+                    return Err(invalid(
+                        "Unknown file format, expected a .toml, .yaml or .yml file".to_owned(),
+                    ))
+                }
+            };
+        profiles
+            .remove(profile)
+            .ok_or_else(|| CredentialsError::ProfileNotFound {
+                path: path.to_owned(),
+                profile: profile.to_owned(),
+            })
+    }
+
+    /// Credentials of the account the b2 command line tool is authorized with
+    ///
+    /// If `path` is `None`, the file set in `B2_ACCOUNT_INFO` or `~/.b2_account_info` is used.
+    pub fn from_b2_account_info(path: Option<&Path>) -> Result<Self, CredentialsError> {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None => default_account_info_path().ok_or(CredentialsError::NotFound)?,
+        };
+        let connection = rusqlite::Connection::open_with_flags(
+            &path,
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+        )
+        .map_err(|error| CredentialsError::AccountInfo {
+            path: path.clone(),
+            error,
+        })?;
+        // older versions of the tool only stored master keys, whose id is the account id
+        connection
+            .query_row(
+                "SELECT COALESCE(application_key_id, account_id), application_key FROM account",
+                [],
+                |row| Ok(Self::from_strings(row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|error| CredentialsError::AccountInfo { path, error })
+    }
+
+    /// Get a reference to the credentials's application key id.
+    pub fn application_key_id(&self) -> &ApplicationKeyId {
+        &self.application_key_id
+    }
+
+    /// Get a reference to the credentials's application key.
+    pub fn application_key(&self) -> &ApplicationKey {
+        &self.application_key
+    }
+
+    /// Calls [b2_authorize_account] with these credentials
+    pub async fn authorize(&self) -> Result<AuthorizeAccountOk, AuthorizeError> {
+        b2_authorize_account(&self.application_key_id, &self.application_key).await
+    }
+}
+
+fn default_account_info_path() -> Option<PathBuf> {
+    match std::env::var_os(ACCOUNT_INFO_VARIABLE) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => home::home_dir().map(|home| home.join(".b2_account_info")),
+    }
+}
+
+/// Loads [Credentials] from the first source containing them, see the [module documentation](self)
+#[derive(Debug, TypedBuilder)]
+pub struct CredentialsLoader<'s> {
+    /// read `B2_APPLICATION_KEY_ID` and `B2_APPLICATION_KEY`
+    #[builder(default = true)]
+    environment: bool,
+
+    #[builder(default, setter(strip_option))]
+    profile_file: Option<&'s Path>,
+
+    /// profile to read from `profile_file`
+    #[builder(default = "default")]
+    profile: &'s str,
+
+    /// read the account store of the b2 command line tool, if it exists
+    #[builder(default = true)]
+    b2_account_info: bool,
+
+    /// path of the account store, `B2_ACCOUNT_INFO` or `~/.b2_account_info` if not set
+    #[builder(default, setter(strip_option))]
+    b2_account_info_path: Option<&'s Path>,
+}
+
+impl<'s> CredentialsLoader<'s> {
+    pub fn load(&self) -> Result<Credentials, CredentialsError> {
+        if self.environment {
+            if let Some(credentials) = Credentials::from_env()? {
+                return Ok(credentials);
+            }
+        }
+        if let Some(profile_file) = self.profile_file {
+            return Credentials::from_profile_file(profile_file, self.profile);
+        }
+        if self.b2_account_info {
+            let path = self
+                .b2_account_info_path
+                .map(Path::to_owned)
+                .or_else(default_account_info_path);
+            if let Some(path) = path.filter(|path| path.exists()) {
+                return Credentials::from_b2_account_info(Some(&path));
+            }
+        }
+        Err(CredentialsError::NotFound)
+    }
+}
+
+#[derive(Debug)]
+pub enum CredentialsError {
+    /// None of the sources contained credentials
+    NotFound,
+    /// Only one of `B2_APPLICATION_KEY_ID` and `B2_APPLICATION_KEY` is set
+    MissingEnvironmentVariable {
+        name: &'static str,
+    },
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    InvalidProfileFile {
+        path: PathBuf,
+        message: String,
+    },
+    ProfileNotFound {
+        path: PathBuf,
+        profile: String,
+    },
+    /// The account store of the b2 command line tool could not be read
+    AccountInfo {
+        path: PathBuf,
+        error: rusqlite::Error,
+    },
+}
+
+impl Display for CredentialsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CredentialsError::NotFound => write!(f, "No credentials found"),
+            CredentialsError::MissingEnvironmentVariable { name } => {
+                write!(f, "Environment variable {} is not set", name)
+            }
+            CredentialsError::Io { path, error } => {
+                write!(f, "Could not read {}: {}", path.display(), error)
+            }
+            CredentialsError::InvalidProfileFile { path, message } => {
+                write!(f, "Invalid profile file {}: {}", path.display(), message)
+            }
+            CredentialsError::ProfileNotFound { path, profile } => {
+                write!(f, "Profile {} not found in {}", profile, path.display())
+            }
+            CredentialsError::AccountInfo { path, error } => write!(
+                f,
+                "Could not read b2 account info {}: {}",
+                path.display(),
+                error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CredentialsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CredentialsError::Io { error, .. } => Some(error),
+            CredentialsError::AccountInfo { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// content of a [TokenCache] file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedAuthorization<Authorization> {
+    application_key_id: ApplicationKeyId,
+    /// seconds since the unix epoch
+    expires: u64,
+    authorization: Authorization,
+}
+
+/// makes the names of temporary cache files unique within the process
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Caches the result of [b2_authorize_account] in a YAML file, so short lived processes can skip authorizing
+///
+/// The file contains the authorization token, it is created readable by the current user only (on unix).
+#[derive(Debug, Clone)]
+pub struct TokenCache {
+    path: PathBuf,
+    max_age: Duration,
+}
+
+impl TokenCache {
+    /// authorization tokens are valid for 24 hours, an hour is left as margin for running calls
+    pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(23 * 60 * 60);
+
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            max_age: Self::DEFAULT_MAX_AGE,
+        }
+    }
+
+    /// Cache whose entries expire after `max_age`
+    pub fn with_max_age(path: PathBuf, max_age: Duration) -> Self {
+        Self { path, max_age }
+    }
+
+    /// Get a reference to the token cache's path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Cached authorization for `credentials`, `None` if there is none, it expired or the file cannot be read
+    pub fn load(&self, credentials: &Credentials) -> Option<AuthorizeAccountOk> {
+        let file = std::fs::File::open(&self.path).ok()?;
+        let cached: CachedAuthorization<AuthorizeAccountOk> = serde_yaml::from_reader(file).ok()?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        if cached.application_key_id == credentials.application_key_id && now < cached.expires {
+            Some(cached.authorization)
+        } else {
+            None
+        }
+    }
+
+    /// Stores an authorization for `credentials`, which has just been received.
+    ///
+    /// The cache is written to a temporary file in the same directory, that replaces the cache file once complete,
+    /// so the token is never readable by others and concurrent readers never see a partially written file.
+    pub fn store(
+        &self,
+        credentials: &Credentials,
+        authorization: &AuthorizeAccountOk,
+    ) -> std::io::Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let cached = CachedAuthorization {
+            application_key_id: credentials.application_key_id.clone(),
+            expires: (now + self.max_age).as_secs(),
+            authorization,
+        };
+        let content = serde_yaml::to_string(&cached)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let mut temp_name = self.path.file_name().unwrap_or_default().to_owned();
+        temp_name.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temp_path = self.path.with_file_name(temp_name);
+        // a leftover of a crashed process may have other permissions, which are only set on creation
+        let _ = std::fs::remove_file(&temp_path);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let written = options.open(&temp_path).and_then(|mut file| {
+            std::io::Write::write_all(&mut file, content.as_bytes())?;
+            file.sync_all()
+        });
+        match written.and_then(|_| std::fs::rename(&temp_path, &self.path)) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                let _ = std::fs::remove_file(&temp_path);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the cached authorization, or authorizes with `credentials` and caches the result.
+    ///
+    /// Failing to write the cache is ignored, as the authorization itself succeeded.
+    pub async fn authorize(
+        &self,
+        credentials: &Credentials,
+    ) -> Result<AuthorizeAccountOk, AuthorizeError> {
+        if let Some(authorization) = self.load(credentials) {
+            return Ok(authorization);
+        }
+        let authorization = credentials.authorize().await?;
+        let _ = self.store(credentials, &authorization);
+        Ok(authorization)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::v2::test::mock_server::fake_authorization;
+
+    /// path in the temp directory, unique for this process & test
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "backblaze_b2_async_plain_{}_{}",
+            std::process::id(),
+            name
+        ))
+    }
+
+    fn credentials(key_id: &str) -> Credentials {
+        Credentials::from_strings(key_id.to_owned(), "K001secret".to_owned())
+    }
+
+    #[test]
+    fn test_profile_files() {
+        let toml_path = temp_path("profiles.toml");
+        std::fs::write(
+            &toml_path,
+            "[default]\napplication_key_id = \"id_default\"\napplication_key = \"K001secret\"\n\n\
+             [backup]\napplication_key_id = \"id_backup\"\napplication_key = \"K001secret\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            credentials("id_backup"),
+            Credentials::from_profile_file(&toml_path, "backup").unwrap()
+        );
+        let loader = CredentialsLoader::builder()
+            .environment(false)
+            .profile_file(&toml_path)
+            .build();
+        assert_eq!(credentials("id_default"), loader.load().unwrap());
+        assert!(matches!(
+            Credentials::from_profile_file(&toml_path, "missing"),
+            Err(CredentialsError::ProfileNotFound { .. })
+        ));
+
+        let yaml_path = temp_path("profiles.yaml");
+        std::fs::write(
+            &yaml_path,
+            "default:\n  application_key_id: id_default\n  application_key: K001secret\n",
+        )
+        .unwrap();
+        assert_eq!(
+            credentials("id_default"),
+            Credentials::from_profile_file(&yaml_path, "default").unwrap()
+        );
+        std::fs::remove_file(toml_path).unwrap();
+        std::fs::remove_file(yaml_path).unwrap();
+    }
+
+    #[test]
+    fn test_b2_account_info() {
+        let path = temp_path("b2_account_info");
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE account (account_id TEXT NOT NULL, application_key TEXT NOT NULL, \
+                 account_auth_token TEXT NOT NULL, api_url TEXT NOT NULL, download_url TEXT NOT NULL, \
+                 minimum_part_size INT NOT NULL, realm TEXT NOT NULL, application_key_id TEXT);
+                 INSERT INTO account VALUES ('account', 'K001secret', 'token', 'https://api', \
+                 'https://download', 100, 'production', 'id_cli');",
+            )
+            .unwrap();
+        drop(connection);
+        let loader = CredentialsLoader::builder()
+            .environment(false)
+            .b2_account_info_path(&path)
+            .build();
+        assert_eq!(credentials("id_cli"), loader.load().unwrap());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_token_cache() {
+        let path = temp_path("token_cache.yaml");
+        let authorization = fake_authorization("https://api001.backblazeb2.com", &["listFiles"]);
+        let cache = TokenCache::new(path.clone());
+        assert!(cache.load(&credentials("id")).is_none());
+        cache.store(&credentials("id"), &authorization).unwrap();
+        #[cfg(unix)]
+        assert_eq!(
+            0o600,
+            std::os::unix::fs::PermissionsExt::mode(
+                &std::fs::metadata(&path).unwrap().permissions()
+            ) & 0o777
+        );
+        // the temporary file was renamed
+        let temp_prefix = format!("{}.", path.file_name().unwrap().to_str().unwrap());
+        assert!(!std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .any(|entry| entry
+                .unwrap()
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with(&temp_prefix))));
+        let cached = cache.load(&credentials("id")).unwrap();
+        assert_eq!(
+            authorization.authorization_token(),
+            cached.authorization_token()
+        );
+        // a different key must not use the token
+        assert!(cache.load(&credentials("other_id")).is_none());
+
+        let expired = TokenCache::with_max_age(path.clone(), Duration::ZERO);
+        expired.store(&credentials("id"), &authorization).unwrap();
+        assert!(expired.load(&credentials("id")).is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+}
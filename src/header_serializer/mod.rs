@@ -2,6 +2,7 @@ mod serde_header_serializer;
 
 use serde::Serialize;
 pub use serde_header_serializer::HeaderSerialzier;
+pub(crate) use serde_header_serializer::{url_decode, url_encode};
 
 pub trait HeadersFrom {
     fn headers_from<V: Serialize>(self, value: V) -> Self;
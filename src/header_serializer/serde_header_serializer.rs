@@ -9,7 +9,7 @@ use serde::{
 };
 use typed_builder::TypedBuilder;
 
-use self::url_encoder::url_encode;
+pub use self::url_encoder::{url_decode, url_encode};
 
 #[derive(Debug)]
 pub enum HeaderSerialzierError {
@@ -95,7 +95,7 @@ impl HeaderSerialzier {
     }
 }
 
-impl<'a> ser::Serializer for &'a mut HeaderSerialzier {
+impl ser::Serializer for &mut HeaderSerialzier {
     type Ok = ();
     type Error = HeaderSerialzierError;
 
@@ -172,9 +172,9 @@ impl<'a> ser::Serializer for &'a mut HeaderSerialzier {
         Ok(())
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(self)
     }
@@ -196,19 +196,19 @@ impl<'a> ser::Serializer for &'a mut HeaderSerialzier {
         panic!("Not supported");
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T>(
         self,
         _name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         //ignore the newtype, just serialize the value
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -216,7 +216,7 @@ impl<'a> ser::Serializer for &'a mut HeaderSerialzier {
         _value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         panic!("Not supported");
     }
@@ -276,13 +276,9 @@ impl<'a> ser::SerializeStruct for &'a mut HeaderSerialzier {
 
     type Error = <&'a mut HeaderSerialzier as ser::Serializer>::Error;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         self.current_field_name = Some(key);
         value.serialize(&mut **self)
@@ -297,9 +293,9 @@ impl<'a> ser::SerializeSeq for &'a mut HeaderSerialzier {
     type Ok = <&'a mut HeaderSerialzier as ser::Serializer>::Ok;
     type Error = <&'a mut HeaderSerialzier as ser::Serializer>::Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, _value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         panic!("Not supported");
     }
@@ -313,16 +309,16 @@ impl<'a> ser::SerializeMap for &'a mut HeaderSerialzier {
     type Ok = <&'a mut HeaderSerialzier as ser::Serializer>::Ok;
     type Error = <&'a mut HeaderSerialzier as ser::Serializer>::Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> Result<(), Self::Error>
+    fn serialize_key<T>(&mut self, _key: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         panic!("Not supported");
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error>
+    fn serialize_value<T>(&mut self, _value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         panic!("Not supported");
     }
@@ -336,9 +332,9 @@ impl<'a> ser::SerializeTuple for &'a mut HeaderSerialzier {
     type Ok = <&'a mut HeaderSerialzier as ser::Serializer>::Ok;
     type Error = <&'a mut HeaderSerialzier as ser::Serializer>::Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, _value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         panic!("Not supported");
     }
@@ -352,9 +348,9 @@ impl<'a> ser::SerializeTupleStruct for &'a mut HeaderSerialzier {
     type Ok = <&'a mut HeaderSerialzier as ser::Serializer>::Ok;
     type Error = <&'a mut HeaderSerialzier as ser::Serializer>::Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, _value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         panic!("Not supported");
     }
@@ -368,9 +364,9 @@ impl<'a> ser::SerializeTupleVariant for &'a mut HeaderSerialzier {
     type Ok = <&'a mut HeaderSerialzier as ser::Serializer>::Ok;
     type Error = <&'a mut HeaderSerialzier as ser::Serializer>::Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, _value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         panic!("Not supported");
     }
@@ -384,13 +380,9 @@ impl<'a> ser::SerializeStructVariant for &'a mut HeaderSerialzier {
     type Ok = <&'a mut HeaderSerialzier as ser::Serializer>::Ok;
     type Error = <&'a mut HeaderSerialzier as ser::Serializer>::Error;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        _key: &'static str,
-        _value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, _key: &'static str, _value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         panic!("Not supported");
     }
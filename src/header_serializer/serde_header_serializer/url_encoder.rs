@@ -1,9 +1,9 @@
-/// custom URL encoder based on [backblaze url encoding](https://www.backblaze.com/b2/docs/string_encoding.html)
-/// urlencoder crate can not be used, as for example it does encode '/' which prevents b2 from realizing it as a folder separator
+//! custom URL encoder/decoder based on [backblaze url encoding](https://www.backblaze.com/b2/docs/string_encoding.html)
+//! urlencoder crate can not be used, as for example it does encode '/' which prevents b2 from realizing it as a folder separator
 
 const ALLOWED_SPECIAL_CHARS: &str = "._-/~!$'()*;=:@";
 pub fn url_encode(s: &str) -> String {
-    let mut res_elems = Vec::with_capacity(s.as_bytes().len());
+    let mut res_elems = Vec::with_capacity(s.len());
     for c in s.chars() {
         if c.is_ascii_alphanumeric() || ALLOWED_SPECIAL_CHARS.contains(c) {
             res_elems.push(String::from(c));
@@ -17,10 +17,53 @@ pub fn url_encode(s: &str) -> String {
     res_elems.concat()
 }
 
+/// decodes values encoded acc. to [backblaze url encoding](https://www.backblaze.com/b2/docs/string_encoding.html),
+/// e.g. file names in response headers. Returns `None` if the value is not validly encoded.
+pub fn url_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => res.push(b' '),
+            b'%' => {
+                let hex = s.get(i + 1..i + 3)?;
+                // from_str_radix alone would accept a sign, e.g. `%+F`
+                if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return None;
+                }
+                res.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 2;
+            }
+            b => res.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8(res).ok()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_decode() {
+        for test_case in TEST_DATA_SETS {
+            assert_eq!(
+                Some(test_case.string),
+                url_decode(test_case.fully_encoded).as_deref()
+            );
+            assert_eq!(
+                Some(test_case.string),
+                url_decode(test_case.minimally_encoded).as_deref()
+            );
+        }
+        assert_eq!(None, url_decode("%2"));
+        assert_eq!(None, url_decode("%zz"));
+        assert_eq!(None, url_decode("%+F"));
+        assert_eq!(None, url_decode("%-1"));
+    }
+
     #[test]
     fn test_encode() {
         for test_case in TEST_DATA_SETS {
@@ -1,2 +1,14 @@
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "client_encryption")]
+pub mod client_encryption;
+#[cfg(feature = "credentials")]
+pub mod credentials;
 mod header_serializer;
+pub mod metrics;
+#[cfg(feature = "rate_limit")]
+pub mod rate_limit;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod transport;
 pub mod v2;
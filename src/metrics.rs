@@ -0,0 +1,345 @@
+//! Hooks to account the transactions of all calls of this crate.
+//!
+//! B2 bills the [TransactionClass]es A, B and C differently. A [MetricsHook] is called for every
+//! request sent through the [crate::transport]. Like the transport, it can be set for the whole
+//! process with [set_metrics_hook], or for a single future with [with_metrics_hook].
+//!
+//! [TransactionCounter] counts transactions and bytes, and optionally refuses Class C transactions
+//! past a budget, so that e.g. a listing loop cannot exceed the transaction cap of the account.
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Duration,
+};
+
+use lazy_static::lazy_static;
+
+use crate::v2::{Endpoint, TransactionClass};
+
+/// Result of a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestOutcome {
+    /// A response was received, B2 reports errors with a status other than success
+    Response(http::StatusCode),
+    /// No response was received (e.g. the connection failed)
+    TransportError,
+}
+
+impl RequestOutcome {
+    pub fn is_success(&self) -> bool {
+        matches!(self, RequestOutcome::Response(status) if status.is_success())
+    }
+}
+
+/// Metrics of a single request, passed to [MetricsHook::on_request]
+#[derive(Debug, Clone)]
+pub struct RequestMetrics {
+    endpoint: Option<Endpoint>,
+    bytes_sent: Option<u64>,
+    bytes_received: Option<u64>,
+    latency: Duration,
+    outcome: RequestOutcome,
+}
+
+impl RequestMetrics {
+    pub(crate) fn new(
+        endpoint: Option<Endpoint>,
+        bytes_sent: Option<u64>,
+        bytes_received: Option<u64>,
+        latency: Duration,
+        outcome: RequestOutcome,
+    ) -> Self {
+        Self {
+            endpoint,
+            bytes_sent,
+            bytes_received,
+            latency,
+            outcome,
+        }
+    }
+
+    /// Get the request metrics's endpoint, `None` if the url does not belong to the B2 api.
+    pub fn endpoint(&self) -> Option<Endpoint> {
+        self.endpoint
+    }
+
+    /// Get the request metrics's transaction class.
+    pub fn transaction_class(&self) -> Option<TransactionClass> {
+        self.endpoint.map(|endpoint| endpoint.transaction_class())
+    }
+
+    /// Get the request metrics's bytes sent, `None` if the length of a streamed body is unknown.
+    pub fn bytes_sent(&self) -> Option<u64> {
+        self.bytes_sent
+    }
+
+    /// Get the request metrics's bytes received, as declared by the `Content-Length` of the response.
+    ///
+    /// The body of downloads is streamed after the request finished, so this is the expected and not
+    /// necessarily the transferred size.
+    pub fn bytes_received(&self) -> Option<u64> {
+        self.bytes_received
+    }
+
+    /// Get the request metrics's latency, until the headers of the response were received.
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Get the request metrics's outcome.
+    pub fn outcome(&self) -> RequestOutcome {
+        self.outcome
+    }
+}
+
+/// A [MetricsHook] refused to send a request, as its transaction budget is used up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetExceeded {
+    endpoint: Endpoint,
+    transaction_class: TransactionClass,
+    limit: u64,
+}
+
+impl BudgetExceeded {
+    pub fn new(endpoint: Endpoint, limit: u64) -> Self {
+        Self {
+            endpoint,
+            transaction_class: endpoint.transaction_class(),
+            limit,
+        }
+    }
+
+    /// Get the budget exceeded's endpoint.
+    pub fn endpoint(&self) -> Endpoint {
+        self.endpoint
+    }
+
+    /// Get the budget exceeded's transaction class.
+    pub fn transaction_class(&self) -> TransactionClass {
+        self.transaction_class
+    }
+
+    /// Get the budget exceeded's limit.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+}
+
+impl Display for BudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Budget of {} Class {:?} transactions exceeded, {} was not called",
+            self.limit,
+            self.transaction_class,
+            self.endpoint.name()
+        )
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+/// Called for every request of this crate
+pub trait MetricsHook: Debug + Send + Sync {
+    /// Called before a request to `endpoint` is sent, returning an error refuses the request.
+    ///
+    /// Requests to urls not belonging to the B2 api are not passed to this function.
+    fn before_request(&self, _endpoint: Endpoint) -> Result<(), BudgetExceeded> {
+        Ok(())
+    }
+
+    /// Called after a request was sent, once the response headers were received or the request failed
+    fn on_request(&self, metrics: &RequestMetrics);
+}
+
+/// Counts the transactions per class and endpoint and the transferred bytes
+///
+/// Transactions are counted in [MetricsHook::before_request], so they include requests which failed
+/// later on. With a Class C budget, all Class C requests after the budget is used up are refused.
+#[derive(Debug, Default)]
+pub struct TransactionCounter {
+    class_a: AtomicU64,
+    class_b: AtomicU64,
+    class_c: AtomicU64,
+    failed_requests: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    per_endpoint: Mutex<HashMap<Endpoint, u64>>,
+    class_c_budget: Option<u64>,
+}
+
+impl TransactionCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counter refusing Class C transactions once `limit` of them were sent
+    pub fn with_class_c_budget(limit: u64) -> Self {
+        Self {
+            class_c_budget: Some(limit),
+            ..Default::default()
+        }
+    }
+
+    fn class_counter(&self, transaction_class: TransactionClass) -> &AtomicU64 {
+        match transaction_class {
+            TransactionClass::A => &self.class_a,
+            TransactionClass::B => &self.class_b,
+            TransactionClass::C => &self.class_c,
+        }
+    }
+
+    /// number of transactions of a class
+    pub fn transactions(&self, transaction_class: TransactionClass) -> u64 {
+        self.class_counter(transaction_class)
+            .load(Ordering::Relaxed)
+    }
+
+    /// number of transactions of an endpoint
+    pub fn endpoint_transactions(&self, endpoint: Endpoint) -> u64 {
+        self.per_endpoint
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&endpoint)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// number of requests, which did not succeed (including error responses)
+    pub fn failed_requests(&self) -> u64 {
+        self.failed_requests.load(Ordering::Relaxed)
+    }
+
+    /// Get the transaction counter's total bytes sent (of requests with a known length).
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Ordering::Relaxed)
+    }
+
+    /// Get the transaction counter's total bytes received (as declared by the responses).
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Ordering::Relaxed)
+    }
+
+    /// Get the transaction counter's class c budget.
+    pub fn class_c_budget(&self) -> Option<u64> {
+        self.class_c_budget
+    }
+}
+
+impl MetricsHook for TransactionCounter {
+    fn before_request(&self, endpoint: Endpoint) -> Result<(), BudgetExceeded> {
+        let transaction_class = endpoint.transaction_class();
+        let counter = self.class_counter(transaction_class);
+        match (transaction_class, self.class_c_budget) {
+            (TransactionClass::C, Some(limit)) => {
+                // only increased if the budget allows it, so concurrent calls cannot exceed it
+                counter
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                        (count < limit).then(|| count + 1)
+                    })
+                    .map_err(|_| BudgetExceeded::new(endpoint, limit))?;
+            }
+            _ => {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        *self
+            .per_endpoint
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(endpoint)
+            .or_default() += 1;
+        Ok(())
+    }
+
+    fn on_request(&self, metrics: &RequestMetrics) {
+        if !metrics.outcome.is_success() {
+            self.failed_requests.fetch_add(1, Ordering::Relaxed);
+        }
+        self.bytes_sent
+            .fetch_add(metrics.bytes_sent.unwrap_or_default(), Ordering::Relaxed);
+        self.bytes_received.fetch_add(
+            metrics.bytes_received.unwrap_or_default(),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+lazy_static! {
+    static ref DEFAULT_METRICS_HOOK: RwLock<Option<Arc<dyn MetricsHook>>> = RwLock::new(None);
+}
+
+tokio::task_local! {
+    static SCOPED_METRICS_HOOK: Arc<dyn MetricsHook>;
+}
+
+/// Sets the hook called by all requests, that are not run inside of [with_metrics_hook], `None` removes it
+pub fn set_metrics_hook(hook: Option<Arc<dyn MetricsHook>>) {
+    // a poisoned lock still contains a valid hook, as it is replaced in a single step
+    let mut default = DEFAULT_METRICS_HOOK
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *default = hook;
+}
+
+/// Runs `future` with all requests inside of it calling `hook`
+pub async fn with_metrics_hook<F: Future>(hook: Arc<dyn MetricsHook>, future: F) -> F::Output {
+    SCOPED_METRICS_HOOK.scope(hook, future).await
+}
+
+/// hook to be called for the current request
+pub fn current_metrics_hook() -> Option<Arc<dyn MetricsHook>> {
+    SCOPED_METRICS_HOOK.try_with(Arc::clone).ok().or_else(|| {
+        DEFAULT_METRICS_HOOK
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use super::*;
+    use crate::v2::{
+        b2_list_file_names, errors::ListFileNamesError, test::mock_server::*, ApiUrl,
+        AuthorizationToken, ListFileNamesRequest,
+    };
+
+    #[tokio::test]
+    async fn test_class_c_budget() {
+        let mock_server = B2MockServer::start().await;
+        mock_server.register_default_list_file_names_handler().await;
+        let counter = Arc::new(TransactionCounter::with_class_c_budget(1));
+        let bucket_id = FAKE_BUCKET_ID.to_owned().try_into().unwrap();
+        let request = ListFileNamesRequest::builder()
+            .bucket_id(&bucket_id)
+            .build();
+        let api_url = ApiUrl(mock_server.uri());
+        let authorization_token = AuthorizationToken(FAKE_AUTHORIZATION_TOKEN.into());
+        let list = || b2_list_file_names(&api_url, &authorization_token, &request);
+
+        let res = with_metrics_hook(counter.clone(), list()).await;
+        assert!(res.is_ok());
+        assert_eq!(1, counter.transactions(TransactionClass::C));
+        assert_eq!(1, counter.endpoint_transactions(Endpoint::ListFileNames));
+        assert_eq!(0, counter.failed_requests());
+        assert!(counter.bytes_sent() > 0);
+
+        let res = with_metrics_hook(counter.clone(), list()).await;
+        match res {
+            Err(ListFileNamesError::Unexpected {
+                raw_error: crate::v2::Error::BudgetExceeded(exceeded),
+                retry: None,
+            }) => assert_eq!(BudgetExceeded::new(Endpoint::ListFileNames, 1), exceeded),
+            other => panic!("Expected BudgetExceeded, got {:?}", other),
+        }
+        assert_eq!(1, counter.transactions(TransactionClass::C));
+    }
+}
@@ -0,0 +1,298 @@
+//! Client side bandwidth and request rate limits.
+//!
+//! A [RateLimiter] holds two token buckets: one for the bytes of uploads ([crate::v2::b2_upload_file],
+//! [crate::v2::b2_upload_part]) and downloads, and one for the number of requests to the B2 api. Both
+//! are shared by all concurrent calls using the same limiter and can be changed while calls are running.
+//! Like the transport, the limiter is set for the whole process with [set_rate_limiter], or for a
+//! single future with [with_rate_limiter].
+//!
+//! Upload bodies created from bytes (e.g. a `Vec<u8>`) and download bodies are throttled automatically.
+//! Bodies created with [reqwest::Body::wrap_stream] cannot be inspected, wrap the stream with
+//! [RateLimiter::throttle_stream] before passing it to the upload instead.
+//! Throttled download responses keep the `Content-Length` header of the original response, but do not
+//! report a [reqwest::Response::content_length] and no [reqwest::Response::url], use the header instead.
+use std::{
+    convert::Infallible,
+    future::Future,
+    num::{NonZeroU32, NonZeroU64},
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures_util::{stream, Stream, StreamExt, TryStream, TryStreamExt};
+use lazy_static::lazy_static;
+use tokio::time::Instant;
+
+/// size of the chunks in which throttled bodies are sent
+const CHUNK_SIZE: usize = 16 * 1024;
+
+#[derive(Debug)]
+struct TokenBucket {
+    /// tokens per second, also the maximum number of tokens, i.e. bursts of up to one second are allowed
+    rate: f64,
+    /// negative if more tokens were reserved than available
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64) -> Self {
+        Self {
+            rate,
+            tokens: rate,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+    }
+
+    fn set_rate(&mut self, rate: f64) {
+        self.refill();
+        self.rate = rate;
+        self.tokens = self.tokens.min(rate);
+    }
+
+    /// reserves `amount` tokens, returns how long to wait until they are available
+    fn reserve(&mut self, amount: f64) -> Duration {
+        self.refill();
+        self.tokens -= amount;
+        if self.tokens < 0.0 {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Limit(Mutex<Option<TokenBucket>>);
+
+impl Limit {
+    fn set(&self, rate: Option<f64>) {
+        let mut bucket = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match (bucket.as_mut(), rate) {
+            (Some(bucket), Some(rate)) => bucket.set_rate(rate),
+            (_, rate) => *bucket = rate.map(TokenBucket::new),
+        }
+    }
+
+    fn rate(&self) -> Option<f64> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_ref()
+            .map(|bucket| bucket.rate)
+    }
+
+    async fn acquire(&self, amount: f64) {
+        let wait = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_mut()
+            .map(|bucket| bucket.reserve(amount))
+            .unwrap_or_default();
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Token bucket limits for bandwidth and requests, see the [module documentation](self)
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    bandwidth: Limit,
+    requests: Limit,
+}
+
+impl RateLimiter {
+    /// Limiter without any limits, set them with [RateLimiter::set_bandwidth_limit] and [RateLimiter::set_request_rate_limit]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum bytes per second of all uploads and downloads, `None` removes the limit
+    pub fn set_bandwidth_limit(&self, bytes_per_second: Option<NonZeroU64>) {
+        self.bandwidth
+            .set(bytes_per_second.map(|limit| limit.get() as f64));
+    }
+
+    /// Get the rate limiter's bandwidth limit in bytes per second.
+    pub fn bandwidth_limit(&self) -> Option<u64> {
+        self.bandwidth.rate().map(|rate| rate as u64)
+    }
+
+    /// Sets the maximum number of requests per second, `None` removes the limit
+    pub fn set_request_rate_limit(&self, requests_per_second: Option<NonZeroU32>) {
+        self.requests
+            .set(requests_per_second.map(|limit| limit.get() as f64));
+    }
+
+    /// Get the rate limiter's request rate limit in requests per second.
+    pub fn request_rate_limit(&self) -> Option<u32> {
+        self.requests.rate().map(|rate| rate as u32)
+    }
+
+    /// waits until the next request may be sent
+    pub async fn acquire_request(&self) {
+        self.requests.acquire(1.0).await
+    }
+
+    /// waits until `bytes` may be transferred
+    pub async fn acquire_bytes(&self, bytes: usize) {
+        self.bandwidth.acquire(bytes as f64).await
+    }
+
+    /// Throttles a stream of chunks to the bandwidth limit of this limiter, e.g. for the body of an upload
+    pub fn throttle_stream<S>(
+        self: &Arc<Self>,
+        stream: S,
+    ) -> impl Stream<Item = Result<S::Ok, S::Error>> + Send + Sync + 'static
+    where
+        S: TryStream + Send + Sync + 'static,
+        S::Ok: AsRef<[u8]> + Send + Sync,
+        S::Error: Send + Sync,
+    {
+        let limiter = Arc::clone(self);
+        stream.into_stream().then(move |chunk| {
+            let limiter = Arc::clone(&limiter);
+            async move {
+                if let Ok(chunk) = &chunk {
+                    limiter.acquire_bytes(chunk.as_ref().len()).await;
+                }
+                chunk
+            }
+        })
+    }
+
+    /// body sending the bytes of `body` in chunks throttled to the bandwidth limit,
+    /// streamed bodies are returned unchanged
+    pub(crate) fn throttle_body(self: &Arc<Self>, body: reqwest::Body) -> reqwest::Body {
+        let len = match body.as_bytes() {
+            Some(bytes) => bytes.len(),
+            None => return body,
+        };
+        // the chunks are copied one at a time, so large parts are not held in memory twice
+        let chunks = stream::iter((0..len).step_by(CHUNK_SIZE)).map(move |start| {
+            // unwrap is safe, the body was checked to consist of bytes above
+            let bytes = body.as_bytes().unwrap();
+            Ok::<_, Infallible>(Bytes::copy_from_slice(
+                &bytes[start..(start + CHUNK_SIZE).min(len)],
+            ))
+        });
+        reqwest::Body::wrap_stream(self.throttle_stream(chunks))
+    }
+
+    /// response with its body throttled to the bandwidth limit
+    pub(crate) fn throttle_response(
+        self: &Arc<Self>,
+        response: reqwest::Response,
+    ) -> reqwest::Response {
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+            // the length of the streamed body is unknown to reqwest, so it is only kept in the header
+            if let Some(length) = response.content_length() {
+                headers.insert(http::header::CONTENT_LENGTH, length.into());
+            }
+        }
+        let body = reqwest::Body::wrap_stream(self.throttle_stream(response.bytes_stream()));
+        // unwrap is safe, status and version were taken from a valid response
+        reqwest::Response::from(builder.body(body).unwrap())
+    }
+}
+
+lazy_static! {
+    static ref DEFAULT_RATE_LIMITER: RwLock<Option<Arc<RateLimiter>>> = RwLock::new(None);
+}
+
+tokio::task_local! {
+    static SCOPED_RATE_LIMITER: Arc<RateLimiter>;
+}
+
+/// Sets the limiter used by all calls, that are not run inside of [with_rate_limiter], `None` removes it
+pub fn set_rate_limiter(limiter: Option<Arc<RateLimiter>>) {
+    // a poisoned lock still contains a valid limiter, as it is replaced in a single step
+    let mut default = DEFAULT_RATE_LIMITER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *default = limiter;
+}
+
+/// Runs `future` with all calls inside of it limited by `limiter`
+pub async fn with_rate_limiter<F: Future>(limiter: Arc<RateLimiter>, future: F) -> F::Output {
+    SCOPED_RATE_LIMITER.scope(limiter, future).await
+}
+
+/// limiter to be used for the current call
+pub fn current_rate_limiter() -> Option<Arc<RateLimiter>> {
+    SCOPED_RATE_LIMITER.try_with(Arc::clone).ok().or_else(|| {
+        DEFAULT_RATE_LIMITER
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bandwidth_limit() {
+        let limiter = Arc::new(RateLimiter::new());
+        limiter.set_bandwidth_limit(NonZeroU64::new(1000));
+        let start = Instant::now();
+        // the first second is available as burst
+        limiter.acquire_bytes(1000).await;
+        limiter.acquire_bytes(2000).await;
+        assert!(start.elapsed() >= Duration::from_secs(2));
+
+        limiter.set_bandwidth_limit(None);
+        let start = Instant::now();
+        limiter.acquire_bytes(1_000_000).await;
+        assert_eq!(Duration::ZERO, start.elapsed());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttle_body() {
+        let limiter = Arc::new(RateLimiter::new());
+        limiter.set_bandwidth_limit(NonZeroU64::new(CHUNK_SIZE as u64));
+        let body = reqwest::Body::from(vec![1u8; 3 * CHUNK_SIZE]);
+        let start = Instant::now();
+        let response = limiter.throttle_response(reqwest::Response::from(
+            http::Response::builder()
+                .body(limiter.throttle_body(body))
+                .unwrap(),
+        ));
+        assert_eq!(3 * CHUNK_SIZE, response.bytes().await.unwrap().len());
+        // uploaded and downloaded with 1 second of burst each
+        assert!(start.elapsed() >= Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_throttle_response_content_length() {
+        let limiter = Arc::new(RateLimiter::new());
+        let response =
+            limiter.throttle_response(reqwest::Response::from(http::Response::new(vec![
+                1u8;
+                CHUNK_SIZE
+            ])));
+        assert_eq!(None, response.content_length());
+        assert_eq!(
+            Some(CHUNK_SIZE as u64),
+            crate::transport::response_content_length(&response)
+        );
+    }
+}
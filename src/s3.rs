@@ -0,0 +1,110 @@
+//! S3 compatible API of Backblaze B2, see [official documentation](https://www.backblaze.com/b2/docs/s3_compatible_api.html)
+//!
+//! Uses the same types as [crate::v2] (e.g. [BucketName], [FileName], [Sha1Digest]), so code holding those
+//! can target either protocol. Requests are signed with AWS signature version 4 using an application key.
+mod common_structs;
+mod errors;
+mod get_object;
+mod list_objects_v2;
+mod multipart_upload;
+mod put_object;
+mod signing;
+
+pub use common_structs::{ETag, S3Credentials, S3Endpoint, S3Region};
+pub use errors::S3Error;
+pub use get_object::{s3_get_object, GetObjectRequest};
+pub use list_objects_v2::{
+    s3_list_objects_v2, ContinuationToken, ListObjectsV2Ok, ListObjectsV2Request, S3Object,
+};
+pub use multipart_upload::{
+    s3_abort_multipart_upload, s3_complete_multipart_upload, s3_create_multipart_upload,
+    s3_upload_part, CompleteMultipartUploadOk, CompletedPart, CreateMultipartUploadRequest,
+    MultipartUpload, S3UploadPartRequest, UploadId,
+};
+pub use put_object::{s3_put_object, PutObjectOk, PutObjectRequest};
+
+use crate::v2::{BucketName, FileName, ServerSideEncryptionCustomerKey};
+
+#[cfg(doc)]
+use crate::v2::Sha1Digest;
+
+use signing::uri_encode;
+
+/// path style url of an object: `{endpoint}/{bucket}/{key}`
+fn object_url(endpoint: &S3Endpoint, bucket_name: &BucketName, file_name: &FileName) -> String {
+    format!(
+        "{}/{}/{}",
+        endpoint.as_str(),
+        uri_encode(bucket_name.as_str(), true),
+        uri_encode(file_name.as_str(), false)
+    )
+}
+
+/// path style url of a bucket: `{endpoint}/{bucket}`
+fn bucket_url(endpoint: &S3Endpoint, bucket_name: &BucketName) -> String {
+    format!(
+        "{}/{}",
+        endpoint.as_str(),
+        uri_encode(bucket_name.as_str(), true)
+    )
+}
+
+/// builds a query string from the given parameters, encoded the same way as they are signed
+fn query_string(params: &[(&str, Option<String>)]) -> String {
+    params
+        .iter()
+        .filter_map(|(key, value)| {
+            value
+                .as_ref()
+                .map(|v| format!("{}={}", uri_encode(key, true), uri_encode(v, true)))
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Adds server side encryption headers as expected by the S3 compatible api
+fn add_server_side_encryption_headers(
+    request: reqwest::RequestBuilder,
+    server_side_encryption: &ServerSideEncryptionCustomerKey,
+) -> reqwest::RequestBuilder {
+    match server_side_encryption {
+        ServerSideEncryptionCustomerKey::None => request,
+        ServerSideEncryptionCustomerKey::SseB2 => {
+            request.header("x-amz-server-side-encryption", "AES256")
+        }
+        ServerSideEncryptionCustomerKey::SseC {
+            customer_key,
+            customer_key_md5,
+        } => request
+            .header("x-amz-server-side-encryption-customer-algorithm", "AES256")
+            .header(
+                "x-amz-server-side-encryption-customer-key",
+                base64::encode(customer_key),
+            )
+            .header(
+                "x-amz-server-side-encryption-customer-key-MD5",
+                base64::encode(customer_key_md5.bytes()),
+            ),
+    }
+}
+
+/// signs the request with the current time and sends it
+async fn send_signed(
+    endpoint: &S3Endpoint,
+    credentials: &S3Credentials,
+    request: reqwest::RequestBuilder,
+    payload_hash: &str,
+) -> Result<reqwest::Response, S3Error> {
+    let mut request = request.build()?;
+    signing::sign_request(
+        &mut request,
+        credentials,
+        endpoint.region(),
+        payload_hash,
+        std::time::SystemTime::now(),
+    );
+    Ok(crate::transport::send(request).await?)
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,124 @@
+//! Common structs used by multiple S3 calls
+
+use serde::{Deserialize, Serialize};
+
+use crate::v2::{ApplicationKey, ApplicationKeyId, S3ApiUrl};
+
+/// Application key used to sign requests, the application key id is used as access key id and
+/// the application key as secret access key
+#[derive(Debug, Clone)]
+pub struct S3Credentials {
+    application_key_id: ApplicationKeyId,
+    application_key: ApplicationKey,
+}
+
+impl S3Credentials {
+    pub fn new(application_key_id: ApplicationKeyId, application_key: ApplicationKey) -> Self {
+        Self {
+            application_key_id,
+            application_key,
+        }
+    }
+
+    /// Get a reference to the s3 credentials's application key id.
+    pub fn application_key_id(&self) -> &ApplicationKeyId {
+        &self.application_key_id
+    }
+
+    pub(crate) fn secret(&self) -> &str {
+        self.application_key.as_str()
+    }
+}
+
+/// Region of a B2 cluster, e.g. `us-west-004`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct S3Region(String);
+
+impl S3Region {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for S3Region {
+    fn from(region: String) -> Self {
+        Self(region)
+    }
+}
+
+/// Url and region of an S3 compatible endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Endpoint {
+    url: String,
+    region: S3Region,
+}
+
+impl S3Endpoint {
+    /// Creates an endpoint for an arbitrary url, e.g. a local S3 stand-in
+    pub fn new(url: String, region: S3Region) -> Self {
+        let url = url.trim_end_matches('/').to_owned();
+        Self { url, region }
+    }
+
+    /// The Backblaze endpoint of the given region, i.e. `https://s3.{region}.backblazeb2.com`
+    pub fn for_region(region: S3Region) -> Self {
+        Self::new(
+            format!("https://s3.{}.backblazeb2.com", region.as_str()),
+            region,
+        )
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.url
+    }
+
+    pub fn region(&self) -> &S3Region {
+        &self.region
+    }
+}
+
+impl TryFrom<&S3ApiUrl> for S3Endpoint {
+    type Error = crate::v2::InvalidData;
+
+    /// extracts the region from an url returned by [crate::v2::b2_authorize_account]
+    fn try_from(s3_api_url: &S3ApiUrl) -> Result<Self, Self::Error> {
+        let url = s3_api_url.as_str();
+        let host = url
+            .split("://")
+            .nth(1)
+            .unwrap_or(url)
+            .split(['/', ':'])
+            .next()
+            .unwrap_or_default();
+        match host.split('.').collect::<Vec<_>>().as_slice() {
+            ["s3", region, ..] if !region.is_empty() => Ok(Self::new(
+                url.to_owned(),
+                S3Region::from(region.to_string()),
+            )),
+            _ => Err(crate::v2::InvalidData::new(format!(
+                "Could not determine region from S3 api url {}",
+                url
+            ))),
+        }
+    }
+}
+
+/// Entity tag of an object or part as returned by the server (including the surrounding quotes)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ETag(String);
+
+impl ETag {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub(crate) fn from_response(response: &reqwest::Response) -> Option<Self> {
+        response
+            .headers()
+            .get(http::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| Self(v.to_owned()))
+    }
+}
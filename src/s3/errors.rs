@@ -0,0 +1,136 @@
+use std::fmt::Display;
+
+use http::StatusCode;
+use serde::Deserialize;
+
+/// error body returned by the server, see [AWS documentation](https://docs.aws.amazon.com/AmazonS3/latest/API/ErrorResponses.html)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct XmlErrorObj {
+    code: String,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Debug)]
+pub enum S3Error {
+    /// Error returned by the server
+    ServerError {
+        status: StatusCode,
+        /// S3 error code, e.g. `NoSuchKey`, if the response had no body this is the reason phrase of the status
+        code: String,
+        message: String,
+    },
+    /// Error reported from reqwest & not in the data of the request or response
+    RequestError { error: reqwest::Error },
+    /// The response could not be parsed
+    InvalidResponse { message: String },
+    /// Error reported by a [crate::transport::HttpTransport] not based on reqwest
+    TransportError {
+        error: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+impl S3Error {
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status();
+        let body = match response.text().await {
+            Ok(body) => body,
+            Err(e) => return e.into(),
+        };
+        Self::from_status_and_body(status, &body)
+    }
+
+    pub(crate) fn from_status_and_body(status: StatusCode, body: &str) -> Self {
+        match quick_xml::de::from_str::<XmlErrorObj>(body) {
+            Ok(err) => Self::ServerError {
+                status,
+                code: err.code,
+                message: err.message,
+            },
+            Err(_) => Self::ServerError {
+                status,
+                code: status.canonical_reason().unwrap_or_default().to_owned(),
+                message: body.to_owned(),
+            },
+        }
+    }
+
+    /// Parses an xml body of a successful response, which might still contain an error
+    /// (e.g. CompleteMultipartUpload may fail after the status was sent)
+    pub(crate) fn parse_xml<'de, T: Deserialize<'de>>(
+        status: StatusCode,
+        body: &'de str,
+    ) -> Result<T, Self> {
+        match quick_xml::de::from_str(body) {
+            Ok(v) => Ok(v),
+            Err(e) => match quick_xml::de::from_str::<XmlErrorObj>(body) {
+                Ok(err) => Err(Self::ServerError {
+                    status,
+                    code: err.code,
+                    message: err.message,
+                }),
+                Err(_) => Err(Self::InvalidResponse {
+                    message: e.to_string(),
+                }),
+            },
+        }
+    }
+
+    /// Http status returned by the server, if any
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::ServerError { status, .. } => Some(*status),
+            Self::RequestError { error } => error.status(),
+            Self::InvalidResponse { .. } | Self::TransportError { .. } => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for S3Error {
+    fn from(error: reqwest::Error) -> Self {
+        Self::RequestError { error }
+    }
+}
+
+impl From<crate::transport::TransportError> for S3Error {
+    fn from(error: crate::transport::TransportError) -> Self {
+        match error {
+            crate::transport::TransportError::Reqwest(error) => Self::RequestError { error },
+            crate::transport::TransportError::Other(error) => Self::TransportError { error },
+            crate::transport::TransportError::BudgetExceeded(error) => Self::TransportError {
+                error: Box::new(error),
+            },
+            crate::transport::TransportError::PermissionDenied(error) => Self::TransportError {
+                error: Box::new(error),
+            },
+        }
+    }
+}
+
+impl Display for S3Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ServerError {
+                status,
+                code,
+                message,
+            } => write!(f, "S3 request failed ({}): {} {}", status, code, message),
+            Self::RequestError { error } => write!(f, "S3 request failed: {}", error),
+            Self::InvalidResponse { message } => {
+                write!(f, "Invalid S3 response received: {}", message)
+            }
+            Self::TransportError { error } => write!(f, "S3 request failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for S3Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RequestError { error } => Some(error),
+            Self::TransportError { error } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
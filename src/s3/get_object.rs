@@ -0,0 +1,48 @@
+use headers::{HeaderMap, HeaderMapExt};
+use typed_builder::TypedBuilder;
+
+use super::{
+    add_server_side_encryption_headers, object_url, send_signed, signing::sha256_hex,
+    S3Credentials, S3Endpoint, S3Error,
+};
+use crate::v2::{BucketName, FileName, ServerSideEncryptionCustomerKey};
+
+#[derive(Debug, TypedBuilder)]
+pub struct GetObjectRequest<'s> {
+    bucket_name: &'s BucketName,
+    file_name: &'s FileName,
+    #[builder(default, setter(strip_option))]
+    range: Option<&'s headers::Range>,
+    /// Must be set to the key used for uploading, if the object was encrypted using SSE-C
+    #[builder(default, setter(strip_option))]
+    server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+}
+
+/// downloads an object, does return a reqwest::Response object, if the server returned http status OK (200)
+/// or PartialContent (206) if a range was used.
+pub async fn s3_get_object(
+    endpoint: &S3Endpoint,
+    credentials: &S3Credentials,
+    request: &GetObjectRequest<'_>,
+) -> Result<reqwest::Response, S3Error> {
+    let url = object_url(endpoint, request.bucket_name, request.file_name);
+    let mut headers = HeaderMap::with_capacity(1);
+    if let Some(range) = request.range {
+        headers.typed_insert(range.clone());
+    }
+    let mut request_builder = crate::transport::client().get(url).headers(headers);
+    if let Some(sse) = request.server_side_encryption {
+        request_builder = add_server_side_encryption_headers(request_builder, sse);
+    }
+    let resp = send_signed(endpoint, credentials, request_builder, &sha256_hex(b"")).await?;
+    let expected_status = if request.range.is_none() {
+        http::StatusCode::OK
+    } else {
+        http::StatusCode::PARTIAL_CONTENT
+    };
+    if resp.status() == expected_status {
+        Ok(resp)
+    } else {
+        Err(S3Error::from_response(resp).await)
+    }
+}
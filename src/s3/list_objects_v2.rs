@@ -0,0 +1,145 @@
+use std::num::NonZeroU16;
+
+use serde::{Deserialize, Serialize};
+use typed_builder::TypedBuilder;
+
+use super::{
+    bucket_url, query_string, send_signed, signing::sha256_hex, ETag, S3Credentials, S3Endpoint,
+    S3Error,
+};
+use crate::v2::{BucketName, FileName, FileNameDelimiter, FileNamePrefix};
+
+/// Token to continue a listing, returned as `NextContinuationToken`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ContinuationToken(String);
+
+impl ContinuationToken {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, TypedBuilder)]
+pub struct ListObjectsV2Request<'s> {
+    bucket_name: &'s BucketName,
+    #[builder(default, setter(strip_option))]
+    prefix: Option<&'s FileNamePrefix>,
+    #[builder(default, setter(strip_option))]
+    delimiter: Option<&'s FileNameDelimiter>,
+    #[builder(default, setter(strip_option))]
+    continuation_token: Option<&'s ContinuationToken>,
+    #[builder(default, setter(strip_option))]
+    start_after: Option<&'s FileName>,
+    /// At most 1000 keys are returned, even if a bigger value is requested
+    #[builder(default, setter(strip_option))]
+    max_keys: Option<NonZeroU16>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct S3Object {
+    key: FileName,
+    #[serde(default)]
+    last_modified: Option<String>,
+    #[serde(default, rename = "ETag")]
+    e_tag: Option<ETag>,
+    size: u64,
+}
+
+impl S3Object {
+    /// Get a reference to the s3 object's key.
+    pub fn file_name(&self) -> &FileName {
+        &self.key
+    }
+
+    /// Get a reference to the s3 object's last modified time stamp (ISO 8601).
+    pub fn last_modified(&self) -> Option<&str> {
+        self.last_modified.as_deref()
+    }
+
+    /// Get a reference to the s3 object's e tag.
+    pub fn e_tag(&self) -> Option<&ETag> {
+        self.e_tag.as_ref()
+    }
+
+    /// Get the s3 object's size.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CommonPrefix {
+    prefix: FileNamePrefix,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListObjectsV2Ok {
+    #[serde(default)]
+    contents: Vec<S3Object>,
+    #[serde(default)]
+    common_prefixes: Vec<CommonPrefix>,
+    #[serde(default)]
+    is_truncated: bool,
+    #[serde(default)]
+    next_continuation_token: Option<ContinuationToken>,
+}
+
+impl ListObjectsV2Ok {
+    /// Get a reference to the list objects v2 ok's objects.
+    pub fn objects(&self) -> &[S3Object] {
+        self.contents.as_slice()
+    }
+
+    /// Get the common prefixes, only set if a delimiter was used.
+    pub fn common_prefixes(&self) -> Vec<&FileNamePrefix> {
+        self.common_prefixes.iter().map(|p| &p.prefix).collect()
+    }
+
+    /// Get the list objects v2 ok's is truncated.
+    pub fn is_truncated(&self) -> bool {
+        self.is_truncated
+    }
+
+    /// Get a reference to the list objects v2 ok's next continuation token.
+    pub fn next_continuation_token(&self) -> Option<&ContinuationToken> {
+        self.next_continuation_token.as_ref()
+    }
+}
+
+pub async fn s3_list_objects_v2(
+    endpoint: &S3Endpoint,
+    credentials: &S3Credentials,
+    request: &ListObjectsV2Request<'_>,
+) -> Result<ListObjectsV2Ok, S3Error> {
+    let query = query_string(&[
+        ("list-type", Some("2".to_owned())),
+        (
+            "continuation-token",
+            request.continuation_token.map(|t| t.as_str().to_owned()),
+        ),
+        (
+            "delimiter",
+            request.delimiter.map(|d| d.as_char().to_string()),
+        ),
+        ("max-keys", request.max_keys.map(|m| m.to_string())),
+        ("prefix", request.prefix.map(|p| p.as_str().to_owned())),
+        (
+            "start-after",
+            request.start_after.map(|f| f.as_str().to_owned()),
+        ),
+    ]);
+    let url = format!("{}?{}", bucket_url(endpoint, request.bucket_name), query);
+    let request_builder = crate::transport::client().get(url);
+    let resp = send_signed(endpoint, credentials, request_builder, &sha256_hex(b"")).await?;
+    let status = resp.status();
+    if status == http::StatusCode::OK {
+        let body = resp.text().await?;
+        S3Error::parse_xml(status, &body)
+    } else {
+        Err(S3Error::from_response(resp).await)
+    }
+}
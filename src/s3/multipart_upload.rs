@@ -0,0 +1,290 @@
+use reqwest::Body;
+use serde::{Deserialize, Serialize};
+use typed_builder::TypedBuilder;
+
+use super::{
+    add_server_side_encryption_headers, object_url, query_string, send_signed,
+    signing::{sha256_hex, UNSIGNED_PAYLOAD},
+    ETag, S3Credentials, S3Endpoint, S3Error,
+};
+use crate::v2::{
+    BucketName, ContentTypeRef, FileName, Md5DigestRef, PartNumber,
+    ServerSideEncryptionCustomerKey, Sha1DigestRef,
+};
+
+/// Id of a multipart upload, the S3 equivalent of the file id of an unfinished large file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UploadId(String);
+
+impl UploadId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for UploadId {
+    fn from(upload_id: String) -> Self {
+        Self(upload_id)
+    }
+}
+
+#[derive(Debug, TypedBuilder)]
+pub struct CreateMultipartUploadRequest<'s> {
+    bucket_name: &'s BucketName,
+    file_name: &'s FileName,
+    /// If not set, the server determines the content type
+    #[builder(default, setter(strip_option))]
+    content_type: Option<ContentTypeRef<'s>>,
+    #[builder(default, setter(strip_option))]
+    server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MultipartUpload {
+    bucket: BucketName,
+    key: FileName,
+    upload_id: UploadId,
+}
+
+impl MultipartUpload {
+    /// Creates the upload information of an already started upload, e.g. to resume it
+    pub fn new(bucket: BucketName, key: FileName, upload_id: UploadId) -> Self {
+        Self {
+            bucket,
+            key,
+            upload_id,
+        }
+    }
+
+    /// Get a reference to the multipart upload's bucket name.
+    pub fn bucket_name(&self) -> &BucketName {
+        &self.bucket
+    }
+
+    /// Get a reference to the multipart upload's file name.
+    pub fn file_name(&self) -> &FileName {
+        &self.key
+    }
+
+    /// Get a reference to the multipart upload's upload id.
+    pub fn upload_id(&self) -> &UploadId {
+        &self.upload_id
+    }
+}
+
+/// Starts a multipart upload, see [crate::v2::b2_start_large_file] for the B2 equivalent
+pub async fn s3_create_multipart_upload(
+    endpoint: &S3Endpoint,
+    credentials: &S3Credentials,
+    request: &CreateMultipartUploadRequest<'_>,
+) -> Result<MultipartUpload, S3Error> {
+    let url = format!(
+        "{}?uploads",
+        object_url(endpoint, request.bucket_name, request.file_name)
+    );
+    let mut request_builder = crate::transport::client().post(url);
+    if let Some(content_type) = request.content_type {
+        request_builder = request_builder.header(
+            http::header::CONTENT_TYPE,
+            mime::Mime::from(content_type.clone()).as_ref(),
+        );
+    }
+    if let Some(sse) = request.server_side_encryption {
+        request_builder = add_server_side_encryption_headers(request_builder, sse);
+    }
+    let resp = send_signed(endpoint, credentials, request_builder, &sha256_hex(b"")).await?;
+    let status = resp.status();
+    if status == http::StatusCode::OK {
+        let body = resp.text().await?;
+        S3Error::parse_xml(status, &body)
+    } else {
+        Err(S3Error::from_response(resp).await)
+    }
+}
+
+#[derive(Debug, TypedBuilder)]
+pub struct S3UploadPartRequest<'s> {
+    upload: &'s MultipartUpload,
+    /// A number from 1 to 10000. All parts but the last one must be at least 5MB.
+    part_number: PartNumber,
+    /// The number of bytes in the part, chunked encoding is not supported.
+    content_length: u64,
+    /// Sent as `x-amz-checksum-sha1`, the server rejects the part if it does not match
+    #[builder(default, setter(strip_option))]
+    content_sha1: Option<Sha1DigestRef<'s>>,
+    /// Sent as `Content-MD5`, the server rejects the part if it does not match
+    #[builder(default, setter(strip_option))]
+    content_md5: Option<Md5DigestRef<'s>>,
+    /// Must match the encryption used when creating the upload, if SSE-C was used
+    #[builder(default, setter(strip_option))]
+    server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+}
+
+/// A part that was uploaded, needed to complete the upload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletedPart {
+    part_number: PartNumber,
+    e_tag: ETag,
+}
+
+impl CompletedPart {
+    pub fn new(part_number: PartNumber, e_tag: ETag) -> Self {
+        Self { part_number, e_tag }
+    }
+
+    /// Get the completed part's part number.
+    pub fn part_number(&self) -> PartNumber {
+        self.part_number
+    }
+
+    /// Get a reference to the completed part's e tag.
+    pub fn e_tag(&self) -> &ETag {
+        &self.e_tag
+    }
+}
+
+/// Uploads one part of a multipart upload, see [crate::v2::b2_upload_part] for the B2 equivalent
+pub async fn s3_upload_part<T: Into<Body>>(
+    endpoint: &S3Endpoint,
+    credentials: &S3Credentials,
+    request: &S3UploadPartRequest<'_>,
+    file_contents: T,
+) -> Result<CompletedPart, S3Error> {
+    let url = format!(
+        "{}?{}",
+        object_url(endpoint, &request.upload.bucket, &request.upload.key),
+        query_string(&[
+            ("partNumber", Some(request.part_number.as_u16().to_string())),
+            (
+                "uploadId",
+                Some(request.upload.upload_id.as_str().to_owned())
+            ),
+        ])
+    );
+    let mut request_builder = crate::transport::client()
+        .put(url)
+        .header(http::header::CONTENT_LENGTH, request.content_length)
+        .body(file_contents);
+    if let Some(sha1) = request.content_sha1 {
+        request_builder =
+            request_builder.header("x-amz-checksum-sha1", base64::encode(sha1.bytes()));
+    }
+    if let Some(md5) = request.content_md5 {
+        request_builder = request_builder.header("Content-MD5", base64::encode(md5.bytes()));
+    }
+    if let Some(sse) = request.server_side_encryption {
+        request_builder = add_server_side_encryption_headers(request_builder, sse);
+    }
+    let resp = send_signed(endpoint, credentials, request_builder, UNSIGNED_PAYLOAD).await?;
+    if resp.status() == http::StatusCode::OK {
+        match ETag::from_response(&resp) {
+            Some(e_tag) => Ok(CompletedPart::new(request.part_number, e_tag)),
+            None => Err(S3Error::InvalidResponse {
+                message: "ETag header missing in upload part response".to_owned(),
+            }),
+        }
+    } else {
+        Err(S3Error::from_response(resp).await)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CompleteMultipartUploadOk {
+    bucket: BucketName,
+    key: FileName,
+    #[serde(rename = "ETag")]
+    e_tag: ETag,
+}
+
+impl CompleteMultipartUploadOk {
+    /// Get a reference to the complete multipart upload ok's bucket name.
+    pub fn bucket_name(&self) -> &BucketName {
+        &self.bucket
+    }
+
+    /// Get a reference to the complete multipart upload ok's file name.
+    pub fn file_name(&self) -> &FileName {
+        &self.key
+    }
+
+    /// Get a reference to the complete multipart upload ok's e tag.
+    pub fn e_tag(&self) -> &ETag {
+        &self.e_tag
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn complete_multipart_upload_body(parts: &[CompletedPart]) -> String {
+    let parts_xml: String = parts
+        .iter()
+        .map(|part| {
+            format!(
+                "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                part.part_number.as_u16(),
+                xml_escape(part.e_tag.as_str())
+            )
+        })
+        .collect();
+    format!(
+        "<CompleteMultipartUpload>{}</CompleteMultipartUpload>",
+        parts_xml
+    )
+}
+
+/// Completes a multipart upload, see [crate::v2::b2_finish_large_file] for the B2 equivalent
+pub async fn s3_complete_multipart_upload(
+    endpoint: &S3Endpoint,
+    credentials: &S3Credentials,
+    upload: &MultipartUpload,
+    parts: &[CompletedPart],
+) -> Result<CompleteMultipartUploadOk, S3Error> {
+    let url = format!(
+        "{}?{}",
+        object_url(endpoint, &upload.bucket, &upload.key),
+        query_string(&[("uploadId", Some(upload.upload_id.as_str().to_owned()))])
+    );
+    let body = complete_multipart_upload_body(parts);
+    let payload_hash = sha256_hex(body.as_bytes());
+    let request_builder = crate::transport::client()
+        .post(url)
+        .header(http::header::CONTENT_TYPE, "application/xml")
+        .body(body);
+    let resp = send_signed(endpoint, credentials, request_builder, &payload_hash).await?;
+    let status = resp.status();
+    if status == http::StatusCode::OK {
+        let body = resp.text().await?;
+        S3Error::parse_xml(status, &body)
+    } else {
+        Err(S3Error::from_response(resp).await)
+    }
+}
+
+/// Aborts a multipart upload, see [crate::v2::b2_cancel_large_file] for the B2 equivalent
+pub async fn s3_abort_multipart_upload(
+    endpoint: &S3Endpoint,
+    credentials: &S3Credentials,
+    upload: &MultipartUpload,
+) -> Result<(), S3Error> {
+    let url = format!(
+        "{}?{}",
+        object_url(endpoint, &upload.bucket, &upload.key),
+        query_string(&[("uploadId", Some(upload.upload_id.as_str().to_owned()))])
+    );
+    let request_builder = crate::transport::client().delete(url);
+    let resp = send_signed(endpoint, credentials, request_builder, &sha256_hex(b"")).await?;
+    if resp.status() == http::StatusCode::NO_CONTENT || resp.status() == http::StatusCode::OK {
+        Ok(())
+    } else {
+        Err(S3Error::from_response(resp).await)
+    }
+}
@@ -0,0 +1,85 @@
+use reqwest::Body;
+use typed_builder::TypedBuilder;
+
+use super::{
+    add_server_side_encryption_headers, object_url, send_signed, signing::UNSIGNED_PAYLOAD, ETag,
+    S3Credentials, S3Endpoint, S3Error,
+};
+use crate::v2::{
+    BucketName, ContentTypeRef, FileName, Md5DigestRef, ServerSideEncryptionCustomerKey,
+    Sha1DigestRef,
+};
+
+#[derive(Debug, TypedBuilder)]
+pub struct PutObjectRequest<'s> {
+    bucket_name: &'s BucketName,
+    file_name: &'s FileName,
+
+    /// The number of bytes in the object, chunked encoding is not supported.
+    content_length: u64,
+
+    /// If not set, the server determines the content type
+    #[builder(default, setter(strip_option))]
+    content_type: Option<ContentTypeRef<'s>>,
+
+    /// Sent as `x-amz-checksum-sha1`, the server rejects the upload if it does not match
+    #[builder(default, setter(strip_option))]
+    content_sha1: Option<Sha1DigestRef<'s>>,
+
+    /// Sent as `Content-MD5`, the server rejects the upload if it does not match
+    #[builder(default, setter(strip_option))]
+    content_md5: Option<Md5DigestRef<'s>>,
+
+    #[builder(default, setter(strip_option))]
+    server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+}
+
+#[derive(Debug)]
+pub struct PutObjectOk {
+    e_tag: Option<ETag>,
+}
+
+impl PutObjectOk {
+    /// Get a reference to the put object ok's e tag.
+    pub fn e_tag(&self) -> Option<&ETag> {
+        self.e_tag.as_ref()
+    }
+}
+
+/// uploads a single object, the body is not signed, so it is only streamed once.
+pub async fn s3_put_object<T: Into<Body>>(
+    endpoint: &S3Endpoint,
+    credentials: &S3Credentials,
+    request: &PutObjectRequest<'_>,
+    file_contents: T,
+) -> Result<PutObjectOk, S3Error> {
+    let url = object_url(endpoint, request.bucket_name, request.file_name);
+    let mut request_builder = crate::transport::client()
+        .put(url)
+        .header(http::header::CONTENT_LENGTH, request.content_length)
+        .body(file_contents);
+    if let Some(content_type) = request.content_type {
+        request_builder = request_builder.header(
+            http::header::CONTENT_TYPE,
+            mime::Mime::from(content_type.clone()).as_ref(),
+        );
+    }
+    if let Some(sha1) = request.content_sha1 {
+        request_builder =
+            request_builder.header("x-amz-checksum-sha1", base64::encode(sha1.bytes()));
+    }
+    if let Some(md5) = request.content_md5 {
+        request_builder = request_builder.header("Content-MD5", base64::encode(md5.bytes()));
+    }
+    if let Some(sse) = request.server_side_encryption {
+        request_builder = add_server_side_encryption_headers(request_builder, sse);
+    }
+    let resp = send_signed(endpoint, credentials, request_builder, UNSIGNED_PAYLOAD).await?;
+    if resp.status() == http::StatusCode::OK {
+        Ok(PutObjectOk {
+            e_tag: ETag::from_response(&resp),
+        })
+    } else {
+        Err(S3Error::from_response(resp).await)
+    }
+}
@@ -0,0 +1,273 @@
+use std::convert::TryInto;
+
+use wiremock::{
+    matchers::{body_string_contains, header, header_exists, method, path, query_param},
+    Match, Mock, MockServer, ResponseTemplate,
+};
+
+use super::*;
+use crate::v2::{test::mock_server::*, FileNameDelimiter, FileNamePrefix, Sha1Digest};
+
+const BUCKET_NAME: &str = "bucket-name";
+
+/// matches requests signed with [FAKE_APPLICATION_KEY_ID]
+struct SignedRequestMatch;
+
+impl Match for SignedRequestMatch {
+    fn matches(&self, request: &wiremock::Request) -> bool {
+        let expected_prefix = format!("AWS4-HMAC-SHA256 Credential={}/", FAKE_APPLICATION_KEY_ID);
+        request
+            .headers
+            .get(&"authorization".into())
+            .map(|v| v.as_str().starts_with(&expected_prefix))
+            .unwrap_or(false)
+            && request.headers.contains_key(&"x-amz-date".into())
+            && request.headers.contains_key(&"x-amz-content-sha256".into())
+    }
+}
+
+fn endpoint(mock_server: &MockServer) -> S3Endpoint {
+    S3Endpoint::new(mock_server.uri(), S3Region::from("us-west-004".to_owned()))
+}
+
+fn credentials() -> S3Credentials {
+    S3Credentials::new(
+        FAKE_APPLICATION_KEY_ID.to_owned().try_into().unwrap(),
+        FAKE_APPLICATION_KEY.to_owned().try_into().unwrap(),
+    )
+}
+
+fn bucket_name() -> BucketName {
+    BUCKET_NAME.to_owned().try_into().unwrap()
+}
+
+#[test]
+fn test_endpoint_from_s3_api_url() {
+    let s3_api_url: crate::v2::S3ApiUrl =
+        serde_json::from_value(serde_json::json!("https://s3.us-west-004.backblazeb2.com"))
+            .unwrap();
+    let endpoint = S3Endpoint::try_from(&s3_api_url).unwrap();
+    assert_eq!("us-west-004", endpoint.region().as_str());
+    assert_eq!(
+        S3Endpoint::for_region(S3Region::from("us-west-004".to_owned())),
+        endpoint
+    );
+}
+
+#[tokio::test]
+async fn test_put_object() {
+    let mock_server = MockServer::start().await;
+    let sha1 = Sha1Digest::new([1u8; 20]);
+    Mock::given(method("PUT"))
+        .and(path("/bucket-name/dir/file%20name.txt"))
+        .and(SignedRequestMatch)
+        .and(header("x-amz-content-sha256", "UNSIGNED-PAYLOAD"))
+        .and(header(
+            "x-amz-checksum-sha1",
+            base64::encode(sha1.bytes()).as_str(),
+        ))
+        .respond_with(ResponseTemplate::new(200).insert_header("ETag", "\"etag-value\""))
+        .mount(&mock_server)
+        .await;
+    let file_name: FileName = "dir/file name.txt".to_owned().try_into().unwrap();
+    let res = s3_put_object(
+        &endpoint(&mock_server),
+        &credentials(),
+        &PutObjectRequest::builder()
+            .bucket_name(&bucket_name())
+            .file_name(&file_name)
+            .content_length(5)
+            .content_sha1(&sha1)
+            .build(),
+        "hello",
+    )
+    .await
+    .unwrap();
+    assert_eq!("\"etag-value\"", res.e_tag().unwrap().as_str());
+}
+
+#[tokio::test]
+async fn test_get_object_range() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/bucket-name/file.txt"))
+        .and(SignedRequestMatch)
+        .and(header("range", "bytes=0-1"))
+        .respond_with(ResponseTemplate::new(206).set_body_string("he"))
+        .mount(&mock_server)
+        .await;
+    let file_name: FileName = "file.txt".to_owned().try_into().unwrap();
+    let range = headers::Range::bytes(0..2).unwrap();
+    let resp = s3_get_object(
+        &endpoint(&mock_server),
+        &credentials(),
+        &GetObjectRequest::builder()
+            .bucket_name(&bucket_name())
+            .file_name(&file_name)
+            .range(&range)
+            .build(),
+    )
+    .await
+    .unwrap();
+    assert_eq!("he", resp.text().await.unwrap());
+}
+
+#[tokio::test]
+async fn test_get_object_not_found() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/bucket-name/missing.txt"))
+        .respond_with(ResponseTemplate::new(404).set_body_raw(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+            <Error><Code>NoSuchKey</Code><Message>Key not found</Message></Error>",
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+    let file_name: FileName = "missing.txt".to_owned().try_into().unwrap();
+    let err = s3_get_object(
+        &endpoint(&mock_server),
+        &credentials(),
+        &GetObjectRequest::builder()
+            .bucket_name(&bucket_name())
+            .file_name(&file_name)
+            .build(),
+    )
+    .await
+    .unwrap_err();
+    match err {
+        S3Error::ServerError { status, code, .. } => {
+            assert_eq!(404, status.as_u16());
+            assert_eq!("NoSuchKey", code);
+        }
+        _ => panic!("Expected ServerError, found {:#?}", err),
+    }
+}
+
+#[tokio::test]
+async fn test_list_objects_v2() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/bucket-name"))
+        .and(SignedRequestMatch)
+        .and(query_param("list-type", "2"))
+        .and(query_param("prefix", "dir/"))
+        .and(query_param("delimiter", "/"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+            <ListBucketResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+                <Name>bucket-name</Name><Prefix>dir/</Prefix><KeyCount>2</KeyCount>\
+                <MaxKeys>1000</MaxKeys><Delimiter>/</Delimiter><IsTruncated>true</IsTruncated>\
+                <Contents><Key>dir/a.txt</Key><LastModified>2021-01-01T00:00:00.000Z</LastModified>\
+                    <ETag>\"etag-a\"</ETag><Size>7</Size><StorageClass>STANDARD</StorageClass></Contents>\
+                <Contents><Key>dir/b.txt</Key><LastModified>2021-01-01T00:00:00.000Z</LastModified>\
+                    <ETag>\"etag-b\"</ETag><Size>8</Size><StorageClass>STANDARD</StorageClass></Contents>\
+                <CommonPrefixes><Prefix>dir/sub/</Prefix></CommonPrefixes>\
+                <NextContinuationToken>next-token</NextContinuationToken>\
+            </ListBucketResult>",
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+    let prefix: FileNamePrefix = "dir/".to_owned().try_into().unwrap();
+    let delimiter: FileNameDelimiter = '/'.try_into().unwrap();
+    let res = s3_list_objects_v2(
+        &endpoint(&mock_server),
+        &credentials(),
+        &ListObjectsV2Request::builder()
+            .bucket_name(&bucket_name())
+            .prefix(&prefix)
+            .delimiter(&delimiter)
+            .build(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(2, res.objects().len());
+    assert_eq!("dir/a.txt", res.objects()[0].file_name().as_str());
+    assert_eq!(8, res.objects()[1].size());
+    assert_eq!(
+        vec!["dir/sub/"],
+        res.common_prefixes()
+            .iter()
+            .map(|p| p.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert!(res.is_truncated());
+    assert_eq!(
+        "next-token",
+        res.next_continuation_token().unwrap().as_str()
+    );
+}
+
+#[tokio::test]
+async fn test_multipart_upload() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/bucket-name/large.bin"))
+        .and(query_param("uploads", ""))
+        .and(SignedRequestMatch)
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            "<InitiateMultipartUploadResult><Bucket>bucket-name</Bucket>\
+            <Key>large.bin</Key><UploadId>upload-id</UploadId></InitiateMultipartUploadResult>",
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/bucket-name/large.bin"))
+        .and(query_param("partNumber", "1"))
+        .and(query_param("uploadId", "upload-id"))
+        .and(SignedRequestMatch)
+        .respond_with(ResponseTemplate::new(200).insert_header("ETag", "\"part-1\""))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/bucket-name/large.bin"))
+        .and(query_param("uploadId", "upload-id"))
+        .and(SignedRequestMatch)
+        .and(header_exists("content-type"))
+        .and(body_string_contains(
+            "<Part><PartNumber>1</PartNumber><ETag>&quot;part-1&quot;</ETag></Part>",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            "<CompleteMultipartUploadResult><Location>loc</Location><Bucket>bucket-name</Bucket>\
+            <Key>large.bin</Key><ETag>\"etag-1\"</ETag></CompleteMultipartUploadResult>",
+            "application/xml",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let endpoint = endpoint(&mock_server);
+    let credentials = credentials();
+    let file_name: FileName = "large.bin".to_owned().try_into().unwrap();
+    let upload = s3_create_multipart_upload(
+        &endpoint,
+        &credentials,
+        &CreateMultipartUploadRequest::builder()
+            .bucket_name(&bucket_name())
+            .file_name(&file_name)
+            .build(),
+    )
+    .await
+    .unwrap();
+    assert_eq!("upload-id", upload.upload_id().as_str());
+
+    let part = s3_upload_part(
+        &endpoint,
+        &credentials,
+        &S3UploadPartRequest::builder()
+            .upload(&upload)
+            .part_number(1u16.try_into().unwrap())
+            .content_length(4)
+            .build(),
+        "part",
+    )
+    .await
+    .unwrap();
+    assert_eq!("\"part-1\"", part.e_tag().as_str());
+
+    let completed = s3_complete_multipart_upload(&endpoint, &credentials, &upload, &[part])
+        .await
+        .unwrap();
+    assert_eq!("\"etag-1\"", completed.e_tag().as_str());
+}
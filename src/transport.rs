@@ -0,0 +1,751 @@
+//! Pluggable http transport used by all calls of this crate.
+//!
+//! By default requests are executed by a [reqwest::Client]. A different [HttpTransport]
+//! (e.g. based on hyper 1.x, with custom middleware, or an in-process fake for tests) can be set
+//! for the whole process with [set_default_transport], or for a single future with [with_transport].
+//!
+//! A transport receives a fully built [http::Request] (method, url, headers, body) and returns an
+//! [http::Response], both with a [Body] that is either held in memory or streamed. Request bodies
+//! are passed as a stream, their length is set in the `Content-Length` header.
+//!
+//! The connections of a [reqwest::Client] belong to the tokio runtime that opened them, so the default
+//! transport uses a separate client for every runtime, which is dropped after the runtime shut down. A
+//! [reqwest::Client] set as transport is shared by all runtimes, only set one if all calls run on the same
+//! runtime.
+//!
+//! Download responses of a transport other than the default one report a [reqwest::Response::content_length]
+//! only if the body is held in memory, and no [reqwest::Response::url], use the `Content-Length` header instead.
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Debug, Display},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, RwLock, Weak},
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use bytes::Bytes;
+use futures_util::{Stream, TryStreamExt};
+use lazy_static::lazy_static;
+
+use crate::metrics::{BudgetExceeded, RequestMetrics, RequestOutcome};
+
+/// Error of an [HttpTransport]
+#[derive(Debug)]
+pub enum TransportError {
+    Reqwest(reqwest::Error),
+    /// Error of a transport not based on reqwest
+    Other(Box<dyn std::error::Error + Send + Sync>),
+    /// The request was refused by a [crate::metrics::MetricsHook] and not sent
+    BudgetExceeded(BudgetExceeded),
+    /// The request was refused by [crate::v2::with_permission_check] and not sent
+    PermissionDenied(crate::v2::PermissionError),
+}
+
+impl From<reqwest::Error> for TransportError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Reqwest(error)
+    }
+}
+
+impl From<BudgetExceeded> for TransportError {
+    fn from(error: BudgetExceeded) -> Self {
+        Self::BudgetExceeded(error)
+    }
+}
+
+impl From<crate::v2::PermissionError> for TransportError {
+    fn from(error: crate::v2::PermissionError) -> Self {
+        Self::PermissionDenied(error)
+    }
+}
+
+impl Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransportError::Reqwest(error) => write!(f, "{}", error),
+            TransportError::Other(error) => write!(f, "{}", error),
+            TransportError::BudgetExceeded(error) => write!(f, "{}", error),
+            TransportError::PermissionDenied(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TransportError::Reqwest(error) => Some(error),
+            TransportError::Other(error) => Some(error.as_ref()),
+            TransportError::BudgetExceeded(error) => Some(error),
+            TransportError::PermissionDenied(error) => Some(error),
+        }
+    }
+}
+
+/// Error of a streamed [Body]
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes, BoxError>> + Send + Sync>>;
+
+/// Body of a request or response passed through an [HttpTransport], held in memory or streamed
+pub struct Body(BodyInner);
+
+enum BodyInner {
+    Bytes(Bytes),
+    Stream(BodyStream),
+}
+
+impl Body {
+    pub fn empty() -> Self {
+        Self(BodyInner::Bytes(Bytes::new()))
+    }
+
+    /// Body streaming the chunks of `stream`
+    pub fn wrap_stream<S, E>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + Sync + 'static,
+        E: Into<BoxError> + 'static,
+    {
+        Self(BodyInner::Stream(Box::pin(stream.map_err(Into::into))))
+    }
+
+    /// Get the contents of the body, `None` if it is streamed.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match &self.0 {
+            BodyInner::Bytes(bytes) => Some(bytes),
+            BodyInner::Stream(_) => None,
+        }
+    }
+}
+
+impl Stream for Body {
+    type Item = Result<Bytes, BoxError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match &mut self.0 {
+            BodyInner::Bytes(bytes) if bytes.is_empty() => Poll::Ready(None),
+            BodyInner::Bytes(bytes) => Poll::Ready(Some(Ok(std::mem::take(bytes)))),
+            BodyInner::Stream(stream) => stream.as_mut().poll_next(cx),
+        }
+    }
+}
+
+impl Debug for Body {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            BodyInner::Bytes(bytes) => f.debug_tuple("Body").field(&bytes.len()).finish(),
+            BodyInner::Stream(_) => f.debug_tuple("Body").field(&"stream").finish(),
+        }
+    }
+}
+
+impl From<Bytes> for Body {
+    fn from(bytes: Bytes) -> Self {
+        Self(BodyInner::Bytes(bytes))
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes::from(bytes).into()
+    }
+}
+
+impl From<String> for Body {
+    fn from(s: String) -> Self {
+        Bytes::from(s).into()
+    }
+}
+
+impl From<&'static str> for Body {
+    fn from(s: &'static str) -> Self {
+        Bytes::from_static(s.as_bytes()).into()
+    }
+}
+
+impl From<Body> for reqwest::Body {
+    fn from(body: Body) -> Self {
+        match body.0 {
+            BodyInner::Bytes(bytes) => bytes.into(),
+            BodyInner::Stream(stream) => reqwest::Body::wrap_stream(stream),
+        }
+    }
+}
+
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<http::Response<Body>, TransportError>> + Send + 'a>>;
+
+/// Executes http requests
+pub trait HttpTransport: Debug + Send + Sync {
+    fn execute(&self, request: http::Request<Body>) -> TransportFuture<'_>;
+}
+
+impl HttpTransport for reqwest::Client {
+    fn execute(&self, request: http::Request<Body>) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let response = reqwest::Client::execute(self, request.try_into()?).await?;
+            from_reqwest_response(response)
+        })
+    }
+}
+
+/// Transport used if none is set, executes requests with the client of the current runtime
+#[derive(Debug)]
+struct DefaultTransport;
+
+impl HttpTransport for DefaultTransport {
+    fn execute(&self, request: http::Request<Body>) -> TransportFuture<'_> {
+        let client = runtime_client();
+        Box::pin(async move { HttpTransport::execute(&client, request).await })
+    }
+}
+
+/// client of a runtime, together with a guard held by a task of the runtime
+struct RuntimeClient {
+    /// the task is dropped when the runtime shuts down, afterwards this can no longer be upgraded
+    runtime_alive: Weak<()>,
+    client: reqwest::Client,
+}
+
+impl RuntimeClient {
+    fn new(runtime: &tokio::runtime::Handle) -> Self {
+        let guard = Arc::new(());
+        let runtime_alive = Arc::downgrade(&guard);
+        runtime.spawn(async move {
+            let _guard = guard;
+            std::future::pending::<()>().await
+        });
+        Self {
+            runtime_alive,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+lazy_static! {
+    static ref REQUEST_CLIENT: reqwest::Client = reqwest::Client::new();
+    static ref RUNTIME_CLIENTS: RwLock<HashMap<tokio::runtime::Id, RuntimeClient>> =
+        RwLock::new(HashMap::new());
+    static ref DEFAULT_TRANSPORT: RwLock<Option<Arc<dyn HttpTransport>>> = RwLock::new(None);
+}
+
+tokio::task_local! {
+    static SCOPED_TRANSPORT: Arc<dyn HttpTransport>;
+}
+
+thread_local! {
+    /// client of the runtime last used on this thread, so most requests do not look up the client in RUNTIME_CLIENTS
+    static THREAD_CLIENT: RefCell<Option<(tokio::runtime::Id, reqwest::Client)>> = const { RefCell::new(None) };
+}
+
+/// Sets the transport used by all calls, that are not run inside of [with_transport]
+pub fn set_default_transport(transport: Arc<dyn HttpTransport>) {
+    // a poisoned lock still contains a valid transport, as it is replaced in a single step
+    let mut default = DEFAULT_TRANSPORT
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *default = Some(transport);
+}
+
+/// Runs `future` with all calls inside of it using `transport`
+pub async fn with_transport<F: Future>(transport: Arc<dyn HttpTransport>, future: F) -> F::Output {
+    SCOPED_TRANSPORT.scope(transport, future).await
+}
+
+/// transport to be used for the current call
+pub fn current_transport() -> Arc<dyn HttpTransport> {
+    custom_transport().unwrap_or_else(|| Arc::new(DefaultTransport))
+}
+
+/// transport set with [with_transport] or [set_default_transport], `None` for the default transport
+fn custom_transport() -> Option<Arc<dyn HttpTransport>> {
+    SCOPED_TRANSPORT.try_with(Arc::clone).ok().or_else(|| {
+        DEFAULT_TRANSPORT
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    })
+}
+
+/// client of the current tokio runtime, its connection pool is only used by this runtime
+fn runtime_client() -> reqwest::Client {
+    let handle = match tokio::runtime::Handle::try_current() {
+        Ok(handle) => handle,
+        // the requests are not pooled outside of a runtime
+        Err(_) => return reqwest::Client::new(),
+    };
+    let runtime = handle.id();
+    THREAD_CLIENT.with(|thread_client| {
+        let mut thread_client = thread_client.borrow_mut();
+        match &*thread_client {
+            Some((id, client)) if *id == runtime => client.clone(),
+            _ => {
+                let client = shared_runtime_client(&handle);
+                *thread_client = Some((runtime, client.clone()));
+                client
+            }
+        }
+    })
+}
+
+/// client of a runtime shared by all of its threads, clients of runtimes that were shut down are dropped
+fn shared_runtime_client(runtime: &tokio::runtime::Handle) -> reqwest::Client {
+    // a poisoned lock still contains valid clients, as each is inserted in a single step
+    if let Some(runtime_client) = RUNTIME_CLIENTS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(&runtime.id())
+    {
+        return runtime_client.client.clone();
+    }
+    let mut clients = RUNTIME_CLIENTS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    clients.retain(|_, runtime_client| runtime_client.runtime_alive.strong_count() > 0);
+    clients
+        .entry(runtime.id())
+        .or_insert_with(|| RuntimeClient::new(runtime))
+        .client
+        .clone()
+}
+
+/// client used to build requests, the requests are executed by the [current_transport]
+pub(crate) fn client() -> &'static reqwest::Client {
+    &REQUEST_CLIENT
+}
+
+/// request passed to a transport, the body is streamed to avoid copying it
+fn into_http_request(mut request: reqwest::Request) -> Result<http::Request<Body>, TransportError> {
+    let content_length = request_content_length(&request);
+    let body = match request.body_mut().take() {
+        // a reqwest body can only be read as the body of a response
+        Some(body) => {
+            Body::wrap_stream(reqwest::Response::from(http::Response::new(body)).bytes_stream())
+        }
+        None => Body::empty(),
+    };
+    let mut builder = http::Request::builder()
+        .method(request.method().clone())
+        .uri(request.url().as_str())
+        .version(request.version());
+    if let Some(headers) = builder.headers_mut() {
+        *headers = std::mem::take(request.headers_mut());
+        if let Some(length) = content_length {
+            headers.insert(http::header::CONTENT_LENGTH, length.into());
+        }
+    }
+    builder
+        .body(body)
+        .map_err(|e| TransportError::Other(Box::new(e)))
+}
+
+fn from_reqwest_response(
+    response: reqwest::Response,
+) -> Result<http::Response<Body>, TransportError> {
+    let mut builder = http::Response::builder()
+        .status(response.status())
+        .version(response.version());
+    if let Some(headers) = builder.headers_mut() {
+        *headers = response.headers().clone();
+    }
+    builder
+        .body(Body::wrap_stream(response.bytes_stream()))
+        .map_err(|e| TransportError::Other(Box::new(e)))
+}
+
+pub(crate) trait RequestBuilderExt {
+    /// builds the request and executes it with the [current_transport]
+    fn send_with_transport(
+        self,
+    ) -> impl Future<Output = Result<reqwest::Response, TransportError>> + Send;
+}
+
+impl RequestBuilderExt for reqwest::RequestBuilder {
+    async fn send_with_transport(self) -> Result<reqwest::Response, TransportError> {
+        let request = self.build()?;
+        let hook = crate::metrics::current_metrics_hook();
+        let endpoint = crate::v2::Endpoint::from_url_path(request.url().path());
+        if let Some(endpoint) = endpoint {
+            crate::v2::check_request_permission(
+                endpoint,
+                request.url().path(),
+                request.headers(),
+                request.body().and_then(reqwest::Body::as_bytes),
+            )?;
+        }
+        if let (Some(hook), Some(endpoint)) = (&hook, endpoint) {
+            hook.before_request(endpoint)?;
+        }
+        #[cfg(feature = "rate_limit")]
+        let request = match crate::rate_limit::current_rate_limiter() {
+            Some(limiter) => return execute_limited(request, endpoint, limiter, hook).await,
+            None => request,
+        };
+        execute_with_metrics(request, endpoint, hook).await
+    }
+}
+
+async fn execute_with_metrics(
+    request: reqwest::Request,
+    endpoint: Option<crate::v2::Endpoint>,
+    hook: Option<Arc<dyn crate::metrics::MetricsHook>>,
+) -> Result<reqwest::Response, TransportError> {
+    let hook = match hook {
+        Some(hook) => hook,
+        None => return execute(request).await,
+    };
+    let bytes_sent = request_content_length(&request);
+    let start = Instant::now();
+    let response = execute(request).await;
+    let (bytes_received, outcome) = match &response {
+        Ok(response) => (
+            response_content_length(response),
+            RequestOutcome::Response(response.status()),
+        ),
+        Err(_) => (None, RequestOutcome::TransportError),
+    };
+    hook.on_request(&RequestMetrics::new(
+        endpoint,
+        bytes_sent,
+        bytes_received,
+        start.elapsed(),
+        outcome,
+    ));
+    response
+}
+
+/// waits for the request rate limit, and throttles the bodies of uploads and downloads
+#[cfg(feature = "rate_limit")]
+async fn execute_limited(
+    mut request: reqwest::Request,
+    endpoint: Option<crate::v2::Endpoint>,
+    limiter: Arc<crate::rate_limit::RateLimiter>,
+    hook: Option<Arc<dyn crate::metrics::MetricsHook>>,
+) -> Result<reqwest::Response, TransportError> {
+    use crate::v2::Endpoint;
+
+    limiter.acquire_request().await;
+    if matches!(endpoint, Some(Endpoint::UploadFile | Endpoint::UploadPart))
+        && limiter.bandwidth_limit().is_some()
+    {
+        if let Some(body) = request.body_mut().take() {
+            *request.body_mut() = Some(limiter.throttle_body(body));
+        }
+    }
+    let response = execute_with_metrics(request, endpoint, hook).await?;
+    if matches!(
+        endpoint,
+        Some(Endpoint::DownloadFileById | Endpoint::DownloadFileByName)
+    ) && limiter.bandwidth_limit().is_some()
+        && response.status().is_success()
+    {
+        Ok(limiter.throttle_response(response))
+    } else {
+        Ok(response)
+    }
+}
+
+/// executes a request with the current transport, the default transport is called without converting the request
+pub(crate) async fn send(request: reqwest::Request) -> Result<reqwest::Response, TransportError> {
+    match custom_transport() {
+        Some(transport) => {
+            let response = transport.execute(into_http_request(request)?).await?;
+            Ok(reqwest::Response::from(response.map(reqwest::Body::from)))
+        }
+        None => Ok(runtime_client().execute(request).await?),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+async fn execute(request: reqwest::Request) -> Result<reqwest::Response, TransportError> {
+    send(request).await
+}
+
+#[cfg(feature = "tracing")]
+async fn execute(request: reqwest::Request) -> Result<reqwest::Response, TransportError> {
+    use tracing::Instrument;
+
+    // only the path is recorded, the query of presigned urls contains an authorization token
+    let span = tracing::debug_span!(
+        "b2_http_request",
+        method = %request.method(),
+        host = request.url().host_str(),
+        path = request.url().path(),
+        request_bytes = request_content_length(&request),
+        status = tracing::field::Empty,
+        response_bytes = tracing::field::Empty,
+    );
+    let response = send(request).instrument(span.clone()).await?;
+    span.record("status", response.status().as_u16());
+    if let Some(length) = response_content_length(&response) {
+        span.record("response_bytes", length);
+    }
+    Ok(response)
+}
+
+fn request_content_length(request: &reqwest::Request) -> Option<u64> {
+    request
+        .headers()
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .or_else(|| {
+            request
+                .body()
+                .and_then(reqwest::Body::as_bytes)
+                .map(|bytes| bytes.len() as u64)
+        })
+}
+
+/// length of a response body, streamed bodies of custom transports and throttled downloads only report it in the header
+pub(crate) fn response_content_length(response: &reqwest::Response) -> Option<u64> {
+    response.content_length().or_else(|| {
+        response
+            .headers()
+            .get(http::header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::v2::{b2_get_file_info, errors::GetFileInfoError, ApiUrl, AuthorizationToken};
+
+    /// answers every request from memory, without opening a socket
+    #[derive(Debug)]
+    struct InProcessTransport {
+        status: u16,
+        body: serde_json::Value,
+    }
+
+    impl HttpTransport for InProcessTransport {
+        fn execute(&self, request: http::Request<Body>) -> TransportFuture<'_> {
+            Box::pin(async move {
+                assert_eq!(
+                    "http://in-process/b2api/v2/b2_get_file_info",
+                    request.uri().to_string()
+                );
+                let response = http::Response::builder()
+                    .status(self.status)
+                    .header("content-type", "application/json")
+                    .body(Body::from(self.body.to_string()))
+                    .unwrap();
+                Ok(response)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_process_transport() {
+        let transport = Arc::new(InProcessTransport {
+            status: 404,
+            body: json!({"status": 404, "code": "not_found", "message": "file not found"}),
+        });
+        let res = with_transport(
+            transport,
+            b2_get_file_info(
+                &ApiUrl("http://in-process".to_owned()),
+                &AuthorizationToken("token".to_owned()),
+                &"4_z27c88f1d182b150646ff0b16_f1004ba650fe24e6b_d20180809_m012348_c100_v0009990_t0000"
+                    .to_owned()
+                    .try_into()
+                    .unwrap(),
+            ),
+        )
+        .await;
+        assert!(matches!(res, Err(GetFileInfoError::NotFound { .. })));
+    }
+
+    /// returns the body of every request as a streamed response body
+    #[derive(Debug)]
+    struct EchoTransport;
+
+    impl HttpTransport for EchoTransport {
+        fn execute(&self, request: http::Request<Body>) -> TransportFuture<'_> {
+            Box::pin(async move {
+                let (parts, body) = request.into_parts();
+                assert!(body.as_bytes().is_none());
+                let content_length = parts.headers[http::header::CONTENT_LENGTH].clone();
+                let chunks: Vec<_> = body.try_collect().await.map_err(TransportError::Other)?;
+                let response = http::Response::builder()
+                    .status(200)
+                    .header(http::header::CONTENT_LENGTH, content_length)
+                    .body(Body::wrap_stream(futures_util::stream::iter(
+                        chunks.into_iter().map(Ok::<_, std::convert::Infallible>),
+                    )))
+                    .unwrap();
+                Ok(response)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streamed_bodies() {
+        let response = with_transport(
+            Arc::new(EchoTransport),
+            client()
+                .post("http://in-process/echo")
+                .body("hello")
+                .send_with_transport(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(Some(5), response_content_length(&response));
+        assert_eq!("hello", response.text().await.unwrap());
+    }
+
+    #[test]
+    fn test_default_transport_on_multiple_runtimes() {
+        // the mock server runs on its own runtime, that outlives the runtimes of the calls
+        let server_runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock_server = server_runtime.block_on(async {
+            let mock_server = wiremock::MockServer::start().await;
+            wiremock::Mock::given(wiremock::matchers::method("POST"))
+                .respond_with(wiremock::ResponseTemplate::new(404).set_body_json(
+                    json!({"status": 404, "code": "not_found", "message": "file not found"}),
+                ))
+                .mount(&mock_server)
+                .await;
+            mock_server
+        });
+        let file_id =
+            "4_z27c88f1d182b150646ff0b16_f1004ba650fe24e6b_d20180809_m012348_c100_v0009990_t0000";
+        // connections pooled by a runtime are only driven while that runtime runs, the runtimes
+        // are kept alive, so a connection of an idle runtime would hang instead of being closed
+        let runtimes: Vec<_> = (0..2)
+            .map(|_| {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+        for runtime in &runtimes {
+            let res = runtime.block_on(async {
+                tokio::time::timeout(
+                    std::time::Duration::from_secs(10),
+                    b2_get_file_info(
+                        &ApiUrl(mock_server.uri()),
+                        &AuthorizationToken("token".to_owned()),
+                        &file_id.to_owned().try_into().unwrap(),
+                    ),
+                )
+                .await
+            });
+            assert!(matches!(res, Ok(Err(GetFileInfoError::NotFound { .. }))));
+        }
+        drop(mock_server);
+    }
+
+    #[test]
+    fn test_clients_of_shut_down_runtimes_are_dropped() {
+        let new_runtime = || {
+            tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap()
+        };
+        let runtime = new_runtime();
+        let (id, client) =
+            runtime.block_on(async { (tokio::runtime::Handle::current().id(), runtime_client()) });
+        let is_cached = |id| {
+            RUNTIME_CLIENTS
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .contains_key(&id)
+        };
+        assert!(is_cached(id));
+        drop(client);
+        drop(runtime);
+        assert!(is_cached(id));
+        // the client of the shut down runtime is dropped, when the client of another runtime is created
+        new_runtime().block_on(async { runtime_client() });
+        assert!(!is_cached(id));
+    }
+
+    /// collects all span names, fields and events as text
+    #[cfg(feature = "tracing")]
+    #[derive(Default)]
+    struct CollectingSubscriber {
+        output: std::sync::Mutex<String>,
+        next_id: std::sync::atomic::AtomicU64,
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::field::Visit for &CollectingSubscriber {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn Debug) {
+            let mut output = self.output.lock().unwrap();
+            output.push_str(&format!("{}={:?} ", field.name(), value));
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for CollectingSubscriber {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            self.output
+                .lock()
+                .unwrap()
+                .push_str(&format!("{} ", span.metadata().name()));
+            span.record(&mut &*self);
+            tracing::span::Id::from_u64(
+                self.next_id
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+                    + 1,
+            )
+        }
+
+        fn record(&self, _: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+            values.record(&mut &*self);
+        }
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            event.record(&mut &*self);
+        }
+
+        fn enter(&self, _: &tracing::span::Id) {}
+
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_tracing_spans() {
+        let subscriber = Arc::new(CollectingSubscriber::default());
+        let _guard = tracing::subscriber::set_default(subscriber.clone());
+        let transport = Arc::new(InProcessTransport {
+            status: 404,
+            body: json!({"status": 404, "code": "not_found", "message": "file not found"}),
+        });
+        let file_id =
+            "4_z27c88f1d182b150646ff0b16_f1004ba650fe24e6b_d20180809_m012348_c100_v0009990_t0000";
+        let _ = with_transport(
+            transport,
+            b2_get_file_info(
+                &ApiUrl("http://in-process".to_owned()),
+                &AuthorizationToken("secret_token".to_owned()),
+                &file_id.to_owned().try_into().unwrap(),
+            ),
+        )
+        .await;
+        let output = subscriber.output.lock().unwrap();
+        assert!(output.contains("b2_get_file_info"));
+        assert!(output.contains(&format!("file_id={:?}", file_id)));
+        assert!(output.contains("path=\"/b2api/v2/b2_get_file_info\""));
+        assert!(output.contains("status=404"));
+        assert!(output.contains("error_code=\"not_found\""));
+        assert!(!output.contains("secret_token"));
+    }
+}
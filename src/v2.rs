@@ -1,5 +1,7 @@
 //! Backblaze API Level V2
 mod common_structs;
+#[macro_use]
+mod owned_request;
 
 mod b2_authorize_account;
 mod b2_cancel_large_file;
@@ -17,6 +19,7 @@ mod b2_get_download_authorization;
 mod b2_get_file_info;
 mod b2_get_upload_part_url;
 mod b2_get_upload_url;
+mod b2_head_file;
 mod b2_hide_file;
 mod b2_list_buckets;
 mod b2_list_file_names;
@@ -31,12 +34,25 @@ mod b2_update_file_retention;
 mod b2_upload_file;
 mod b2_upload_part;
 mod buckets;
+mod bulk_delete;
 mod capabilities;
+#[cfg(feature = "customer_keys")]
+mod customer_key;
+#[cfg(feature = "b2_unstable")]
+mod customer_key_rotation;
+mod endpoint;
 pub mod errors;
 mod file;
 mod file_lock;
+mod file_lock_management;
 mod file_part;
+mod key_manager;
+mod large_file_janitor;
+mod lifecycle;
+mod permission_check;
+mod presign_download;
 mod server_side_encryption;
+mod timestamp;
 
 use serde::ser::SerializeSeq;
 use serde::{Deserialize, Serialize};
@@ -63,16 +79,22 @@ impl TryFrom<String> for KeyName {
 
 pub type KeyNameRef<'a> = &'a KeyName;
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq)]
 #[serde(transparent)]
 pub struct ApplicationKey(String);
 
 impl ApplicationKey {
-    fn as_str(&self) -> &str {
+    pub(crate) fn as_str(&self) -> &str {
         &self.0
     }
 }
 
+impl std::fmt::Debug for ApplicationKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ApplicationKey(<redacted>)")
+    }
+}
+
 impl TryFrom<String> for ApplicationKey {
     type Error = ();
 
@@ -90,7 +112,7 @@ pub type ApplicationKeyRef<'a> = &'a ApplicationKey;
 pub struct ApplicationKeyId(String);
 
 impl ApplicationKeyId {
-    fn as_str(&self) -> &str {
+    pub(crate) fn as_str(&self) -> &str {
         &self.0
     }
 }
@@ -106,8 +128,18 @@ impl TryFrom<String> for ApplicationKeyId {
 
 pub type ApplicationKeyIdRef<'a> = &'a ApplicationKeyId;
 
-pub use b2_authorize_account::AuthorizeAccountOk;
+pub use b2_authorize_account::{AllowedBucket, AuthorizeAccountAllowed, AuthorizeAccountOk};
 /// Authorize account function see [official documentation](https://www.backblaze.com/b2/docs/b2_authorize_account.html)
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            application_key_id = application_key_id.as_str(),
+        )
+    )
+)]
 pub async fn b2_authorize_account(
     application_key_id: &ApplicationKeyId,
     application_key: &ApplicationKey,
@@ -123,74 +155,123 @@ pub async fn b2_authorize_account(
 
 pub use buckets::{
     BucketId, BucketInfo, BucketInfoKey, BucketInfoValue, BucketName, BucketType, BucketTypes,
-    LifeCycleRule,
+    LifeCycleRule, LifeCycleRulesError, SettableBucketType, MAX_LIFECYCLE_RULES,
 };
 
 pub use capabilities::{all_per_bucket_capabilites, Capabilities, Capability};
 pub use common_structs::*;
+#[cfg(feature = "customer_keys")]
+pub use customer_key::{CustomerKey, CustomerKeyError, CustomerKeyRing};
+#[cfg(feature = "b2_unstable")]
+pub use customer_key_rotation::{
+    b2_rotate_customer_key, KeyRotationProgress, KeyRotationSkipped, RotateCustomerKeyOk,
+    RotateCustomerKeyRequest,
+};
 pub use file::*;
 pub use file_lock::*;
-pub use server_side_encryption::{ServerSideEncryption, ServerSideEncryptionCustomerKey};
+pub use file_lock_management::{
+    b2_list_protected_file_versions, b2_update_file_locks, FileLockUpdate, FileLockUpdateSkipped,
+    UpdateFileLocksOk, UpdateFileLocksRequest,
+};
+pub use server_side_encryption::{
+    OwnedServerSideEncryptionCustomerKey, ServerSideEncryption, ServerSideEncryptionCustomerKey,
+    SSE_C_KEY_ID_FILE_INFO,
+};
+pub use timestamp::TimeStamp;
 
-pub use b2_create_bucket::{b2_create_bucket, CreateBucketRequest};
+pub use b2_create_bucket::{b2_create_bucket, CreateBucketRequest, OwnedCreateBucketRequest};
 pub use b2_delete_bucket::b2_delete_bucket;
 pub use b2_list_buckets::b2_list_buckets;
-pub use b2_list_buckets::{Bucket, ListBucketsOk, ListBucketsRequest};
-pub use b2_update_bucket::{b2_update_bucket, UpdateBucketRequest};
+pub use b2_list_buckets::{Bucket, ListBucketsOk, ListBucketsRequest, OwnedListBucketsRequest};
+pub use b2_update_bucket::{b2_update_bucket, OwnedUpdateBucketRequest, UpdateBucketRequest};
 
 pub use b2_get_file_info::b2_get_file_info;
 pub use b2_list_file_names::{
     b2_list_file_names, ListFileNamesOk, ListFileNamesRequest, MaxFileCount,
+    OwnedListFileNamesRequest,
 };
 pub use b2_list_file_versions::{
     b2_list_file_versions, ListFileVersionsOk, ListFileVersionsRequest,
+    OwnedListFileVersionsRequest,
 };
 
-pub use b2_download_file_by_id::{b2_download_file_by_id, DownloadParams};
+pub use b2_download_file_by_id::{b2_download_file_by_id, DownloadParams, OwnedDownloadParams};
 pub use b2_download_file_by_name::{
     b2_download_file_by_name, get_b2_download_file_by_name_url, DownloadFileByNameRequest,
-    DownloadFileByNameUrlParameters,
+    DownloadFileByNameUrlParameters, OwnedDownloadFileByNameRequest,
 };
 pub use b2_get_download_authorization::{
     b2_get_download_authorization, GetDownloadAuthorizationOk, GetDownloadAuthorizationRequest,
-    ValidDownloadAuthorizationDurationInSeconds,
+    OwnedGetDownloadAuthorizationRequest, ValidDownloadAuthorizationDurationInSeconds,
+};
+pub use b2_head_file::{b2_head_file_by_id, b2_head_file_by_name, HeadFileOk};
+
+pub use bulk_delete::{
+    b2_delete_all_file_versions, DeleteAllFileVersionsOk, DeleteAllFileVersionsRequest,
+};
+pub use endpoint::{Endpoint, TransactionClass};
+pub use large_file_janitor::{
+    b2_cancel_unfinished_large_files, CancelUnfinishedLargeFilesOk,
+    CancelUnfinishedLargeFilesRequest, UnfinishedLargeFileReport,
+};
+pub use lifecycle::{
+    simulate_lifecycle_rules, LifecycleAction, LifecycleSimulation, SimulatedLifecycleAction,
+};
+pub(crate) use permission_check::check_request as check_request_permission;
+pub use permission_check::{with_permission_check, PermissionCheck, PermissionError};
+pub use presign_download::{
+    presign_download, presign_downloads, PresignDownloadRequest, PresignDownloadsRequest,
 };
 
 pub use b2_get_upload_url::{b2_get_upload_url, UploadParameters};
-pub use b2_upload_file::{b2_upload_file, UploadFileParameters};
+pub use b2_upload_file::{b2_upload_file, OwnedUploadFileParameters, UploadFileParameters};
 
 pub use b2_cancel_large_file::{b2_cancel_large_file, CancelFileOk};
 pub use b2_finish_large_file::b2_finish_large_file;
 pub use b2_get_upload_part_url::{b2_get_upload_part_url, UploadPartUrlParameters};
-pub use b2_list_parts::{b2_list_parts, ListPartsOk, ListPartsRequest, MaxPartCount, Part};
+pub use b2_list_parts::{
+    b2_list_parts, ListPartsOk, ListPartsRequest, MaxPartCount, OwnedListPartsRequest, Part,
+};
 pub use b2_list_unfinished_large_files::{
-    b2_list_unfinished_large_files, ListUnfinishedLargeFilesRequest, MaxUnfinishedLargeFileCount,
+    b2_list_unfinished_large_files, ListUnfinishedLargeFilesOk, ListUnfinishedLargeFilesRequest,
+    MaxUnfinishedLargeFileCount, OwnedListUnfinishedLargeFilesRequest,
+};
+pub use b2_start_large_file::{
+    b2_start_large_file, OwnedStartLargeFileParameters, StartLargeFileParameters,
+};
+pub use b2_upload_part::{
+    b2_upload_part, OwnedUploadPartParameters, UploadPartOk, UploadPartParameters,
 };
-pub use b2_start_large_file::{b2_start_large_file, StartLargeFileParameters};
-pub use b2_upload_part::{b2_upload_part, UploadPartOk, UploadPartParameters};
 pub use file_part::PartNumber;
 
-pub use b2_copy_file::{b2_copy_file, CopyFileRequest, MetadataDirective};
-pub use b2_copy_part::{b2_copy_part, CopyPartRequest};
+pub use b2_copy_file::{b2_copy_file, CopyFileRequest, MetadataDirective, OwnedCopyFileRequest};
+pub use b2_copy_part::{b2_copy_part, CopyPartRequest, OwnedCopyPartRequest};
 
 pub use b2_delete_file_version::{
     b2_delete_file_version, DeleteFileVersionOk, DeleteFileVersionRequest,
+    OwnedDeleteFileVersionRequest,
 };
 pub use b2_hide_file::b2_hide_file;
 
-pub use b2_create_key::{b2_create_key, CreateKeyRequest, CreatedKeyInformation};
+pub use b2_create_key::{
+    b2_create_key, CreateKeyRequest, CreatedKeyInformation, InvalidKeyLifeTimeError,
+    OwnedCreateKeyRequest, ValidKeyLifeTimeInSeconds,
+};
 pub use b2_delete_key::{b2_delete_key, KeyInformation};
-pub use b2_list_keys::{b2_list_keys, ListKeysOk, ListKeysRequest};
+pub use b2_list_keys::{b2_list_keys, ListKeysOk, ListKeysRequest, OwnedListKeysRequest};
+pub use key_manager::{KeyManager, KeyManagerError, KeyTemplate, RotateKeyOk};
 
 pub use b2_update_file_legal_hold::{
-    b2_update_file_legal_hold, UpdateFileLegalHoldOk, UpdateFileLegalHoldRequest,
+    b2_update_file_legal_hold, OwnedUpdateFileLegalHoldRequest, UpdateFileLegalHoldOk,
+    UpdateFileLegalHoldRequest,
 };
 pub use b2_update_file_retention::{
-    b2_update_file_retention, UpdateFileRetentionOk, UpdateFileRetentionRequest,
+    b2_update_file_retention, OwnedUpdateFileRetentionRequest, UpdateFileRetentionOk,
+    UpdateFileRetentionRequest,
 };
 
 #[cfg(test)]
-mod test;
+pub(crate) mod test;
 
 // not sure, why this function is necessary ... but direct calls to serialize_header did not work,
 // and since this should be optimized away, it should not cause overhead in release builds
@@ -252,3 +333,51 @@ where
         None => serializer.serialize_none(),
     }
 }
+
+/// decodes a headers::Header value from its string representation
+fn decode_header<H, E>(value: &str) -> Result<H, E>
+where
+    H: headers::Header,
+    E: serde::de::Error,
+{
+    let value = headers::HeaderValue::from_str(value).map_err(serde::de::Error::custom)?;
+    H::decode(&mut std::iter::once(&value)).map_err(serde::de::Error::custom)
+}
+
+/// (de)serializes owned headers::Header values as strings, used by the owned requests
+mod owned_header {
+    use serde::Deserialize;
+
+    pub(super) use super::serialize_header as serialize;
+
+    pub(super) fn deserialize<'de, D, H>(deserializer: D) -> Result<H, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        H: headers::Header,
+    {
+        super::decode_header(&String::deserialize(deserializer)?)
+    }
+}
+
+/// (de)serializes Options of owned headers::Header values as strings, used by the owned requests
+mod owned_header_option {
+    use serde::Deserialize;
+
+    pub(super) fn serialize<S, H>(header: &Option<H>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+        H: headers::Header + Clone,
+    {
+        super::serialize_header_option(&header.as_ref(), serializer)
+    }
+
+    pub(super) fn deserialize<'de, D, H>(deserializer: D) -> Result<Option<H>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        H: headers::Header,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|value| super::decode_header(&value))
+            .transpose()
+    }
+}
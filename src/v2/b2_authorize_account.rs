@@ -1,8 +1,9 @@
 use super::{
     errors, AccountId, ApiUrl, ApplicationKeyIdRef, ApplicationKeyRef, AuthorizationToken,
-    Capabilities, DownloadUrl,
+    BucketId, BucketName, Capabilities, DownloadUrl, FileNamePrefix, S3ApiUrl,
 };
-use serde::{Deserialize, Serialize};
+use crate::transport::RequestBuilderExt;
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -14,6 +15,8 @@ pub struct AuthorizeAccountOk {
     authorization_token: AuthorizationToken,
     download_url: DownloadUrl,
     recommended_part_size: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    s3_api_url: Option<S3ApiUrl>,
 }
 
 impl AuthorizeAccountOk {
@@ -51,20 +54,120 @@ impl AuthorizeAccountOk {
     pub fn recommended_part_size(&self) -> u32 {
         self.recommended_part_size
     }
+
+    /// Get a reference to the authorize account ok's s3 api url.
+    pub fn s3_api_url(&self) -> Option<&S3ApiUrl> {
+        self.s3_api_url.as_ref()
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// A bucket an application key is restricted to
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AllowedBucket {
+    pub id: BucketId,
+    /// name of the bucket, is `None` if the bucket no longer exists
+    #[serde(default)]
+    pub name: Option<BucketName>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AuthorizeAccountAllowed {
     pub capabilities: Capabilities,
-    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// buckets the key is restricted to, empty if the key may access all buckets
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub buckets: Vec<AllowedBucket>,
+    /// id of the first bucket the key is restricted to
+    #[deprecated(note = "keys can be restricted to multiple buckets, use `buckets` instead")]
+    #[serde(skip)]
     pub bucket_id: Option<String>,
-    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// name of the first bucket the key is restricted to
+    #[deprecated(note = "keys can be restricted to multiple buckets, use `buckets` instead")]
+    #[serde(skip)]
     pub bucket_name: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub name_prefix: Option<String>,
+    pub name_prefix: Option<FileNamePrefix>,
+}
+
+impl AuthorizeAccountAllowed {
+    /// Get the id of the bucket the key is restricted to.
+    ///
+    /// If the key is restricted to multiple buckets, this is the first of them, use [Self::buckets] to get all.
+    pub fn bucket_id(&self) -> Option<&BucketId> {
+        self.buckets.first().map(|bucket| &bucket.id)
+    }
+
+    /// Get the name of the bucket the key is restricted to.
+    ///
+    /// If the key is restricted to multiple buckets, this is the first of them, use [Self::buckets] to get all.
+    pub fn bucket_name(&self) -> Option<&BucketName> {
+        self.buckets.first().and_then(|bucket| bucket.name.as_ref())
+    }
+
+    /// Returns `true` if the key may access the bucket with the given id.
+    pub fn is_bucket_allowed(&self, bucket_id: &BucketId) -> bool {
+        self.buckets.is_empty() || self.buckets.iter().any(|bucket| &bucket.id == bucket_id)
+    }
 }
 
+/// Accepts the legacy single bucket form (`bucketId`/`bucketName`) as well as the list form (`buckets`)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeserializableAuthorizeAccountAllowed {
+    capabilities: Capabilities,
+    #[serde(default)]
+    bucket_id: Option<BucketId>,
+    #[serde(default)]
+    bucket_name: Option<BucketName>,
+    #[serde(default)]
+    buckets: Option<Vec<AllowedBucket>>,
+    #[serde(default)]
+    name_prefix: Option<FileNamePrefix>,
+}
+
+impl<'de> Deserialize<'de> for AuthorizeAccountAllowed {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let deserialized = DeserializableAuthorizeAccountAllowed::deserialize(deserializer)?;
+        let mut buckets = deserialized.buckets.unwrap_or_default();
+        if let Some(id) = deserialized.bucket_id {
+            if !buckets.iter().any(|bucket| bucket.id == id) {
+                buckets.insert(
+                    0,
+                    AllowedBucket {
+                        id,
+                        name: deserialized.bucket_name,
+                    },
+                );
+            }
+        }
+        let first_bucket = buckets.first();
+        #[allow(deprecated)]
+        Ok(Self {
+            capabilities: deserialized.capabilities,
+            bucket_id: first_bucket.map(|bucket| bucket.id.as_str().to_owned()),
+            bucket_name: first_bucket
+                .and_then(|bucket| bucket.name.as_ref())
+                .map(|name| name.as_str().to_owned()),
+            buckets,
+            name_prefix: deserialized.name_prefix,
+        })
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            application_key_id = application_key_id.as_str(),
+        )
+    )
+)]
 pub async fn b2_authorize_account<'a>(
     basic_uri: &str,
     application_key_id: ApplicationKeyIdRef<'a>,
@@ -72,10 +175,10 @@ pub async fn b2_authorize_account<'a>(
 ) -> Result<AuthorizeAccountOk, errors::AuthorizeError> {
     let url = format!("{}/b2api/v2/b2_authorize_account", basic_uri);
     //https://api.backblazeb2.com
-    let resp = reqwest::Client::new()
+    let resp = crate::transport::client()
         .get(url)
         .basic_auth(application_key_id.as_str(), Some(application_key.as_str()))
-        .send()
+        .send_with_transport()
         .await?;
     if resp.status() == http::StatusCode::OK {
         let auth_ok: AuthorizeAccountOk = resp.json().await?;
@@ -100,7 +203,39 @@ mod test {
             &FAKE_APPLICATION_KEY.to_owned().try_into().unwrap(),
         )
         .await;
-        assert!(res.is_ok());
+        let auth_ok = res.unwrap();
+        let allowed = auth_ok.allowed();
+        assert_eq!(1, allowed.buckets.len());
+        assert_eq!("BUCKET_ID", allowed.bucket_id().unwrap().as_str());
+        assert_eq!("BUCKET_NAME", allowed.bucket_name().unwrap().as_str());
+        #[allow(deprecated)]
+        {
+            assert_eq!(Some("BUCKET_ID"), allowed.bucket_id.as_deref());
+            assert_eq!(Some("BUCKET_NAME"), allowed.bucket_name.as_deref());
+        }
+    }
+
+    #[test]
+    fn test_deserialize_allowed_multiple_buckets() {
+        let allowed: AuthorizeAccountAllowed = serde_json::from_value(serde_json::json!({
+            "capabilities": ["listFiles", "readFiles"],
+            "buckets": [
+                {"id": "bucket_id_1", "name": "bucket-name-1"},
+                {"id": "bucket_id_2", "name": null},
+            ],
+            "namePrefix": null,
+        }))
+        .unwrap();
+        assert_eq!(2, allowed.buckets.len());
+        assert_eq!("bucket_id_1", allowed.bucket_id().unwrap().as_str());
+        assert!(allowed.buckets[1].name.is_none());
+        assert!(allowed.is_bucket_allowed(&"bucket_id_2".to_owned().try_into().unwrap()));
+        assert!(!allowed.is_bucket_allowed(&"bucket_id_3".to_owned().try_into().unwrap()));
+
+        // serialized form uses the list, so it can be read back
+        let serialized = serde_json::to_value(&allowed).unwrap();
+        let reread: AuthorizeAccountAllowed = serde_json::from_value(serialized).unwrap();
+        assert_eq!(allowed.buckets, reread.buckets);
     }
 
     #[tokio::test]
@@ -1,6 +1,7 @@
 use super::{
     errors::GenericB2Error, AccountId, ApiUrl, AuthorizationToken, BucketId, FileId, FileName,
 };
+use crate::transport::RequestBuilderExt;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize)]
@@ -40,6 +41,10 @@ struct CancelFileRequest<'s> {
     file_id: &'s FileId,
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(file_id = file_id.as_str()))
+)]
 pub async fn b2_cancel_large_file(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
@@ -47,11 +52,11 @@ pub async fn b2_cancel_large_file(
 ) -> Result<CancelFileOk, GenericB2Error> {
     let url = format!("{}/b2api/v2/b2_cancel_large_file", api_url.as_str());
     let request_data = CancelFileRequest { file_id };
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(&request_data);
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
     } else {
@@ -1,16 +1,19 @@
+use crate::transport::RequestBuilderExt;
 use headers::Range;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use typed_builder::TypedBuilder;
 
 use super::{
-    errors, serialize_header_option, ApiUrl, AuthorizationToken, BucketId, ContentTypeRef, FileId,
-    FileInformation, FileName, FileRetention, LegalHoldOnOff, ServerSideEncryptionCustomerKey,
+    errors, owned_header_option, serialize_header_option,
+    server_side_encryption::json_with_key_id_in_file_info, ApiUrl, AuthorizationToken, BucketId,
+    ContentType, ContentTypeRef, FileId, FileInformation, FileName, FileRetention, LegalHoldOnOff,
+    OwnedServerSideEncryptionCustomerKey, ServerSideEncryptionCustomerKey,
 };
 
 #[cfg(feature = "b2_unstable")]
 use super::FileInfo;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum MetadataDirective {
     COPY,
     REPLACE,
@@ -20,16 +23,16 @@ pub enum MetadataDirective {
 #[serde(rename_all = "camelCase")]
 pub struct CopyFileRequest<'s> {
     ///The ID of the source file being copied.
-    source_file_id: &'s FileId,
+    pub(crate) source_file_id: &'s FileId,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
     /// The ID of the bucket where the copied file will be stored. If this is not set, the copied file will be added to the same bucket as the source file.
     /// Note that the bucket containing the source file and the destination bucket must belong to the same account.
-    destination_bucket_id: Option<&'s BucketId>,
+    pub(crate) destination_bucket_id: Option<&'s BucketId>,
 
     /// The name of the new file being created.
-    file_name: &'s FileName,
+    pub(crate) file_name: &'s FileName,
 
     #[builder(default, setter(strip_option))]
     #[serde(
@@ -37,12 +40,12 @@ pub struct CopyFileRequest<'s> {
         serialize_with = "serialize_header_option"
     )]
     /// The range of bytes to copy. If not provided, the whole source file will be copied.
-    range: Option<&'s Range>,
+    pub(crate) range: Option<&'s Range>,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
     /// The strategy for how to populate metadata for the new file. If COPY is the indicated strategy, then supplying the contentType or fileInfo param is an error.
-    metadata_directive: Option<MetadataDirective>,
+    pub(crate) metadata_directive: Option<MetadataDirective>,
 
     #[builder(default, setter(strip_option))]
     #[serde(
@@ -51,7 +54,7 @@ pub struct CopyFileRequest<'s> {
     )]
     /// Must only be supplied if the metadataDirective is REPLACE.
     /// The MIME type of the content of the file, which will be returned in the Content-Type header when downloading the file.
-    content_type: Option<ContentTypeRef<'s>>,
+    pub(crate) content_type: Option<ContentTypeRef<'s>>,
 
     #[cfg(feature = "b2_unstable")]
     #[builder(default, setter(strip_option))]
@@ -59,40 +62,85 @@ pub struct CopyFileRequest<'s> {
     /// Must only be supplied if the metadataDirective is REPLACE.
     /// This field stores the metadata that will be stored with the file.
     /// It follows the same rules that are applied to b2_upload_file
-    file_info: Option<&'s FileInfo>,
+    pub(crate) file_info: Option<&'s FileInfo>,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
     /// If present, specifies the File Lock retention settings for the new file. Setting the value requires the writeFileRetentions capability and that the destination bucket is File Lock-enabled.
-    file_retention: Option<&'s FileRetention>,
+    pub(crate) file_retention: Option<&'s FileRetention>,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
     /// If present, specifies the File Lock legal hold status for the new file. Setting the value requires the writeFileLegalHolds capability and that the destination bucket is File Lock-enabled.
-    legal_hold: Option<&'s LegalHoldOnOff>,
+    pub(crate) legal_hold: Option<&'s LegalHoldOnOff>,
 
-    #[builder(default, setter(strip_option))]
+    #[builder(default, setter(strip_option, into))]
     #[serde(skip_serializing_if = "Option::is_none")]
     /// If present, specifies the parameters for Backblaze B2 to use for accessing the source file data using Server-Side Encryption. This parameter is required if and only if the source file has been encrypted using Server-Side Encryption with Customer-Managed Keys (SSE-C), and the provided encryption key must match the one with which the source file was encrypted.
-    source_server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+    pub(crate) source_server_side_encryption: Option<ServerSideEncryptionCustomerKey<'s>>,
 
-    #[builder(default, setter(strip_option))]
+    #[builder(default, setter(strip_option, into))]
     #[serde(skip_serializing_if = "Option::is_none")]
     /// If present, specifies the parameters for Backblaze B2 to use for encrypting the copied data before storing the destination file using Server-Side Encryption.
-    destination_server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+    pub(crate) destination_server_side_encryption: Option<ServerSideEncryptionCustomerKey<'s>>,
+
+    #[builder(default)]
+    #[serde(skip)]
+    /// If set and the metadataDirective is REPLACE, the key id of an SSE-C destination key is recorded in the file info (see [crate::v2::SSE_C_KEY_ID_FILE_INFO]), unless `file_info` already contains it.
+    pub(crate) record_sse_c_key_id: bool,
+}
+
+owned_request! {
+    /// Owned version of [CopyFileRequest], see [OwnedCopyFileRequest::as_request]
+    OwnedCopyFileRequest => CopyFileRequest {
+        source_file_id: FileId [borrow],
+        destination_bucket_id: Option<BucketId> [borrow_option],
+        file_name: FileName [borrow],
+        #[serde(default, with = "owned_header_option")]
+        range: Option<Range> [borrow_option],
+        metadata_directive: Option<MetadataDirective> [clone],
+        #[serde(default, with = "owned_header_option")]
+        content_type: Option<ContentType> [borrow_option],
+        file_info: Option<FileInfo> [borrow_option, feature = "b2_unstable"],
+        file_retention: Option<FileRetention> [borrow_option],
+        legal_hold: Option<LegalHoldOnOff> [borrow_option],
+        source_server_side_encryption: Option<OwnedServerSideEncryptionCustomerKey> [customer_key],
+        destination_server_side_encryption: Option<OwnedServerSideEncryptionCustomerKey> [customer_key],
+        #[serde(default)]
+        record_sse_c_key_id: bool [clone],
+    }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            file_id = request.source_file_id.as_str(),
+            file_name = request.file_name.as_str(),
+        )
+    )
+)]
 pub async fn b2_copy_file(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
     request: &CopyFileRequest<'_>,
 ) -> Result<FileInformation, errors::CopyError> {
     let url = format!("{}/b2api/v2/b2_copy_file", api_url.as_str());
-    let request = reqwest::Client::new()
+    // the file info can only be set when the metadata is replaced
+    let sse = match request.metadata_directive {
+        Some(MetadataDirective::REPLACE) if request.record_sse_c_key_id => {
+            request.destination_server_side_encryption.as_ref()
+        }
+        _ => None,
+    };
+    let body = json_with_key_id_in_file_info(request, sse);
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
-        .json(request);
-    let resp = request.send().await?;
+        .json(&body);
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
     } else {
@@ -1,23 +1,25 @@
+use crate::transport::RequestBuilderExt;
 use headers::Range;
 use serde::Serialize;
 use typed_builder::TypedBuilder;
 
 use super::{
-    errors, serialize_header_option, ApiUrl, AuthorizationToken, FileId, PartNumber,
-    ServerSideEncryptionCustomerKey, UploadPartOk,
+    errors, owned_header_option, serialize_header_option, ApiUrl, AuthorizationToken, FileId,
+    OwnedServerSideEncryptionCustomerKey, PartNumber, ServerSideEncryptionCustomerKey,
+    UploadPartOk,
 };
 
 #[derive(Debug, Serialize, TypedBuilder)]
 #[serde(rename_all = "camelCase")]
 pub struct CopyPartRequest<'s> {
     ///The ID of the source file being copied.
-    source_file_id: &'s FileId,
+    pub(crate) source_file_id: &'s FileId,
 
     ///The ID of the large file the part will belong to, as returned by b2_start_large_file.
-    large_file_id: &'s FileId,
+    pub(crate) large_file_id: &'s FileId,
 
     ///A number from 1 to 10000. The parts uploaded for one file must have contiguous numbers, starting with 1.
-    part_number: PartNumber,
+    pub(crate) part_number: PartNumber,
 
     #[builder(default, setter(strip_option))]
     #[serde(
@@ -25,30 +27,54 @@ pub struct CopyPartRequest<'s> {
         serialize_with = "serialize_header_option"
     )]
     /// The range of bytes to copy. If not provided, the whole source file will be copied.
-    range: Option<&'s Range>,
+    pub(crate) range: Option<&'s Range>,
 
-    #[builder(default, setter(strip_option))]
+    #[builder(default, setter(strip_option, into))]
     #[serde(skip_serializing_if = "Option::is_none")]
     /// If present, specifies the parameters for Backblaze B2 to use for accessing the source file data using Server-Side Encryption. This parameter is required if and only if the source file has been encrypted using Server-Side Encryption with Customer-Managed Keys (SSE-C), and the provided encryption key must match the one with which the source file was encrypted.
-    source_server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+    pub(crate) source_server_side_encryption: Option<ServerSideEncryptionCustomerKey<'s>>,
 
-    #[builder(default, setter(strip_option))]
+    #[builder(default, setter(strip_option, into))]
     #[serde(skip_serializing_if = "Option::is_none")]
     /// If present, specifies the parameters for Backblaze B2 to use for encrypting the copied data before storing the destination file using Server-Side Encryption.
-    destination_server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+    pub(crate) destination_server_side_encryption: Option<ServerSideEncryptionCustomerKey<'s>>,
+}
+
+owned_request! {
+    /// Owned version of [CopyPartRequest], see [OwnedCopyPartRequest::as_request]
+    OwnedCopyPartRequest => CopyPartRequest {
+        source_file_id: FileId [borrow],
+        large_file_id: FileId [borrow],
+        part_number: PartNumber [clone],
+        #[serde(default, with = "owned_header_option")]
+        range: Option<Range> [borrow_option],
+        source_server_side_encryption: Option<OwnedServerSideEncryptionCustomerKey> [customer_key],
+        destination_server_side_encryption: Option<OwnedServerSideEncryptionCustomerKey> [customer_key],
+    }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            file_id = request.large_file_id.as_str(),
+            part_number = request.part_number.as_u16(),
+        )
+    )
+)]
 pub async fn b2_copy_part<'a>(
     api_url: &'a ApiUrl,
     authorization_token: &'a AuthorizationToken,
     request: &'a CopyPartRequest<'a>,
 ) -> Result<UploadPartOk, errors::CopyError> {
     let url = format!("{}/b2api/v2/b2_copy_part", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(request);
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
     } else {
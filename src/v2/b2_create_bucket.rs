@@ -1,9 +1,11 @@
+use crate::transport::RequestBuilderExt;
 use serde::Serialize;
 use typed_builder::TypedBuilder;
 
 use super::{
     b2_list_buckets::Bucket, buckets::LifeCycleRule, errors::CreateBucketError, AccountId, ApiUrl,
-    AuthorizationToken, BucketInfo, BucketName, BucketType, ServerSideEncryption,
+    AuthorizationToken, BucketInfo, BucketName, InvalidData, ServerSideEncryption,
+    SettableBucketType,
 };
 
 #[derive(Debug, Serialize, TypedBuilder)]
@@ -13,7 +15,8 @@ pub struct CreateBucketRequest<'s> {
     account_id: &'s AccountId,
     /// The name to give the new bucket.
     bucket_name: &'s BucketName,
-    bucket_type: BucketType, // TODO: use differnt type, that allows all private / all public only
+    /// Either `allPublic`, meaning that files in this bucket can be downloaded by anybody, or `allPrivate`, meaning that you need a bucket authorization token to download the files.
+    bucket_type: SettableBucketType,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -33,7 +36,7 @@ pub struct CreateBucketRequest<'s> {
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    /// The initial list of lifecycle rules for this bucket.
+    /// The initial list of lifecycle rules for this bucket, checked with [LifeCycleRule::validate_rules] (invalid rules are rejected without being sent).
     lifecycle_rules: Option<&'s [LifeCycleRule]>,
 
     #[builder(default, setter(strip_option))]
@@ -42,20 +45,90 @@ pub struct CreateBucketRequest<'s> {
     default_server_side_encryption: Option<ServerSideEncryption>,
 }
 
+owned_request! {
+    /// Owned version of [CreateBucketRequest], see [OwnedCreateBucketRequest::as_request]
+    OwnedCreateBucketRequest => CreateBucketRequest {
+        account_id: AccountId [borrow],
+        bucket_name: BucketName [borrow],
+        bucket_type: SettableBucketType [clone],
+        bucket_info: Option<BucketInfo> [borrow_option],
+        cors_rules: Option<serde_json::Value> [borrow_option, feature = "b2_unstable"],
+        file_lock_enabled: Option<bool> [clone],
+        lifecycle_rules: Option<Vec<LifeCycleRule>> [borrow_option],
+        default_server_side_encryption: Option<ServerSideEncryption> [clone],
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            bucket_name = request.bucket_name.as_str(),
+        )
+    )
+)]
 pub async fn b2_create_bucket<'a>(
     api_url: &'a ApiUrl,
     authorization_token: &AuthorizationToken,
     request: &'a CreateBucketRequest<'a>,
 ) -> Result<Bucket, CreateBucketError> {
+    if let Some(lifecycle_rules) = request.lifecycle_rules {
+        LifeCycleRule::validate_rules(lifecycle_rules).map_err(|error| {
+            CreateBucketError::Unexpected {
+                raw_error: crate::v2::Error::InvalidData(InvalidData::new(error.to_string())),
+                retry: None,
+            }
+        })?;
+    }
     let url = format!("{}/b2api/v2/b2_create_bucket", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(request);
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
     } else {
         Err(CreateBucketError::from_response(resp).await)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::v2::test::mock_server::{FAKE_ACCOUNT_ID, FAKE_AUTHORIZATION_TOKEN};
+
+    #[tokio::test]
+    async fn test_invalid_lifecycle_rules() {
+        // no day field set
+        let rules = [LifeCycleRule::new(
+            None,
+            None,
+            "logs/".to_owned().try_into().unwrap(),
+        )];
+        let account_id = AccountId(FAKE_ACCOUNT_ID.to_owned());
+        let bucket_name: BucketName = "new-bucket".to_owned().try_into().unwrap();
+        let request = CreateBucketRequest::builder()
+            .account_id(&account_id)
+            .bucket_name(&bucket_name)
+            .bucket_type(SettableBucketType::AllPrivate)
+            .lifecycle_rules(&rules)
+            .build();
+        // the request is rejected before it is sent, so nothing listens on this url
+        let res = b2_create_bucket(
+            &ApiUrl("http://localhost:1".to_owned()),
+            &AuthorizationToken(FAKE_AUTHORIZATION_TOKEN.to_owned()),
+            &request,
+        )
+        .await;
+        assert!(matches!(
+            res,
+            Err(CreateBucketError::Unexpected {
+                raw_error: crate::v2::Error::InvalidData(_),
+                retry: None,
+            })
+        ));
+    }
+}
@@ -1,12 +1,13 @@
+use crate::transport::RequestBuilderExt;
 use std::{fmt::Display, num::NonZeroU32, time::Duration};
 
 use serde::{Deserialize, Serialize};
 use typed_builder::TypedBuilder;
 
 use super::{
-    errors::GenericB2Error, AccountId, ApiUrl, ApplicationKey, ApplicationKeyId,
-    ApplicationKeyIdRef, AuthorizationToken, BucketId, Capabilities, FileNamePrefix, KeyName,
-    KeyNameRef, TimeStamp,
+    b2_delete_key::KeyBuckets, errors::GenericB2Error, AccountId, ApiUrl, ApplicationKey,
+    ApplicationKeyId, ApplicationKeyIdRef, AuthorizationToken, BucketId, Capabilities,
+    FileNamePrefix, InvalidData, KeyName, KeyNameRef, TimeStamp,
 };
 
 #[derive(Debug)]
@@ -74,25 +75,43 @@ impl TryFrom<Duration> for ValidKeyLifeTimeInSeconds {
 #[derive(Debug, Serialize, TypedBuilder)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateKeyRequest<'s> {
-    account_id: &'s AccountId,
-    capabilities: &'s Capabilities,
+    pub(crate) account_id: &'s AccountId,
+    pub(crate) capabilities: &'s Capabilities,
     /// A name for this key. There is no requirement that the name be unique. The name cannot be used to look up the key. Names can contain letters, numbers, and "-", and are limited to 100 characters.
-    key_name: KeyNameRef<'s>,
+    pub(crate) key_name: KeyNameRef<'s>,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
     /// When provided, the key will expire after the given number of seconds, and will have expirationTimestamp set. Value must be a positive integer, and must be less than 1000 days (in seconds).
-    valid_duration_in_seconds: Option<ValidKeyLifeTimeInSeconds>, //todo
+    pub(crate) valid_duration_in_seconds: Option<ValidKeyLifeTimeInSeconds>, //todo
 
     #[builder(default, setter(strip_option))]
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip)] // serialized by CreateKeyBody, which also takes a single entry of bucket_ids
     /// When present, the new key can only access this bucket. When set, only these capabilities can be specified: listAllBucketNames, listBuckets, readBuckets, readBucketEncryption, writeBucketEncryption, readBucketRetentions, writeBucketRetentions, listFiles, readFiles, shareFiles, writeFiles, deleteFiles, readFileLegalHolds, writeFileLegalHolds, readFileRetentions, writeFileRetentions, and bypassGovernance.
-    bucket_id: Option<&'s BucketId>,
+    pub(crate) bucket_id: Option<&'s BucketId>,
+
+    #[builder(default, setter(strip_option))]
+    #[serde(skip)]
+    /// When present, the new key can only access this bucket, same as `bucket_id`. API v2 can only restrict a key to a single bucket, so requests with more or less than one bucket or with `bucket_id` set as well are rejected without being sent.
+    pub(crate) bucket_ids: Option<&'s [BucketId]>,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    ///When present, restricts access to files whose names start with the prefix. You must set bucketId when setting this.
-    name_prefix: Option<&'s FileNamePrefix>,
+    ///When present, restricts access to files whose names start with the prefix. You must set bucketId (or bucketIds) when setting this.
+    pub(crate) name_prefix: Option<&'s FileNamePrefix>,
+}
+
+owned_request! {
+    /// Owned version of [CreateKeyRequest], see [OwnedCreateKeyRequest::as_request]
+    OwnedCreateKeyRequest => CreateKeyRequest {
+        account_id: AccountId [borrow],
+        capabilities: Capabilities [borrow],
+        key_name: KeyName [borrow],
+        valid_duration_in_seconds: Option<ValidKeyLifeTimeInSeconds> [clone],
+        bucket_id: Option<BucketId> [borrow_option],
+        bucket_ids: Option<Vec<BucketId>> [borrow_option],
+        name_prefix: Option<FileNamePrefix> [borrow_option],
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -114,8 +133,9 @@ pub struct CreatedKeyInformation {
 
     expiration_timestamp: Option<TimeStamp>,
 
-    /// When present, restricts access to one bucket.
-    bucket_id: Option<BucketId>,
+    /// When not empty, restricts access to these buckets.
+    #[serde(flatten)]
+    buckets: KeyBuckets,
 
     ///When present, restricts access to files whose names start with the prefix.
     name_prefix: Option<FileNamePrefix>,
@@ -132,7 +152,7 @@ impl CreatedKeyInformation {
     }
 
     /// Get a reference to the created key information's application key id.
-    pub fn application_key_id(&self) -> ApplicationKeyIdRef {
+    pub fn application_key_id(&self) -> ApplicationKeyIdRef<'_> {
         &self.application_key_id
     }
 
@@ -157,8 +177,15 @@ impl CreatedKeyInformation {
     }
 
     /// Get a reference to the created key information's bucket id.
+    ///
+    /// If the key is restricted to multiple buckets, this is the first of them, use [Self::bucket_ids] to get all.
     pub fn bucket_id(&self) -> Option<&BucketId> {
-        self.bucket_id.as_ref()
+        self.buckets.as_slice().first()
+    }
+
+    /// Get the ids of all buckets the created key is restricted to, empty if the key is not restricted to any bucket.
+    pub fn bucket_ids(&self) -> &[BucketId] {
+        self.buckets.as_slice()
     }
 
     /// Get a reference to the created key information's name prefix.
@@ -173,20 +200,147 @@ impl CreatedKeyInformation {
     }
 }
 
+/// Body of [b2_create_key], with a single entry of [CreateKeyRequest::bucket_ids] sent as `bucketId`
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateKeyBody<'s> {
+    #[serde(flatten)]
+    request: &'s CreateKeyRequest<'s>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bucket_id: Option<&'s BucketId>,
+}
+
+fn invalid_request(message: &str) -> GenericB2Error {
+    GenericB2Error::Unexpected {
+        raw_error: crate::v2::Error::InvalidData(InvalidData::new(message.to_owned())),
+        retry: None,
+    }
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 pub async fn b2_create_key<'a>(
     api_url: &'a ApiUrl,
     authorization_token: &'a AuthorizationToken,
     request: &'a CreateKeyRequest<'a>,
 ) -> Result<CreatedKeyInformation, GenericB2Error> {
+    let bucket_id = match (request.bucket_id, request.bucket_ids) {
+        (bucket_id, None) => bucket_id,
+        (None, Some([bucket_id])) => Some(bucket_id),
+        (Some(_), Some(_)) => {
+            return Err(invalid_request(
+                "Only one of bucket_id and bucket_ids may be set",
+            ))
+        }
+        (None, Some(_)) => {
+            // b2_create_key of API v2 ignores bucketIds, so the key would not be restricted at all
+            return Err(invalid_request(
+                "API v2 can only restrict a key to exactly one bucket",
+            ));
+        }
+    };
     let url = format!("{}/b2api/v2/b2_create_key", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
-        .json(request);
-    let resp = request.send().await?;
+        .json(&CreateKeyBody { request, bucket_id });
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
     } else {
         Err(GenericB2Error::from_response(resp).await)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::v2::{
+        test::mock_server::{FAKE_ACCOUNT_ID, FAKE_AUTHORIZATION_TOKEN, FAKE_BUCKET_ID},
+        Capability,
+    };
+
+    fn bucket_id(id: &str) -> BucketId {
+        id.to_owned().try_into().unwrap()
+    }
+
+    async fn create_key(
+        api_url: &ApiUrl,
+        bucket_id: Option<&BucketId>,
+        bucket_ids: Option<&[BucketId]>,
+    ) -> Result<CreatedKeyInformation, GenericB2Error> {
+        let account_id = AccountId(FAKE_ACCOUNT_ID.to_owned());
+        let capabilities = Capability::ListFiles | Capability::ReadFiles;
+        let key_name: KeyName = "customer-1".to_owned().try_into().unwrap();
+        let request = CreateKeyRequest {
+            account_id: &account_id,
+            capabilities: &capabilities,
+            key_name: &key_name,
+            valid_duration_in_seconds: None,
+            bucket_id,
+            bucket_ids,
+            name_prefix: None,
+        };
+        let authorization_token = AuthorizationToken(FAKE_AUTHORIZATION_TOKEN.to_owned());
+        b2_create_key(api_url, &authorization_token, &request).await
+    }
+
+    fn assert_rejected(res: Result<CreatedKeyInformation, GenericB2Error>) {
+        assert!(matches!(
+            res,
+            Err(GenericB2Error::Unexpected {
+                raw_error: crate::v2::Error::InvalidData(_),
+                retry: None,
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_single_bucket_ids_sent_as_bucket_id() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_create_key"))
+            .and(body_json(json!({
+                "accountId": FAKE_ACCOUNT_ID,
+                "capabilities": ["listFiles", "readFiles"],
+                "keyName": "customer-1",
+                "bucketId": FAKE_BUCKET_ID,
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "keyName": "customer-1",
+                "applicationKeyId": "k1",
+                "applicationKey": "secret",
+                "capabilities": ["listFiles", "readFiles"],
+                "accountId": FAKE_ACCOUNT_ID,
+                "expirationTimestamp": null,
+                "bucketId": FAKE_BUCKET_ID,
+                "namePrefix": null,
+                "options": ["s3"],
+            })))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        let api_url = ApiUrl(mock_server.uri());
+        let bucket_ids = [bucket_id(FAKE_BUCKET_ID)];
+        let created = create_key(&api_url, None, Some(&bucket_ids)).await.unwrap();
+        assert_eq!(&bucket_ids[..], created.bucket_ids());
+        create_key(&api_url, Some(&bucket_ids[0]), None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bucket_ids_rejected() {
+        // the requests are rejected before they are sent, so nothing listens on this url
+        let api_url = ApiUrl("http://localhost:1".to_owned());
+        let bucket_ids = [bucket_id(FAKE_BUCKET_ID), bucket_id("other_bucket")];
+        assert_rejected(create_key(&api_url, Some(&bucket_ids[0]), Some(&bucket_ids[..1])).await);
+        assert_rejected(create_key(&api_url, None, Some(&bucket_ids)).await);
+        assert_rejected(create_key(&api_url, None, Some(&[])).await);
+    }
+}
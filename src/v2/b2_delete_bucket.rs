@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use serde::Serialize;
 
 use super::{
@@ -12,6 +13,10 @@ struct DeleteBucketRequest<'s> {
     bucket_id: &'s BucketId,
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(bucket_id = bucket_id.as_str()))
+)]
 pub async fn b2_delete_bucket(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
@@ -23,11 +28,11 @@ pub async fn b2_delete_bucket(
         account_id,
         bucket_id,
     };
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(&delete_bucket_request);
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
     } else {
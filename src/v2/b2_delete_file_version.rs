@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use serde::{Deserialize, Serialize};
 use typed_builder::TypedBuilder;
 
@@ -16,6 +17,15 @@ pub struct DeleteFileVersionRequest<'s> {
     bypass_governance: Option<bool>,
 }
 
+owned_request! {
+    /// Owned version of [DeleteFileVersionRequest], see [OwnedDeleteFileVersionRequest::as_request]
+    OwnedDeleteFileVersionRequest => DeleteFileVersionRequest {
+        file_name: FileName [borrow],
+        file_id: FileId [borrow],
+        bypass_governance: Option<bool> [clone],
+    }
+}
+
 impl<'s> DeleteFileVersionRequest<'s> {
     pub fn new(
         file_name: &'s FileName,
@@ -54,17 +64,28 @@ impl DeleteFileVersionOk {
 /// If the version you delete is the latest version, and there are older versions, then the most recent older version will become the current version, and be the one that you'll get when downloading by name. See the File Versions page for more details.
 ///
 ///When used on an unfinished large file, this call has the same effect as b2_cancel_large_file.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            file_id = request.file_id.as_str(),
+            file_name = request.file_name.as_str(),
+        )
+    )
+)]
 pub async fn b2_delete_file_version<'a>(
     api_url: &'a ApiUrl,
     authorization_token: &'a AuthorizationToken,
     request: &'a DeleteFileVersionRequest<'a>,
 ) -> Result<DeleteFileVersionOk, DeleteFileVersionError> {
     let url = format!("{}/b2api/v2/b2_delete_file_version", api_url.as_str());
-    let resp = reqwest::Client::new()
+    let resp = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(&request)
-        .send()
+        .send_with_transport()
         .await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
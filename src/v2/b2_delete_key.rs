@@ -1,4 +1,5 @@
-use serde::{Deserialize, Serialize};
+use crate::transport::RequestBuilderExt;
+use serde::{Deserialize, Deserializer, Serialize};
 
 use super::{
     errors::GenericB2Error, AccountId, ApiUrl, ApplicationKeyId, ApplicationKeyIdRef,
@@ -11,6 +12,44 @@ struct DeleteKeyRequest<'s> {
     application_key_id: ApplicationKeyIdRef<'s>,
 }
 
+/// Buckets an application key is restricted to.
+///
+/// Older keys (and the v2 api) report a single `bucketId`, keys restricted to multiple buckets report a
+/// `bucketIds` list instead. Both are accepted and merged into one list, which is empty for unrestricted keys.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct KeyBuckets(Vec<BucketId>);
+
+impl KeyBuckets {
+    pub(crate) fn as_slice(&self) -> &[BucketId] {
+        self.0.as_slice()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeserializableKeyBuckets {
+    #[serde(default)]
+    bucket_id: Option<BucketId>,
+    #[serde(default)]
+    bucket_ids: Option<Vec<BucketId>>,
+}
+
+impl<'de> Deserialize<'de> for KeyBuckets {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let deserialized = DeserializableKeyBuckets::deserialize(deserializer)?;
+        let mut bucket_ids = deserialized.bucket_ids.unwrap_or_default();
+        if let Some(bucket_id) = deserialized.bucket_id {
+            if !bucket_ids.contains(&bucket_id) {
+                bucket_ids.insert(0, bucket_id);
+            }
+        }
+        Ok(Self(bucket_ids))
+    }
+}
+
 //TODO: used by list_keys as well, maybe move?
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -28,8 +67,9 @@ pub struct KeyInformation {
 
     expiration_timestamp: Option<TimeStamp>,
 
-    /// When present, restricts access to one bucket.
-    bucket_id: Option<BucketId>,
+    /// When not empty, restricts access to these buckets.
+    #[serde(flatten)]
+    buckets: KeyBuckets,
 
     ///When present, restricts access to files whose names start with the prefix.
     name_prefix: Option<FileNamePrefix>,
@@ -46,7 +86,7 @@ impl KeyInformation {
     }
 
     /// Get a reference to the key information's application key id.
-    pub fn application_key_id(&self) -> super::ApplicationKeyIdRef {
+    pub fn application_key_id(&self) -> super::ApplicationKeyIdRef<'_> {
         &self.application_key_id
     }
 
@@ -66,8 +106,15 @@ impl KeyInformation {
     }
 
     /// Get a reference to the key information's bucket id.
+    ///
+    /// If the key is restricted to multiple buckets, this is the first of them, use [Self::bucket_ids] to get all.
     pub fn bucket_id(&self) -> Option<&BucketId> {
-        self.bucket_id.as_ref()
+        self.buckets.as_slice().first()
+    }
+
+    /// Get the ids of all buckets the key is restricted to, empty if the key is not restricted to any bucket.
+    pub fn bucket_ids(&self) -> &[BucketId] {
+        self.buckets.as_slice()
     }
 
     /// Get a reference to the key information's name prefix.
@@ -82,6 +129,16 @@ impl KeyInformation {
     }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            application_key_id = application_key_id.as_str(),
+        )
+    )
+)]
 pub async fn b2_delete_key(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
@@ -89,11 +146,11 @@ pub async fn b2_delete_key(
 ) -> Result<KeyInformation, GenericB2Error> {
     let request_body = DeleteKeyRequest { application_key_id };
     let url = format!("{}/b2api/v2/b2_delete_key", api_url.as_str());
-    let resp = reqwest::Client::new()
+    let resp = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(&request_body)
-        .send()
+        .send_with_transport()
         .await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
@@ -101,3 +158,59 @@ pub async fn b2_delete_key(
         Err(GenericB2Error::from_response(resp).await)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_key_information_single_bucket() {
+        let key: KeyInformation = serde_json::from_value(serde_json::json!({
+            "keyName": "single-bucket-key",
+            "applicationKeyId": "key_id",
+            "capabilities": ["readFiles"],
+            "accountId": "account_id",
+            "expirationTimestamp": null,
+            "bucketId": "bucket_id_1",
+            "namePrefix": null,
+            "options": ["s3"],
+        }))
+        .unwrap();
+        assert_eq!("bucket_id_1", key.bucket_id().unwrap().as_str());
+        assert_eq!(1, key.bucket_ids().len());
+    }
+
+    #[test]
+    fn test_deserialize_key_information_multiple_buckets() {
+        let key: KeyInformation = serde_json::from_value(serde_json::json!({
+            "keyName": "multi-bucket-key",
+            "applicationKeyId": "key_id",
+            "capabilities": ["readFiles"],
+            "accountId": "account_id",
+            "expirationTimestamp": null,
+            "bucketIds": ["bucket_id_1", "bucket_id_2"],
+            "namePrefix": null,
+            "options": ["s3"],
+        }))
+        .unwrap();
+        let bucket_ids: Vec<&str> = key.bucket_ids().iter().map(BucketId::as_str).collect();
+        assert_eq!(vec!["bucket_id_1", "bucket_id_2"], bucket_ids);
+    }
+
+    #[test]
+    fn test_deserialize_key_information_unrestricted() {
+        let key: KeyInformation = serde_json::from_value(serde_json::json!({
+            "keyName": "unrestricted-key",
+            "applicationKeyId": "key_id",
+            "capabilities": ["readFiles"],
+            "accountId": "account_id",
+            "expirationTimestamp": null,
+            "bucketId": null,
+            "namePrefix": null,
+            "options": ["s3"],
+        }))
+        .unwrap();
+        assert!(key.bucket_id().is_none());
+        assert!(key.bucket_ids().is_empty());
+    }
+}
@@ -1,8 +1,11 @@
 use super::{
-    errors::DownloadFileError, serialize_header_option, AuthorizationToken,
-    CacheControlHeaderValueRef, ContentDispositionRef, ContentEncodingRef, ContentLanguageRef,
-    ContentTypeRef, DownloadUrl, ExpiresHeaderValueRef, FileId, ServerSideEncryptionCustomerKey,
+    errors::DownloadFileError, owned_header_option, serialize_header_option, AuthorizationToken,
+    CacheControlHeaderValue, CacheControlHeaderValueRef, ContentDisposition, ContentDispositionRef,
+    ContentEncoding, ContentEncodingRef, ContentLanguage, ContentLanguageRef, ContentType,
+    ContentTypeRef, DownloadUrl, ExpiresHeaderValue, ExpiresHeaderValueRef, FileId,
+    OwnedServerSideEncryptionCustomerKey, ServerSideEncryptionCustomerKey,
 };
+use crate::transport::RequestBuilderExt;
 
 use headers::{HeaderMap, HeaderMapExt};
 use serde::Serialize;
@@ -11,7 +14,7 @@ use typed_builder::TypedBuilder;
 #[derive(Debug, TypedBuilder, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DownloadParams<'s> {
-    file_id: &'s FileId,
+    pub(crate) file_id: &'s FileId,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip)] //serialized manually, as it may not be a url parameter
@@ -59,40 +62,87 @@ pub struct DownloadParams<'s> {
     )]
     b2_content_type: Option<ContentTypeRef<'s>>,
 
-    #[builder(default, setter(strip_option))]
+    #[builder(default, setter(strip_option, into))]
     #[serde(skip)] //use custom serializer
-    server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+    server_side_encryption: Option<ServerSideEncryptionCustomerKey<'s>>,
+}
+
+owned_request! {
+    /// Owned version of [DownloadParams], see [OwnedDownloadParams::as_request]
+    OwnedDownloadParams => DownloadParams {
+        file_id: FileId [borrow],
+        #[serde(default, with = "owned_header_option")]
+        range: Option<headers::Range> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        b2_content_disposition: Option<ContentDisposition> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        b2_content_language: Option<ContentLanguage> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        b2_expires: Option<ExpiresHeaderValue> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        b2_cache_control: Option<CacheControlHeaderValue> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        b2_content_encoding: Option<ContentEncoding> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        b2_content_type: Option<ContentType> [borrow_option],
+        server_side_encryption: Option<OwnedServerSideEncryptionCustomerKey> [customer_key],
+    }
+}
+
+impl<'s> DownloadParams<'s> {
+    /// creates a request with all parameters set, shared by download and head requests
+    pub(crate) fn request_builder(
+        &self,
+        download_url: &DownloadUrl,
+        authorization_token: Option<&AuthorizationToken>,
+        method: http::Method,
+    ) -> reqwest::RequestBuilder {
+        let url = format!(
+            "{}/b2api/v2/b2_download_file_by_id?{}",
+            download_url.as_str(),
+            serde_urlencoded::to_string(self).unwrap()
+        );
+        let mut headers = HeaderMap::with_capacity(1);
+        if let Some(range) = self.range {
+            headers.typed_insert(range.clone());
+        }
+
+        let mut request_builder = crate::transport::client()
+            .request(method, url)
+            .headers(headers);
+        if let Some(auth) = authorization_token {
+            request_builder = request_builder.header("Authorization", auth.as_str());
+        }
+        if let Some(sse) = self.server_side_encryption {
+            request_builder = sse.add_to_request_as_header(request_builder);
+        }
+        request_builder
+    }
+
+    /// returns `true` if a range was requested, i.e. PartialContent (206) is expected instead of OK (200)
+    pub(crate) fn has_range(&self) -> bool {
+        self.range.is_some()
+    }
 }
 
 /// downloads a file by ID, does return a reqwest::Response object, if the server returned http status OK (200)
 /// or PartialContent (206) if a range was used.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(file_id = params.file_id.as_str()))
+)]
 pub async fn b2_download_file_by_id(
     download_url: &DownloadUrl,
     authorization_token: Option<&AuthorizationToken>,
     params: &DownloadParams<'_>,
 ) -> Result<reqwest::Response, DownloadFileError> {
-    let url = format!(
-        "{}/b2api/v2/b2_download_file_by_id?{}",
-        download_url.as_str(),
-        serde_urlencoded::to_string(params).unwrap()
-    );
-    let mut headers = HeaderMap::with_capacity(1);
-    if let Some(range) = params.range {
-        headers.typed_insert(range.clone());
-    }
-
-    let mut request_builder = reqwest::Client::new().get(url).headers(headers);
-    if let Some(auth) = authorization_token {
-        request_builder = request_builder.header("Authorization", auth.as_str());
-    }
-    if let Some(sse) = params.server_side_encryption {
-        request_builder = sse.add_to_request_as_header(request_builder);
-    }
+    let request_builder =
+        params.request_builder(download_url, authorization_token, http::Method::GET);
     let resp = request_builder
-        .send()
+        .send_with_transport()
         .await
         .map_err(DownloadFileError::from)?;
-    let expected_status = if params.range.is_none() {
+    let expected_status = if !params.has_range() {
         http::StatusCode::OK
     } else {
         http::StatusCode::PARTIAL_CONTENT
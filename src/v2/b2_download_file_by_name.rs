@@ -1,12 +1,15 @@
 use super::{
-    errors::DownloadFileError, serialize_header_option, BucketName, CacheControlHeaderValueRef,
-    ContentDispositionRef, ContentEncodingRef, ContentLanguageRef, ContentTypeRef,
-    DownloadAuthorizationToken, DownloadUrl, ExpiresHeaderValueRef, FileName,
+    errors::DownloadFileError, owned_header_option, serialize_header_option, BucketName,
+    CacheControlHeaderValue, CacheControlHeaderValueRef, ContentDisposition, ContentDispositionRef,
+    ContentEncoding, ContentEncodingRef, ContentLanguage, ContentLanguageRef, ContentType,
+    ContentTypeRef, DownloadAuthorizationToken, DownloadUrl, ExpiresHeaderValue,
+    ExpiresHeaderValueRef, FileName, OwnedServerSideEncryptionCustomerKey,
     ServerSideEncryptionCustomerKey,
 };
+use crate::{header_serializer::url_encode, transport::RequestBuilderExt};
 
 use headers::{HeaderMap, HeaderMapExt};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use typed_builder::TypedBuilder;
 
 #[derive(Debug, TypedBuilder)]
@@ -14,8 +17,8 @@ pub struct DownloadFileByNameRequest<'s, AuthToken>
 where
     AuthToken: 's + DownloadAuthorizationToken + Serialize,
 {
-    bucket_name: &'s BucketName,
-    file_name: &'s FileName,
+    pub(crate) bucket_name: &'s BucketName,
+    pub(crate) file_name: &'s FileName,
     #[builder(default, setter(strip_option))]
     range: Option<&'s headers::Range>,
     #[builder(default, setter(strip_option))]
@@ -32,8 +35,8 @@ where
     b2_content_encoding: Option<ContentEncodingRef<'s>>,
     #[builder(default, setter(strip_option))]
     b2_content_type: Option<ContentTypeRef<'s>>,
-    #[builder(default, setter(strip_option))]
-    server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+    #[builder(default, setter(strip_option, into))]
+    server_side_encryption: Option<ServerSideEncryptionCustomerKey<'s>>,
 }
 
 impl<'s, AuthToken> DownloadFileByNameRequest<'s, AuthToken>
@@ -41,7 +44,7 @@ where
     AuthToken: DownloadAuthorizationToken + Serialize,
 {
     /// returns the parameters that cannot be passed as headers as [DownloadFileByNameUrlParameters]
-    fn as_url_params(&'s self) -> DownloadFileByNameUrlParameters<'s> {
+    fn as_url_params(&self) -> DownloadFileByNameUrlParameters<'s> {
         DownloadFileByNameUrlParameters {
             b2_content_disposition: self.b2_content_disposition,
             b2_content_language: self.b2_content_language,
@@ -51,6 +54,114 @@ where
             b2_content_type: self.b2_content_type,
         }
     }
+
+    /// creates a request with all parameters set, shared by download and head requests
+    pub(crate) fn request_builder(
+        &self,
+        download_url: &DownloadUrl,
+        method: http::Method,
+    ) -> reqwest::RequestBuilder {
+        let url = get_b2_download_file_by_name_url(
+            download_url,
+            self.bucket_name,
+            self.file_name,
+            &self.as_url_params(),
+        );
+        let mut request_builder = crate::transport::client().request(method, url);
+        if let Some(auth) = self.authorization {
+            request_builder = request_builder.header("Authorization", auth.download_token_as_str());
+        }
+        if let Some(sse) = self.server_side_encryption {
+            request_builder = sse.add_to_request_as_header(request_builder);
+        }
+        if let Some(range) = self.range {
+            let mut headers = HeaderMap::with_capacity(1);
+            headers.typed_insert(range.clone());
+            request_builder = request_builder.headers(headers);
+        }
+        request_builder
+    }
+
+    /// returns `true` if a range was requested, i.e. PartialContent (206) is expected instead of OK (200)
+    pub(crate) fn has_range(&self) -> bool {
+        self.range.is_some()
+    }
+}
+
+/// Owned version of [DownloadFileByNameRequest], see [OwnedDownloadFileByNameRequest::as_request]
+///
+/// The authorization token is stored as well, keep in mind that it expires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnedDownloadFileByNameRequest<AuthToken> {
+    bucket_name: BucketName,
+    file_name: FileName,
+    #[serde(default, with = "owned_header_option")]
+    range: Option<headers::Range>,
+    authorization: Option<AuthToken>,
+    #[serde(default, with = "owned_header_option")]
+    b2_content_disposition: Option<ContentDisposition>,
+    #[serde(default, with = "owned_header_option")]
+    b2_content_language: Option<ContentLanguage>,
+    #[serde(default, with = "owned_header_option")]
+    b2_expires: Option<ExpiresHeaderValue>,
+    #[serde(default, with = "owned_header_option")]
+    b2_cache_control: Option<CacheControlHeaderValue>,
+    #[serde(default, with = "owned_header_option")]
+    b2_content_encoding: Option<ContentEncoding>,
+    #[serde(default, with = "owned_header_option")]
+    b2_content_type: Option<ContentType>,
+    server_side_encryption: Option<OwnedServerSideEncryptionCustomerKey>,
+}
+
+impl<AuthToken> OwnedDownloadFileByNameRequest<AuthToken>
+where
+    AuthToken: DownloadAuthorizationToken + Serialize,
+{
+    /// request borrowing the values of this owned request
+    pub fn as_request(&self) -> DownloadFileByNameRequest<'_, AuthToken> {
+        DownloadFileByNameRequest {
+            bucket_name: &self.bucket_name,
+            file_name: &self.file_name,
+            range: self.range.as_ref(),
+            authorization: self.authorization.as_ref(),
+            b2_content_disposition: self.b2_content_disposition.as_ref(),
+            b2_content_language: self.b2_content_language.as_ref(),
+            b2_expires: self.b2_expires.as_ref(),
+            b2_cache_control: self.b2_cache_control.as_ref(),
+            b2_content_encoding: self.b2_content_encoding.as_ref(),
+            b2_content_type: self.b2_content_type.as_ref(),
+            server_side_encryption: self
+                .server_side_encryption
+                .as_ref()
+                .map(OwnedServerSideEncryptionCustomerKey::as_customer_key),
+        }
+    }
+}
+
+impl<'s, AuthToken> From<&DownloadFileByNameRequest<'s, AuthToken>>
+    for OwnedDownloadFileByNameRequest<AuthToken>
+where
+    AuthToken: DownloadAuthorizationToken + Serialize + Clone,
+{
+    fn from(request: &DownloadFileByNameRequest<'s, AuthToken>) -> Self {
+        Self {
+            bucket_name: request.bucket_name.clone(),
+            file_name: request.file_name.clone(),
+            range: request.range.cloned(),
+            authorization: request.authorization.cloned(),
+            b2_content_disposition: request.b2_content_disposition.cloned(),
+            b2_content_language: request.b2_content_language.cloned(),
+            b2_expires: request.b2_expires.cloned(),
+            b2_cache_control: request.b2_cache_control.cloned(),
+            b2_content_encoding: request.b2_content_encoding.cloned(),
+            b2_content_type: request.b2_content_type.cloned(),
+            server_side_encryption: request
+                .server_side_encryption
+                .as_ref()
+                .map(OwnedServerSideEncryptionCustomerKey::from),
+        }
+    }
 }
 
 /// Parameters to generate a download url
@@ -103,9 +214,27 @@ pub struct DownloadFileByNameUrlParameters<'s> {
     b2_content_type: Option<ContentTypeRef<'s>>,
 }
 
+impl<'s> DownloadFileByNameUrlParameters<'s> {
+    /// creates parameters, that only override content disposition and type (as used for presigned urls)
+    pub(crate) fn with_content_overrides(
+        b2_content_disposition: Option<ContentDispositionRef<'s>>,
+        b2_content_type: Option<ContentTypeRef<'s>>,
+    ) -> Self {
+        Self {
+            b2_content_disposition,
+            b2_content_language: None,
+            b2_expires: None,
+            b2_cache_control: None,
+            b2_content_encoding: None,
+            b2_content_type,
+        }
+    }
+}
+
 /// gets a download URL for a given file in a given directory
 ///
 /// public as it might be usefull to give this url to a different application/client/...
+/// The file name is percent-encoded, except for the `/` separating its folders.
 pub fn get_b2_download_file_by_name_url<'s>(
     download_url: &DownloadUrl,
     bucket_name: &BucketName,
@@ -116,43 +245,37 @@ pub fn get_b2_download_file_by_name_url<'s>(
         "{}/file/{}/{}?{}",
         download_url.as_str(),
         bucket_name.as_str(),
-        file_name.as_str(),
+        url_encode(file_name.as_str()),
         serde_urlencoded::to_string(params).unwrap()
     )
 }
 
 /// downloads a file by Name, does return a reqwest::Response object, if the server returned http status OK (200)
 /// or PartialContent (206) if a range was used.
-pub async fn b2_download_file_by_name<'a, 'b, AuthToken>(
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            bucket_name = request.bucket_name.as_str(),
+            file_name = request.file_name.as_str(),
+        )
+    )
+)]
+pub async fn b2_download_file_by_name<'b, AuthToken>(
     download_url: &DownloadUrl,
     request: &DownloadFileByNameRequest<'b, AuthToken>,
 ) -> Result<reqwest::Response, DownloadFileError>
 where
     AuthToken: DownloadAuthorizationToken + Serialize,
 {
-    let url = get_b2_download_file_by_name_url(
-        download_url,
-        request.bucket_name,
-        request.file_name,
-        &request.as_url_params(),
-    );
-    let mut request_builder = reqwest::Client::new().get(url);
-    if let Some(auth) = request.authorization {
-        request_builder = request_builder.header("Authorization", auth.download_token_as_str());
-    }
-    if let Some(sse) = request.server_side_encryption {
-        request_builder = sse.add_to_request_as_header(request_builder);
-    }
-    if let Some(range) = request.range {
-        let mut headers = HeaderMap::with_capacity(1);
-        headers.typed_insert(range.clone());
-        request_builder = request_builder.headers(headers);
-    }
+    let request_builder = request.request_builder(download_url, http::Method::GET);
     let resp = request_builder
-        .send()
+        .send_with_transport()
         .await
         .map_err(DownloadFileError::from)?;
-    let expected_status = if request.range.is_none() {
+    let expected_status = if !request.has_range() {
         http::StatusCode::OK
     } else {
         http::StatusCode::PARTIAL_CONTENT
@@ -1,6 +1,7 @@
 use super::{
     errors::LargeFileError, ApiUrl, AuthorizationToken, FileId, FileInformation, Sha1DigestRef,
 };
+use crate::transport::RequestBuilderExt;
 use serde::Serialize;
 
 #[derive(Serialize)]
@@ -10,6 +11,17 @@ struct FinishLargeFileRequest<'shas, 'file_id> {
     part_sha1_array: &'shas [Sha1DigestRef<'shas>],
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            file_id = file_id.as_str(),
+            part_count = part_sha1s.len(),
+        )
+    )
+)]
 pub async fn b2_finish_large_file<'a>(
     api_url: &'a ApiUrl,
     authorization_token: &'a AuthorizationToken,
@@ -22,12 +34,12 @@ pub async fn b2_finish_large_file<'a>(
     };
     let url = format!("{}/b2api/v2/b2_finish_large_file", api_url.as_str());
 
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(&request_data);
 
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
     } else {
@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use std::{
     convert::{TryFrom, TryInto},
     fmt::Display,
@@ -8,9 +9,11 @@ use serde::{Deserialize, Serialize};
 use typed_builder::TypedBuilder;
 
 use super::{
-    errors, serialize_header_option, ApiUrl, AuthorizationToken, BucketId,
-    CacheControlHeaderValueRef, ContentDispositionRef, ContentEncodingRef, ContentLanguageRef,
-    ContentTypeRef, DownloadOnlyAuthorizationToken, ExpiresHeaderValueRef, FileNamePrefix,
+    errors, owned_header_option, serialize_header_option, ApiUrl, AuthorizationToken, BucketId,
+    CacheControlHeaderValue, CacheControlHeaderValueRef, ContentDisposition, ContentDispositionRef,
+    ContentEncoding, ContentEncodingRef, ContentLanguage, ContentLanguageRef, ContentType,
+    ContentTypeRef, DownloadOnlyAuthorizationToken, ExpiresHeaderValue, ExpiresHeaderValueRef,
+    FileNamePrefix,
 };
 
 #[derive(Debug)]
@@ -107,6 +110,50 @@ pub struct GetDownloadAuthorizationRequest<'s> {
     b2_content_type: Option<ContentTypeRef<'s>>,
 }
 
+owned_request! {
+    /// Owned version of [GetDownloadAuthorizationRequest], see [OwnedGetDownloadAuthorizationRequest::as_request]
+    OwnedGetDownloadAuthorizationRequest => GetDownloadAuthorizationRequest {
+        bucket_id: BucketId [borrow],
+        file_name_prefix: FileNamePrefix [borrow],
+        valid_duration_in_seconds: ValidDownloadAuthorizationDurationInSeconds [clone],
+        #[serde(default, with = "owned_header_option")]
+        b2_content_disposition: Option<ContentDisposition> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        b2_content_language: Option<ContentLanguage> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        b2_expires: Option<ExpiresHeaderValue> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        b2_cache_control: Option<CacheControlHeaderValue> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        b2_content_encoding: Option<ContentEncoding> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        b2_content_type: Option<ContentType> [borrow_option],
+    }
+}
+
+impl<'s> GetDownloadAuthorizationRequest<'s> {
+    /// creates a request, that only overrides content disposition and type (as used for presigned urls)
+    pub(crate) fn with_content_overrides(
+        bucket_id: &'s BucketId,
+        file_name_prefix: &'s FileNamePrefix,
+        valid_duration_in_seconds: ValidDownloadAuthorizationDurationInSeconds,
+        b2_content_disposition: Option<ContentDispositionRef<'s>>,
+        b2_content_type: Option<ContentTypeRef<'s>>,
+    ) -> Self {
+        Self {
+            bucket_id,
+            file_name_prefix,
+            valid_duration_in_seconds,
+            b2_content_disposition,
+            b2_content_language: None,
+            b2_expires: None,
+            b2_cache_control: None,
+            b2_content_encoding: None,
+            b2_content_type,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetDownloadAuthorizationOk {
@@ -132,6 +179,16 @@ impl GetDownloadAuthorizationOk {
     }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            bucket_id = request_data.bucket_id.as_str(),
+        )
+    )
+)]
 pub async fn b2_get_download_authorization<'a>(
     api_url: &'a ApiUrl,
     authorization_token: &'a AuthorizationToken,
@@ -142,12 +199,12 @@ pub async fn b2_get_download_authorization<'a>(
         api_url.as_str()
     );
 
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(&request_data);
 
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
     } else {
@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use serde::Serialize;
 
 use super::{errors::GetFileInfoError, ApiUrl, AuthorizationToken, FileId, FileInformation};
@@ -8,17 +9,21 @@ struct GetFileInfoRequest<'a> {
     file_id: &'a FileId,
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(file_id = file_id.as_str()))
+)]
 pub async fn b2_get_file_info(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
     file_id: &FileId,
 ) -> Result<FileInformation, GetFileInfoError> {
     let url = format!("{}/b2api/v2/b2_get_file_info", api_url.as_str());
-    let resp = reqwest::Client::new()
+    let resp = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(&GetFileInfoRequest { file_id })
-        .send()
+        .send_with_transport()
         .await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
@@ -1,6 +1,7 @@
 use super::{
     b2_get_upload_url::UploadUrl, errors::GetUploadUrlError, ApiUrl, AuthorizationToken, FileId,
 };
+use crate::transport::RequestBuilderExt;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -40,17 +41,24 @@ impl<'a> From<&'a FileId> for GetUploadPartUrlRequest<'a> {
     }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(file_id = file_id.as_str()))
+)]
 pub async fn b2_get_upload_part_url(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
     file_id: &FileId,
 ) -> Result<UploadPartUrlParameters, GetUploadUrlError> {
     let url = format!("{}/b2api/v2/b2_get_upload_part_url", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(&GetUploadPartUrlRequest::from(file_id));
-    let resp = request.send().await.map_err(GetUploadUrlError::from)?;
+    let resp = request
+        .send_with_transport()
+        .await
+        .map_err(GetUploadUrlError::from)?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await.map_err(GetUploadUrlError::from)?)
     } else {
@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use serde::{Deserialize, Serialize};
 
 use super::{errors::GetUploadUrlError, ApiUrl, AuthorizationToken, BucketId};
@@ -49,17 +50,24 @@ impl UploadParameters {
     }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(bucket_id = bucket_id.as_str()))
+)]
 pub async fn b2_get_upload_url(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
     bucket_id: &BucketId,
 ) -> Result<UploadParameters, GetUploadUrlError> {
     let url = format!("{}/b2api/v2/b2_get_upload_url", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(&GetUploadUrlRequest::from(bucket_id));
-    let resp = request.send().await.map_err(GetUploadUrlError::from)?;
+    let resp = request
+        .send_with_transport()
+        .await
+        .map_err(GetUploadUrlError::from)?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await.map_err(GetUploadUrlError::from)?)
     } else {
@@ -0,0 +1,382 @@
+//! Retrieves the metadata of a file using a HEAD request on the download endpoints, i.e. without
+//! downloading its content (see [b2_download_file_by_name](https://www.backblaze.com/b2/docs/b2_download_file_by_name.html))
+use crate::transport::RequestBuilderExt;
+use std::{collections::HashMap, str::FromStr};
+
+use headers::HeaderMap;
+use mime::Mime;
+use serde::Serialize;
+
+use crate::header_serializer::url_decode;
+
+use super::{
+    errors::DownloadFileError, AuthorizationToken, DownloadAuthorizationToken,
+    DownloadFileByNameRequest, DownloadParams, DownloadUrl, FileId, FileName, FileRetention,
+    FileRetentionMode, InvalidData, LegalHoldOnOff, ServerSideEncryption, Sha1Digest, TimeStamp,
+    SSE_C_KEY_ID_FILE_INFO,
+};
+
+const FILE_INFO_HEADER_PREFIX: &str = "x-bz-info-";
+
+/// Metadata of a file as returned by the download endpoints
+#[derive(Debug)]
+pub struct HeadFileOk {
+    file_id: FileId,
+    file_name: FileName,
+    content_length: u64,
+    content_type: Option<Mime>,
+    content_sha1: Option<Sha1Digest>,
+    upload_timestamp: TimeStamp,
+    file_info: HashMap<String, String>,
+    server_side_encryption: Option<ServerSideEncryption>,
+    file_retention: Option<FileRetention>,
+    legal_hold: Option<LegalHoldOnOff>,
+}
+
+fn header_str<'h>(headers: &'h HeaderMap, name: &str) -> Result<Option<&'h str>, InvalidData> {
+    headers
+        .get(name)
+        .map(|v| {
+            v.to_str()
+                .map_err(|_| InvalidData::new(format!("Header {} is not valid ascii", name)))
+        })
+        .transpose()
+}
+
+fn required_header_str<'h>(headers: &'h HeaderMap, name: &str) -> Result<&'h str, InvalidData> {
+    header_str(headers, name)?
+        .ok_or_else(|| InvalidData::new(format!("Header {} is missing", name)))
+}
+
+fn parse_header<T: FromStr>(value: &str, name: &str) -> Result<T, InvalidData> {
+    value
+        .parse()
+        .map_err(|_| InvalidData::new(format!("Invalid value for header {}: {}", name, value)))
+}
+
+fn decode_header(value: &str, name: &str) -> Result<String, InvalidData> {
+    url_decode(value).ok_or_else(|| {
+        InvalidData::new(format!(
+            "Header {} is not correctly url encoded: {}",
+            name, value
+        ))
+    })
+}
+
+impl HeadFileOk {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, InvalidData> {
+        let file_id = FileId::try_from(required_header_str(headers, "x-bz-file-id")?.to_owned())
+            .map_err(|e| InvalidData::new(format!("Invalid file id: {}", e)))?;
+        let file_name = FileName::try_from(decode_header(
+            required_header_str(headers, "x-bz-file-name")?,
+            "x-bz-file-name",
+        )?)
+        .map_err(|e| InvalidData::new(format!("Invalid file name: {}", e)))?;
+        let content_length = parse_header(
+            required_header_str(headers, "content-length")?,
+            "content-length",
+        )?;
+        let content_type = header_str(headers, "content-type")?
+            .map(|v| parse_header(v, "content-type"))
+            .transpose()?;
+        // large files do not have a sha1 ("none"), files uploaded with the checksum at the end
+        // of the body are reported as "unverified:<sha1>"
+        let content_sha1 = match header_str(headers, "x-bz-content-sha1")? {
+            None | Some("none") => None,
+            Some(v) => {
+                let v = v.strip_prefix("unverified:").unwrap_or(v);
+                Some(Sha1Digest::try_from(v).map_err(|_| {
+                    InvalidData::new(format!("Invalid value for header x-bz-content-sha1: {}", v))
+                })?)
+            }
+        };
+        let upload_timestamp = parse_header(
+            required_header_str(headers, "x-bz-upload-timestamp")?,
+            "x-bz-upload-timestamp",
+        )?;
+
+        let mut file_info = HashMap::new();
+        for (name, value) in headers.iter() {
+            if let Some(key) = name.as_str().strip_prefix(FILE_INFO_HEADER_PREFIX) {
+                let value = value
+                    .to_str()
+                    .map_err(|_| InvalidData::new(format!("Header {} is not valid ascii", name)))?;
+                file_info.insert(
+                    decode_header(key, name.as_str())?,
+                    decode_header(value, name.as_str())?,
+                );
+            }
+        }
+
+        let server_side_encryption =
+            if header_str(headers, "x-bz-server-side-encryption-customer-algorithm")?.is_some() {
+                Some(ServerSideEncryption::SseC)
+            } else if header_str(headers, "x-bz-server-side-encryption")?.is_some() {
+                Some(ServerSideEncryption::SseB2)
+            } else {
+                None
+            };
+
+        let file_retention = match (
+            header_str(headers, "x-bz-file-retention-mode")?,
+            header_str(headers, "x-bz-file-retention-retain-until-timestamp")?,
+        ) {
+            (Some(mode), Some(retain_until)) => {
+                let mode = match mode {
+                    "governance" => FileRetentionMode::Governance,
+                    "compliance" => FileRetentionMode::Compliance,
+                    _ => {
+                        return Err(InvalidData::new(format!(
+                            "Invalid file retention mode: {}",
+                            mode
+                        )))
+                    }
+                };
+                Some(FileRetention::new(
+                    mode,
+                    parse_header(retain_until, "x-bz-file-retention-retain-until-timestamp")?,
+                ))
+            }
+            _ => None,
+        };
+        let legal_hold = match header_str(headers, "x-bz-file-legal-hold")? {
+            None => None,
+            Some("on") => Some(LegalHoldOnOff::On),
+            Some("off") => Some(LegalHoldOnOff::Off),
+            Some(v) => return Err(InvalidData::new(format!("Invalid legal hold: {}", v))),
+        };
+
+        Ok(Self {
+            file_id,
+            file_name,
+            content_length,
+            content_type,
+            content_sha1,
+            upload_timestamp,
+            file_info,
+            server_side_encryption,
+            file_retention,
+            legal_hold,
+        })
+    }
+
+    /// Get a reference to the head file ok's file id.
+    pub fn file_id(&self) -> &FileId {
+        &self.file_id
+    }
+
+    /// Get a reference to the head file ok's file name.
+    pub fn file_name(&self) -> &FileName {
+        &self.file_name
+    }
+
+    /// Get the head file ok's content length.
+    pub fn content_length(&self) -> u64 {
+        self.content_length
+    }
+
+    /// Get a reference to the head file ok's content type.
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// Get a reference to the head file ok's content sha1.
+    pub fn content_sha1(&self) -> Option<&Sha1Digest> {
+        self.content_sha1.as_ref()
+    }
+
+    /// Get the head file ok's upload timestamp.
+    pub fn upload_timestamp(&self) -> TimeStamp {
+        self.upload_timestamp
+    }
+
+    /// Get a reference to the head file ok's file info (the `x-bz-info-*` headers, url decoded).
+    pub fn file_info(&self) -> &HashMap<String, String> {
+        &self.file_info
+    }
+
+    /// Id of the SSE-C key the file was uploaded with, as recorded in its file info (see [crate::v2::SSE_C_KEY_ID_FILE_INFO]).
+    pub fn sse_c_key_id(&self) -> Option<&str> {
+        self.file_info
+            .get(SSE_C_KEY_ID_FILE_INFO)
+            .map(String::as_str)
+    }
+
+    /// Get a reference to the head file ok's server side encryption.
+    pub fn server_side_encryption(&self) -> Option<&ServerSideEncryption> {
+        self.server_side_encryption.as_ref()
+    }
+
+    /// Get a reference to the head file ok's file retention.
+    pub fn file_retention(&self) -> Option<&FileRetention> {
+        self.file_retention.as_ref()
+    }
+
+    /// Get the head file ok's legal hold.
+    pub fn legal_hold(&self) -> Option<LegalHoldOnOff> {
+        self.legal_hold
+    }
+}
+
+async fn head_file(
+    request_builder: reqwest::RequestBuilder,
+) -> Result<HeadFileOk, DownloadFileError> {
+    let resp = request_builder
+        .send_with_transport()
+        .await
+        .map_err(DownloadFileError::from)?;
+    if resp.status() == http::StatusCode::OK {
+        HeadFileOk::from_headers(resp.headers()).map_err(|e| DownloadFileError::Unexpected {
+            raw_error: crate::v2::Error::InvalidData(e),
+            retry: None,
+        })
+    } else {
+        Err(DownloadFileError::from_response_without_body(&resp))
+    }
+}
+
+/// Retrieves the metadata of a file by name without downloading it.
+///
+/// Takes the same request as [crate::v2::b2_download_file_by_name], so authorization and
+/// server side encryption (SSE-C) are handled the same way. The content related url parameters
+/// (e.g. `b2_content_type`) only change the returned headers, not the stored metadata.
+///
+/// As HEAD responses do not contain a body, the error code of an error is derived from the http status.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            bucket_name = request.bucket_name.as_str(),
+            file_name = request.file_name.as_str(),
+        )
+    )
+)]
+pub async fn b2_head_file_by_name<AuthToken>(
+    download_url: &DownloadUrl,
+    request: &DownloadFileByNameRequest<'_, AuthToken>,
+) -> Result<HeadFileOk, DownloadFileError>
+where
+    AuthToken: DownloadAuthorizationToken + Serialize,
+{
+    head_file(request.request_builder(download_url, http::Method::HEAD)).await
+}
+
+/// Retrieves the metadata of a file by ID without downloading it.
+///
+/// Takes the same parameters as [crate::v2::b2_download_file_by_id].
+///
+/// As HEAD responses do not contain a body, the error code of an error is derived from the http status.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(file_id = params.file_id.as_str()))
+)]
+pub async fn b2_head_file_by_id(
+    download_url: &DownloadUrl,
+    authorization_token: Option<&AuthorizationToken>,
+    params: &DownloadParams<'_>,
+) -> Result<HeadFileOk, DownloadFileError> {
+    head_file(params.request_builder(download_url, authorization_token, http::Method::HEAD)).await
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::v2::test::mock_server::FAKE_AUTHORIZATION_TOKEN;
+    use crate::v2::{errors::DownloadFileError, BucketName};
+
+    const FILE_ID: &str =
+        "4_zb2f6f21365e1d29f6c580f18_f10904e5ca06493a1_d20180914_m223119_c002_v0001094_t0002";
+
+    #[tokio::test]
+    async fn test_head_file_by_name() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/file/bucket/dir/my%20file.txt"))
+            .and(header("Authorization", FAKE_AUTHORIZATION_TOKEN))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-length", "7")
+                    .insert_header("content-type", "text/plain")
+                    .insert_header("x-bz-file-id", FILE_ID)
+                    .insert_header("x-bz-file-name", "dir/my%20file.txt")
+                    .insert_header(
+                        "x-bz-content-sha1",
+                        "unverified:dc724af18fbdd4e59189f5fe768a5f8311527050",
+                    )
+                    .insert_header("x-bz-upload-timestamp", "1536964279000")
+                    .insert_header("x-bz-info-src_last_modified_millis", "1536964184056")
+                    .insert_header("x-bz-info-author", "J%C3%BCrgen")
+                    .insert_header("x-bz-server-side-encryption", "AES256")
+                    .insert_header("x-bz-file-legal-hold", "on"),
+            )
+            .mount(&mock_server)
+            .await;
+        let bucket_name: BucketName = "bucket".to_owned().try_into().unwrap();
+        let file_name: FileName = "dir/my file.txt".to_owned().try_into().unwrap();
+        let token = AuthorizationToken(FAKE_AUTHORIZATION_TOKEN.to_owned());
+        let request = DownloadFileByNameRequest::builder()
+            .bucket_name(&bucket_name)
+            .file_name(&file_name)
+            .authorization(&token)
+            .build();
+        let res = b2_head_file_by_name(
+            &serde_json::from_value(serde_json::Value::String(mock_server.uri())).unwrap(),
+            &request,
+        )
+        .await
+        .unwrap();
+        assert_eq!(FILE_ID, res.file_id().as_str());
+        assert_eq!("dir/my file.txt", res.file_name().as_str());
+        assert_eq!(7, res.content_length());
+        assert_eq!(Some(&mime::TEXT_PLAIN), res.content_type());
+        assert_eq!(
+            Some(&Sha1Digest::try_from("dc724af18fbdd4e59189f5fe768a5f8311527050").unwrap()),
+            res.content_sha1()
+        );
+        assert_eq!(1536964279000, res.upload_timestamp().as_millis());
+        assert_eq!(
+            Some(&"1536964184056".to_owned()),
+            res.file_info().get("src_last_modified_millis")
+        );
+        assert_eq!(Some(&"Jürgen".to_owned()), res.file_info().get("author"));
+        assert_eq!(
+            Some(&ServerSideEncryption::SseB2),
+            res.server_side_encryption()
+        );
+        assert_eq!(None, res.file_retention());
+        assert_eq!(Some(LegalHoldOnOff::On), res.legal_hold());
+    }
+
+    #[tokio::test]
+    async fn test_head_file_by_id_not_found() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/b2api/v2/b2_download_file_by_id"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        let file_id: FileId = FILE_ID.to_owned().try_into().unwrap();
+        let res = b2_head_file_by_id(
+            &serde_json::from_value(serde_json::Value::String(mock_server.uri())).unwrap(),
+            None,
+            &DownloadParams::builder().file_id(&file_id).build(),
+        )
+        .await;
+        match res {
+            Err(DownloadFileError::NotFound { raw_error, retry }) => {
+                assert_eq!(404, raw_error.status.as_u16());
+                assert_eq!(None, retry);
+            }
+            _ => panic!("Expected NotFound, got {:#?}", res),
+        }
+    }
+}
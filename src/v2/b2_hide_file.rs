@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use serde::Serialize;
 
 use super::{
@@ -11,6 +12,17 @@ struct HideFileRequest<'a> {
     file_name: &'a FileName,
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            bucket_id = bucket_id.as_str(),
+            file_name = file_name.as_str(),
+        )
+    )
+)]
 pub async fn b2_hide_file(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
@@ -18,14 +30,14 @@ pub async fn b2_hide_file(
     file_name: &FileName,
 ) -> Result<FileInformation, GetFileInfoError> {
     let url = format!("{}/b2api/v2/b2_hide_file", api_url.as_str());
-    let resp = reqwest::Client::new()
+    let resp = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(&HideFileRequest {
             bucket_id,
             file_name,
         })
-        .send()
+        .send_with_transport()
         .await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
@@ -5,6 +5,7 @@ use super::{
     errors::GenericB2Error,
     AccountId, ApiUrl, AuthorizationToken, FileLockConfiguration, ServerSideEncryption,
 };
+use crate::transport::RequestBuilderExt;
 use serde::{Deserialize, Serialize};
 use typed_builder::TypedBuilder;
 
@@ -24,6 +25,16 @@ pub struct ListBucketsRequest<'a> {
     bucket_types: Option<&'a BucketTypes>,
 }
 
+owned_request! {
+    /// Owned version of [ListBucketsRequest], see [OwnedListBucketsRequest::as_request]
+    OwnedListBucketsRequest => ListBucketsRequest {
+        account_id: AccountId [borrow],
+        bucket_id: Option<BucketId> [borrow_option],
+        bucket_name: Option<BucketName> [borrow_option],
+        bucket_types: Option<BucketTypes> [borrow_option],
+    }
+}
+
 impl<'a> ListBucketsRequest<'a> {
     pub fn new(
         account_id: &'a AccountId,
@@ -133,17 +144,21 @@ impl ListBucketsOk {
     }
 }
 
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 pub async fn b2_list_buckets(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
     request_body: &ListBucketsRequest<'_>,
 ) -> Result<ListBucketsOk, GenericB2Error> {
     let url = format!("{}/b2api/v2/b2_list_buckets", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(request_body);
-    let resp = request.send().await.map_err(GenericB2Error::from)?;
+    let resp = request
+        .send_with_transport()
+        .await
+        .map_err(GenericB2Error::from)?;
     if resp.status() == http::StatusCode::OK {
         let auth_ok: ListBucketsOk = resp.json().await.map_err(GenericB2Error::from)?;
         Ok(auth_ok)
@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use std::{convert::TryFrom, num::NonZeroU16};
 
 use super::{
@@ -46,6 +47,17 @@ pub struct ListFileNamesRequest<'s> {
     delimiter: Option<&'s FileNameDelimiter>,
 }
 
+owned_request! {
+    /// Owned version of [ListFileNamesRequest], see [OwnedListFileNamesRequest::as_request]
+    OwnedListFileNamesRequest => ListFileNamesRequest {
+        bucket_id: BucketId [borrow],
+        start_file_name: Option<FileName> [borrow_option],
+        max_file_count: Option<MaxFileCount> [clone],
+        prefix: Option<FileNamePrefix> [borrow_option],
+        delimiter: Option<FileNameDelimiter> [borrow_option],
+    }
+}
+
 impl<'s> ListFileNamesRequest<'s> {
     pub fn new(
         bucket_id: &'s BucketId,
@@ -83,17 +95,30 @@ impl ListFileNamesOk {
     }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            bucket_id = request_body.bucket_id.as_str(),
+        )
+    )
+)]
 pub async fn b2_list_file_names<'a>(
     api_url: &'a ApiUrl,
     authorization_token: &AuthorizationToken,
     request_body: &'a ListFileNamesRequest<'a>,
 ) -> Result<ListFileNamesOk, ListFileNamesError> {
     let url = format!("{}/b2api/v2/b2_list_file_names", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(request_body);
-    let resp = request.send().await.map_err(ListFileNamesError::from)?;
+    let resp = request
+        .send_with_transport()
+        .await
+        .map_err(ListFileNamesError::from)?;
     if resp.status() == http::StatusCode::OK {
         let auth_ok: ListFileNamesOk = resp.json().await.map_err(ListFileNamesError::from)?;
         Ok(auth_ok)
@@ -108,7 +133,7 @@ mod test {
 
     use crate::v2::test::mock_server::*;
 
-    use super::{b2_list_file_names, ListFileNamesRequest};
+    use super::{b2_list_file_names, ListFileNamesRequest, OwnedListFileNamesRequest};
     use super::{ApiUrl, AuthorizationToken};
 
     #[tokio::test]
@@ -125,4 +150,31 @@ mod test {
         .await;
         assert!(res.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_owned_request() {
+        let mock_server = B2MockServer::start().await;
+        mock_server.register_default_list_file_names_handler().await;
+        let bucket_id = FAKE_BUCKET_ID.to_owned().try_into().unwrap();
+        let owned = OwnedListFileNamesRequest::from(
+            ListFileNamesRequest::builder()
+                .bucket_id(&bucket_id)
+                .build(),
+        );
+        // e.g. persisted in a job queue and executed by another task
+        let json = serde_json::to_string(&owned).unwrap();
+        let owned: OwnedListFileNamesRequest = serde_json::from_str(&json).unwrap();
+        let uri = mock_server.uri();
+        let res = tokio::spawn(async move {
+            b2_list_file_names(
+                &ApiUrl(uri),
+                &AuthorizationToken(FAKE_AUTHORIZATION_TOKEN.into()),
+                &owned.as_request(),
+            )
+            .await
+        })
+        .await
+        .unwrap();
+        assert!(res.is_ok());
+    }
 }
@@ -1,4 +1,6 @@
+use crate::transport::RequestBuilderExt;
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use typed_builder::TypedBuilder;
 
 use super::{
@@ -27,6 +29,18 @@ pub struct ListFileVersionsRequest<'s> {
     delimiter: Option<&'s FileNameDelimiter>,
 }
 
+owned_request! {
+    /// Owned version of [ListFileVersionsRequest], see [OwnedListFileVersionsRequest::as_request]
+    OwnedListFileVersionsRequest => ListFileVersionsRequest {
+        bucket_id: BucketId [borrow],
+        start_file_name: Option<FileName> [borrow_option],
+        start_file_id: Option<FileId> [borrow_option],
+        max_file_count: Option<MaxFileCount> [clone],
+        prefix: Option<FileNamePrefix> [borrow_option],
+        delimiter: Option<FileNameDelimiter> [borrow_option],
+    }
+}
+
 impl<'s> ListFileVersionsRequest<'s> {
     pub fn new(
         bucket_id: &'s BucketId,
@@ -61,6 +75,11 @@ impl ListFileVersionsOk {
         self.files.as_slice()
     }
 
+    /// Get the list file versions ok's files.
+    pub fn into_files(self) -> Vec<FileInformation> {
+        self.files
+    }
+
     /// Get a reference to the list file versions ok's next file name.
     pub fn next_file_name(&self) -> Option<&FileName> {
         self.next_file_name.as_ref()
@@ -72,17 +91,27 @@ impl ListFileVersionsOk {
     }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            bucket_id = request_body.bucket_id.as_str(),
+        )
+    )
+)]
 pub async fn b2_list_file_versions<'a>(
     api_url: &'a ApiUrl,
     authorization_token: &AuthorizationToken,
     request_body: &'a ListFileVersionsRequest<'a>,
 ) -> Result<ListFileVersionsOk, ListFileVersionsError> {
     let url = format!("{}/b2api/v2/b2_list_file_versions", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(request_body);
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         let auth_ok = resp.json().await?;
         Ok(auth_ok)
@@ -90,3 +119,57 @@ pub async fn b2_list_file_versions<'a>(
         Err(ListFileVersionsError::from_response(resp).await)
     }
 }
+
+/// Pages through all versions of a bucket or file name prefix, for the bulk operations
+pub(crate) struct FileVersionPager<'s> {
+    bucket_id: &'s BucketId,
+    prefix: Option<&'s FileNamePrefix>,
+    start_file_name: Option<FileName>,
+    start_file_id: Option<FileId>,
+    done: bool,
+}
+
+impl<'s> FileVersionPager<'s> {
+    pub(crate) fn new(bucket_id: &'s BucketId, prefix: Option<&'s FileNamePrefix>) -> Self {
+        Self {
+            bucket_id,
+            prefix,
+            start_file_name: None,
+            start_file_id: None,
+            done: false,
+        }
+    }
+
+    /// starts the listing at the versions of `file_name` instead of the first file
+    #[cfg(feature = "b2_unstable")]
+    pub(crate) fn starting_at(mut self, file_name: FileName) -> Self {
+        self.start_file_name = Some(file_name);
+        self
+    }
+
+    /// next page of at most 1000 versions, `None` after the last page
+    pub(crate) async fn next_page(
+        &mut self,
+        api_url: &ApiUrl,
+        authorization_token: &AuthorizationToken,
+    ) -> Result<Option<Vec<FileInformation>>, ListFileVersionsError> {
+        if self.done {
+            return Ok(None);
+        }
+        // unwrap is safe, 1000 is the maximum page size
+        let max_file_count = MaxFileCount::try_from(1000).unwrap();
+        let request = ListFileVersionsRequest::new(
+            self.bucket_id,
+            self.start_file_name.as_ref(),
+            self.start_file_id.as_ref(),
+            Some(max_file_count),
+            self.prefix,
+            None,
+        );
+        let page = b2_list_file_versions(api_url, authorization_token, &request).await?;
+        self.start_file_name = page.next_file_name().cloned();
+        self.start_file_id = page.next_file_id().cloned();
+        self.done = self.start_file_name.is_none();
+        Ok(Some(page.into_files()))
+    }
+}
@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use std::num::NonZeroU16;
 
 use serde::{Deserialize, Serialize};
@@ -21,6 +22,15 @@ pub struct ListKeysRequest<'s> {
     start_application_key_id: Option<ApplicationKeyIdRef<'s>>,
 }
 
+owned_request! {
+    /// Owned version of [ListKeysRequest], see [OwnedListKeysRequest::as_request]
+    OwnedListKeysRequest => ListKeysRequest {
+        account_id: AccountId [borrow],
+        max_key_count: Option<NonZeroU16> [clone],
+        start_application_key_id: Option<ApplicationKeyId> [borrow_option],
+    }
+}
+
 impl<'s> ListKeysRequest<'s> {
     pub fn new(
         account_id: &'s AccountId,
@@ -53,19 +63,25 @@ impl ListKeysOk {
     pub fn keys(&self) -> &[KeyInformation] {
         self.keys.as_slice()
     }
+
+    /// Get the list keys ok's keys, consuming it.
+    pub fn into_keys(self) -> Vec<KeyInformation> {
+        self.keys
+    }
 }
 
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 pub async fn b2_list_keys<'a>(
     api_url: &'a ApiUrl,
     authorization_token: &'a AuthorizationToken,
     params: &'a ListKeysRequest<'a>,
 ) -> Result<ListKeysOk, GenericB2Error> {
     let url = format!("{}/b2api/v2/b2_list_keys", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(params);
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
     } else {
@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use std::{convert::TryFrom, num::NonZeroU16};
 
 use super::{
@@ -35,15 +36,24 @@ impl TryFrom<u16> for MaxPartCount {
 #[derive(Debug, Serialize, TypedBuilder)]
 #[serde(rename_all = "camelCase")]
 pub struct ListPartsRequest<'s> {
-    file_id: &'s FileId,
+    pub(crate) file_id: &'s FileId,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    start_part_number: Option<PartNumber>,
+    pub(crate) start_part_number: Option<PartNumber>,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    max_part_count: Option<MaxPartCount>,
+    pub(crate) max_part_count: Option<MaxPartCount>,
+}
+
+owned_request! {
+    /// Owned version of [ListPartsRequest], see [OwnedListPartsRequest::as_request]
+    OwnedListPartsRequest => ListPartsRequest {
+        file_id: FileId [borrow],
+        start_part_number: Option<PartNumber> [clone],
+        max_part_count: Option<MaxPartCount> [clone],
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -114,17 +124,27 @@ impl Part {
     }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            file_id = request_parameters.file_id.as_str(),
+        )
+    )
+)]
 pub async fn b2_list_parts(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
     request_parameters: &ListPartsRequest<'_>,
 ) -> Result<ListPartsOk, GenericB2Error> {
     let url = format!("{}/b2api/v2/b2_list_parts", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(request_parameters);
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         let ok: ListPartsOk = resp.json().await?;
         Ok(ok)
@@ -1,11 +1,12 @@
+use crate::transport::RequestBuilderExt;
 use std::{convert::TryFrom, num::NonZeroU8};
 
 use serde::{Deserialize, Serialize};
 use typed_builder::TypedBuilder;
 
 use super::{
-    errors::GenericB2Error, ApiUrl, AuthorizationToken, BucketId, FileId, FileName, InvalidData,
-    ListFileNamesOk,
+    errors::GenericB2Error, ApiUrl, AuthorizationToken, BucketId, FileId, FileInformation,
+    FileName, InvalidData,
 };
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
@@ -35,37 +36,81 @@ impl TryFrom<u8> for MaxUnfinishedLargeFileCount {
 #[derive(Debug, Serialize, TypedBuilder)]
 #[serde(rename_all = "camelCase")]
 pub struct ListUnfinishedLargeFilesRequest<'s> {
-    bucket_id: &'s BucketId,
+    pub(crate) bucket_id: &'s BucketId,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    name_prefix: Option<&'s FileName>,
+    pub(crate) name_prefix: Option<&'s FileName>,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    start_file_id: Option<&'s FileId>,
+    pub(crate) start_file_id: Option<&'s FileId>,
 
     #[builder(default, setter(strip_option, into))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    max_file_count: Option<MaxUnfinishedLargeFileCount>,
+    pub(crate) max_file_count: Option<MaxUnfinishedLargeFileCount>,
 }
 
+owned_request! {
+    /// Owned version of [ListUnfinishedLargeFilesRequest], see [OwnedListUnfinishedLargeFilesRequest::as_request]
+    OwnedListUnfinishedLargeFilesRequest => ListUnfinishedLargeFilesRequest {
+        bucket_id: BucketId [borrow],
+        name_prefix: Option<FileName> [borrow_option],
+        start_file_id: Option<FileId> [borrow_option],
+        max_file_count: Option<MaxUnfinishedLargeFileCount> [clone],
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListUnfinishedLargeFilesOk {
+    files: Vec<FileInformation>,
+    next_file_id: Option<FileId>,
+}
+
+impl ListUnfinishedLargeFilesOk {
+    /// Get a reference to the list unfinished large files ok's files.
+    pub fn files(&self) -> &[FileInformation] {
+        self.files.as_slice()
+    }
+
+    /// Get the list unfinished large files ok's files.
+    pub fn into_files(self) -> Vec<FileInformation> {
+        self.files
+    }
+
+    /// Get a reference to the list unfinished large files ok's next file id.
+    pub fn next_file_id(&self) -> Option<&FileId> {
+        self.next_file_id.as_ref()
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            bucket_id = request_parameters.bucket_id.as_str(),
+        )
+    )
+)]
 pub async fn b2_list_unfinished_large_files(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
     request_parameters: &ListUnfinishedLargeFilesRequest<'_>,
-) -> Result<ListFileNamesOk, GenericB2Error> {
+) -> Result<ListUnfinishedLargeFilesOk, GenericB2Error> {
     let url = format!(
         "{}/b2api/v2/b2_list_unfinished_large_files",
         api_url.as_str()
     );
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(request_parameters);
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
-        let auth_ok: ListFileNamesOk = resp.json().await?;
+        let auth_ok: ListUnfinishedLargeFilesOk = resp.json().await?;
         Ok(auth_ok)
     } else {
         Err(GenericB2Error::from_response(resp).await)
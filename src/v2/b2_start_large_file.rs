@@ -1,48 +1,87 @@
 #[cfg(feature = "b2_unstable")]
 use super::FileInfo;
 use super::{
-    errors::LargeFileError, serialize_content_type_header, ApiUrl, AuthorizationToken, BucketId,
-    ContentTypeRef, FileInformation, FileName, FileRetention, LegalHoldOnOff,
-    ServerSideEncryptionCustomerKey, CONTENT_TYPE_AUTO,
+    errors::LargeFileError, owned_header, serialize_content_type_header,
+    server_side_encryption::json_with_key_id_in_file_info, ApiUrl, AuthorizationToken, BucketId,
+    ContentType, ContentTypeRef, FileInformation, FileName, FileRetention, LegalHoldOnOff,
+    OwnedServerSideEncryptionCustomerKey, ServerSideEncryptionCustomerKey, CONTENT_TYPE_AUTO,
 };
+use crate::transport::RequestBuilderExt;
 use serde::Serialize;
 use typed_builder::TypedBuilder;
 
 #[derive(Debug, Serialize, TypedBuilder)]
 #[serde(rename_all = "camelCase")]
 pub struct StartLargeFileParameters<'s> {
-    bucket_id: &'s BucketId,
-    file_name: &'s FileName,
+    pub(crate) bucket_id: &'s BucketId,
+    pub(crate) file_name: &'s FileName,
 
     #[builder(default = &CONTENT_TYPE_AUTO)]
     #[serde(serialize_with = "serialize_content_type_header")]
-    content_type: ContentTypeRef<'s>,
+    pub(crate) content_type: ContentTypeRef<'s>,
 
     #[cfg(feature = "b2_unstable")]
     #[builder(default, setter(strip_option))]
-    file_info: Option<&'s FileInfo>, // <- TODO: right type??
+    pub(crate) file_info: Option<&'s FileInfo>, // <- TODO: right type??
 
     #[builder(default, setter(strip_option))]
-    file_retention: Option<&'s FileRetention>,
+    pub(crate) file_retention: Option<&'s FileRetention>,
 
     #[builder(default, setter(strip_option))]
-    legal_hold: Option<&'s LegalHoldOnOff>,
+    pub(crate) legal_hold: Option<&'s LegalHoldOnOff>,
 
-    #[builder(default, setter(strip_option))]
-    server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+    #[builder(default, setter(strip_option, into))]
+    pub(crate) server_side_encryption: Option<ServerSideEncryptionCustomerKey<'s>>,
+
+    /// if set, the key id of an SSE-C key is recorded in the file info (see [crate::v2::SSE_C_KEY_ID_FILE_INFO]), unless `file_info` already contains it
+    #[serde(skip)]
+    #[builder(default)]
+    pub(crate) record_sse_c_key_id: bool,
+}
+
+owned_request! {
+    /// Owned version of [StartLargeFileParameters], see [OwnedStartLargeFileParameters::as_request]
+    OwnedStartLargeFileParameters => StartLargeFileParameters {
+        bucket_id: BucketId [borrow],
+        file_name: FileName [borrow],
+        #[serde(with = "owned_header")]
+        content_type: ContentType [borrow],
+        file_info: Option<FileInfo> [borrow_option, feature = "b2_unstable"],
+        file_retention: Option<FileRetention> [borrow_option],
+        legal_hold: Option<LegalHoldOnOff> [borrow_option],
+        server_side_encryption: Option<OwnedServerSideEncryptionCustomerKey> [customer_key],
+        #[serde(default)]
+        record_sse_c_key_id: bool [clone],
+    }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            bucket_id = params.bucket_id.as_str(),
+            file_name = params.file_name.as_str(),
+        )
+    )
+)]
 pub async fn b2_start_large_file<'a>(
     api_url: &'a ApiUrl,
     authorization: &'a AuthorizationToken,
     params: &'a StartLargeFileParameters<'a>,
 ) -> Result<FileInformation, LargeFileError> {
     let url = format!("{}/b2api/v2/b2_start_large_file", api_url.as_str());
-    let resp = reqwest::Client::new()
+    let sse = params
+        .server_side_encryption
+        .as_ref()
+        .filter(|_| params.record_sse_c_key_id);
+    let body = json_with_key_id_in_file_info(params, sse);
+    let resp = crate::transport::client()
         .post(url)
         .header("Authorization", authorization.as_str())
-        .json(&params)
-        .send()
+        .json(&body)
+        .send_with_transport()
         .await
         .map_err(LargeFileError::from)?;
     if resp.status() == http::StatusCode::OK {
@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use serde::Serialize;
 use typed_builder::TypedBuilder;
 
@@ -5,8 +6,8 @@ use super::{
     b2_list_buckets::Bucket,
     buckets::{BucketRevision, LifeCycleRule},
     errors::UpdateBucketError,
-    AccountId, ApiUrl, AuthorizationToken, BucketId, BucketInfo, BucketType, DefaultFileRetention,
-    ServerSideEncryption,
+    AccountId, ApiUrl, AuthorizationToken, BucketId, BucketInfo, DefaultFileRetention, InvalidData,
+    ServerSideEncryption, SettableBucketType,
 };
 
 #[derive(Debug, Serialize, TypedBuilder)]
@@ -18,7 +19,8 @@ pub struct UpdateBucketRequest<'s> {
     bucket_id: &'s BucketId,
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    bucket_type: Option<&'s BucketType>, // TODO: use differnt type, that allows all private / all public only
+    /// If present, the new bucket type: `allPublic` or `allPrivate`.
+    bucket_type: Option<SettableBucketType>,
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -45,7 +47,7 @@ pub struct UpdateBucketRequest<'s> {
 
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    /// The initial list of lifecycle rules for this bucket.
+    /// If present, the new list of lifecycle rules for this bucket, checked with [LifeCycleRule::validate_rules] (invalid rules are rejected without being sent).
     lifecycle_rules: Option<&'s [LifeCycleRule]>,
 
     #[builder(default, setter(strip_option))]
@@ -54,20 +56,88 @@ pub struct UpdateBucketRequest<'s> {
     if_revision_is: Option<&'s BucketRevision>,
 }
 
+owned_request! {
+    /// Owned version of [UpdateBucketRequest], see [OwnedUpdateBucketRequest::as_request]
+    OwnedUpdateBucketRequest => UpdateBucketRequest {
+        account_id: AccountId [borrow],
+        bucket_id: BucketId [borrow],
+        bucket_type: Option<SettableBucketType> [clone],
+        bucket_info: Option<BucketInfo> [borrow_option],
+        cors_rules: Option<serde_json::Value> [borrow_option, feature = "b2_unstable"],
+        default_retention: Option<DefaultFileRetention> [borrow_option],
+        default_server_side_encryption: Option<ServerSideEncryption> [borrow_option],
+        lifecycle_rules: Option<Vec<LifeCycleRule>> [borrow_option],
+        if_revision_is: Option<BucketRevision> [borrow_option],
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(bucket_id = request.bucket_id.as_str()))
+)]
 pub async fn b2_update_bucket(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
     request: &UpdateBucketRequest<'_>,
 ) -> Result<Bucket, UpdateBucketError> {
+    if let Some(lifecycle_rules) = request.lifecycle_rules {
+        LifeCycleRule::validate_rules(lifecycle_rules).map_err(|error| {
+            UpdateBucketError::Unexpected {
+                raw_error: crate::v2::Error::InvalidData(InvalidData::new(error.to_string())),
+                retry: None,
+            }
+        })?;
+    }
     let url = format!("{}/b2api/v2/b2_update_bucket", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(request);
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
     } else {
         Err(UpdateBucketError::from_response(resp).await)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroU64;
+
+    use super::*;
+    use crate::v2::test::mock_server::{FAKE_ACCOUNT_ID, FAKE_AUTHORIZATION_TOKEN, FAKE_BUCKET_ID};
+
+    #[tokio::test]
+    async fn test_overlapping_lifecycle_rules() {
+        let rule = |prefix: &str| {
+            LifeCycleRule::new(
+                NonZeroU64::new(1),
+                None,
+                prefix.to_owned().try_into().unwrap(),
+            )
+        };
+        let rules = [rule("logs/"), rule("logs/2024/")];
+        let account_id = AccountId(FAKE_ACCOUNT_ID.to_owned());
+        let bucket_id: BucketId = FAKE_BUCKET_ID.to_owned().try_into().unwrap();
+        let request = UpdateBucketRequest::builder()
+            .account_id(&account_id)
+            .bucket_id(&bucket_id)
+            .lifecycle_rules(&rules)
+            .build();
+        // the request is rejected before it is sent, so nothing listens on this url
+        let res = b2_update_bucket(
+            &ApiUrl("http://localhost:1".to_owned()),
+            &AuthorizationToken(FAKE_AUTHORIZATION_TOKEN.to_owned()),
+            &request,
+        )
+        .await;
+        assert!(matches!(
+            res,
+            Err(UpdateBucketError::Unexpected {
+                raw_error: crate::v2::Error::InvalidData(_),
+                retry: None,
+            })
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use serde::{Deserialize, Serialize};
 use typed_builder::TypedBuilder;
 
@@ -13,6 +14,15 @@ pub struct UpdateFileLegalHoldRequest<'s> {
     legal_hold: LegalHoldOnOff,
 }
 
+owned_request! {
+    /// Owned version of [UpdateFileLegalHoldRequest], see [OwnedUpdateFileLegalHoldRequest::as_request]
+    OwnedUpdateFileLegalHoldRequest => UpdateFileLegalHoldRequest {
+        file_name: FileName [borrow],
+        file_id: FileId [borrow],
+        legal_hold: LegalHoldOnOff [clone],
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateFileLegalHoldOk {
@@ -38,17 +48,28 @@ impl UpdateFileLegalHoldOk {
     }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            file_id = request.file_id.as_str(),
+            file_name = request.file_name.as_str(),
+        )
+    )
+)]
 pub async fn b2_update_file_legal_hold(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
     request: &UpdateFileLegalHoldRequest<'_>,
 ) -> Result<UpdateFileLegalHoldOk, UpdateFileLockError> {
     let url = format!("{}/b2api/v2/b2_update_file_legal_hold", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(request);
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
     } else {
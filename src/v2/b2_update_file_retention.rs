@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use serde::{Deserialize, Serialize};
 use typed_builder::TypedBuilder;
 
@@ -8,12 +9,22 @@ use super::{
 #[derive(Debug, Serialize, TypedBuilder)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateFileRetentionRequest<'s> {
-    file_name: &'s FileName,
-    file_id: &'s FileId,
-    file_retention: &'s FileRetention,
+    pub(crate) file_name: &'s FileName,
+    pub(crate) file_id: &'s FileId,
+    pub(crate) file_retention: &'s FileRetention,
     #[builder(default, setter(strip_option, into))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    bypass_governance: Option<bool>,
+    pub(crate) bypass_governance: Option<bool>,
+}
+
+owned_request! {
+    /// Owned version of [UpdateFileRetentionRequest], see [OwnedUpdateFileRetentionRequest::as_request]
+    OwnedUpdateFileRetentionRequest => UpdateFileRetentionRequest {
+        file_name: FileName [borrow],
+        file_id: FileId [borrow],
+        file_retention: FileRetention [borrow],
+        bypass_governance: Option<bool> [clone],
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -41,17 +52,28 @@ impl UpdateFileRetentionOk {
     }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            file_id = request.file_id.as_str(),
+            file_name = request.file_name.as_str(),
+        )
+    )
+)]
 pub async fn b2_update_file_retention(
     api_url: &ApiUrl,
     authorization_token: &AuthorizationToken,
     request: &UpdateFileRetentionRequest<'_>,
 ) -> Result<UpdateFileRetentionOk, UpdateFileLockError> {
     let url = format!("{}/b2api/v2/b2_update_file_retention", api_url.as_str());
-    let request = reqwest::Client::new()
+    let request = crate::transport::client()
         .post(url)
         .header("Authorization", authorization_token.as_str())
         .json(request);
-    let resp = request.send().await?;
+    let resp = request.send_with_transport().await?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await?)
     } else {
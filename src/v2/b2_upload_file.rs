@@ -1,14 +1,18 @@
+use crate::transport::RequestBuilderExt;
 use reqwest::Body;
 use serde::Serialize;
+use std::collections::HashMap;
 use typed_builder::TypedBuilder;
 
-use crate::header_serializer::HeadersFrom;
+use crate::header_serializer::{url_encode, HeadersFrom};
 
 use super::{
-    errors::UploadFileError, serialize_content_type_header, serialize_header_option,
-    CacheControlHeaderValueRef, ContentDispositionRef, ContentLanguageRef, ContentTypeRef,
-    ExpiresHeaderValueRef, FileInformation, FileName, ServerSideEncryptionCustomerKey,
-    Sha1DigestRef, TimeStamp, UploadParameters, CONTENT_TYPE_AUTO,
+    errors::UploadFileError, owned_header, owned_header_option, serialize_content_type_header,
+    serialize_header_option, CacheControlHeaderValue, CacheControlHeaderValueRef,
+    ContentDisposition, ContentDispositionRef, ContentLanguage, ContentLanguageRef, ContentSha1,
+    ContentType, ContentTypeRef, ExpiresHeaderValue, ExpiresHeaderValueRef, FileInformation,
+    FileName, OwnedContentSha1, OwnedServerSideEncryptionCustomerKey,
+    ServerSideEncryptionCustomerKey, TimeStamp, UploadParameters, CONTENT_TYPE_AUTO,
 };
 
 #[derive(Debug, Serialize, TypedBuilder)]
@@ -29,7 +33,8 @@ pub struct UploadFileParameters<'s> {
     content_length: u64,
 
     #[serde(rename = "X-Bz-Content-Sha1")]
-    content_sha1: Sha1DigestRef<'s>,
+    #[builder(setter(into))]
+    content_sha1: ContentSha1<'s>,
 
     #[serde(rename = "X-Bz-Info-src_last_modified_millis")]
     #[builder(default, setter(strip_option))]
@@ -66,17 +71,62 @@ pub struct UploadFileParameters<'s> {
     #[builder(default, setter(strip_option))]
     cache_control: Option<CacheControlHeaderValueRef<'s>>,
 
+    /// custom file info, B2 allows at most 10 entries including the `b2-*` ones above
     #[serde(skip)] // will be serialized manually
     #[builder(default, setter(strip_option))]
-    server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+    file_info: Option<&'s HashMap<String, String>>,
+
+    #[serde(skip)] // will be serialized manually
+    #[builder(default, setter(strip_option, into))]
+    server_side_encryption: Option<ServerSideEncryptionCustomerKey<'s>>,
+
+    /// if set, the key id of an SSE-C key is recorded in the file info (see [crate::v2::SSE_C_KEY_ID_FILE_INFO]), unless `file_info` already contains it
+    #[serde(skip)]
+    #[builder(default)]
+    record_sse_c_key_id: bool,
 }
 
+owned_request! {
+    /// Owned version of [UploadFileParameters], see [OwnedUploadFileParameters::as_request]
+    OwnedUploadFileParameters => UploadFileParameters {
+        file_name: FileName [borrow],
+        #[serde(with = "owned_header")]
+        content_type: ContentType [borrow],
+        content_length: u64 [clone],
+        content_sha1: OwnedContentSha1 [content_sha1],
+        src_last_modified_millis: Option<TimeStamp> [clone],
+        #[serde(default, with = "owned_header_option")]
+        content_disposition: Option<ContentDisposition> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        content_language: Option<ContentLanguage> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        expires: Option<ExpiresHeaderValue> [borrow_option],
+        #[serde(default, with = "owned_header_option")]
+        cache_control: Option<CacheControlHeaderValue> [borrow_option],
+        file_info: Option<HashMap<String, String>> [borrow_option],
+        server_side_encryption: Option<OwnedServerSideEncryptionCustomerKey> [customer_key],
+        #[serde(default)]
+        record_sse_c_key_id: bool [clone],
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            file_name = upload_file_params.file_name.as_str(),
+            content_length = upload_file_params.content_length,
+        )
+    )
+)]
 pub async fn b2_upload_file<'a, T: Into<Body>>(
     uploader_params: &'a mut UploadParameters,
     upload_file_params: &'a UploadFileParameters<'a>,
     file_contents: T,
 ) -> Result<FileInformation, UploadFileError> {
-    let mut request = reqwest::Client::new()
+    let mut request = crate::transport::client()
         .post(uploader_params.upload_url().as_str())
         .header(
             "Authorization",
@@ -84,10 +134,19 @@ pub async fn b2_upload_file<'a, T: Into<Body>>(
         )
         .headers_from(upload_file_params)
         .body(file_contents);
+    for (key, value) in upload_file_params.file_info.into_iter().flatten() {
+        request = request.header(format!("X-Bz-Info-{}", key), url_encode(value));
+    }
     if let Some(sse) = upload_file_params.server_side_encryption {
         request = sse.add_to_request_as_header(request);
+        if upload_file_params.record_sse_c_key_id {
+            request = sse.add_key_id_to_request_as_file_info(request, upload_file_params.file_info);
+        }
     }
-    let resp = request.send().await.map_err(UploadFileError::from)?;
+    let resp = request
+        .send_with_transport()
+        .await
+        .map_err(UploadFileError::from)?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await.map_err(UploadFileError::from)?)
     } else {
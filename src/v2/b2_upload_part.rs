@@ -1,3 +1,4 @@
+use crate::transport::RequestBuilderExt;
 use reqwest::Body;
 use serde::{Deserialize, Serialize};
 use typed_builder::TypedBuilder;
@@ -5,8 +6,9 @@ use typed_builder::TypedBuilder;
 use crate::header_serializer::HeadersFrom;
 
 use super::{
-    errors::UploadPartError, FileId, Md5Digest, PartNumber, ServerSideEncryption,
-    ServerSideEncryptionCustomerKey, Sha1Digest, Sha1DigestRef, TimeStamp, UploadPartUrlParameters,
+    errors::UploadPartError, ContentSha1, FileId, Md5Digest, OwnedContentSha1,
+    OwnedServerSideEncryptionCustomerKey, PartNumber, ServerSideEncryption,
+    ServerSideEncryptionCustomerKey, Sha1Digest, TimeStamp, UploadPartUrlParameters,
 };
 
 #[derive(Debug, Serialize, TypedBuilder)]
@@ -25,11 +27,22 @@ pub struct UploadPartParameters<'s> {
     /// The SHA1 checksum of the this part of the file. B2 will check this when the part is uploaded, to make sure that the data arrived correctly.
     /// The same SHA1 checksum must be passed to b2_finish_large_file.
     /// You may optionally provide the SHA1 at the end of the upload.
-    content_sha1: Sha1DigestRef<'s>,
+    #[builder(setter(into))]
+    content_sha1: ContentSha1<'s>,
 
-    #[builder(default, setter(strip_option))]
+    #[builder(default, setter(strip_option, into))]
     #[serde(skip)] //will be serialized manually
-    server_side_encryption: Option<&'s ServerSideEncryptionCustomerKey<'s>>,
+    server_side_encryption: Option<ServerSideEncryptionCustomerKey<'s>>,
+}
+
+owned_request! {
+    /// Owned version of [UploadPartParameters], see [OwnedUploadPartParameters::as_request]
+    OwnedUploadPartParameters => UploadPartParameters {
+        part_number: PartNumber [clone],
+        content_length: u64 [clone],
+        content_sha1: OwnedContentSha1 [content_sha1],
+        server_side_encryption: Option<OwnedServerSideEncryptionCustomerKey> [customer_key],
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -76,17 +89,28 @@ impl UploadPartOk {
     }
 
     /// Get the upload part ok's upload timestamp.
-    pub fn upload_timestamp(&self) -> i64 {
+    pub fn upload_timestamp(&self) -> TimeStamp {
         self.upload_timestamp
     }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            part_number = upload_part_params.part_number.as_u16(),
+            content_length = upload_part_params.content_length,
+        )
+    )
+)]
 pub async fn b2_upload_part<'a, T: Into<Body>>(
     uploader_params: &'a mut UploadPartUrlParameters,
     upload_part_params: &'a UploadPartParameters<'a>,
     file_contents: T,
 ) -> Result<UploadPartOk, UploadPartError> {
-    let mut request = reqwest::Client::new()
+    let mut request = crate::transport::client()
         .post(uploader_params.upload_url().as_str())
         .header(
             "Authorization",
@@ -97,7 +121,10 @@ pub async fn b2_upload_part<'a, T: Into<Body>>(
     if let Some(ssec) = upload_part_params.server_side_encryption {
         request = ssec.add_to_request_as_header(request);
     }
-    let resp = request.send().await.map_err(UploadPartError::from)?;
+    let resp = request
+        .send_with_transport()
+        .await
+        .map_err(UploadPartError::from)?;
     if resp.status() == http::StatusCode::OK {
         Ok(resp.json().await.map_err(UploadPartError::from)?)
     } else {
@@ -1,4 +1,4 @@
-///! types for B2 buckets, based on https://www.backblaze.com/b2/docs/buckets.html
+//! types for B2 buckets, based on https://www.backblaze.com/b2/docs/buckets.html
 use std::hash::{Hash, Hasher};
 use std::num::NonZeroU64;
 use std::{
@@ -17,7 +17,7 @@ use typed_builder::TypedBuilder;
 
 use super::{FileNamePrefix, InvalidData, StringSpecializationError};
 
-#[derive(Debug, Serialize, Deserialize, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq)]
 /// Bucket names must be a minimum of 6 and a maximum of 50 characters long, and must be globally unique; two different B2 accounts cannot have buckets with the name name. Bucket names can consist of: letters, digits, and "-". Bucket names cannot start with "b2-"; these are reserved for internal Backblaze use.
 pub struct BucketName(String);
 
@@ -45,7 +45,7 @@ impl TryFrom<String> for BucketName {
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum BucketType {
     AllPublic,
     AllPrivate,
@@ -95,6 +95,40 @@ impl Serialize for BucketType {
     }
 }
 
+/// The bucket types, that can be set by [crate::v2::CreateBucketRequest] and [crate::v2::UpdateBucketRequest]
+///
+/// All other types (e.g. `snapshot`) are assigned by B2 and can only be read as [BucketType].
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SettableBucketType {
+    AllPublic,
+    AllPrivate,
+}
+
+impl From<SettableBucketType> for BucketType {
+    fn from(bucket_type: SettableBucketType) -> Self {
+        match bucket_type {
+            SettableBucketType::AllPublic => BucketType::AllPublic,
+            SettableBucketType::AllPrivate => BucketType::AllPrivate,
+        }
+    }
+}
+
+impl TryFrom<BucketType> for SettableBucketType {
+    type Error = InvalidData;
+
+    fn try_from(bucket_type: BucketType) -> Result<Self, Self::Error> {
+        match bucket_type {
+            BucketType::AllPublic => Ok(SettableBucketType::AllPublic),
+            BucketType::AllPrivate => Ok(SettableBucketType::AllPrivate),
+            other => Err(InvalidData::new(format!(
+                "Bucket type {:?} cannot be set, only allPublic and allPrivate are allowed",
+                other
+            ))),
+        }
+    }
+}
+
 struct BucketTypeVisitor;
 impl<'de> de::Visitor<'de> for BucketTypeVisitor {
     type Value = BucketType;
@@ -127,7 +161,7 @@ impl<'de> Deserialize<'de> for BucketType {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum BucketTypes {
     All,
     List(HashSet<BucketType>),
@@ -156,7 +190,23 @@ impl Serialize for BucketTypes {
     }
 }
 
-#[derive(Debug, Eq, Serialize, Deserialize)]
+impl<'de> Deserialize<'de> for BucketTypes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let types = Vec::<String>::deserialize(deserializer)?;
+        if types.iter().any(|t| t == "all") {
+            Ok(BucketTypes::All)
+        } else {
+            Ok(BucketTypes::List(
+                types.into_iter().map(Into::into).collect(),
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Serialize, Deserialize)]
 pub struct BucketInfoKey(String);
 
 impl TryFrom<String> for BucketInfoKey {
@@ -205,7 +255,7 @@ impl Hash for BucketInfoKey {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct BucketInfoValue(Vec<u8>);
 
 impl<'de> Deserialize<'de> for BucketInfoValue {
@@ -248,7 +298,7 @@ impl From<&str> for BucketInfoValue {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BucketInfo {
     key_values: HashMap<BucketInfoKey, BucketInfoValue>,
 }
@@ -352,7 +402,7 @@ impl TryFrom<HashMap<BucketInfoKey, BucketInfoValue>> for BucketInfo {
 /// unless BigUint or something similar is used
 pub type BucketRevision = u128;
 
-#[derive(Debug, Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Hash, PartialEq, Eq, Clone)]
 pub struct BucketId(String);
 
 impl BucketId {
@@ -372,16 +422,22 @@ impl TryFrom<String> for BucketId {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, TypedBuilder, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, TypedBuilder, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct LifeCycleRule {
     #[builder(default, setter(strip_option))]
     days_from_hiding_to_deleting: Option<NonZeroU64>,
     #[builder(default, setter(strip_option))]
     days_from_uploading_to_hiding: Option<NonZeroU64>,
+    #[builder(default, setter(strip_option))]
+    #[serde(default)]
+    days_from_starting_to_canceling_unfinished_large_files: Option<NonZeroU64>,
     file_name_prefix: FileNamePrefix,
 }
 
+/// Maximum number of lifecycle rules of a bucket
+pub const MAX_LIFECYCLE_RULES: usize = 100;
+
 impl LifeCycleRule {
     pub fn new(
         days_from_hiding_to_deleting: Option<NonZeroU64>,
@@ -391,7 +447,213 @@ impl LifeCycleRule {
         Self {
             days_from_hiding_to_deleting,
             days_from_uploading_to_hiding,
+            days_from_starting_to_canceling_unfinished_large_files: None,
             file_name_prefix,
         }
     }
+
+    /// Checks that at least one of the day fields is set
+    pub fn validate(&self) -> Result<(), LifeCycleRulesError> {
+        if self.days_from_hiding_to_deleting.is_none()
+            && self.days_from_uploading_to_hiding.is_none()
+            && self
+                .days_from_starting_to_canceling_unfinished_large_files
+                .is_none()
+        {
+            Err(LifeCycleRulesError::NoDaysSet {
+                file_name_prefix: self.file_name_prefix.clone(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks the rules of a bucket, before they are sent with [crate::v2::CreateBucketRequest] or
+    /// [crate::v2::UpdateBucketRequest]: at most [MAX_LIFECYCLE_RULES] rules, each valid acc. to
+    /// [LifeCycleRule::validate], and no prefix may be a prefix of another one.
+    pub fn validate_rules(rules: &[LifeCycleRule]) -> Result<(), LifeCycleRulesError> {
+        if rules.len() > MAX_LIFECYCLE_RULES {
+            return Err(LifeCycleRulesError::TooManyRules {
+                number_of_rules: rules.len(),
+            });
+        }
+        rules.iter().try_for_each(LifeCycleRule::validate)?;
+        match super::lifecycle::overlapping_prefixes(rules).first() {
+            Some((a, b)) if a == b => Err(LifeCycleRulesError::DuplicatePrefix {
+                file_name_prefix: (*a).clone(),
+            }),
+            Some((a, b)) => Err(LifeCycleRulesError::OverlappingPrefixes {
+                file_name_prefix: (*a).clone(),
+                other: (*b).clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the life cycle rule's days from hiding to deleting.
+    pub fn days_from_hiding_to_deleting(&self) -> Option<NonZeroU64> {
+        self.days_from_hiding_to_deleting
+    }
+
+    /// Get the life cycle rule's days from uploading to hiding.
+    pub fn days_from_uploading_to_hiding(&self) -> Option<NonZeroU64> {
+        self.days_from_uploading_to_hiding
+    }
+
+    /// Get the life cycle rule's days from starting to canceling unfinished large files.
+    pub fn days_from_starting_to_canceling_unfinished_large_files(&self) -> Option<NonZeroU64> {
+        self.days_from_starting_to_canceling_unfinished_large_files
+    }
+
+    /// Get a reference to the life cycle rule's file name prefix.
+    pub fn file_name_prefix(&self) -> &FileNamePrefix {
+        &self.file_name_prefix
+    }
+
+    /// Set the life cycle rule's days from hiding to deleting.
+    pub fn set_days_from_hiding_to_deleting(&mut self, days: Option<NonZeroU64>) {
+        self.days_from_hiding_to_deleting = days;
+    }
+
+    /// Set the life cycle rule's days from uploading to hiding.
+    pub fn set_days_from_uploading_to_hiding(&mut self, days: Option<NonZeroU64>) {
+        self.days_from_uploading_to_hiding = days;
+    }
+
+    /// Set the life cycle rule's days from starting to canceling unfinished large files.
+    pub fn set_days_from_starting_to_canceling_unfinished_large_files(
+        &mut self,
+        days: Option<NonZeroU64>,
+    ) {
+        self.days_from_starting_to_canceling_unfinished_large_files = days;
+    }
+
+    /// Set the life cycle rule's file name prefix.
+    pub fn set_file_name_prefix(&mut self, file_name_prefix: FileNamePrefix) {
+        self.file_name_prefix = file_name_prefix;
+    }
+}
+
+/// A set of lifecycle rules would be rejected by B2, see [LifeCycleRule::validate_rules]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LifeCycleRulesError {
+    TooManyRules {
+        number_of_rules: usize,
+    },
+    NoDaysSet {
+        file_name_prefix: FileNamePrefix,
+    },
+    DuplicatePrefix {
+        file_name_prefix: FileNamePrefix,
+    },
+    OverlappingPrefixes {
+        file_name_prefix: FileNamePrefix,
+        other: FileNamePrefix,
+    },
+}
+
+impl Display for LifeCycleRulesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LifeCycleRulesError::TooManyRules { number_of_rules } => write!(
+                f,
+                "At most {} lifecycle rules are allowed, but {} were given",
+                MAX_LIFECYCLE_RULES, number_of_rules
+            ),
+            LifeCycleRulesError::NoDaysSet { file_name_prefix } => write!(
+                f,
+                "Lifecycle rule for prefix '{}' does not set any number of days",
+                file_name_prefix.as_str()
+            ),
+            LifeCycleRulesError::DuplicatePrefix { file_name_prefix } => write!(
+                f,
+                "Multiple lifecycle rules for prefix '{}'",
+                file_name_prefix.as_str()
+            ),
+            LifeCycleRulesError::OverlappingPrefixes {
+                file_name_prefix,
+                other,
+            } => write!(
+                f,
+                "Lifecycle rule prefixes '{}' and '{}' overlap",
+                file_name_prefix.as_str(),
+                other.as_str()
+            ),
+        }
+    }
+}
+
+impl Error for LifeCycleRulesError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_settable_bucket_type() {
+        assert_eq!(
+            "\"allPrivate\"",
+            serde_json::to_string(&SettableBucketType::AllPrivate).unwrap()
+        );
+        for settable in [
+            SettableBucketType::AllPublic,
+            SettableBucketType::AllPrivate,
+        ] {
+            // serialized like the matching BucketType
+            assert_eq!(
+                serde_json::to_string(&BucketType::from(settable)).unwrap(),
+                serde_json::to_string(&settable).unwrap()
+            );
+            assert_eq!(
+                settable,
+                SettableBucketType::try_from(BucketType::from(settable)).unwrap()
+            );
+        }
+        assert!(SettableBucketType::try_from(BucketType::Snapshot).is_err());
+        assert!(SettableBucketType::try_from(BucketType::from("restricted")).is_err());
+    }
+
+    #[test]
+    fn test_validate_lifecycle_rules() {
+        let rule = |prefix: &str| {
+            LifeCycleRule::builder()
+                .file_name_prefix(prefix.to_owned().try_into().unwrap())
+                .days_from_starting_to_canceling_unfinished_large_files(NonZeroU64::new(7).unwrap())
+                .build()
+        };
+        let json = serde_json::to_value(rule("logs/")).unwrap();
+        assert_eq!(7, json["daysFromStartingToCancelingUnfinishedLargeFiles"]);
+
+        assert_eq!(
+            Ok(()),
+            LifeCycleRule::validate_rules(&[rule("a/"), rule("b/")])
+        );
+        assert_eq!(
+            Err(LifeCycleRulesError::OverlappingPrefixes {
+                file_name_prefix: "a/".to_owned().try_into().unwrap(),
+                other: "a/b/".to_owned().try_into().unwrap(),
+            }),
+            LifeCycleRule::validate_rules(&[rule("a/"), rule("a/b/")])
+        );
+        assert!(matches!(
+            LifeCycleRule::validate_rules(&[rule("a/"), rule("b/"), rule("a/")]),
+            Err(LifeCycleRulesError::DuplicatePrefix { .. })
+        ));
+        let too_many: Vec<_> = (0..=MAX_LIFECYCLE_RULES)
+            .map(|i| rule(&format!("{}/", i)))
+            .collect();
+        assert_eq!(
+            Err(LifeCycleRulesError::TooManyRules {
+                number_of_rules: MAX_LIFECYCLE_RULES + 1
+            }),
+            LifeCycleRule::validate_rules(&too_many)
+        );
+
+        let mut no_days = rule("c/");
+        no_days.set_days_from_starting_to_canceling_unfinished_large_files(None);
+        assert!(matches!(
+            LifeCycleRule::validate_rules(&[no_days]),
+            Err(LifeCycleRulesError::NoDaysSet { .. })
+        ));
+    }
 }
@@ -0,0 +1,438 @@
+//! Deleting all file versions of a bucket or of a file name prefix, e.g. to empty a bucket before deleting it.
+//!
+//! The versions are listed page by page with [crate::v2::b2_list_file_versions] and deleted with
+//! [crate::v2::b2_delete_file_version], at most `max_concurrency` at once. Versions under a legal hold or an active
+//! retention are skipped and reported, except for governance retentions if the key has the `bypassGovernance`
+//! capability. Unfinished large files are canceled with [crate::v2::b2_cancel_unfinished_large_files].
+use std::{convert::TryFrom, num::NonZeroUsize};
+
+use futures_util::{stream, StreamExt};
+use typed_builder::TypedBuilder;
+
+use super::{
+    b2_cancel_unfinished_large_files, b2_delete_bucket, b2_delete_file_version,
+    b2_list_file_versions::FileVersionPager,
+    errors::{B2ApiError, GenericB2Error},
+    large_file_janitor::DEFAULT_MAX_CONCURRENCY,
+    AuthorizeAccountOk, Bucket, BucketId, CancelUnfinishedLargeFilesOk,
+    CancelUnfinishedLargeFilesRequest, Capability, DeleteFileVersionOk, DeleteFileVersionRequest,
+    FileAction, FileInformation, FileName, FileNamePrefix, FileProtection, FileRetentionMode,
+    InvalidData, TimeStamp,
+};
+
+/// Selection of the file versions to be deleted by [b2_delete_all_file_versions]
+#[derive(Debug, TypedBuilder)]
+pub struct DeleteAllFileVersionsRequest<'s> {
+    bucket_id: &'s BucketId,
+
+    /// only versions of files with names starting with this prefix are deleted
+    #[builder(default, setter(strip_option))]
+    prefix: Option<&'s FileNamePrefix>,
+
+    /// maximum number of requests sent at once
+    #[builder(default = NonZeroUsize::new(DEFAULT_MAX_CONCURRENCY).unwrap())]
+    max_concurrency: NonZeroUsize,
+
+    /// delete the bucket afterwards, if all versions were deleted, must not be combined with a prefix
+    #[builder(default)]
+    delete_bucket: bool,
+}
+
+/// Result of [b2_delete_all_file_versions]
+#[derive(Debug)]
+pub struct DeleteAllFileVersionsOk {
+    deleted: Vec<DeleteFileVersionOk>,
+    protected: Vec<(FileInformation, FileProtection)>,
+    failed: Vec<(FileInformation, B2ApiError)>,
+    unfinished_large_files: Result<CancelUnfinishedLargeFilesOk, B2ApiError>,
+    deleted_bucket: Option<Result<Bucket, B2ApiError>>,
+}
+
+impl DeleteAllFileVersionsOk {
+    /// Get a reference to the deleted file versions.
+    pub fn deleted(&self) -> &[DeleteFileVersionOk] {
+        &self.deleted
+    }
+
+    /// Get a reference to the file versions, which were skipped because of a legal hold or retention.
+    pub fn protected(&self) -> &[(FileInformation, FileProtection)] {
+        &self.protected
+    }
+
+    /// Get a reference to the file versions, which could not be deleted.
+    pub fn failed(&self) -> &[(FileInformation, B2ApiError)] {
+        &self.failed
+    }
+
+    /// Get a reference to the canceled unfinished large files, or the error if they could not be listed.
+    pub fn unfinished_large_files(&self) -> Result<&CancelUnfinishedLargeFilesOk, &B2ApiError> {
+        self.unfinished_large_files.as_ref()
+    }
+
+    /// Get a reference to the deleted bucket or the error deleting it, `None` if the bucket was not to be deleted or
+    /// still contains files.
+    pub fn deleted_bucket(&self) -> Option<Result<&Bucket, &B2ApiError>> {
+        self.deleted_bucket.as_ref().map(Result::as_ref)
+    }
+
+    /// Returns `true` if every selected file version was deleted and every unfinished large file was canceled.
+    pub fn is_complete(&self) -> bool {
+        self.protected.is_empty()
+            && self.failed.is_empty()
+            && self
+                .unfinished_large_files
+                .as_ref()
+                .is_ok_and(|unfinished| unfinished.failed().is_empty())
+    }
+}
+
+/// Deletes all file versions selected by `request`, see the [module documentation](self)
+///
+/// An error is only returned, if the file versions could not be listed, versions which could not be deleted are
+/// reported in [DeleteAllFileVersionsOk::failed], errors canceling the unfinished large files and deleting the bucket
+/// in [DeleteAllFileVersionsOk::unfinished_large_files] and [DeleteAllFileVersionsOk::deleted_bucket].
+pub async fn b2_delete_all_file_versions(
+    authorization: &AuthorizeAccountOk,
+    request: &DeleteAllFileVersionsRequest<'_>,
+) -> Result<DeleteAllFileVersionsOk, B2ApiError> {
+    if request.delete_bucket && request.prefix.is_some() {
+        return Err(GenericB2Error::Unexpected {
+            raw_error: crate::v2::Error::InvalidData(InvalidData::new(
+                "The bucket cannot be deleted, if only the versions of a prefix are deleted"
+                    .to_owned(),
+            )),
+            retry: None,
+        }
+        .into());
+    }
+    let api_url = authorization.api_url();
+    let authorization_token = authorization.authorization_token();
+    let bypass_governance = authorization
+        .allowed()
+        .capabilities
+        .contains(Capability::BypassGovernance);
+    let now = TimeStamp::now();
+    let mut ok = DeleteAllFileVersionsOk {
+        deleted: Vec::new(),
+        protected: Vec::new(),
+        failed: Vec::new(),
+        unfinished_large_files: Ok(CancelUnfinishedLargeFilesOk::default()),
+        deleted_bucket: None,
+    };
+
+    let mut pages = FileVersionPager::new(request.bucket_id, request.prefix);
+    while let Some(page) = pages.next_page(api_url, authorization_token).await? {
+        let mut to_delete = Vec::new();
+        for file in page {
+            // unfinished large files are canceled afterwards
+            let file_id = match (file.action(), file.file_id()) {
+                (FileAction::Start | FileAction::Folder, _) | (_, None) => continue,
+                (_, Some(file_id)) => file_id.clone(),
+            };
+            match file.protection(now) {
+                Some(FileProtection::Retention {
+                    mode: FileRetentionMode::Governance,
+                    ..
+                }) if bypass_governance => to_delete.push((file_id, file)),
+                Some(protection) => ok.protected.push((file, protection)),
+                None => to_delete.push((file_id, file)),
+            }
+        }
+        let mut results = stream::iter(to_delete)
+            .map(|(file_id, file)| async move {
+                let delete_request = DeleteFileVersionRequest::new(
+                    file.file_name(),
+                    &file_id,
+                    bypass_governance.then_some(true),
+                );
+                let result =
+                    b2_delete_file_version(api_url, authorization_token, &delete_request).await;
+                (file, result)
+            })
+            .buffer_unordered(request.max_concurrency.get());
+        while let Some((file, result)) = results.next().await {
+            match result {
+                Ok(deleted) => ok.deleted.push(deleted),
+                Err(error) => ok.failed.push((file, error.into())),
+            }
+        }
+    }
+
+    let name_prefix = request
+        .prefix
+        .and_then(|prefix| FileName::try_from(prefix.as_str().to_owned()).ok());
+    let cancel_request = match name_prefix.as_ref() {
+        Some(name_prefix) => CancelUnfinishedLargeFilesRequest::builder()
+            .bucket_id(request.bucket_id)
+            .name_prefix(name_prefix)
+            .max_concurrency(request.max_concurrency)
+            .build(),
+        None => CancelUnfinishedLargeFilesRequest::builder()
+            .bucket_id(request.bucket_id)
+            .max_concurrency(request.max_concurrency)
+            .build(),
+    };
+    ok.unfinished_large_files =
+        b2_cancel_unfinished_large_files(api_url, authorization_token, &cancel_request)
+            .await
+            .map_err(B2ApiError::from);
+
+    if request.delete_bucket && ok.is_complete() {
+        ok.deleted_bucket = Some(
+            b2_delete_bucket(
+                api_url,
+                authorization_token,
+                authorization.account_id(),
+                request.bucket_id,
+            )
+            .await
+            .map_err(B2ApiError::from),
+        );
+    }
+    Ok(ok)
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::v2::test::mock_server::{
+        self, fake_authorization, FAKE_ACCOUNT_ID, FAKE_BUCKET_ID, FAR_FUTURE,
+    };
+
+    fn file_version(
+        file_id: &str,
+        action: &str,
+        retention_mode: Option<&str>,
+        legal_hold: Option<&str>,
+    ) -> serde_json::Value {
+        mock_server::file_version(
+            file_id,
+            &format!("logs/{}.txt", file_id),
+            action,
+            retention_mode,
+            legal_hold,
+        )
+    }
+
+    async fn mock_delete(mock_server: &MockServer, file_id: &str, bypass_governance: bool) {
+        let mut expected_body =
+            json!({"fileName": format!("logs/{}.txt", file_id), "fileId": file_id});
+        if bypass_governance {
+            expected_body["bypassGovernance"] = json!(true);
+        }
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_delete_file_version"))
+            .and(body_json(expected_body))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "fileId": file_id,
+                "fileName": format!("logs/{}.txt", file_id),
+            })))
+            .expect(1)
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_delete_all_file_versions() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_file_versions"))
+            .and(body_json(json!({
+                "bucketId": FAKE_BUCKET_ID,
+                "maxFileCount": 1000,
+                "prefix": "logs/",
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "files": [
+                    file_version("plain", "upload", None, None),
+                    file_version("governance", "upload", Some("governance"), None),
+                    file_version("compliance", "upload", Some("compliance"), None),
+                    file_version("held", "hide", None, Some("on")),
+                    file_version("unfinished", "start", None, None),
+                ],
+                "nextFileName": null,
+                "nextFileId": null,
+            })))
+            .mount(&mock_server)
+            .await;
+        mock_delete(&mock_server, "plain", true).await;
+        mock_delete(&mock_server, "governance", true).await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_unfinished_large_files"))
+            .and(body_json(json!({
+                "bucketId": FAKE_BUCKET_ID,
+                "namePrefix": "logs/",
+                "maxFileCount": 100,
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "files": [file_version("unfinished", "start", None, None)],
+                "nextFileId": null,
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_cancel_large_file"))
+            .and(body_json(json!({"fileId": "unfinished"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "fileId": "unfinished",
+                "accountId": FAKE_ACCOUNT_ID,
+                "bucketId": FAKE_BUCKET_ID,
+                "fileName": "logs/unfinished.txt",
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_delete_bucket"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let authorization = fake_authorization(
+            &mock_server.uri(),
+            &["listFiles", "deleteFiles", "bypassGovernance"],
+        );
+        let bucket_id = FAKE_BUCKET_ID.to_owned().try_into().unwrap();
+        let prefix = "logs/".to_owned().try_into().unwrap();
+        let request = DeleteAllFileVersionsRequest::builder()
+            .bucket_id(&bucket_id)
+            .prefix(&prefix)
+            .build();
+        let ok = b2_delete_all_file_versions(&authorization, &request)
+            .await
+            .unwrap();
+
+        assert_eq!(2, ok.deleted().len());
+        let protected: Vec<_> = ok
+            .protected()
+            .iter()
+            .map(|(file, protection)| (file.file_id().unwrap().as_str(), *protection))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "compliance",
+                    FileProtection::Retention {
+                        mode: FileRetentionMode::Compliance,
+                        retain_until_timestamp: TimeStamp::from_millis(FAR_FUTURE)
+                    }
+                ),
+                ("held", FileProtection::LegalHold),
+            ],
+            protected
+        );
+        assert!(ok.failed().is_empty());
+        assert_eq!(1, ok.unfinished_large_files().unwrap().canceled().len());
+        assert!(!ok.is_complete());
+        assert!(ok.deleted_bucket().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_delete_bucket_with_prefix() {
+        // the request is rejected before it is sent, so nothing listens on this url
+        let authorization = fake_authorization(
+            "http://localhost:1",
+            &["listFiles", "deleteFiles", "deleteBuckets"],
+        );
+        let bucket_id = FAKE_BUCKET_ID.to_owned().try_into().unwrap();
+        let prefix = "logs/".to_owned().try_into().unwrap();
+        let request = DeleteAllFileVersionsRequest::builder()
+            .bucket_id(&bucket_id)
+            .prefix(&prefix)
+            .delete_bucket(true)
+            .build();
+        let error = b2_delete_all_file_versions(&authorization, &request)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            crate::v2::errors::B2ApiErrorKind::Unexpected(crate::v2::Error::InvalidData(_))
+        ));
+    }
+
+    /// server without file versions, answering the listing of unfinished large files and the deletion of the bucket
+    /// with the given status
+    async fn empty_bucket_server(
+        list_unfinished_status: u16,
+        delete_bucket_status: u16,
+    ) -> MockServer {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_file_versions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "files": [],
+                "nextFileName": null,
+                "nextFileId": null,
+            })))
+            .mount(&mock_server)
+            .await;
+        let list_unfinished_response = match list_unfinished_status {
+            200 => {
+                ResponseTemplate::new(200).set_body_json(json!({"files": [], "nextFileId": null}))
+            }
+            status => ResponseTemplate::new(status).set_body_json(json!({
+                "status": status,
+                "code": "bad_request",
+                "message": "listing failed",
+            })),
+        };
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_unfinished_large_files"))
+            .respond_with(list_unfinished_response)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_delete_bucket"))
+            .respond_with(
+                ResponseTemplate::new(delete_bucket_status).set_body_json(json!({
+                    "status": delete_bucket_status,
+                    "code": "bad_request",
+                    "message": "bucket not empty",
+                })),
+            )
+            .mount(&mock_server)
+            .await;
+        mock_server
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_reported() {
+        let bucket_id = FAKE_BUCKET_ID.to_owned().try_into().unwrap();
+        let request = DeleteAllFileVersionsRequest::builder()
+            .bucket_id(&bucket_id)
+            .delete_bucket(true)
+            .build();
+        for (list_unfinished_status, unfinished_listed) in [(400, false), (200, true)] {
+            let mock_server = empty_bucket_server(list_unfinished_status, 400).await;
+            let authorization = fake_authorization(
+                &mock_server.uri(),
+                &["listFiles", "deleteFiles", "deleteBuckets"],
+            );
+            let ok = b2_delete_all_file_versions(&authorization, &request)
+                .await
+                .unwrap();
+            assert_eq!(unfinished_listed, ok.is_complete());
+            if unfinished_listed {
+                assert!(ok.unfinished_large_files().is_ok());
+                assert_eq!(
+                    Some("bad_request"),
+                    ok.deleted_bucket().unwrap().unwrap_err().code()
+                );
+            } else {
+                assert_eq!(
+                    Some("bad_request"),
+                    ok.unfinished_large_files().unwrap_err().code()
+                );
+                assert!(ok.deleted_bucket().is_none());
+            }
+        }
+    }
+}
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, EnumSetType, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-#[enumset(serialize_as_list)]
+#[enumset(serialize_repr = "list")]
 pub enum Capability {
     ListKeys,
     WriteKeys,
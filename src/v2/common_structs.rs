@@ -70,6 +70,12 @@ pub struct JsonErrorObj {
     pub message: String,
 }
 
+impl Display for JsonErrorObj {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}: {}", self.status, self.code, self.message)
+    }
+}
+
 /// Invalid data was received, the contents of message are subject to change,
 /// so please do not implement logic based on those.
 #[derive(Debug)]
@@ -100,7 +106,45 @@ impl std::error::Error for InvalidData {}
 pub enum Error {
     JsonError(JsonErrorObj),
     ReqwestError(reqwest::Error),
-    InvalidRetryAfterHeader { header: headers::HeaderValue },
+    InvalidRetryAfterHeader {
+        header: headers::HeaderValue,
+    },
+    /// The server responded successfully, but the response could not be interpreted
+    InvalidData(InvalidData),
+    /// Error reported by a [crate::transport::HttpTransport] not based on reqwest
+    TransportError(Box<dyn std::error::Error + Send + Sync>),
+    /// The call was refused by a [crate::metrics::MetricsHook] and not sent
+    BudgetExceeded(crate::metrics::BudgetExceeded),
+    /// The call was refused by [crate::v2::with_permission_check] and not sent
+    PermissionDenied(crate::v2::PermissionError),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::JsonError(raw_error) => write!(f, "Unexpected error response: {}", raw_error),
+            Error::ReqwestError(error) => write!(f, "Request failed: {}", error),
+            Error::InvalidRetryAfterHeader { header } => {
+                write!(f, "Invalid Retry-After header: {:?}", header)
+            }
+            Error::InvalidData(invalid_data) => write!(f, "{}", invalid_data),
+            Error::TransportError(error) => write!(f, "Request failed: {}", error),
+            Error::BudgetExceeded(error) => write!(f, "{}", error),
+            Error::PermissionDenied(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ReqwestError(error) => Some(error),
+            Error::TransportError(error) => Some(error.as_ref()),
+            Error::BudgetExceeded(error) => Some(error),
+            Error::PermissionDenied(error) => Some(error),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -120,7 +164,17 @@ impl DownloadUrl {
     }
 }
 
+/// Url of the S3 compatible api of the account, e.g. `https://s3.us-west-004.backblazeb2.com`
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct S3ApiUrl(String);
+
+impl S3ApiUrl {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AccountId(pub(crate) String);
 
 impl AccountId {
@@ -128,7 +182,7 @@ impl AccountId {
         &self.0
     }
 }
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AuthorizationToken(pub(crate) String);
 
 impl AuthorizationToken {
@@ -137,7 +191,7 @@ impl AuthorizationToken {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DownloadOnlyAuthorizationToken(String);
 
 impl DownloadOnlyAuthorizationToken {
@@ -146,6 +200,19 @@ impl DownloadOnlyAuthorizationToken {
     }
 }
 
+// tokens are never printed, so they cannot end up in logs or traces
+impl std::fmt::Debug for AuthorizationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AuthorizationToken(<redacted>)")
+    }
+}
+
+impl std::fmt::Debug for DownloadOnlyAuthorizationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DownloadOnlyAuthorizationToken(<redacted>)")
+    }
+}
+
 pub trait DownloadAuthorizationToken {
     fn download_token_as_str(&self) -> &str;
 }
@@ -0,0 +1,276 @@
+//! Owned SSE-C keys, see [CustomerKey]
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use zeroize::Zeroize;
+
+use super::{
+    server_side_encryption::{base64_decode_key, CUSTOMER_KEY_BYTES},
+    Md5Digest, OwnedServerSideEncryptionCustomerKey, ServerSideEncryptionCustomerKey,
+    SSE_C_KEY_ID_FILE_INFO,
+};
+
+/// A 256 bit key for SSE-C together with its md5 digest
+///
+/// The key is overwritten with zeros when it is dropped and never printed. It is identified by its
+/// [key id](CustomerKey::key_id), which is recorded in the file info of files uploaded with it.
+#[derive(Clone)]
+pub struct CustomerKey {
+    key: [u8; CUSTOMER_KEY_BYTES],
+    md5: Md5Digest,
+}
+
+impl CustomerKey {
+    /// New random key from the random number generator of the operating system
+    pub fn generate() -> Result<Self, CustomerKeyError> {
+        let mut key = [0u8; CUSTOMER_KEY_BYTES];
+        getrandom::getrandom(&mut key).map_err(CustomerKeyError::Random)?;
+        Ok(Self::from_bytes(key))
+    }
+
+    pub fn from_bytes(key: [u8; CUSTOMER_KEY_BYTES]) -> Self {
+        let md5 = md5::compute(key).into();
+        Self { key, md5 }
+    }
+
+    /// Key from its base64 encoding (as sent in the `X-Bz-Server-Side-Encryption-Customer-Key` header)
+    pub fn from_base64(encoded: &str) -> Result<Self, CustomerKeyError> {
+        base64_decode_key(encoded.trim())
+            .map(Self::from_bytes)
+            .map_err(|message| CustomerKeyError::InvalidKey { message })
+    }
+
+    /// Key from its hex encoding
+    pub fn from_hex(encoded: &str) -> Result<Self, CustomerKeyError> {
+        let mut key = [0u8; CUSTOMER_KEY_BYTES];
+        hex::decode_to_slice(encoded.trim(), &mut key).map_err(|e| {
+            CustomerKeyError::InvalidKey {
+                message: e.to_string(),
+            }
+        })?;
+        Ok(Self::from_bytes(key))
+    }
+
+    /// Key from a file containing either the 32 bytes of the key or its base64 or hex encoding
+    pub fn from_file(path: &Path) -> Result<Self, CustomerKeyError> {
+        let mut content = std::fs::read(path).map_err(|error| CustomerKeyError::Io {
+            path: path.to_owned(),
+            error,
+        })?;
+        let key = if let Ok(key) = <[u8; CUSTOMER_KEY_BYTES]>::try_from(content.as_slice()) {
+            Ok(Self::from_bytes(key))
+        } else {
+            match std::str::from_utf8(&content).map(str::trim) {
+                Ok(text) if text.len() == 2 * CUSTOMER_KEY_BYTES => Self::from_hex(text),
+                Ok(text) => Self::from_base64(text),
+                Err(_) => Err(CustomerKeyError::InvalidKey {
+                    message: format!(
+                        "expected {} bytes or their base64 or hex encoding",
+                        CUSTOMER_KEY_BYTES
+                    ),
+                }),
+            }
+        };
+        content.zeroize();
+        key
+    }
+
+    /// Get a reference to the customer key's md5 digest.
+    pub fn md5(&self) -> &Md5Digest {
+        &self.md5
+    }
+
+    /// Id of the key, the hex encoded md5 digest of the key
+    pub fn key_id(&self) -> String {
+        self.md5.clone().into()
+    }
+
+    /// borrowed form of this key, as used by the requests
+    pub fn as_customer_key(&self) -> ServerSideEncryptionCustomerKey<'_> {
+        ServerSideEncryptionCustomerKey::SseC {
+            customer_key: &self.key,
+            customer_key_md5: &self.md5,
+        }
+    }
+}
+
+impl Drop for CustomerKey {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+// the key itself is never printed, so it cannot end up in logs or traces
+impl std::fmt::Debug for CustomerKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomerKey")
+            .field("key", &"<redacted>")
+            .field("md5", &self.md5)
+            .finish()
+    }
+}
+
+impl<'s> From<&'s CustomerKey> for ServerSideEncryptionCustomerKey<'s> {
+    fn from(key: &'s CustomerKey) -> Self {
+        key.as_customer_key()
+    }
+}
+
+impl From<&CustomerKey> for OwnedServerSideEncryptionCustomerKey {
+    fn from(key: &CustomerKey) -> Self {
+        (&key.as_customer_key()).into()
+    }
+}
+
+/// SSE-C keys by their key id, to find the key a file was encrypted with
+#[derive(Debug, Clone, Default)]
+pub struct CustomerKeyRing {
+    keys: HashMap<String, CustomerKey>,
+}
+
+impl CustomerKeyRing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a key, returns the key with the same id if there was one
+    pub fn insert(&mut self, key: CustomerKey) -> Option<CustomerKey> {
+        self.keys.insert(key.key_id(), key)
+    }
+
+    pub fn remove(&mut self, key_id: &str) -> Option<CustomerKey> {
+        self.keys.remove(key_id)
+    }
+
+    /// Key by its key id (hex encoded md5 digest, upper or lower case)
+    pub fn get(&self, key_id: &str) -> Option<&CustomerKey> {
+        self.keys
+            .get(key_id)
+            .or_else(|| self.keys.get(&key_id.to_ascii_lowercase()))
+    }
+
+    pub fn get_by_md5(&self, md5: &Md5Digest) -> Option<&CustomerKey> {
+        self.keys.get(&String::from(md5.clone()))
+    }
+
+    /// Key recorded in a file info, e.g. [crate::v2::HeadFileOk::file_info]
+    pub fn for_file_info(&self, file_info: &HashMap<String, String>) -> Option<&CustomerKey> {
+        self.get(file_info.get(SSE_C_KEY_ID_FILE_INFO)?)
+    }
+
+    /// Key recorded in the file info of a file
+    #[cfg(feature = "b2_unstable")]
+    pub fn for_file(&self, file: &super::FileInformation) -> Option<&CustomerKey> {
+        self.get(file.sse_c_key_id()?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CustomerKey> {
+        self.keys.values()
+    }
+}
+
+impl FromIterator<CustomerKey> for CustomerKeyRing {
+    fn from_iter<T: IntoIterator<Item = CustomerKey>>(iter: T) -> Self {
+        let mut key_ring = Self::new();
+        for key in iter {
+            key_ring.insert(key);
+        }
+        key_ring
+    }
+}
+
+#[derive(Debug)]
+pub enum CustomerKeyError {
+    /// The random number generator of the operating system failed
+    Random(getrandom::Error),
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    /// The value is not a (base64 or hex encoded) 256 bit key
+    InvalidKey { message: String },
+}
+
+impl Display for CustomerKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CustomerKeyError::Random(error) => write!(f, "Could not generate a key: {}", error),
+            CustomerKeyError::Io { path, error } => {
+                write!(f, "Could not read {}: {}", path.display(), error)
+            }
+            CustomerKeyError::InvalidKey { message } => write!(f, "Invalid key: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for CustomerKeyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CustomerKeyError::Random(error) => Some(error),
+            CustomerKeyError::Io { error, .. } => Some(error),
+            CustomerKeyError::InvalidKey { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_customer_key() {
+        let key = CustomerKey::from_bytes([0x42u8; CUSTOMER_KEY_BYTES]);
+        let encoded = base64::encode([0x42u8; CUSTOMER_KEY_BYTES]);
+        assert_eq!(
+            key.key_id(),
+            CustomerKey::from_base64(&encoded).unwrap().key_id()
+        );
+        assert_eq!(
+            key.key_id(),
+            CustomerKey::from_hex(&"42".repeat(CUSTOMER_KEY_BYTES))
+                .unwrap()
+                .key_id()
+        );
+        assert_eq!(
+            md5::compute([0x42u8; CUSTOMER_KEY_BYTES]).0,
+            *key.md5().bytes()
+        );
+        assert!(matches!(
+            CustomerKey::from_base64("QkJC"),
+            Err(CustomerKeyError::InvalidKey { .. })
+        ));
+        assert!(!format!("{:?}", key).contains(&encoded));
+
+        let generated = CustomerKey::generate().unwrap();
+        assert_ne!(key.key_id(), generated.key_id());
+
+        let path = std::env::temp_dir().join(format!("customer_key_{}", generated.key_id()));
+        std::fs::write(&path, format!("{}\n", encoded)).unwrap();
+        let from_file = CustomerKey::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(key.key_id(), from_file.unwrap().key_id());
+
+        let key_ring: CustomerKeyRing = vec![key.clone(), generated].into_iter().collect();
+        assert_eq!(2, key_ring.len());
+        let file_info = HashMap::from([(
+            SSE_C_KEY_ID_FILE_INFO.to_owned(),
+            key.key_id().to_ascii_uppercase(),
+        )]);
+        assert_eq!(
+            Some(key.key_id()),
+            key_ring.for_file_info(&file_info).map(CustomerKey::key_id)
+        );
+        assert!(key_ring.for_file_info(&HashMap::new()).is_none());
+    }
+}
@@ -0,0 +1,872 @@
+//! Rotating the SSE-C key of the files of a bucket or of a file name prefix, e.g. after the old key was compromised.
+//!
+//! Every version encrypted with the old key is copied in place with [crate::v2::b2_copy_file] (or with
+//! [crate::v2::b2_copy_part] for files larger than 5 GB), encrypted with the new key, and the old version is deleted
+//! afterwards. The versions of a file are copied oldest first, so they keep their order. If the latest version of a
+//! file is not copied itself (e.g. it is a hide marker or encrypted with the new key already), it is copied (or the
+//! file is hidden) again afterwards, so it stays the latest version. Versions under a legal hold or an active
+//! retention are skipped, as they could not be deleted.
+//!
+//! Which key a version is encrypted with is taken from its file info (see [crate::v2::SSE_C_KEY_ID_FILE_INFO]), so
+//! this needs the file info of the listed versions, which is only available with the `b2_unstable` feature.
+//!
+//! The files are processed in the order of their names, at most `max_concurrency` at once. After each file the
+//! progress is reported, its [KeyRotationProgress::last_file_name] is the name up to which all files are done. It
+//! does not advance past a file with a version that could not be copied or deleted, so passing it as `start_after`
+//! resumes an interrupted rotation and retries the failed versions. A version is deleted right after it was copied, so an
+//! interruption leaves at most one version per file encrypted with both keys.
+use std::{convert::TryFrom, num::NonZeroUsize};
+
+use futures_util::{stream, StreamExt};
+use headers::Range;
+use typed_builder::TypedBuilder;
+
+use super::{
+    b2_cancel_large_file, b2_copy_file, b2_copy_part, b2_delete_file_version, b2_finish_large_file,
+    b2_hide_file,
+    b2_list_file_versions::FileVersionPager,
+    b2_start_large_file,
+    errors::{B2ApiError, GenericB2Error},
+    large_file_janitor::DEFAULT_MAX_CONCURRENCY,
+    AuthorizeAccountOk, BucketId, ContentType, CopyFileRequest, CopyPartRequest,
+    DeleteFileVersionRequest, FileAction, FileInformation, FileName, FileNamePrefix,
+    FileProtection, InvalidData, MetadataDirective, PartNumber, ServerSideEncryption,
+    ServerSideEncryptionCustomerKey, StartLargeFileParameters, TimeStamp, CONTENT_TYPE_AUTO,
+    SSE_C_KEY_ID_FILE_INFO,
+};
+
+/// largest file, that can be copied with a single call of b2_copy_file
+const MAX_COPY_LENGTH: u64 = 5_000_000_000;
+const MAX_PART_COUNT: u64 = 10_000;
+
+/// Selection of the files and keys for [b2_rotate_customer_key]
+#[derive(TypedBuilder)]
+pub struct RotateCustomerKeyRequest<'s> {
+    bucket_id: &'s BucketId,
+
+    /// only files with names starting with this prefix are copied
+    #[builder(default, setter(strip_option))]
+    prefix: Option<&'s FileNamePrefix>,
+
+    /// the SSE-C key the files are encrypted with
+    #[builder(setter(into))]
+    old_key: ServerSideEncryptionCustomerKey<'s>,
+
+    /// the key the files are encrypted with afterwards, usually another SSE-C key
+    #[builder(setter(into))]
+    new_key: ServerSideEncryptionCustomerKey<'s>,
+
+    /// also copy SSE-C versions without a recorded key id (e.g. uploaded by other tools) with the old key,
+    /// those encrypted with another key fail
+    #[builder(default)]
+    include_unrecorded: bool,
+
+    /// continue after the files up to this name, see [KeyRotationProgress::last_file_name]
+    #[builder(default, setter(strip_option))]
+    start_after: Option<&'s FileName>,
+
+    /// maximum number of files copied at once
+    #[builder(default = NonZeroUsize::new(DEFAULT_MAX_CONCURRENCY).unwrap())]
+    max_concurrency: NonZeroUsize,
+
+    /// called after each file
+    #[builder(default, setter(strip_option))]
+    progress: Option<&'s (dyn Fn(&KeyRotationProgress) + Sync)>,
+}
+
+impl<'s> std::fmt::Debug for RotateCustomerKeyRequest<'s> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RotateCustomerKeyRequest")
+            .field("bucket_id", &self.bucket_id)
+            .field("prefix", &self.prefix)
+            .field("old_key", &self.old_key)
+            .field("new_key", &self.new_key)
+            .field("include_unrecorded", &self.include_unrecorded)
+            .field("start_after", &self.start_after)
+            .field("max_concurrency", &self.max_concurrency)
+            .field(
+                "progress",
+                &self.progress.map(|_| "Fn(&KeyRotationProgress)"),
+            )
+            .finish()
+    }
+}
+
+/// Why a version encrypted with the old key was not copied by [b2_rotate_customer_key]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRotationSkipped {
+    /// the version is under a legal hold or an active retention, so it could not be deleted
+    Protected(FileProtection),
+    /// the latest version of the file is encrypted with an unknown SSE-C key, so it could not be copied again to
+    /// stay the latest version
+    LatestVersionWithOtherKey,
+}
+
+/// Progress of [b2_rotate_customer_key], reported after each file
+#[derive(Debug, Clone)]
+pub struct KeyRotationProgress {
+    last_file_name: Option<FileName>,
+    rotated_versions: usize,
+    copied_bytes: u64,
+    failed_versions: usize,
+}
+
+impl KeyRotationProgress {
+    /// Get a reference to the name of the last file done, all files up to this name are done.
+    ///
+    /// Files from the first one with a failed version on are not done, `None` if no file is done yet.
+    pub fn last_file_name(&self) -> Option<&FileName> {
+        self.last_file_name.as_ref()
+    }
+
+    /// Get the number of versions copied with the new key so far.
+    pub fn rotated_versions(&self) -> usize {
+        self.rotated_versions
+    }
+
+    /// Get the number of bytes copied so far.
+    pub fn copied_bytes(&self) -> u64 {
+        self.copied_bytes
+    }
+
+    /// Get the number of versions, which could not be copied or deleted so far.
+    pub fn failed_versions(&self) -> usize {
+        self.failed_versions
+    }
+}
+
+/// Result of [b2_rotate_customer_key]
+#[derive(Debug, Default)]
+pub struct RotateCustomerKeyOk {
+    rotated: Vec<(FileInformation, FileInformation)>,
+    restored: Vec<FileInformation>,
+    skipped: Vec<(FileInformation, KeyRotationSkipped)>,
+    failed: Vec<(FileInformation, B2ApiError)>,
+    copied_bytes: u64,
+    last_file_name: Option<FileName>,
+}
+
+impl RotateCustomerKeyOk {
+    /// Get a reference to the rotated versions, the deleted version encrypted with the old key and its copy.
+    pub fn rotated(&self) -> &[(FileInformation, FileInformation)] {
+        &self.rotated
+    }
+
+    /// Get a reference to the copies (or hide markers) created to keep the latest version of a file on top.
+    pub fn restored(&self) -> &[FileInformation] {
+        &self.restored
+    }
+
+    /// Get a reference to the versions encrypted with the old key, which were not copied.
+    pub fn skipped(&self) -> &[(FileInformation, KeyRotationSkipped)] {
+        &self.skipped
+    }
+
+    /// Get a reference to the versions, which could not be copied or deleted.
+    pub fn failed(&self) -> &[(FileInformation, B2ApiError)] {
+        &self.failed
+    }
+
+    /// Get the number of bytes copied.
+    pub fn copied_bytes(&self) -> u64 {
+        self.copied_bytes
+    }
+
+    /// Get a reference to the name of the last file done, see [KeyRotationProgress::last_file_name].
+    pub fn last_file_name(&self) -> Option<&FileName> {
+        self.last_file_name.as_ref()
+    }
+
+    /// Returns `true` if every version encrypted with the old key was copied and deleted.
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty() && self.failed.is_empty()
+    }
+
+    fn append(&mut self, other: RotateCustomerKeyOk) {
+        // failed versions have to be retried when resuming, so the files after them are not done either
+        if self.failed.is_empty() && other.failed.is_empty() {
+            self.last_file_name = other.last_file_name;
+        }
+        self.rotated.extend(other.rotated);
+        self.restored.extend(other.restored);
+        self.skipped.extend(other.skipped);
+        self.failed.extend(other.failed);
+        self.copied_bytes += other.copied_bytes;
+    }
+
+    fn progress(&self) -> KeyRotationProgress {
+        KeyRotationProgress {
+            last_file_name: self.last_file_name.clone(),
+            rotated_versions: self.rotated.len(),
+            copied_bytes: self.copied_bytes,
+            failed_versions: self.failed.len(),
+        }
+    }
+}
+
+/// Copies all versions encrypted with the old key of `request` with the new key, see the
+/// [module documentation](self)
+///
+/// An error is only returned, if the old key is not an SSE-C key or the file versions could not be listed, versions
+/// which could not be copied or deleted are reported in [RotateCustomerKeyOk::failed].
+pub async fn b2_rotate_customer_key(
+    authorization: &AuthorizeAccountOk,
+    request: &RotateCustomerKeyRequest<'_>,
+) -> Result<RotateCustomerKeyOk, B2ApiError> {
+    if !matches!(
+        request.old_key,
+        ServerSideEncryptionCustomerKey::SseC { .. }
+    ) {
+        return Err(GenericB2Error::Unexpected {
+            raw_error: crate::v2::Error::InvalidData(InvalidData::new(
+                "The old key of a key rotation must be an SSE-C key".to_owned(),
+            )),
+            retry: None,
+        }
+        .into());
+    }
+    let rotation = Rotation {
+        authorization,
+        request,
+        old_key_id: request.old_key.key_id(),
+        new_key_id: request.new_key.key_id(),
+    };
+    let mut ok = RotateCustomerKeyOk::default();
+    let mut pages = FileVersionPager::new(request.bucket_id, request.prefix);
+    if let Some(start_after) = request.start_after {
+        pages = pages.starting_at(start_after.clone());
+    }
+    // versions of the last file of a page, the next page might contain more of them
+    let mut versions: Vec<FileInformation> = Vec::new();
+    loop {
+        let page = pages
+            .next_page(authorization.api_url(), authorization.authorization_token())
+            .await?;
+        let is_last_page = page.is_none();
+        let mut files = Vec::new();
+        for file in page.into_iter().flatten() {
+            if Some(file.file_name()) == request.start_after
+                || !matches!(
+                    file.action(),
+                    FileAction::Upload | FileAction::Copy | FileAction::Hide
+                )
+            {
+                continue;
+            }
+            if versions
+                .last()
+                .is_some_and(|last| last.file_name() != file.file_name())
+            {
+                files.push(std::mem::take(&mut versions));
+            }
+            versions.push(file);
+        }
+        if is_last_page && !versions.is_empty() {
+            files.push(std::mem::take(&mut versions));
+        }
+        // files are completed in order, so the progress always refers to all files before
+        let mut results = stream::iter(files)
+            .map(|versions| rotation.rotate_file(versions))
+            .buffered(request.max_concurrency.get());
+        while let Some(file_ok) = results.next().await {
+            ok.append(file_ok);
+            if let Some(progress_fn) = request.progress {
+                progress_fn(&ok.progress());
+            }
+        }
+        if is_last_page {
+            return Ok(ok);
+        }
+    }
+}
+
+struct Rotation<'a, 's> {
+    authorization: &'a AuthorizeAccountOk,
+    request: &'a RotateCustomerKeyRequest<'s>,
+    old_key_id: Option<String>,
+    new_key_id: Option<String>,
+}
+
+impl<'a, 's> Rotation<'a, 's> {
+    /// SSE-C key needed to read a version, `Err(())` if it is encrypted with an unknown key
+    fn source_key(
+        &self,
+        file: &FileInformation,
+    ) -> Result<Option<ServerSideEncryptionCustomerKey<'s>>, ()> {
+        if file.server_side_encryption() != Some(&ServerSideEncryption::SseC) {
+            return Ok(None);
+        }
+        let key_id = file.sse_c_key_id().map(str::to_ascii_lowercase);
+        match key_id {
+            Some(key_id) if Some(&key_id) == self.old_key_id.as_ref() => {
+                Ok(Some(self.request.old_key))
+            }
+            Some(key_id) if Some(&key_id) == self.new_key_id.as_ref() => {
+                Ok(Some(self.request.new_key))
+            }
+            None if self.request.include_unrecorded => Ok(Some(self.request.old_key)),
+            _ => Err(()),
+        }
+    }
+
+    fn is_encrypted_with_old_key(&self, file: &FileInformation) -> bool {
+        !matches!(file.action(), FileAction::Hide)
+            && self.old_key_id.is_some()
+            && matches!(self.source_key(file), Ok(Some(key)) if key.key_id() == self.old_key_id)
+    }
+
+    /// rotates the versions of a single file, as listed (newest first)
+    async fn rotate_file(&self, versions: Vec<FileInformation>) -> RotateCustomerKeyOk {
+        let now = TimeStamp::now();
+        let mut ok = RotateCustomerKeyOk {
+            last_file_name: versions.first().map(|file| file.file_name().clone()),
+            ..Default::default()
+        };
+        let mut versions = versions.into_iter();
+        let latest = match versions.next() {
+            Some(latest) => latest,
+            None => return ok,
+        };
+        // versions to be copied, oldest first
+        let mut to_rotate = Vec::new();
+        for file in versions.rev() {
+            if self.is_encrypted_with_old_key(&file) {
+                match file.protection(now) {
+                    Some(protection) => ok
+                        .skipped
+                        .push((file, KeyRotationSkipped::Protected(protection))),
+                    None => to_rotate.push(file),
+                }
+            }
+        }
+        let latest_is_old = self.is_encrypted_with_old_key(&latest);
+        let latest_protection = latest.protection(now);
+        let latest = if latest_is_old && latest_protection.is_none() {
+            to_rotate.push(latest);
+            None
+        } else {
+            Some(latest)
+        };
+        if let Some(latest) = latest.as_ref() {
+            if !matches!(latest.action(), FileAction::Hide) && self.source_key(latest).is_err() {
+                ok.skipped.extend(
+                    to_rotate
+                        .into_iter()
+                        .map(|file| (file, KeyRotationSkipped::LatestVersionWithOtherKey)),
+                );
+                return ok;
+            }
+        }
+
+        let mut copied = false;
+        for file in to_rotate {
+            match self
+                .copy_in_place(
+                    &file,
+                    Some(self.request.old_key),
+                    Some(self.request.new_key),
+                )
+                .await
+            {
+                Ok(copy) => {
+                    copied = true;
+                    ok.copied_bytes += *file.content_length();
+                    match self.delete(&file).await {
+                        Ok(()) => ok.rotated.push((file, copy)),
+                        Err(error) => ok.failed.push((file, error)),
+                    }
+                }
+                Err(error) => {
+                    // the remaining versions are left as they are, so they keep their order
+                    ok.failed.push((file, error));
+                    break;
+                }
+            }
+        }
+
+        if let Some(latest) = latest {
+            // a protected latest version encrypted with the old key stays, but its copy is on top
+            let result = if copied || latest_is_old {
+                self.restore_latest(&latest, latest_protection.is_none(), &mut ok)
+                    .await
+            } else {
+                Ok(())
+            };
+            match (result, latest_protection) {
+                (Err(error), _) => ok.failed.push((latest, error)),
+                (Ok(()), Some(protection)) if latest_is_old => ok
+                    .skipped
+                    .push((latest, KeyRotationSkipped::Protected(protection))),
+                (Ok(()), _) => {}
+            }
+        }
+        ok
+    }
+
+    /// copies (or hides) the latest version again, so it is on top of the copied versions
+    async fn restore_latest(
+        &self,
+        latest: &FileInformation,
+        delete: bool,
+        ok: &mut RotateCustomerKeyOk,
+    ) -> Result<(), B2ApiError> {
+        let api_url = self.authorization.api_url();
+        let authorization_token = self.authorization.authorization_token();
+        if let FileAction::Hide = latest.action() {
+            match b2_hide_file(
+                api_url,
+                authorization_token,
+                latest.bucket_id(),
+                latest.file_name(),
+            )
+            .await
+            {
+                Ok(hidden) => ok.restored.push(hidden),
+                Err(error) => return Err(error.into()),
+            }
+            return Ok(());
+        }
+        // unwrap is safe, versions encrypted with an unknown key are not restored
+        let source = self.source_key(latest).unwrap();
+        let destination = match (source, latest.server_side_encryption()) {
+            (Some(_), _) => Some(self.request.new_key),
+            (None, Some(ServerSideEncryption::SseB2)) => {
+                Some(ServerSideEncryptionCustomerKey::SseB2)
+            }
+            (None, _) => None,
+        };
+        let copy = self.copy_in_place(latest, source, destination).await?;
+        ok.copied_bytes += *latest.content_length();
+        ok.restored.push(copy);
+        if delete {
+            self.delete(latest).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, file: &FileInformation) -> Result<(), B2ApiError> {
+        // unwrap is safe, uploaded versions always have a file id
+        let request =
+            DeleteFileVersionRequest::new(file.file_name(), file.file_id().unwrap(), None);
+        b2_delete_file_version(
+            self.authorization.api_url(),
+            self.authorization.authorization_token(),
+            &request,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// copies a version to a new version of the same file, with the same content type and file info
+    async fn copy_in_place(
+        &self,
+        file: &FileInformation,
+        source: Option<ServerSideEncryptionCustomerKey<'s>>,
+        destination: Option<ServerSideEncryptionCustomerKey<'s>>,
+    ) -> Result<FileInformation, B2ApiError> {
+        let api_url = self.authorization.api_url();
+        let authorization_token = self.authorization.authorization_token();
+        // unwrap is safe, uploaded versions always have a file id
+        let file_id = file.file_id().unwrap();
+        let content_type = file
+            .content_type()
+            .cloned()
+            .map(ContentType::from)
+            .unwrap_or_else(|| CONTENT_TYPE_AUTO.clone());
+        let content_length = *file.content_length();
+        // the key id of the source is replaced by the one of the destination
+        let mut file_info = file.file_info().clone();
+        if let Some(file_info) = file_info.as_object_mut() {
+            file_info.remove(SSE_C_KEY_ID_FILE_INFO);
+        }
+        if content_length <= MAX_COPY_LENGTH {
+            let request = CopyFileRequest {
+                source_file_id: file_id,
+                destination_bucket_id: None,
+                file_name: file.file_name(),
+                range: None,
+                metadata_directive: Some(MetadataDirective::REPLACE),
+                content_type: Some(&content_type),
+                file_info: Some(&file_info),
+                file_retention: None,
+                legal_hold: None,
+                source_server_side_encryption: source,
+                destination_server_side_encryption: destination,
+                record_sse_c_key_id: true,
+            };
+            return Ok(b2_copy_file(api_url, authorization_token, &request).await?);
+        }
+
+        let start_request = StartLargeFileParameters {
+            bucket_id: file.bucket_id(),
+            file_name: file.file_name(),
+            content_type: &content_type,
+            file_info: Some(&file_info),
+            file_retention: None,
+            legal_hold: None,
+            server_side_encryption: destination,
+            record_sse_c_key_id: true,
+        };
+        let large_file = b2_start_large_file(api_url, authorization_token, &start_request).await?;
+        // unwrap is safe, started large files always have a file id
+        let large_file_id = large_file.file_id().unwrap();
+        let part_size = u64::from(self.authorization.recommended_part_size())
+            .max(content_length.div_ceil(MAX_PART_COUNT))
+            .min(MAX_COPY_LENGTH);
+        let mut parts = Vec::new();
+        let mut start = 0;
+        while start < content_length {
+            let end = (start + part_size).min(content_length);
+            // unwraps are safe, the range is not empty and there are at most 10000 parts
+            let range = Range::bytes(start..end).unwrap();
+            let part_number = PartNumber::try_from(parts.len() as u16 + 1).unwrap();
+            let request = CopyPartRequest {
+                source_file_id: file_id,
+                large_file_id,
+                part_number,
+                range: Some(&range),
+                source_server_side_encryption: source,
+                destination_server_side_encryption: destination,
+            };
+            match b2_copy_part(api_url, authorization_token, &request).await {
+                Ok(part) => parts.push(part),
+                Err(error) => {
+                    // the error of the copy is more interesting than the one of the cleanup
+                    let _ = b2_cancel_large_file(api_url, authorization_token, large_file_id).await;
+                    return Err(error.into());
+                }
+            }
+            start = end;
+        }
+        let part_sha1s: Vec<_> = parts.iter().map(|part| part.content_sha1()).collect();
+        Ok(b2_finish_large_file(api_url, authorization_token, large_file_id, &part_sha1s).await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{convert::TryInto, sync::Mutex};
+
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::v2::{
+        server_side_encryption::base64_encode_sse,
+        test::mock_server::{self, fake_authorization, FAKE_BUCKET_ID},
+        Md5Digest,
+    };
+
+    const OLD_KEY: [u8; 32] = [1u8; 32];
+    const NEW_KEY: [u8; 32] = [2u8; 32];
+
+    fn file_version(
+        file_id: &str,
+        file_name: &str,
+        action: &str,
+        key_id: Option<String>,
+        legal_hold: Option<&str>,
+    ) -> serde_json::Value {
+        let mut version = mock_server::file_version(file_id, file_name, action, None, legal_hold);
+        if let Some(key_id) = key_id {
+            version["fileInfo"] = json!({"author": "me", "sse_c_key_id": key_id});
+            version["serverSideEncryption"] = json!({"algorithm": "AES256", "mode": "SSE-C"});
+        }
+        version
+    }
+
+    fn sse_c(key: &[u8; 32], md5: &Md5Digest) -> serde_json::Value {
+        json!({
+            "algorithm": "AES256",
+            "mode": "SSE-C",
+            "customerKey": base64_encode_sse(key),
+            "customerKeyMd5": base64_encode_sse(md5.bytes()),
+        })
+    }
+
+    async fn mock_copy(mock_server: &MockServer, file_id: &str, file_name: &str, new_key_id: &str) {
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_copy_file"))
+            .and(body_partial_json(json!({"sourceFileId": file_id})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_version(
+                &format!("{}_copy", file_id),
+                file_name,
+                "copy",
+                Some(new_key_id.to_owned()),
+                None,
+            )))
+            .expect(1)
+            .mount(mock_server)
+            .await;
+    }
+
+    async fn mock_delete(mock_server: &MockServer, file_id: &str, file_name: &str) {
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_delete_file_version"))
+            .and(body_json(json!({"fileName": file_name, "fileId": file_id})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "fileId": file_id,
+                "fileName": file_name,
+            })))
+            .expect(1)
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_rotate_customer_key() {
+        let old_md5 = Md5Digest::new([0xaau8; 16]);
+        let new_md5 = Md5Digest::new([0xbbu8; 16]);
+        let old_key = ServerSideEncryptionCustomerKey::SseC {
+            customer_key: &OLD_KEY,
+            customer_key_md5: &old_md5,
+        };
+        let new_key = ServerSideEncryptionCustomerKey::SseC {
+            customer_key: &NEW_KEY,
+            customer_key_md5: &new_md5,
+        };
+        let old_key_id = old_key.key_id().unwrap();
+        let new_key_id = new_key.key_id().unwrap();
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_file_versions"))
+            .and(body_json(json!({
+                "bucketId": FAKE_BUCKET_ID,
+                "startFileName": "a.txt",
+                "maxFileCount": 1000,
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "files": [
+                    file_version("a1", "a.txt", "upload", Some(old_key_id.clone()), None),
+                    // the versions of a file are listed newest first
+                    file_version("b3", "b.txt", "hide", None, None),
+                    file_version("b2", "b.txt", "upload", Some(old_key_id.clone()), None),
+                    file_version("b1", "b.txt", "upload", Some(old_key_id.clone()), None),
+                    file_version("c2", "c.txt", "upload", Some("ff".repeat(16)), None),
+                    file_version("c1", "c.txt", "upload", Some(old_key_id.clone()), None),
+                    file_version("d1", "d.txt", "upload", Some(old_key_id.clone()), Some("on")),
+                    file_version("e1", "e.txt", "upload", Some(new_key_id.clone()), None),
+                ],
+                "nextFileName": null,
+                "nextFileId": null,
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_copy_file"))
+            .and(body_json(json!({
+                "sourceFileId": "b1",
+                "fileName": "b.txt",
+                "metadataDirective": "REPLACE",
+                "contentType": "text/plain",
+                "fileInfo": {"author": "me", "sse_c_key_id": new_key_id},
+                "sourceServerSideEncryption": sse_c(&OLD_KEY, &old_md5),
+                "destinationServerSideEncryption": sse_c(&NEW_KEY, &new_md5),
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_version(
+                "b1_copy",
+                "b.txt",
+                "copy",
+                Some(new_key_id.clone()),
+                None,
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        mock_delete(&mock_server, "b1", "b.txt").await;
+        mock_copy(&mock_server, "b2", "b.txt", &new_key_id).await;
+        mock_delete(&mock_server, "b2", "b.txt").await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_hide_file"))
+            .and(body_json(
+                json!({"bucketId": FAKE_BUCKET_ID, "fileName": "b.txt"}),
+            ))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(file_version("b4", "b.txt", "hide", None, None)),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        // the protected version stays, but its copy with the new key becomes the latest version
+        mock_copy(&mock_server, "d1", "d.txt", &new_key_id).await;
+
+        let authorization = fake_authorization(
+            &mock_server.uri(),
+            &["listFiles", "readFiles", "writeFiles", "deleteFiles"],
+        );
+        let bucket_id = FAKE_BUCKET_ID.to_owned().try_into().unwrap();
+        let start_after = "a.txt".to_owned().try_into().unwrap();
+        let progress = Mutex::new(Vec::new());
+        let report = |p: &KeyRotationProgress| {
+            progress.lock().unwrap().push((
+                p.last_file_name().unwrap().as_str().to_owned(),
+                p.rotated_versions(),
+            ))
+        };
+        let request = RotateCustomerKeyRequest::builder()
+            .bucket_id(&bucket_id)
+            .old_key(old_key)
+            .new_key(new_key)
+            .start_after(&start_after)
+            .progress(&report)
+            .build();
+        let ok = b2_rotate_customer_key(&authorization, &request)
+            .await
+            .unwrap();
+
+        let rotated: Vec<_> = ok
+            .rotated()
+            .iter()
+            .map(|(old, new)| {
+                (
+                    old.file_id().unwrap().as_str(),
+                    new.file_id().unwrap().as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(vec![("b1", "b1_copy"), ("b2", "b2_copy")], rotated);
+        let restored: Vec<_> = ok
+            .restored()
+            .iter()
+            .map(|file| file.file_id().unwrap().as_str())
+            .collect();
+        assert_eq!(vec!["b4", "d1_copy"], restored);
+        let skipped: Vec<_> = ok
+            .skipped()
+            .iter()
+            .map(|(file, skipped)| (file.file_id().unwrap().as_str(), *skipped))
+            .collect();
+        assert_eq!(
+            vec![
+                ("c1", KeyRotationSkipped::LatestVersionWithOtherKey),
+                (
+                    "d1",
+                    KeyRotationSkipped::Protected(FileProtection::LegalHold)
+                ),
+            ],
+            skipped
+        );
+        assert!(ok.failed().is_empty());
+        assert_eq!(21, ok.copied_bytes());
+        assert!(!ok.is_complete());
+        assert_eq!("e.txt", ok.last_file_name().unwrap().as_str());
+        assert_eq!(
+            vec![
+                ("b.txt".to_owned(), 2),
+                ("c.txt".to_owned(), 2),
+                ("d.txt".to_owned(), 2),
+                ("e.txt".to_owned(), 2),
+            ],
+            progress.into_inner().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resume_point_before_failed_file() {
+        let old_md5 = Md5Digest::new([0xaau8; 16]);
+        let new_md5 = Md5Digest::new([0xbbu8; 16]);
+        let old_key = ServerSideEncryptionCustomerKey::SseC {
+            customer_key: &OLD_KEY,
+            customer_key_md5: &old_md5,
+        };
+        let new_key = ServerSideEncryptionCustomerKey::SseC {
+            customer_key: &NEW_KEY,
+            customer_key_md5: &new_md5,
+        };
+        let old_key_id = old_key.key_id().unwrap();
+        let new_key_id = new_key.key_id().unwrap();
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_file_versions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "files": [
+                    file_version("a1", "a.txt", "upload", Some(old_key_id.clone()), None),
+                    file_version("b1", "b.txt", "upload", Some(old_key_id.clone()), None),
+                    file_version("c1", "c.txt", "upload", Some(old_key_id.clone()), None),
+                ],
+                "nextFileName": null,
+                "nextFileId": null,
+            })))
+            .mount(&mock_server)
+            .await;
+        mock_copy(&mock_server, "a1", "a.txt", &new_key_id).await;
+        mock_delete(&mock_server, "a1", "a.txt").await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_copy_file"))
+            .and(body_partial_json(json!({"sourceFileId": "b1"})))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "status": 400,
+                "code": "bad_request",
+                "message": "copy failed",
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        mock_copy(&mock_server, "c1", "c.txt", &new_key_id).await;
+        mock_delete(&mock_server, "c1", "c.txt").await;
+
+        let authorization = fake_authorization(
+            &mock_server.uri(),
+            &["listFiles", "readFiles", "writeFiles", "deleteFiles"],
+        );
+        let bucket_id = FAKE_BUCKET_ID.to_owned().try_into().unwrap();
+        let progress = Mutex::new(Vec::new());
+        let report = |p: &KeyRotationProgress| {
+            progress.lock().unwrap().push(
+                p.last_file_name()
+                    .map(|file_name| file_name.as_str().to_owned()),
+            )
+        };
+        let request = RotateCustomerKeyRequest::builder()
+            .bucket_id(&bucket_id)
+            .old_key(old_key)
+            .new_key(new_key)
+            .progress(&report)
+            .build();
+        let ok = b2_rotate_customer_key(&authorization, &request)
+            .await
+            .unwrap();
+
+        assert_eq!(2, ok.rotated().len());
+        assert_eq!(1, ok.failed().len());
+        assert_eq!("b1", ok.failed()[0].0.file_id().unwrap().as_str());
+        // resuming after a.txt retries b.txt
+        assert_eq!("a.txt", ok.last_file_name().unwrap().as_str());
+        assert_eq!(
+            vec![Some("a.txt".to_owned()); 3],
+            progress.into_inner().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_old_key_not_sse_c() {
+        let new_md5 = Md5Digest::new([0xbbu8; 16]);
+        // the request is rejected before it is sent, so nothing listens on this url
+        let authorization = fake_authorization(
+            "http://localhost:1",
+            &["listFiles", "readFiles", "writeFiles", "deleteFiles"],
+        );
+        let bucket_id = FAKE_BUCKET_ID.to_owned().try_into().unwrap();
+        let request = RotateCustomerKeyRequest::builder()
+            .bucket_id(&bucket_id)
+            .old_key(ServerSideEncryptionCustomerKey::SseB2)
+            .new_key(ServerSideEncryptionCustomerKey::SseC {
+                customer_key: &NEW_KEY,
+                customer_key_md5: &new_md5,
+            })
+            .build();
+        let error = b2_rotate_customer_key(&authorization, &request)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            crate::v2::errors::B2ApiErrorKind::Unexpected(crate::v2::Error::InvalidData(_))
+        ));
+    }
+}
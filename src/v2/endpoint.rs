@@ -0,0 +1,187 @@
+//! The endpoints of the B2 api, with the capabilities they require and their transaction class.
+//!
+//! Capabilities are taken from the [application key documentation](https://www.backblaze.com/b2/docs/application_keys.html),
+//! transaction classes from the [pricing page](https://www.backblaze.com/b2/b2-transactions-price.html).
+use super::{Capabilities, Capability};
+
+/// Class of a transaction, B2 bills each class differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransactionClass {
+    /// uploads, deletes and other changes of files (free of charge)
+    A,
+    /// downloads and file information
+    B,
+    /// listings, bucket and key management
+    C,
+}
+
+macro_rules! endpoints {
+    ($($variant:ident => $name:literal,)*) => {
+        /// Endpoints of the B2 api
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Endpoint {
+            $($variant,)*
+        }
+
+        impl Endpoint {
+            /// name of the call in the B2 api, e.g. `b2_list_file_names`
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Endpoint::$variant => $name,)*
+                }
+            }
+
+            /// endpoint for a name of the B2 api, e.g. `b2_list_file_names`
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($name => Some(Endpoint::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+endpoints! {
+    AuthorizeAccount => "b2_authorize_account",
+    CancelLargeFile => "b2_cancel_large_file",
+    CopyFile => "b2_copy_file",
+    CopyPart => "b2_copy_part",
+    CreateBucket => "b2_create_bucket",
+    CreateKey => "b2_create_key",
+    DeleteBucket => "b2_delete_bucket",
+    DeleteFileVersion => "b2_delete_file_version",
+    DeleteKey => "b2_delete_key",
+    DownloadFileById => "b2_download_file_by_id",
+    DownloadFileByName => "b2_download_file_by_name",
+    FinishLargeFile => "b2_finish_large_file",
+    GetDownloadAuthorization => "b2_get_download_authorization",
+    GetFileInfo => "b2_get_file_info",
+    GetUploadPartUrl => "b2_get_upload_part_url",
+    GetUploadUrl => "b2_get_upload_url",
+    HideFile => "b2_hide_file",
+    ListBuckets => "b2_list_buckets",
+    ListFileNames => "b2_list_file_names",
+    ListFileVersions => "b2_list_file_versions",
+    ListKeys => "b2_list_keys",
+    ListParts => "b2_list_parts",
+    ListUnfinishedLargeFiles => "b2_list_unfinished_large_files",
+    StartLargeFile => "b2_start_large_file",
+    UpdateBucket => "b2_update_bucket",
+    UpdateFileLegalHold => "b2_update_file_legal_hold",
+    UpdateFileRetention => "b2_update_file_retention",
+    UploadFile => "b2_upload_file",
+    UploadPart => "b2_upload_part",
+}
+
+impl Endpoint {
+    /// Endpoint called by a request to the given url path, e.g. `/b2api/v2/b2_list_file_names`.
+    ///
+    /// Downloads by name (`/file/<bucket>/<file>`) are reported as [Endpoint::DownloadFileByName],
+    /// paths not belonging to the B2 api return `None`.
+    pub fn from_url_path(path: &str) -> Option<Self> {
+        if path.starts_with("/file/") {
+            return Some(Endpoint::DownloadFileByName);
+        }
+        let name = path.strip_prefix("/b2api/v2/")?.split('/').next()?;
+        Self::from_name(name)
+    }
+
+    /// capabilities a key needs to call this endpoint
+    pub fn required_capabilities(&self) -> Capabilities {
+        match self {
+            Endpoint::AuthorizeAccount => Capabilities::empty(),
+            Endpoint::CancelLargeFile
+            | Endpoint::FinishLargeFile
+            | Endpoint::GetUploadPartUrl
+            | Endpoint::GetUploadUrl
+            | Endpoint::HideFile
+            | Endpoint::ListParts
+            | Endpoint::StartLargeFile
+            | Endpoint::UploadFile
+            | Endpoint::UploadPart => Capability::WriteFiles.into(),
+            Endpoint::CopyFile | Endpoint::CopyPart => {
+                Capability::ReadFiles | Capability::WriteFiles
+            }
+            Endpoint::CreateBucket | Endpoint::UpdateBucket => Capability::WriteBuckets.into(),
+            Endpoint::CreateKey => Capability::WriteKeys.into(),
+            Endpoint::DeleteBucket => Capability::DeleteBuckets.into(),
+            Endpoint::DeleteFileVersion => Capability::DeleteFiles.into(),
+            Endpoint::DeleteKey => Capability::DeleteKeys.into(),
+            Endpoint::DownloadFileById | Endpoint::DownloadFileByName | Endpoint::GetFileInfo => {
+                Capability::ReadFiles.into()
+            }
+            Endpoint::GetDownloadAuthorization => Capability::ShareFiles.into(),
+            Endpoint::ListBuckets => Capability::ListBuckets.into(),
+            Endpoint::ListFileNames
+            | Endpoint::ListFileVersions
+            | Endpoint::ListUnfinishedLargeFiles => Capability::ListFiles.into(),
+            Endpoint::ListKeys => Capability::ListKeys.into(),
+            Endpoint::UpdateFileLegalHold => Capability::WriteFileLegalHolds.into(),
+            Endpoint::UpdateFileRetention => Capability::WriteFileRetentions.into(),
+        }
+    }
+
+    /// class the transaction of a call to this endpoint is billed as
+    pub fn transaction_class(&self) -> TransactionClass {
+        match self {
+            Endpoint::CancelLargeFile
+            | Endpoint::DeleteBucket
+            | Endpoint::DeleteFileVersion
+            | Endpoint::DeleteKey
+            | Endpoint::FinishLargeFile
+            | Endpoint::GetUploadPartUrl
+            | Endpoint::GetUploadUrl
+            | Endpoint::HideFile
+            | Endpoint::StartLargeFile
+            | Endpoint::UpdateFileLegalHold
+            | Endpoint::UpdateFileRetention
+            | Endpoint::UploadFile
+            | Endpoint::UploadPart => TransactionClass::A,
+            Endpoint::DownloadFileById | Endpoint::DownloadFileByName | Endpoint::GetFileInfo => {
+                TransactionClass::B
+            }
+            Endpoint::AuthorizeAccount
+            | Endpoint::CopyFile
+            | Endpoint::CopyPart
+            | Endpoint::CreateBucket
+            | Endpoint::CreateKey
+            | Endpoint::GetDownloadAuthorization
+            | Endpoint::ListBuckets
+            | Endpoint::ListFileNames
+            | Endpoint::ListFileVersions
+            | Endpoint::ListKeys
+            | Endpoint::ListParts
+            | Endpoint::ListUnfinishedLargeFiles
+            | Endpoint::UpdateBucket => TransactionClass::C,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_url_path() {
+        assert_eq!(
+            Some(Endpoint::ListFileNames),
+            Endpoint::from_url_path("/b2api/v2/b2_list_file_names")
+        );
+        assert_eq!(
+            Some(Endpoint::UploadPart),
+            Endpoint::from_url_path("/b2api/v2/b2_upload_part/4_ze73ede9c9c8412db49f60715/0037")
+        );
+        assert_eq!(
+            Some(Endpoint::DownloadFileByName),
+            Endpoint::from_url_path("/file/bucket/photos/cat.jpg")
+        );
+        assert_eq!(None, Endpoint::from_url_path("/bucket/photos/cat.jpg"));
+        assert_eq!(
+            TransactionClass::C,
+            Endpoint::from_name(Endpoint::ListFileNames.name())
+                .unwrap()
+                .transaction_class()
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use super::JsonErrorObj;
 
 /// How this error should be handled acc. to [https://www.backblaze.com/b2/docs/calling.html#error_handling] and [https://www.backblaze.com/b2/docs/integration_checklist.html]
@@ -15,10 +17,149 @@ pub enum RecommendedReaction<'s> {
     /// get a new upload url
     GetNewUploadUrl,
 }
-trait B2Error {
+/// Errors returned by B2, that come with a recommendation how to handle them
+pub trait B2Error {
     fn recommended_action(&self) -> RecommendedReaction<'_>;
 }
 
+/// [RecommendedReaction] without the borrowed retry delay, so it can be stored in [B2ApiError]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum ReactionKind {
+    Raise,
+    Retry,
+    Reauthenticate,
+    GetNewUploadUrl,
+}
+
+impl From<&RecommendedReaction<'_>> for ReactionKind {
+    fn from(reaction: &RecommendedReaction<'_>) -> Self {
+        match reaction {
+            RecommendedReaction::Raise => Self::Raise,
+            RecommendedReaction::Retry { delay: _ } => Self::Retry,
+            RecommendedReaction::Reauthenticate => Self::Reauthenticate,
+            RecommendedReaction::GetNewUploadUrl => Self::GetNewUploadUrl,
+        }
+    }
+}
+
+/// What went wrong in a [B2ApiError]
+#[derive(Debug)]
+pub enum B2ApiErrorKind {
+    /// Error returned by the server
+    Server(JsonErrorObj),
+    /// Error reported from reqwest & not in the data of the request or response
+    Request(reqwest::Error),
+    Unexpected(crate::v2::Error),
+}
+
+/// Error of any endpoint, every per endpoint error (e.g. [CopyError]) can be converted into this one,
+/// so errors of different calls can be handled (or passed on with `?`) together
+#[derive(Debug)]
+pub struct B2ApiError {
+    endpoint_error: &'static str,
+    kind: B2ApiErrorKind,
+    retry: Option<usize>,
+    reaction: ReactionKind,
+}
+
+impl B2ApiError {
+    /// Get a reference to the b2 api error's kind.
+    pub fn kind(&self) -> &B2ApiErrorKind {
+        &self.kind
+    }
+
+    /// name of the per endpoint error this was created from, e.g. `CopyError`
+    pub fn endpoint_error(&self) -> &'static str {
+        self.endpoint_error
+    }
+
+    fn json_error(&self) -> Option<&JsonErrorObj> {
+        match &self.kind {
+            B2ApiErrorKind::Server(raw_error)
+            | B2ApiErrorKind::Unexpected(crate::v2::Error::JsonError(raw_error)) => Some(raw_error),
+            _ => None,
+        }
+    }
+
+    /// http status returned by the server, if any
+    pub fn status(&self) -> Option<http::StatusCode> {
+        match &self.kind {
+            B2ApiErrorKind::Request(error)
+            | B2ApiErrorKind::Unexpected(crate::v2::Error::ReqwestError(error)) => error.status(),
+            _ => self.json_error().map(|raw_error| raw_error.status),
+        }
+    }
+
+    /// error code returned by the server, e.g. `bad_request`
+    pub fn code(&self) -> Option<&str> {
+        self.json_error().map(|raw_error| raw_error.code.as_str())
+    }
+
+    /// error message returned by the server
+    pub fn message(&self) -> Option<&str> {
+        self.json_error()
+            .map(|raw_error| raw_error.message.as_str())
+    }
+
+    /// number of seconds from the RetryAfter Header, if any
+    pub fn retry_after(&self) -> Option<usize> {
+        self.retry
+    }
+}
+
+impl B2Error for B2ApiError {
+    fn recommended_action(&self) -> RecommendedReaction<'_> {
+        match self.reaction {
+            ReactionKind::Raise => RecommendedReaction::Raise,
+            ReactionKind::Retry => RecommendedReaction::Retry { delay: &self.retry },
+            ReactionKind::Reauthenticate => RecommendedReaction::Reauthenticate,
+            ReactionKind::GetNewUploadUrl => RecommendedReaction::GetNewUploadUrl,
+        }
+    }
+}
+
+impl Display for B2ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            B2ApiErrorKind::Server(raw_error) => {
+                write!(f, "{}: {}", self.endpoint_error, raw_error)
+            }
+            B2ApiErrorKind::Request(error) => {
+                write!(f, "{}: request failed: {}", self.endpoint_error, error)
+            }
+            B2ApiErrorKind::Unexpected(raw_error) => {
+                write!(f, "{}: {}", self.endpoint_error, raw_error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for B2ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            B2ApiErrorKind::Server(_) => None,
+            B2ApiErrorKind::Request(error) => Some(error),
+            B2ApiErrorKind::Unexpected(raw_error) => raw_error.source(),
+        }
+    }
+}
+
+/// most likely error code for a response without body, as returned for HEAD requests
+pub(crate) fn error_code_from_status(status: http::StatusCode) -> &'static str {
+    match status.as_u16() {
+        400 => "bad_request",
+        401 => "unauthorized",
+        403 => "access_denied",
+        404 => "not_found",
+        408 => "request_timeout",
+        416 => "range_not_satisfiable",
+        429 => "too_many_requests",
+        500 => "internal_error",
+        503 => "service_unavailable",
+        _ => "unknown",
+    }
+}
+
 macro_rules! reactionIdentToRecommendedReaction {
     (Raise, $retry:ident) => {
         RecommendedReaction::Raise
@@ -45,19 +186,37 @@ macro_rules! error_enum{
                 /// Retry delay in seconds as returned by the retry-after header, if any
                 retry: Option<usize>,
             },)*
+            /// Error with a code not documented for this call, but a status that should be retried (429, 500 or 503)
+            RetryableServerError {
+                raw_error: crate::v2::JsonErrorObj,
+                /// Retry delay in seconds as returned by the retry-after header, if any
+                retry: Option<usize>,
+            },
             /// Error reported from reqwest & not in the data of the request or response
             RequestError{error: reqwest::Error},
             Unexpected {
                 raw_error: crate::v2::Error,
+                /// Retry delay in seconds as returned by the retry-after header, if any
+                retry: Option<usize>,
             },
         }
 
         impl $enum_name {
             fn from_json_error_obj(raw_error: crate::v2::JsonErrorObj, retry: Option<usize>) -> Self {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    status = raw_error.status.as_u16(),
+                    error_code = raw_error.code.as_str(),
+                    retry_after = retry,
+                    "{} returned by B2",
+                    stringify!($enum_name)
+                );
                 match (raw_error.status.as_u16(), raw_error.code.as_str()) {
                     $(($variant_code, $variant_text) => Self::$variant_name { raw_error, retry },)*
+                    (429 | 500 | 503, _) => Self::RetryableServerError { raw_error, retry },
                     _ => Self::Unexpected {
                         raw_error: crate::v2::Error::JsonError(raw_error),
+                        retry,
                     },
                 }
             }
@@ -76,16 +235,17 @@ macro_rules! error_enum{
                 Ok(v)
             }
 
+            fn retry_after_from_headers(headers: &headers::HeaderMap) -> Result<Option<usize>, crate::v2::Error> {
+                headers
+                    .get("retry-after")
+                    .map(Self::header_to_usize)
+                    .transpose()
+            }
+
             pub async fn from_response(response: reqwest::Response) -> Self {
-                let retry_after = {
-                    if let Some(retry_after_header) = response.headers().get("retry-after") {
-                        match Self::header_to_usize(retry_after_header) {
-                            Ok(retry_after) => Some(retry_after),
-                            Err(e) => return Self::Unexpected{raw_error: e},
-                        }
-                    } else {
-                        None
-                    }
+                let retry_after = match Self::retry_after_from_headers(response.headers()) {
+                    Ok(retry_after) => retry_after,
+                    Err(e) => return Self::Unexpected{raw_error: e, retry: None},
                 };
 
                 let res: Result<JsonErrorObj, _> = response.json().await;
@@ -95,17 +255,55 @@ macro_rules! error_enum{
                 }
             }
 
+            /// Creates the error for a response without a body (e.g. to a HEAD request),
+            /// the error code is guessed from the http status
+            #[allow(dead_code)]
+            pub(crate) fn from_response_without_body(response: &reqwest::Response) -> Self {
+                let retry_after = match Self::retry_after_from_headers(response.headers()) {
+                    Ok(retry_after) => retry_after,
+                    Err(e) => return Self::Unexpected{raw_error: e, retry: None},
+                };
+                let status = response.status();
+                let raw_error = crate::v2::JsonErrorObj {
+                    status,
+                    code: crate::v2::errors::error_code_from_status(status).to_owned(),
+                    message: status.canonical_reason().unwrap_or_default().to_owned(),
+                };
+                Self::from_json_error_obj(raw_error, retry_after)
+            }
+
             /// number of seconds from the RetryAfter Header, if any
             pub fn retry_after(&self) -> Option<usize> {
                 match self {
                     $( Self::$variant_name { raw_error: _, retry } => *retry,)*
+                    Self::RetryableServerError { raw_error: _, retry } => *retry,
                     Self::RequestError { error: _ } => None,
-                    Self::Unexpected { raw_error: _ } => None,
+                    Self::Unexpected { raw_error: _, retry } => *retry,
                 }
             }
         }
 
 
+        impl From<crate::transport::TransportError> for $enum_name {
+            fn from(e: crate::transport::TransportError) -> Self {
+                match e {
+                    crate::transport::TransportError::Reqwest(e) => e.into(),
+                    crate::transport::TransportError::Other(e) => Self::Unexpected {
+                        raw_error: crate::v2::Error::TransportError(e),
+                        retry: None,
+                    },
+                    crate::transport::TransportError::BudgetExceeded(e) => Self::Unexpected {
+                        raw_error: crate::v2::Error::BudgetExceeded(e),
+                        retry: None,
+                    },
+                    crate::transport::TransportError::PermissionDenied(e) => Self::Unexpected {
+                        raw_error: crate::v2::Error::PermissionDenied(e),
+                        retry: None,
+                    },
+                }
+            }
+        }
+
         impl From<reqwest::Error> for $enum_name {
             fn from(e: reqwest::Error) -> Self {
                 if e.is_timeout() || e.is_connect() {
@@ -115,11 +313,53 @@ macro_rules! error_enum{
                 } else {
                     Self::Unexpected {
                         raw_error: crate::v2::Error::ReqwestError(e),
+                        retry: None,
                     }
                 }
             }
         }
 
+        impl std::fmt::Display for $enum_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $( Self::$variant_name { raw_error, retry: _ } => write!(f, "{}: {}", stringify!($enum_name), raw_error),)*
+                    Self::RetryableServerError { raw_error, retry: _ } => write!(f, "{}: {}", stringify!($enum_name), raw_error),
+                    Self::RequestError { error } => write!(f, "{}: request failed: {}", stringify!($enum_name), error),
+                    Self::Unexpected { raw_error, retry: _ } => write!(f, "{}: {}", stringify!($enum_name), raw_error),
+                }
+            }
+        }
+
+        impl std::error::Error for $enum_name {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                match self {
+                    Self::RequestError { error } => Some(error),
+                    Self::Unexpected { raw_error, retry: _ } => std::error::Error::source(raw_error),
+                    _ => None,
+                }
+            }
+        }
+
+        impl From<$enum_name> for crate::v2::errors::B2ApiError {
+            fn from(e: $enum_name) -> Self {
+                use crate::v2::errors::B2Error;
+                let reaction = crate::v2::errors::ReactionKind::from(&e.recommended_action());
+                let retry = e.retry_after();
+                let kind = match e {
+                    $( $enum_name::$variant_name { raw_error, retry: _ } => crate::v2::errors::B2ApiErrorKind::Server(raw_error),)*
+                    $enum_name::RetryableServerError { raw_error, retry: _ } => crate::v2::errors::B2ApiErrorKind::Server(raw_error),
+                    $enum_name::RequestError { error } => crate::v2::errors::B2ApiErrorKind::Request(error),
+                    $enum_name::Unexpected { raw_error, retry: _ } => crate::v2::errors::B2ApiErrorKind::Unexpected(raw_error),
+                };
+                Self {
+                    endpoint_error: stringify!($enum_name),
+                    kind,
+                    retry,
+                    reaction,
+                }
+            }
+        }
+
         impl crate::v2::errors::B2Error for $enum_name {
             #[allow(unused_variables)]
             fn recommended_action(&self) -> RecommendedReaction<'_> {
@@ -128,10 +368,11 @@ macro_rules! error_enum{
                         Self::$variant_name { raw_error: _, retry } => {reactionIdentToRecommendedReaction!($reaction, retry)}
                     ,)*
 
-                    Self::RequestError{ error: _ } => {
-                        todo!()
-                    },
-                    Self::Unexpected { raw_error: _ } => RecommendedReaction::Raise,
+                    Self::RetryableServerError { raw_error: _, retry } => RecommendedReaction::Retry { delay: retry },
+
+                    // timeouts and connection errors
+                    Self::RequestError{ error: _ } => RecommendedReaction::Retry { delay: &None },
+                    Self::Unexpected { raw_error: _, retry: _ } => RecommendedReaction::Raise,
 
                 }
             }
@@ -139,11 +380,17 @@ macro_rules! error_enum{
     }
 }
 
+// Error codes acc. to the documentation of the individual calls, extended by the codes any call
+// may return acc. to https://www.backblaze.com/b2/docs/calling.html#error_handling
+
 error_enum!(AuthorizeError {
-    (401, "bad_request", BadRequest, Raise),
+    (400, "bad_request", BadRequest, Raise),
     (401, "unauthorized", Unauthorized, Raise),
     (401, "unsupported", Unsupported, Raise),
     (403, "transaction_cap_exceeded", TransactionCapExceeded, Raise),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
 error_enum!(CopyError {
@@ -153,9 +400,13 @@ error_enum!(CopyError {
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
     (403, "access_denied", AccessDenied, Raise),
     (403, "cap_exceeded", CapExceeded, Raise),
+    (403, "storage_cap_exceeded", StorageCapExceeded, Raise),
     (404, "not_found", NotFound, Raise),
     (408, "request_timeout", RequestTimeout, Retry),
     (416, "range_not_satisfiable", RangeNotSatisfiable, Raise),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
 error_enum!(CreateBucketError {
@@ -165,6 +416,10 @@ error_enum!(CreateBucketError {
     (401, "unauthorized", Unauthorized, Raise),
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
+    (403, "transaction_cap_exceeded", TransactionCapExceeded, Raise),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
 error_enum!(DeleteFileVersionError {
@@ -175,6 +430,10 @@ error_enum!(DeleteFileVersionError {
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
     (401, "access_denied", AccessDenied, Raise),
+    (403, "transaction_cap_exceeded", TransactionCapExceeded, Raise),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
 error_enum!(DownloadFileError {
@@ -185,7 +444,11 @@ error_enum!(DownloadFileError {
     (403, "access_denied", AccessDenied, Raise),
     (403, "download_cap_exceeded", DownloadCapExceeded, Raise),
     (404, "not_found", NotFound, Raise),
+    (408, "request_timeout", RequestTimeout, Retry),
     (416, "range_not_satisfiable", RangeNotSatisfiable, Raise),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
 error_enum!(GetDownloadAuthorizationError {
@@ -193,6 +456,9 @@ error_enum!(GetDownloadAuthorizationError {
     (401, "unauthorized", Unauthorized, Raise),
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
+    (403, "transaction_cap_exceeded", TransactionCapExceeded, Raise),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
     (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
@@ -201,7 +467,11 @@ error_enum!(GetFileInfoError {
     (401, "unauthorized", Unauthorized, Raise),
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
+    (403, "transaction_cap_exceeded", TransactionCapExceeded, Raise),
     (404, "not_found", NotFound, Raise),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
 error_enum!(GetUploadUrlError {
@@ -210,7 +480,9 @@ error_enum!(GetUploadUrlError {
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
     (403, "storage_cap_exceeded", StorageCapExceeded, Raise),
-    (503, "service_unavailable", ServiceUnavaliabe, Retry),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
 //Generic Error caused by backblaze, error value for multiple functions
@@ -219,6 +491,10 @@ error_enum!(GenericB2Error {
     (401, "unauthorized", Unauthorized, Raise),
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
+    (403, "transaction_cap_exceeded", TransactionCapExceeded, Raise),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
 error_enum!(ListFileNamesError {
@@ -228,7 +504,9 @@ error_enum!(ListFileNamesError {
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
     (403, "transaction_cap_exceeded", TransactionCapExceeded, Raise),
-    (503, "bad_request", BadRequestTimeout, Retry),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
 error_enum!(LargeFileError {
@@ -237,6 +515,10 @@ error_enum!(LargeFileError {
     (401, "unauthorized", Unauthorized, Raise),
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
+    (403, "storage_cap_exceeded", StorageCapExceeded, Raise),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
 error_enum!(UpdateBucketError {
@@ -244,25 +526,37 @@ error_enum!(UpdateBucketError {
     (401, "unauthorized", Unauthorized, Raise),
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
+    (403, "transaction_cap_exceeded", TransactionCapExceeded, Raise),
     (409, "conflict", Conflict, Raise),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
 error_enum!(UpdateFileLockError {
     (400, "bad_request", BadRequest, Raise),
+    (401, "unauthorized", Unauthorized, Raise),
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
     (401, "access_denied", AccessDenied, Raise),
     (403, "cap_exceeded", CapExceeded, Raise),
     (405, "method_not_allowed", MethodNotAllowed, Raise),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
+// acc. to the documentation uploads should get a new upload url on timeouts and server errors
 error_enum!(UploadFileError {
     (400, "bad_request", BadRequest, Raise),
     (401, "unauthorized", Unauthorized, Raise),
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
     (403, "cap_exceeded", CapExceeded, Raise),
+    (403, "storage_cap_exceeded", StorageCapExceeded, Raise),
     (408, "request_timeout", RequestTimeout, GetNewUploadUrl),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, GetNewUploadUrl),
     (503, "service_unavailable", ServiceUnavailable, GetNewUploadUrl),
 });
 
@@ -271,22 +565,33 @@ error_enum!(UploadPartError {
     (401, "unauthorized", Unauthorized, Raise),
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
+    (403, "cap_exceeded", CapExceeded, Raise),
+    (403, "storage_cap_exceeded", StorageCapExceeded, Raise),
     (408, "request_timeout", RequestTimeout, GetNewUploadUrl),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, GetNewUploadUrl),
     (503, "service_unavailable", ServiceUnavailable, GetNewUploadUrl),
 });
 
 error_enum!(ListFileVersionsError {
-    (400, "out_of_range",	OutOfRange, Raise),
+    (400, "bad_request", BadRequest, Raise),
+    (400, "out_of_range", OutOfRange, Raise),
     (400, "invalid_file_id", InvalidFileId, Raise),
     (401, "unauthorized", Unauthorized, Raise),
     (401, "bad_auth_token", BadAuthToken, Reauthenticate),
     (401, "expired_auth_token", ExpiredAuthToken, Reauthenticate),
-    (503, "bad_request", BadRequest, Retry),
+    (403, "transaction_cap_exceeded", TransactionCapExceeded, Raise),
+    (429, "too_many_requests", TooManyRequests, Retry),
+    (500, "internal_error", InternalError, Retry),
+    (503, "service_unavailable", ServiceUnavailable, Retry),
 });
 
 #[cfg(test)]
 mod test {
-    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
 
     use super::*;
     use crate::v2::JsonErrorObj;
@@ -328,6 +633,7 @@ mod test {
         match &err {
             TestEnum::Unexpected {
                 raw_error: crate::v2::Error::JsonError(raw_error),
+                retry: None,
             } => {
                 assert_eq!(&json_err, raw_error)
             }
@@ -374,4 +680,354 @@ mod test {
             error.recommended_action()
         );
     }
+
+    #[tokio::test]
+    async fn test_into_b2_api_error() {
+        let raw_error = JsonErrorObj {
+            status: 408u16.try_into().unwrap(),
+            code: "request_timeout".to_owned(),
+            message: "message".to_owned(),
+        };
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(408)
+                    .insert_header("Retry-After", "5")
+                    .set_body_json(raw_error),
+            )
+            .mount(&mock_server)
+            .await;
+        let resp = reqwest::get(mock_server.uri()).await.unwrap();
+        let error: B2ApiError = TestEnum::from_response(resp).await.into();
+        assert_eq!("TestEnum", error.endpoint_error());
+        assert_eq!(Some(http::StatusCode::REQUEST_TIMEOUT), error.status());
+        assert_eq!(Some("request_timeout"), error.code());
+        assert_eq!(Some("message"), error.message());
+        assert_eq!(Some(5), error.retry_after());
+        assert_eq!(
+            RecommendedReaction::Retry { delay: &Some(5) },
+            error.recommended_action()
+        );
+        assert_eq!(
+            "TestEnum: 408 Request Timeout request_timeout: message",
+            error.to_string()
+        );
+        assert!(std::error::Error::source(&error).is_none());
+
+        let boxed: Box<dyn std::error::Error> = Box::new(error);
+        assert!(boxed.to_string().starts_with("TestEnum"));
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_into_b2_api_error() {
+        let raw_error = JsonErrorObj {
+            status: 400u16.try_into().unwrap(),
+            code: "does_not_exist".to_owned(),
+            message: "message".to_owned(),
+        };
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(400)
+                    .insert_header("Retry-After", "7")
+                    .set_body_json(raw_error),
+            )
+            .mount(&mock_server)
+            .await;
+        let resp = reqwest::get(mock_server.uri()).await.unwrap();
+        let error = TestEnum::from_response(resp).await;
+        assert_eq!(Some(7), error.retry_after());
+        let error: B2ApiError = error.into();
+        assert!(matches!(error.kind(), B2ApiErrorKind::Unexpected(_)));
+        assert_eq!(Some(7), error.retry_after());
+        assert_eq!(RecommendedReaction::Raise, error.recommended_action());
+    }
+
+    #[tokio::test]
+    async fn test_retryable_status_fallback() {
+        let raw_error = JsonErrorObj {
+            status: 503u16.try_into().unwrap(),
+            code: "not_in_table".to_owned(),
+            message: "message".to_owned(),
+        };
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(503)
+                    .insert_header("Retry-After", "3")
+                    .set_body_json(raw_error.clone()),
+            )
+            .mount(&mock_server)
+            .await;
+        let resp = reqwest::get(mock_server.uri()).await.unwrap();
+        let error = TestEnum::from_response(resp).await;
+        match &error {
+            TestEnum::RetryableServerError {
+                raw_error: received,
+                retry: Some(3),
+            } => assert_eq!(&raw_error, received),
+            _ => panic!("Expected RetryableServerError, found {:#?}", error),
+        }
+        assert_eq!(
+            RecommendedReaction::Retry { delay: &Some(3) },
+            error.recommended_action()
+        );
+        let error: B2ApiError = error.into();
+        assert_eq!(Some("not_in_table"), error.code());
+        assert_eq!(
+            RecommendedReaction::Retry { delay: &Some(3) },
+            error.recommended_action()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_error_source() {
+        // nothing listens on port 1, so the connection is refused
+        let error: B2ApiError =
+            TestEnum::from(reqwest::get("http://127.0.0.1:1").await.unwrap_err()).into();
+        assert!(matches!(error.kind(), B2ApiErrorKind::Request(_)));
+        assert!(std::error::Error::source(&error)
+            .unwrap()
+            .downcast_ref::<reqwest::Error>()
+            .is_some());
+        assert_eq!(
+            RecommendedReaction::Retry { delay: &None },
+            error.recommended_action()
+        );
+    }
+
+    /// feeds each error of the table through `from_response` and checks the variant and recommended reaction
+    macro_rules! error_table_test {
+        ($test_name:ident, $enum_name:ident {
+            $(($status:literal, $code:literal, $reaction:ident)),* $(,)?
+        }) => {
+            #[tokio::test]
+            async fn $test_name() {
+                let mock_server = MockServer::start().await;
+                $(
+                    let raw_error = JsonErrorObj {
+                        status: $status.try_into().unwrap(),
+                        code: $code.to_owned(),
+                        message: "message".to_owned(),
+                    };
+                    let error_path = format!("/{}/{}", $status, $code);
+                    Mock::given(method("GET"))
+                        .and(path(error_path.as_str()))
+                        .respond_with(ResponseTemplate::new($status).set_body_json(raw_error.clone()))
+                        .mount(&mock_server)
+                        .await;
+                    let resp = reqwest::get(format!("{}{}", mock_server.uri(), error_path))
+                        .await
+                        .unwrap();
+                    let error: B2ApiError = $enum_name::from_response(resp).await.into();
+                    match error.kind() {
+                        B2ApiErrorKind::Server(received) => assert_eq!(&raw_error, received),
+                        _ => panic!("{} not in table of {}: {:#?}", error_path, stringify!($enum_name), error),
+                    }
+                    assert_eq!(ReactionKind::$reaction, error.reaction, "{}", error_path);
+                )*
+            }
+        };
+    }
+
+    error_table_test!(test_authorize_error_table, AuthorizeError {
+        (400, "bad_request", Raise),
+        (401, "unauthorized", Raise),
+        (401, "unsupported", Raise),
+        (403, "transaction_cap_exceeded", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_copy_error_table, CopyError {
+        (400, "bad_request", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "access_denied", Raise),
+        (403, "cap_exceeded", Raise),
+        (403, "storage_cap_exceeded", Raise),
+        (404, "not_found", Raise),
+        (408, "request_timeout", Retry),
+        (416, "range_not_satisfiable", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_create_bucket_error_table, CreateBucketError {
+        (400, "bad_request", Raise),
+        (400, "too_many_buckets", Raise),
+        (400, "duplicate_bucket_name", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "transaction_cap_exceeded", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_delete_file_version_error_table, DeleteFileVersionError {
+        (400, "bad_request", Raise),
+        (400, "bad_bucket_id", Raise),
+        (400, "file_not_present", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (401, "access_denied", Raise),
+        (403, "transaction_cap_exceeded", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_download_file_error_table, DownloadFileError {
+        (400, "bad_request", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "access_denied", Raise),
+        (403, "download_cap_exceeded", Raise),
+        (404, "not_found", Raise),
+        (408, "request_timeout", Retry),
+        (416, "range_not_satisfiable", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_get_download_authorization_error_table, GetDownloadAuthorizationError {
+        (400, "bad_request", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "transaction_cap_exceeded", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_get_file_info_error_table, GetFileInfoError {
+        (400, "bad_request", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "transaction_cap_exceeded", Raise),
+        (404, "not_found", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_get_upload_url_error_table, GetUploadUrlError {
+        (400, "bad_request", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "storage_cap_exceeded", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_generic_b2_error_table, GenericB2Error {
+        (400, "bad_request", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "transaction_cap_exceeded", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_list_file_names_error_table, ListFileNamesError {
+        (400, "bad_request", Raise),
+        (400, "invalid_bucket_id", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "transaction_cap_exceeded", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_large_file_error_table, LargeFileError {
+        (400, "bad_request", Raise),
+        (400, "bad_bucket_id", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "storage_cap_exceeded", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_update_bucket_error_table, UpdateBucketError {
+        (400, "bad_request", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "transaction_cap_exceeded", Raise),
+        (409, "conflict", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_update_file_lock_error_table, UpdateFileLockError {
+        (400, "bad_request", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (401, "access_denied", Raise),
+        (403, "cap_exceeded", Raise),
+        (405, "method_not_allowed", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
+
+    error_table_test!(test_upload_file_error_table, UploadFileError {
+        (400, "bad_request", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "cap_exceeded", Raise),
+        (403, "storage_cap_exceeded", Raise),
+        (408, "request_timeout", GetNewUploadUrl),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", GetNewUploadUrl),
+        (503, "service_unavailable", GetNewUploadUrl),
+    });
+
+    error_table_test!(test_upload_part_error_table, UploadPartError {
+        (400, "bad_request", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "cap_exceeded", Raise),
+        (403, "storage_cap_exceeded", Raise),
+        (408, "request_timeout", GetNewUploadUrl),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", GetNewUploadUrl),
+        (503, "service_unavailable", GetNewUploadUrl),
+    });
+
+    error_table_test!(test_list_file_versions_error_table, ListFileVersionsError {
+        (400, "bad_request", Raise),
+        (400, "out_of_range", Raise),
+        (400, "invalid_file_id", Raise),
+        (401, "unauthorized", Raise),
+        (401, "bad_auth_token", Reauthenticate),
+        (401, "expired_auth_token", Reauthenticate),
+        (403, "transaction_cap_exceeded", Raise),
+        (429, "too_many_requests", Retry),
+        (500, "internal_error", Retry),
+        (503, "service_unavailable", Retry),
+    });
 }
@@ -1,6 +1,9 @@
+#[cfg(feature = "b2_unstable")]
+use super::SSE_C_KEY_ID_FILE_INFO;
 use super::{
-    AccountId, BucketId, FileLegalHold, FileRetention, InvalidCharacterError, ServerSideEncryption,
-    StringSpecializationError,
+    file_lock::deserialize_file_retention_option, AccountId, BucketId, FileLegalHold,
+    FileProtection, FileRetention, InvalidCharacterError, LegalHoldOnOff, ServerSideEncryption,
+    StringSpecializationError, TimeStamp,
 };
 use headers::CacheControl;
 use hex::{FromHex, FromHexError, ToHex};
@@ -14,7 +17,11 @@ use std::{convert::TryFrom, str::FromStr};
 #[serde(transparent)]
 pub struct FileNameDelimiter(char);
 
-impl FileNameDelimiter {}
+impl FileNameDelimiter {
+    pub fn as_char(&self) -> char {
+        self.0
+    }
+}
 
 impl TryFrom<char> for FileNameDelimiter {
     type Error = InvalidCharacterError;
@@ -135,7 +142,7 @@ impl TryFrom<String> for FileId {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(try_from = "&str", into = "String")]
+#[serde(try_from = "String", into = "String")]
 pub struct Sha1Digest {
     bytes: [u8; 20],
 }
@@ -158,10 +165,23 @@ impl<'a> TryFrom<&'a str> for Sha1Digest {
     }
 }
 
+// owned strings are needed by deserializers which cannot borrow from their input, e.g. serde_yaml
+impl TryFrom<String> for Sha1Digest {
+    type Error = FromHexError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
 impl Sha1Digest {
     pub fn new(bytes: [u8; 20]) -> Self {
         Self { bytes }
     }
+
+    pub fn bytes(&self) -> &[u8; 20] {
+        &self.bytes
+    }
 }
 
 #[cfg(feature = "sha1")]
@@ -175,8 +195,85 @@ impl From<sha1::Digest> for Sha1Digest {
 
 pub type Sha1DigestRef<'s> = &'s Sha1Digest;
 
+const HEX_DIGITS_AT_END: &str = "hex_digits_at_end";
+
+/// SHA1 checksum of an uploaded file or part
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentSha1<'s> {
+    Digest(Sha1DigestRef<'s>),
+    /// The 40 hex digits of the checksum are appended to the content, e.g. if it is streamed. The content length
+    /// includes these 40 bytes.
+    HexDigitsAtEnd,
+}
+
+impl<'s> From<Sha1DigestRef<'s>> for ContentSha1<'s> {
+    fn from(digest: Sha1DigestRef<'s>) -> Self {
+        Self::Digest(digest)
+    }
+}
+
+impl<'s> Serialize for ContentSha1<'s> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            ContentSha1::Digest(digest) => digest.serialize(serializer),
+            ContentSha1::HexDigitsAtEnd => serializer.serialize_str(HEX_DIGITS_AT_END),
+        }
+    }
+}
+
+/// Owned version of [ContentSha1], used by the owned requests (e.g. [crate::v2::OwnedUploadFileParameters])
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum OwnedContentSha1 {
+    Digest(Sha1Digest),
+    HexDigitsAtEnd,
+}
+
+impl OwnedContentSha1 {
+    /// borrowed form of this checksum, as used by the requests
+    pub fn as_content_sha1(&self) -> ContentSha1<'_> {
+        match self {
+            OwnedContentSha1::Digest(digest) => ContentSha1::Digest(digest),
+            OwnedContentSha1::HexDigitsAtEnd => ContentSha1::HexDigitsAtEnd,
+        }
+    }
+}
+
+impl<'s> From<ContentSha1<'s>> for OwnedContentSha1 {
+    fn from(content_sha1: ContentSha1<'s>) -> Self {
+        match content_sha1 {
+            ContentSha1::Digest(digest) => Self::Digest(digest.clone()),
+            ContentSha1::HexDigitsAtEnd => Self::HexDigitsAtEnd,
+        }
+    }
+}
+
+impl From<OwnedContentSha1> for String {
+    fn from(content_sha1: OwnedContentSha1) -> Self {
+        match content_sha1 {
+            OwnedContentSha1::Digest(digest) => digest.into(),
+            OwnedContentSha1::HexDigitsAtEnd => HEX_DIGITS_AT_END.to_owned(),
+        }
+    }
+}
+
+impl TryFrom<String> for OwnedContentSha1 {
+    type Error = FromHexError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value == HEX_DIGITS_AT_END {
+            Ok(Self::HexDigitsAtEnd)
+        } else {
+            Sha1Digest::try_from(value).map(Self::Digest)
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(try_from = "&str", into = "String")]
+#[serde(try_from = "String", into = "String")]
 pub struct Md5Digest {
     bytes: [u8; 16],
 }
@@ -202,6 +299,14 @@ impl<'a> TryFrom<&'a str> for Md5Digest {
     }
 }
 
+impl TryFrom<String> for Md5Digest {
+    type Error = FromHexError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
 #[cfg(feature = "md5")]
 impl From<md5::Digest> for Md5Digest {
     fn from(digest: md5::Digest) -> Self {
@@ -218,7 +323,6 @@ impl From<Md5Digest> for String {
 pub type Md5DigestRef<'s> = &'s Md5Digest;
 #[cfg(feature = "b2_unstable")]
 pub type FileInfo = serde_json::Value;
-pub type TimeStamp = i64;
 /// Content Disposition value acc. to the grammar specified in RFC 6266
 pub type ContentDisposition = headers::ContentDisposition;
 pub type ContentDispositionRef<'s> = &'s ContentDisposition;
@@ -363,6 +467,7 @@ pub struct FileInformation {
     #[cfg(feature = "b2_unstable")]
     file_info: FileInfo,
     file_name: FileName,
+    #[serde(default, deserialize_with = "deserialize_file_retention_option")]
     file_retention: Option<FileRetention>,
     legal_hold: Option<FileLegalHold>,
     server_side_encryption: Option<ServerSideEncryption>,
@@ -411,7 +516,13 @@ impl FileInformation {
         &self.file_info
     }
 
-    /// Get a reference to the file information's file retention.
+    #[cfg(feature = "b2_unstable")]
+    /// Id of the SSE-C key the file was uploaded with, as recorded in its file info (see [SSE_C_KEY_ID_FILE_INFO]).
+    pub fn sse_c_key_id(&self) -> Option<&str> {
+        self.file_info.get(SSE_C_KEY_ID_FILE_INFO)?.as_str()
+    }
+
+    /// Get a reference to the file information's file retention, `None` if the client is not authorized to read it.
     pub fn file_retention(&self) -> Option<&FileRetention> {
         self.file_retention.as_ref()
     }
@@ -421,6 +532,26 @@ impl FileInformation {
         self.legal_hold.as_ref()
     }
 
+    /// What protects the file version from being deleted at `now`.
+    ///
+    /// Legal holds and retentions the client is not authorized to read are not reported.
+    pub fn protection(&self, now: TimeStamp) -> Option<FileProtection> {
+        if let Some(FileLegalHold::ClientAuthorizedToRead {
+            value: LegalHoldOnOff::On,
+        }) = self.legal_hold
+        {
+            return Some(FileProtection::LegalHold);
+        }
+        match self.file_retention.as_ref() {
+            Some(retention) if retention.is_active(now) => Some(FileProtection::Retention {
+                // unwraps are safe, an active retention has a mode and a timestamp
+                mode: retention.mode().unwrap(),
+                retain_until_timestamp: retention.retain_until_timestamp().unwrap(),
+            }),
+            _ => None,
+        }
+    }
+
     /// Get a reference to the file information's server side encryption.
     pub fn server_side_encryption(&self) -> Option<&ServerSideEncryption> {
         self.server_side_encryption.as_ref()
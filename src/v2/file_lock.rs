@@ -1,8 +1,10 @@
+use std::time::Duration;
+
 use serde::{de, Deserialize, Serialize};
 
 use super::TimeStamp;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(tag = "unit", rename_all = "camelCase")]
 pub enum Period {
     Days { value: u64 },
@@ -17,9 +19,25 @@ impl Period {
     pub fn years(years: u64) -> Self {
         Self::Years { value: years }
     }
+
+    /// End of the period starting at `start`, same as `start + period`.
+    ///
+    /// Years are calendar years in UTC, a period starting on February 29th ends on February 28th if the end is not
+    /// in a leap year.
+    pub fn retain_until(&self, start: TimeStamp) -> TimeStamp {
+        start + *self
+    }
+
+    /// Length of the period starting at `start`, years have 365 or 366 days depending on the leap days they contain.
+    ///
+    /// Like the end of the period, the length saturates at the range of [TimeStamp].
+    pub fn duration_from(&self, start: TimeStamp) -> Duration {
+        // the end of a period is never before its start
+        (start + *self).duration_since(start).unwrap_or_default()
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(tag = "mode", rename_all = "camelCase")]
 pub enum DefaultFileRetention {
     #[serde(rename = "null")]
@@ -81,14 +99,14 @@ struct DeserialiableDefaultFileRetention {
     period: Option<Period>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum FileRetentionMode {
     Compliance,
     Governance,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct FileRetention {
     mode: Option<FileRetentionMode>,
@@ -111,6 +129,61 @@ impl FileRetention {
             retain_until_timestamp: None,
         }
     }
+
+    /// Get the file retention's mode, `None` if the retention is disabled.
+    pub fn mode(&self) -> Option<FileRetentionMode> {
+        self.mode
+    }
+
+    /// Get the file retention's retain until timestamp, `None` if the retention is disabled.
+    pub fn retain_until_timestamp(&self) -> Option<TimeStamp> {
+        self.retain_until_timestamp
+    }
+
+    /// Returns `true` if the retention protects the file at `now`.
+    pub fn is_active(&self, now: TimeStamp) -> bool {
+        self.mode.is_some()
+            && self
+                .retain_until_timestamp
+                .is_some_and(|retain_until| retain_until > now)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeserializeableFileRetentionSetting {
+    is_client_authorized_to_read: bool,
+    value: Option<FileRetention>,
+}
+
+/// Deserializes the `fileRetention` of a file version, `None` if the client is not authorized to read it
+pub(crate) fn deserialize_file_retention_option<'de, D>(
+    deserializer: D,
+) -> Result<Option<FileRetention>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let deserialized = Option::<DeserializeableFileRetentionSetting>::deserialize(deserializer)?;
+    Ok(deserialized.and_then(|setting| {
+        if setting.is_client_authorized_to_read {
+            Some(setting.value.unwrap_or_else(FileRetention::disabled))
+        } else {
+            None
+        }
+    }))
+}
+
+/// Why a file version can currently not be deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileProtection {
+    /// the legal hold of the file version is on
+    LegalHold,
+    /// the file version is retained until the given time, a governance retention may be bypassed with the
+    /// `bypassGovernance` capability
+    Retention {
+        mode: FileRetentionMode,
+        retain_until_timestamp: TimeStamp,
+    },
 }
 
 #[derive(Debug, Deserialize)]
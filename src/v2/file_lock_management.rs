@@ -0,0 +1,438 @@
+//! Bulk updates and audits of the File Lock settings of all file versions of a bucket or of a file name prefix.
+//!
+//! [b2_update_file_locks] applies a [FileLockUpdate] with [crate::v2::b2_update_file_retention] or
+//! [crate::v2::b2_update_file_legal_hold], at most `max_concurrency` versions at once. Compliance retentions can only
+//! be extended, updates that would shorten or remove them are skipped without a request. Shortening or removing a
+//! governance retention requires the `bypassGovernance` capability, which is used if the key has it.
+//!
+//! [b2_list_protected_file_versions] lists the versions, which are currently under a legal hold or an active
+//! retention.
+use std::num::NonZeroUsize;
+
+use futures_util::{stream, StreamExt};
+use typed_builder::TypedBuilder;
+
+use super::{
+    b2_list_file_versions::FileVersionPager, b2_update_file_legal_hold, b2_update_file_retention,
+    errors::B2ApiError, large_file_janitor::DEFAULT_MAX_CONCURRENCY, ApiUrl, AuthorizationToken,
+    AuthorizeAccountOk, BucketId, Capability, FileAction, FileId, FileInformation, FileNamePrefix,
+    FileProtection, FileRetention, FileRetentionMode, LegalHoldOnOff, Period, TimeStamp,
+    UpdateFileLegalHoldOk, UpdateFileLegalHoldRequest, UpdateFileRetentionOk,
+    UpdateFileRetentionRequest,
+};
+
+/// Change of the File Lock settings applied by [b2_update_file_locks]
+#[derive(Debug, Clone, Copy)]
+pub enum FileLockUpdate {
+    /// Sets the retention of every version to `mode` until the end of `period`, starting now
+    SetRetention {
+        mode: FileRetentionMode,
+        period: Period,
+    },
+    /// Extends the active retentions by `period` past their current end, keeping their mode
+    ExtendRetention { period: Period },
+    /// Removes the active retentions
+    RemoveRetention,
+    /// Turns the legal hold of every version on or off
+    SetLegalHold(LegalHoldOnOff),
+}
+
+/// Why [b2_update_file_locks] did not update a file version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileLockUpdateSkipped {
+    /// the update would shorten or remove a compliance retention
+    ComplianceRetention { retain_until_timestamp: TimeStamp },
+    /// the retention is to be extended or removed, but the version has no active retention
+    NoActiveRetention,
+    /// the retention of the version cannot be read with the key, so it cannot be checked
+    NotAuthorizedToRead,
+}
+
+/// Selection of the file versions to be updated by [b2_update_file_locks]
+#[derive(Debug, TypedBuilder)]
+pub struct UpdateFileLocksRequest<'s> {
+    bucket_id: &'s BucketId,
+
+    /// only versions of files with names starting with this prefix are updated
+    #[builder(default, setter(strip_option))]
+    prefix: Option<&'s FileNamePrefix>,
+
+    update: FileLockUpdate,
+
+    /// maximum number of requests sent at once
+    #[builder(default = NonZeroUsize::new(DEFAULT_MAX_CONCURRENCY).unwrap())]
+    max_concurrency: NonZeroUsize,
+}
+
+/// Result of [b2_update_file_locks]
+#[derive(Debug, Default)]
+pub struct UpdateFileLocksOk {
+    retentions: Vec<UpdateFileRetentionOk>,
+    legal_holds: Vec<UpdateFileLegalHoldOk>,
+    skipped: Vec<(FileInformation, FileLockUpdateSkipped)>,
+    failed: Vec<(FileInformation, B2ApiError)>,
+}
+
+impl UpdateFileLocksOk {
+    /// Get a reference to the updated retentions.
+    pub fn retentions(&self) -> &[UpdateFileRetentionOk] {
+        &self.retentions
+    }
+
+    /// Get a reference to the updated legal holds.
+    pub fn legal_holds(&self) -> &[UpdateFileLegalHoldOk] {
+        &self.legal_holds
+    }
+
+    /// Get a reference to the file versions, which were not updated.
+    pub fn skipped(&self) -> &[(FileInformation, FileLockUpdateSkipped)] {
+        &self.skipped
+    }
+
+    /// Get a reference to the file versions, whose update failed.
+    pub fn failed(&self) -> &[(FileInformation, B2ApiError)] {
+        &self.failed
+    }
+}
+
+/// Result of the update of a single file version
+enum UpdatedFileLock {
+    Retention(UpdateFileRetentionOk),
+    LegalHold(UpdateFileLegalHoldOk),
+}
+
+/// Planned request for a single file version
+enum PlannedUpdate {
+    Retention {
+        file_retention: FileRetention,
+        bypass_governance: bool,
+    },
+    LegalHold(LegalHoldOnOff),
+}
+
+impl FileLockUpdate {
+    fn plan(
+        &self,
+        file: &FileInformation,
+        now: TimeStamp,
+        may_bypass_governance: bool,
+    ) -> Result<PlannedUpdate, FileLockUpdateSkipped> {
+        let current = match self {
+            FileLockUpdate::SetLegalHold(legal_hold) => {
+                return Ok(PlannedUpdate::LegalHold(*legal_hold))
+            }
+            _ => file
+                .file_retention()
+                .ok_or(FileLockUpdateSkipped::NotAuthorizedToRead)?,
+        };
+        // unwraps are safe, an active retention has a mode and a timestamp
+        let active = current.is_active(now).then(|| {
+            (
+                current.mode().unwrap(),
+                current.retain_until_timestamp().unwrap(),
+            )
+        });
+        let file_retention = match (self, active) {
+            (FileLockUpdate::SetRetention { mode, period }, _) => {
+                FileRetention::new(*mode, period.retain_until(now))
+            }
+            (FileLockUpdate::ExtendRetention { period }, Some((mode, retain_until))) => {
+                FileRetention::new(mode, period.retain_until(retain_until))
+            }
+            (FileLockUpdate::RemoveRetention, Some(_)) => FileRetention::disabled(),
+            (FileLockUpdate::ExtendRetention { .. } | FileLockUpdate::RemoveRetention, None)
+            | (FileLockUpdate::SetLegalHold(_), _) => {
+                return Err(FileLockUpdateSkipped::NoActiveRetention)
+            }
+        };
+        match active {
+            Some((FileRetentionMode::Compliance, retain_until))
+                if file_retention.mode() != Some(FileRetentionMode::Compliance)
+                    || file_retention.retain_until_timestamp() < Some(retain_until) =>
+            {
+                Err(FileLockUpdateSkipped::ComplianceRetention {
+                    retain_until_timestamp: retain_until,
+                })
+            }
+            Some((FileRetentionMode::Governance, _)) => Ok(PlannedUpdate::Retention {
+                file_retention,
+                bypass_governance: may_bypass_governance,
+            }),
+            _ => Ok(PlannedUpdate::Retention {
+                file_retention,
+                bypass_governance: false,
+            }),
+        }
+    }
+}
+
+/// Applies the update of `request` to all selected file versions, see the [module documentation](self)
+///
+/// Hide markers, folders and unfinished large files have no File Lock settings and are ignored. An error is only
+/// returned, if the file versions could not be listed.
+pub async fn b2_update_file_locks(
+    authorization: &AuthorizeAccountOk,
+    request: &UpdateFileLocksRequest<'_>,
+) -> Result<UpdateFileLocksOk, B2ApiError> {
+    let api_url = authorization.api_url();
+    let authorization_token = authorization.authorization_token();
+    let may_bypass_governance = authorization
+        .allowed()
+        .capabilities
+        .contains(Capability::BypassGovernance);
+    let now = TimeStamp::now();
+    let mut ok = UpdateFileLocksOk::default();
+
+    let mut pages = FileVersionPager::new(request.bucket_id, request.prefix);
+    while let Some(page) = pages.next_page(api_url, authorization_token).await? {
+        let mut planned = Vec::new();
+        for file in page {
+            let file_id = match (file.action(), file.file_id()) {
+                (FileAction::Upload | FileAction::Copy, Some(file_id)) => file_id.clone(),
+                _ => continue,
+            };
+            match request.update.plan(&file, now, may_bypass_governance) {
+                Ok(update) => planned.push((file_id, file, update)),
+                Err(skipped) => ok.skipped.push((file, skipped)),
+            }
+        }
+        let mut results = stream::iter(planned)
+            .map(|(file_id, file, update)| async move {
+                let result = apply(api_url, authorization_token, &file_id, &file, update).await;
+                (file, result)
+            })
+            .buffer_unordered(request.max_concurrency.get());
+        while let Some((file, result)) = results.next().await {
+            match result {
+                Ok(UpdatedFileLock::Retention(retention)) => ok.retentions.push(retention),
+                Ok(UpdatedFileLock::LegalHold(legal_hold)) => ok.legal_holds.push(legal_hold),
+                Err(error) => ok.failed.push((file, error)),
+            }
+        }
+    }
+    Ok(ok)
+}
+
+async fn apply(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    file_id: &FileId,
+    file: &FileInformation,
+    update: PlannedUpdate,
+) -> Result<UpdatedFileLock, B2ApiError> {
+    match update {
+        PlannedUpdate::Retention {
+            file_retention,
+            bypass_governance,
+        } => {
+            let request = UpdateFileRetentionRequest {
+                file_name: file.file_name(),
+                file_id,
+                file_retention: &file_retention,
+                bypass_governance: bypass_governance.then_some(true),
+            };
+            Ok(UpdatedFileLock::Retention(
+                b2_update_file_retention(api_url, authorization_token, &request).await?,
+            ))
+        }
+        PlannedUpdate::LegalHold(legal_hold) => {
+            let request = UpdateFileLegalHoldRequest::builder()
+                .file_name(file.file_name())
+                .file_id(file_id)
+                .legal_hold(legal_hold)
+                .build();
+            Ok(UpdatedFileLock::LegalHold(
+                b2_update_file_legal_hold(api_url, authorization_token, &request).await?,
+            ))
+        }
+    }
+}
+
+/// Lists the file versions of a bucket or file name prefix, which are currently protected by a legal hold or an
+/// active retention.
+///
+/// Legal holds and retentions the key is not authorized to read are not reported.
+pub async fn b2_list_protected_file_versions(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    bucket_id: &BucketId,
+    prefix: Option<&FileNamePrefix>,
+) -> Result<Vec<(FileInformation, FileProtection)>, B2ApiError> {
+    let now = TimeStamp::now();
+    let mut protected = Vec::new();
+    let mut pages = FileVersionPager::new(bucket_id, prefix);
+    while let Some(page) = pages.next_page(api_url, authorization_token).await? {
+        protected.extend(page.into_iter().filter_map(|file| {
+            let protection = file.protection(now)?;
+            Some((file, protection))
+        }));
+    }
+    Ok(protected)
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::v2::test::mock_server::{self, fake_authorization, FAKE_BUCKET_ID, FAR_FUTURE};
+
+    const DAY: i64 = 24 * 60 * 60 * 1000;
+
+    fn file_version(
+        file_id: &str,
+        action: &str,
+        retention_mode: Option<&str>,
+    ) -> serde_json::Value {
+        mock_server::file_version(
+            file_id,
+            &format!("{}.txt", file_id),
+            action,
+            retention_mode,
+            None,
+        )
+    }
+
+    async fn mock_update_retention(mock_server: &MockServer, expected_body: serde_json::Value) {
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_update_file_retention"))
+            .and(body_json(&expected_body))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "fileId": expected_body["fileId"],
+                "fileName": expected_body["fileName"],
+                "fileRetention": expected_body["fileRetention"],
+            })))
+            .expect(1)
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_update_file_locks() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_file_versions"))
+            .and(body_json(
+                json!({"bucketId": FAKE_BUCKET_ID, "maxFileCount": 1000}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "files": [
+                    file_version("compliance", "upload", Some("compliance")),
+                    file_version("governance", "upload", Some("governance")),
+                    file_version("unlocked", "upload", None),
+                    file_version("hidden", "hide", None),
+                ],
+                "nextFileName": null,
+                "nextFileId": null,
+            })))
+            .mount(&mock_server)
+            .await;
+        mock_update_retention(
+            &mock_server,
+            json!({
+                "fileName": "compliance.txt",
+                "fileId": "compliance",
+                "fileRetention": {"mode": "compliance", "retainUntilTimestamp": FAR_FUTURE + DAY},
+            }),
+        )
+        .await;
+        mock_update_retention(
+            &mock_server,
+            json!({
+                "fileName": "governance.txt",
+                "fileId": "governance",
+                "fileRetention": {"mode": "governance", "retainUntilTimestamp": FAR_FUTURE + DAY},
+                "bypassGovernance": true,
+            }),
+        )
+        .await;
+        mock_update_retention(
+            &mock_server,
+            json!({
+                "fileName": "governance.txt",
+                "fileId": "governance",
+                "fileRetention": {"mode": null, "retainUntilTimestamp": null},
+                "bypassGovernance": true,
+            }),
+        )
+        .await;
+
+        let authorization = fake_authorization(
+            &mock_server.uri(),
+            &["listFiles", "writeFileRetentions", "bypassGovernance"],
+        );
+        let bucket_id = FAKE_BUCKET_ID.to_owned().try_into().unwrap();
+        let skipped = |ok: &UpdateFileLocksOk| {
+            ok.skipped()
+                .iter()
+                .map(|(file, skipped)| (file.file_id().unwrap().as_str().to_owned(), *skipped))
+                .collect::<Vec<_>>()
+        };
+
+        let request = UpdateFileLocksRequest::builder()
+            .bucket_id(&bucket_id)
+            .update(FileLockUpdate::ExtendRetention {
+                period: Period::days(1),
+            })
+            .build();
+        let ok = b2_update_file_locks(&authorization, &request)
+            .await
+            .unwrap();
+        assert_eq!(2, ok.retentions().len());
+        assert!(ok.failed().is_empty());
+        assert_eq!(
+            vec![(
+                "unlocked".to_owned(),
+                FileLockUpdateSkipped::NoActiveRetention
+            )],
+            skipped(&ok)
+        );
+
+        let request = UpdateFileLocksRequest::builder()
+            .bucket_id(&bucket_id)
+            .update(FileLockUpdate::RemoveRetention)
+            .build();
+        let ok = b2_update_file_locks(&authorization, &request)
+            .await
+            .unwrap();
+        assert_eq!(1, ok.retentions().len());
+        assert_eq!(
+            vec![
+                (
+                    "compliance".to_owned(),
+                    FileLockUpdateSkipped::ComplianceRetention {
+                        retain_until_timestamp: TimeStamp::from_millis(FAR_FUTURE)
+                    }
+                ),
+                (
+                    "unlocked".to_owned(),
+                    FileLockUpdateSkipped::NoActiveRetention
+                ),
+            ],
+            skipped(&ok)
+        );
+
+        let protected = b2_list_protected_file_versions(
+            authorization.api_url(),
+            authorization.authorization_token(),
+            &bucket_id,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(2, protected.len());
+        assert_eq!(
+            FileProtection::Retention {
+                mode: FileRetentionMode::Governance,
+                retain_until_timestamp: TimeStamp::from_millis(FAR_FUTURE)
+            },
+            protected[1].1
+        );
+    }
+}
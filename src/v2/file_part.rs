@@ -28,6 +28,10 @@ impl PartNumber {
     pub const fn max_part_number() -> u16 {
         10000u16
     }
+
+    pub fn as_u16(&self) -> u16 {
+        self.0
+    }
 }
 
 impl TryFrom<u16> for PartNumber {
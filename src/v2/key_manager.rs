@@ -0,0 +1,569 @@
+//! Lifecycle of application keys, e.g. per customer keys, see [KeyManager].
+//!
+//! Keys are created from a [KeyTemplate] (e.g. read only access to a bucket or a file name prefix), listed with all
+//! pages of [crate::v2::b2_list_keys] and rotated by creating a replacement with the same name and restrictions
+//! before the old key is deleted, so there is no point in time without a valid key. Keys, which are restricted to
+//! buckets that no longer exist, are reported as orphaned.
+use std::{collections::HashSet, num::NonZeroU16, time::Duration};
+
+use super::{
+    b2_create_key, b2_delete_key, b2_list_buckets, b2_list_keys, errors::GenericB2Error,
+    AuthorizeAccountOk, BucketId, BucketTypes, Capabilities, Capability, CreateKeyRequest,
+    CreatedKeyInformation, FileNamePrefix, KeyInformation, KeyName, ListBucketsRequest,
+    ListKeysRequest, TimeStamp, ValidKeyLifeTimeInSeconds,
+};
+
+/// number of keys listed per request, each started 1000 keys are billed as one transaction
+const MAX_KEY_COUNT: u16 = 1000;
+
+/// Capabilities and restrictions of the keys created by [KeyManager::create_key]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyTemplate {
+    capabilities: Capabilities,
+    bucket_ids: Vec<BucketId>,
+    name_prefix: Option<FileNamePrefix>,
+    valid_duration: Option<ValidKeyLifeTimeInSeconds>,
+}
+
+impl KeyTemplate {
+    /// Template for keys with the given capabilities, which are not restricted to a bucket
+    pub fn new(capabilities: Capabilities) -> Self {
+        Self {
+            capabilities,
+            bucket_ids: Vec::new(),
+            name_prefix: None,
+            valid_duration: None,
+        }
+    }
+
+    /// Template for keys with the given capabilities, which are restricted to a bucket
+    ///
+    /// Only the capabilities of [crate::v2::all_per_bucket_capabilites] are allowed for these keys.
+    pub fn for_bucket(capabilities: Capabilities, bucket_id: BucketId) -> Self {
+        Self::new(capabilities).with_bucket(bucket_id)
+    }
+
+    /// Keys that may list and download the files of a bucket
+    pub fn read_only(bucket_id: BucketId) -> Self {
+        Self::for_bucket(
+            Capability::ListAllBucketNames
+                | Capability::ListBuckets
+                | Capability::ReadBuckets
+                | Capability::ReadBucketEncryption
+                | Capability::ReadBucketRetentions
+                | Capability::ListFiles
+                | Capability::ReadFiles
+                | Capability::ShareFiles
+                | Capability::ReadFileLegalHolds
+                | Capability::ReadFileRetentions,
+            bucket_id,
+        )
+    }
+
+    /// Keys that may list, download, upload and delete the files of a bucket
+    pub fn read_write(bucket_id: BucketId) -> Self {
+        let mut template = Self::read_only(bucket_id);
+        template.capabilities |= Capability::WriteFiles | Capability::DeleteFiles;
+        template
+    }
+
+    /// Keys that may only upload files to a bucket
+    pub fn upload_only(bucket_id: BucketId) -> Self {
+        Self::for_bucket(
+            Capability::ListAllBucketNames
+                | Capability::ListBuckets
+                | Capability::ReadBucketEncryption
+                | Capability::WriteFiles,
+            bucket_id,
+        )
+    }
+
+    /// Template with the capabilities and restrictions of an existing key, e.g. to replace it
+    pub fn from_key(key: &KeyInformation) -> Self {
+        Self {
+            capabilities: *key.capabilities(),
+            bucket_ids: key.bucket_ids().to_vec(),
+            name_prefix: key.name_prefix().cloned(),
+            valid_duration: None,
+        }
+    }
+
+    /// restricts the keys to a bucket, replacing the bucket set before
+    ///
+    /// API v2 can only restrict a key to a single bucket, so templates of keys restricted to multiple buckets
+    /// (see [Self::from_key]) are rejected by [KeyManager::create_key].
+    pub fn with_bucket(mut self, bucket_id: BucketId) -> Self {
+        self.bucket_ids = vec![bucket_id];
+        self
+    }
+
+    /// restricts the keys to files starting with `name_prefix`, the keys must be restricted to a bucket as well
+    pub fn with_name_prefix(mut self, name_prefix: FileNamePrefix) -> Self {
+        self.name_prefix = Some(name_prefix);
+        self
+    }
+
+    /// the keys expire after `valid_duration`
+    pub fn valid_for(mut self, valid_duration: ValidKeyLifeTimeInSeconds) -> Self {
+        self.valid_duration = Some(valid_duration);
+        self
+    }
+
+    /// Get a reference to the key template's capabilities.
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Get the ids of all buckets the keys are restricted to, empty if they are not restricted to any bucket.
+    pub fn bucket_ids(&self) -> &[BucketId] {
+        &self.bucket_ids
+    }
+
+    /// Get a reference to the key template's name prefix.
+    pub fn name_prefix(&self) -> Option<&FileNamePrefix> {
+        self.name_prefix.as_ref()
+    }
+
+    /// Get the key template's valid duration.
+    pub fn valid_duration(&self) -> Option<ValidKeyLifeTimeInSeconds> {
+        self.valid_duration
+    }
+}
+
+/// Result of [KeyManager::rotate_key]
+#[derive(Debug)]
+pub struct RotateKeyOk {
+    replacement: CreatedKeyInformation,
+    deleted: KeyInformation,
+}
+
+impl RotateKeyOk {
+    /// Get a reference to the new key, this is the only time its secret is returned.
+    pub fn replacement(&self) -> &CreatedKeyInformation {
+        &self.replacement
+    }
+
+    /// Get a reference to the deleted key.
+    pub fn deleted(&self) -> &KeyInformation {
+        &self.deleted
+    }
+}
+
+/// Creates, lists, rotates and deletes the application keys of an account
+///
+/// The authorization needs the `listKeys`, `writeKeys` and `deleteKeys` capabilities and, to find orphaned keys,
+/// `listBuckets` for all buckets of the account.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyManager<'a> {
+    authorization: &'a AuthorizeAccountOk,
+}
+
+impl<'a> KeyManager<'a> {
+    pub fn new(authorization: &'a AuthorizeAccountOk) -> Self {
+        Self { authorization }
+    }
+
+    /// Creates a key from a template
+    pub async fn create_key(
+        &self,
+        key_name: &KeyName,
+        template: &KeyTemplate,
+    ) -> Result<CreatedKeyInformation, GenericB2Error> {
+        // more than one bucket is rejected by b2_create_key before the request is sent
+        let bucket_ids =
+            (!template.bucket_ids.is_empty()).then_some(template.bucket_ids.as_slice());
+        let request = CreateKeyRequest {
+            account_id: self.authorization.account_id(),
+            capabilities: &template.capabilities,
+            key_name,
+            valid_duration_in_seconds: template.valid_duration,
+            bucket_id: None,
+            bucket_ids,
+            name_prefix: template.name_prefix.as_ref(),
+        };
+        b2_create_key(
+            self.authorization.api_url(),
+            self.authorization.authorization_token(),
+            &request,
+        )
+        .await
+    }
+
+    /// All keys of the account, from all pages of the listing
+    pub async fn list_keys(&self) -> Result<Vec<KeyInformation>, GenericB2Error> {
+        let max_key_count = NonZeroU16::new(MAX_KEY_COUNT);
+        let mut keys = Vec::new();
+        let mut start_application_key_id = None;
+        loop {
+            let request = ListKeysRequest::new(
+                self.authorization.account_id(),
+                max_key_count,
+                start_application_key_id.as_ref(),
+            );
+            let page = b2_list_keys(
+                self.authorization.api_url(),
+                self.authorization.authorization_token(),
+                &request,
+            )
+            .await?;
+            let next_application_key_id = page.next_application_key_id().cloned();
+            keys.extend(page.into_keys());
+            match next_application_key_id {
+                Some(next) => start_application_key_id = Some(next),
+                None => return Ok(keys),
+            }
+        }
+    }
+
+    /// Keys expiring within `duration` from now (including keys that already expired), the earliest first
+    pub async fn expiring_keys(
+        &self,
+        duration: Duration,
+    ) -> Result<Vec<KeyInformation>, GenericB2Error> {
+        let expires_before = TimeStamp::now() + duration;
+        let mut keys: Vec<KeyInformation> = self
+            .list_keys()
+            .await?
+            .into_iter()
+            .filter(|key| {
+                key.expiration_timestamp()
+                    .is_some_and(|expiration| *expiration <= expires_before)
+            })
+            .collect();
+        keys.sort_by_key(|key| key.expiration_timestamp().copied());
+        Ok(keys)
+    }
+
+    /// Keys restricted to buckets, of which none exists any longer
+    ///
+    /// Fails with [KeyManagerError::RestrictedAuthorization] if the authorization is restricted to buckets, as the
+    /// other buckets of the account cannot be listed with it.
+    pub async fn orphaned_keys(&self) -> Result<Vec<KeyInformation>, KeyManagerError> {
+        if !self.authorization.allowed().buckets.is_empty() {
+            return Err(KeyManagerError::RestrictedAuthorization);
+        }
+        // without the bucket types, only allPublic, allPrivate and snapshot buckets are listed
+        let request = ListBucketsRequest::new(
+            self.authorization.account_id(),
+            None,
+            None,
+            Some(&BucketTypes::All),
+        );
+        let buckets = b2_list_buckets(
+            self.authorization.api_url(),
+            self.authorization.authorization_token(),
+            &request,
+        )
+        .await?;
+        let existing: HashSet<&BucketId> = buckets
+            .buckets()
+            .iter()
+            .map(|bucket| bucket.bucket_id())
+            .collect();
+        Ok(self
+            .list_keys()
+            .await?
+            .into_iter()
+            .filter(|key| {
+                !key.bucket_ids().is_empty()
+                    && key
+                        .bucket_ids()
+                        .iter()
+                        .all(|bucket_id| !existing.contains(bucket_id))
+            })
+            .collect())
+    }
+
+    /// Replaces a key by a new key with the same name, capabilities and restrictions, then deletes the old key
+    ///
+    /// The replacement expires after `valid_duration`, if set, otherwise at the same time as the old key (keys without
+    /// an expiration get a replacement without one). Expired keys are rejected with [KeyManagerError::KeyExpired]. If the old key cannot be deleted, the replacement is
+    /// returned in [KeyManagerError::OldKeyNotDeleted], as its secret cannot be retrieved again. Rotating the key
+    /// this manager is authorized with invalidates the authorization.
+    pub async fn rotate_key(
+        &self,
+        key: &KeyInformation,
+        valid_duration: Option<ValidKeyLifeTimeInSeconds>,
+    ) -> Result<RotateKeyOk, KeyManagerError> {
+        let mut template = KeyTemplate::from_key(key);
+        template.valid_duration = match valid_duration {
+            Some(valid_duration) => Some(valid_duration),
+            None => remaining_life_time(key, TimeStamp::now())?,
+        };
+        let replacement = self.create_key(key.key_name(), &template).await?;
+        match self.delete_key(key).await {
+            Ok(deleted) => Ok(RotateKeyOk {
+                replacement,
+                deleted,
+            }),
+            Err(error) => Err(KeyManagerError::OldKeyNotDeleted {
+                replacement: Box::new(replacement),
+                error,
+            }),
+        }
+    }
+
+    pub async fn delete_key(&self, key: &KeyInformation) -> Result<KeyInformation, GenericB2Error> {
+        b2_delete_key(
+            self.authorization.api_url(),
+            self.authorization.authorization_token(),
+            key.application_key_id(),
+        )
+        .await
+    }
+}
+
+/// Life time left of `key` at `now`, `None` if the key does not expire
+fn remaining_life_time(
+    key: &KeyInformation,
+    now: TimeStamp,
+) -> Result<Option<ValidKeyLifeTimeInSeconds>, KeyManagerError> {
+    match key.expiration_timestamp() {
+        None => Ok(None),
+        Some(expiration) => expiration
+            .duration_since(now)
+            .and_then(|remaining| ValidKeyLifeTimeInSeconds::try_from(remaining).ok())
+            .map(Some)
+            .ok_or(KeyManagerError::KeyExpired),
+    }
+}
+
+#[derive(Debug)]
+pub enum KeyManagerError {
+    B2(GenericB2Error),
+    /// The authorization is restricted to buckets, so the buckets of the account cannot be listed
+    RestrictedAuthorization,
+    /// The key to rotate expired (or expires within a second), so the life time of its replacement cannot be taken from it
+    KeyExpired,
+    /// The replacement of a rotated key was created, but the old key could not be deleted
+    OldKeyNotDeleted {
+        replacement: Box<CreatedKeyInformation>,
+        error: GenericB2Error,
+    },
+}
+
+impl From<GenericB2Error> for KeyManagerError {
+    fn from(error: GenericB2Error) -> Self {
+        Self::B2(error)
+    }
+}
+
+impl std::fmt::Display for KeyManagerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyManagerError::B2(error) => error.fmt(f),
+            KeyManagerError::RestrictedAuthorization => write!(
+                f,
+                "The application key is restricted to buckets and cannot list all buckets"
+            ),
+            KeyManagerError::KeyExpired => write!(
+                f,
+                "The application key expired, set the valid duration of its replacement"
+            ),
+            KeyManagerError::OldKeyNotDeleted { replacement, error } => write!(
+                f,
+                "Created replacement {} but could not delete the old key: {}",
+                replacement.application_key_id().as_str(),
+                error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KeyManagerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KeyManagerError::B2(error) => Some(error),
+            KeyManagerError::RestrictedAuthorization => None,
+            KeyManagerError::KeyExpired => None,
+            KeyManagerError::OldKeyNotDeleted { error, .. } => Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::v2::test::mock_server::{fake_authorization, FAKE_ACCOUNT_ID, FAKE_BUCKET_ID};
+
+    fn key(
+        application_key_id: &str,
+        bucket_id: Option<&str>,
+        expiration: Option<i64>,
+    ) -> serde_json::Value {
+        json!({
+            "keyName": "customer-1",
+            "applicationKeyId": application_key_id,
+            "capabilities": ["listFiles", "readFiles"],
+            "accountId": FAKE_ACCOUNT_ID,
+            "expirationTimestamp": expiration,
+            "bucketId": bucket_id,
+            "namePrefix": bucket_id.map(|_| "customer-1/"),
+            "options": ["s3"],
+        })
+    }
+
+    async fn register_handlers(mock_server: &MockServer, soon: i64, later: i64) {
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_keys"))
+            .and(body_json(
+                json!({"accountId": FAKE_ACCOUNT_ID, "maxKeyCount": 1000}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "keys": [key("k1", Some(FAKE_BUCKET_ID), Some(later)), key("k2", Some("gone"), None)],
+                "nextApplicationKeyId": "k3",
+            })))
+            .mount(mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_keys"))
+            .and(body_json(json!({
+                "accountId": FAKE_ACCOUNT_ID,
+                "maxKeyCount": 1000,
+                "startApplicationKeyId": "k3",
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "keys": [key("k3", Some(FAKE_BUCKET_ID), Some(soon)), key("k4", None, None)],
+                "nextApplicationKeyId": null,
+            })))
+            .mount(mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_buckets"))
+            .and(body_json(
+                json!({"accountId": FAKE_ACCOUNT_ID, "bucketTypes": ["all"]}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "buckets": [{
+                    "accountId": FAKE_ACCOUNT_ID,
+                    "bucketId": FAKE_BUCKET_ID,
+                    "bucketInfo": {},
+                    "bucketName": "customer-files",
+                    "bucketType": "allPrivate",
+                    "defaultServerSideEncryption": {
+                        "isClientAuthorizedToRead": true,
+                        "value": {"algorithm": null, "mode": null},
+                    },
+                    "fileLockConfiguration": {"isClientAuthorizedToRead": false, "value": null},
+                    "lifecycleRules": [],
+                    "options": [],
+                    "revision": 2,
+                }],
+            })))
+            .mount(mock_server)
+            .await;
+        let mut created = key("k5", Some(FAKE_BUCKET_ID), None);
+        created["applicationKey"] = json!("secret");
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_create_key"))
+            .and(body_json(json!({
+                "accountId": FAKE_ACCOUNT_ID,
+                "capabilities": ["listFiles", "readFiles"],
+                "keyName": "customer-1",
+                "validDurationInSeconds": 86400,
+                "bucketId": FAKE_BUCKET_ID,
+                "namePrefix": "customer-1/",
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(created))
+            .expect(1)
+            .mount(mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_delete_key"))
+            .and(body_json(json!({"applicationKeyId": "k3"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(key(
+                "k3",
+                Some(FAKE_BUCKET_ID),
+                Some(soon),
+            )))
+            .expect(1)
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_key_manager() {
+        let mock_server = MockServer::start().await;
+        let now = TimeStamp::now();
+        let soon = (now + Duration::from_secs(3600)).as_millis();
+        let later = (now + Duration::from_secs(30 * 86400)).as_millis();
+        register_handlers(&mock_server, soon, later).await;
+
+        let authorization = fake_authorization(
+            &mock_server.uri(),
+            &["listKeys", "writeKeys", "deleteKeys", "listBuckets"],
+        );
+        let key_manager = KeyManager::new(&authorization);
+
+        let keys = key_manager.list_keys().await.unwrap();
+        assert_eq!(4, keys.len());
+
+        let expiring = key_manager
+            .expiring_keys(Duration::from_secs(7 * 86400))
+            .await
+            .unwrap();
+        assert_eq!(1, expiring.len());
+        assert_eq!("k3", expiring[0].application_key_id().as_str());
+
+        let orphaned = key_manager.orphaned_keys().await.unwrap();
+        assert_eq!(1, orphaned.len());
+        assert_eq!("k2", orphaned[0].application_key_id().as_str());
+
+        let ok = key_manager
+            .rotate_key(&expiring[0], Some(86400.try_into().unwrap()))
+            .await
+            .unwrap();
+        assert_eq!("k5", ok.replacement().application_key_id().as_str());
+        assert_eq!("k3", ok.deleted().application_key_id().as_str());
+
+        let template = KeyTemplate::read_only(FAKE_BUCKET_ID.to_owned().try_into().unwrap())
+            .with_name_prefix("customer-1/".to_owned().try_into().unwrap());
+        assert!(template.capabilities().contains(Capability::ReadFiles));
+        assert!(!template.capabilities().contains(Capability::WriteFiles));
+        assert!(crate::v2::all_per_bucket_capabilites().is_superset(
+            *KeyTemplate::read_write(FAKE_BUCKET_ID.to_owned().try_into().unwrap()).capabilities()
+        ));
+    }
+
+    #[test]
+    fn test_with_bucket_replaces_bucket() {
+        let other: BucketId = "other_bucket".to_owned().try_into().unwrap();
+        let template = KeyTemplate::read_only(FAKE_BUCKET_ID.to_owned().try_into().unwrap())
+            .with_bucket(other.clone());
+        assert_eq!(&[other], template.bucket_ids());
+    }
+
+    #[test]
+    fn test_remaining_life_time() {
+        let now = TimeStamp::now();
+        let key_expiring_at = |expiration: Option<TimeStamp>| -> KeyInformation {
+            serde_json::from_value(key(
+                "k1",
+                Some(FAKE_BUCKET_ID),
+                expiration.map(|expiration| expiration.as_millis()),
+            ))
+            .unwrap()
+        };
+        assert_eq!(
+            Some(3600.try_into().unwrap()),
+            remaining_life_time(&key_expiring_at(Some(now + Duration::from_secs(3600))), now)
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            remaining_life_time(&key_expiring_at(None), now).unwrap()
+        );
+        assert!(matches!(
+            remaining_life_time(&key_expiring_at(Some(now - Duration::from_secs(60))), now),
+            Err(KeyManagerError::KeyExpired)
+        ));
+    }
+}
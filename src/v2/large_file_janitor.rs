@@ -0,0 +1,405 @@
+//! Explicit cleanup of unfinished large files.
+//!
+//! Unlike the `daysFromStartingToCancelingUnfinishedLargeFiles` of a [crate::v2::LifeCycleRule], which B2 applies
+//! once a day, [b2_cancel_unfinished_large_files] cancels the selected files right away. The unfinished large files
+//! of a bucket (or of a file name prefix) are listed page by page with [crate::v2::b2_list_unfinished_large_files],
+//! selected by the time they were started and an optional filter (e.g. on their file info), and canceled with
+//! [crate::v2::b2_cancel_large_file], at most `max_concurrency` at once.
+//!
+//! A dry run cancels nothing, but lists the parts of each selected file with [crate::v2::b2_list_parts], to report
+//! how much storage a cleanup would free.
+use std::{convert::TryFrom, num::NonZeroUsize};
+
+use futures_util::{stream, StreamExt};
+use typed_builder::TypedBuilder;
+
+use super::{
+    b2_cancel_large_file, b2_list_parts, b2_list_unfinished_large_files, errors::GenericB2Error,
+    ApiUrl, AuthorizationToken, BucketId, CancelFileOk, FileId, FileInformation, FileName,
+    ListPartsRequest, ListUnfinishedLargeFilesRequest, MaxPartCount, MaxUnfinishedLargeFileCount,
+    TimeStamp,
+};
+
+/// number of requests sent at once, if not set otherwise
+pub(crate) const DEFAULT_MAX_CONCURRENCY: usize = 4;
+
+/// Selection of the unfinished large files to be canceled by [b2_cancel_unfinished_large_files]
+#[derive(TypedBuilder)]
+pub struct CancelUnfinishedLargeFilesRequest<'s> {
+    bucket_id: &'s BucketId,
+
+    /// only files with names starting with this prefix are canceled
+    #[builder(default, setter(strip_option))]
+    name_prefix: Option<&'s FileName>,
+
+    /// only files started before this time are canceled
+    #[builder(default, setter(strip_option))]
+    started_before: Option<TimeStamp>,
+
+    /// only files for which this function returns `true` are canceled
+    #[builder(default, setter(strip_option))]
+    filter: Option<&'s (dyn Fn(&FileInformation) -> bool + Sync)>,
+
+    /// maximum number of requests sent at once
+    #[builder(default = NonZeroUsize::new(DEFAULT_MAX_CONCURRENCY).unwrap())]
+    max_concurrency: NonZeroUsize,
+
+    /// report the selected files with their parts instead of canceling them
+    #[builder(default)]
+    dry_run: bool,
+}
+
+impl<'s> std::fmt::Debug for CancelUnfinishedLargeFilesRequest<'s> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancelUnfinishedLargeFilesRequest")
+            .field("bucket_id", &self.bucket_id)
+            .field("name_prefix", &self.name_prefix)
+            .field("started_before", &self.started_before)
+            .field(
+                "filter",
+                &self.filter.map(|_| "Fn(&FileInformation) -> bool"),
+            )
+            .field("max_concurrency", &self.max_concurrency)
+            .field("dry_run", &self.dry_run)
+            .finish()
+    }
+}
+
+impl<'s> CancelUnfinishedLargeFilesRequest<'s> {
+    fn is_selected(&self, file: &FileInformation) -> bool {
+        self.started_before
+            .is_none_or(|started_before| *file.upload_timestamp() < started_before)
+            && self.filter.is_none_or(|filter| filter(file))
+    }
+}
+
+/// An unfinished large file selected by a dry run of [b2_cancel_unfinished_large_files]
+#[derive(Debug)]
+pub struct UnfinishedLargeFileReport {
+    file: FileInformation,
+    part_count: usize,
+    content_length: u64,
+}
+
+impl UnfinishedLargeFileReport {
+    /// Get a reference to the unfinished large file report's file.
+    pub fn file(&self) -> &FileInformation {
+        &self.file
+    }
+
+    /// Get the unfinished large file report's number of uploaded parts.
+    pub fn part_count(&self) -> usize {
+        self.part_count
+    }
+
+    /// Get the unfinished large file report's total length of the uploaded parts in bytes.
+    pub fn content_length(&self) -> u64 {
+        self.content_length
+    }
+}
+
+/// Result of [b2_cancel_unfinished_large_files]
+#[derive(Debug, Default)]
+pub struct CancelUnfinishedLargeFilesOk {
+    canceled: Vec<CancelFileOk>,
+    dry_run: Vec<UnfinishedLargeFileReport>,
+    failed: Vec<(FileId, GenericB2Error)>,
+}
+
+impl CancelUnfinishedLargeFilesOk {
+    /// Get a reference to the canceled files, empty for a dry run.
+    pub fn canceled(&self) -> &[CancelFileOk] {
+        &self.canceled
+    }
+
+    /// Get a reference to the files, which would have been canceled, empty if it was not a dry run.
+    pub fn dry_run(&self) -> &[UnfinishedLargeFileReport] {
+        &self.dry_run
+    }
+
+    /// Get a reference to the files, which could not be canceled (or whose parts could not be listed in a dry run).
+    pub fn failed(&self) -> &[(FileId, GenericB2Error)] {
+        &self.failed
+    }
+
+    /// total number of uploaded parts of the files of a dry run
+    pub fn part_count(&self) -> usize {
+        self.dry_run.iter().map(|report| report.part_count).sum()
+    }
+
+    /// total length in bytes of the uploaded parts of the files of a dry run
+    pub fn content_length(&self) -> u64 {
+        self.dry_run
+            .iter()
+            .map(|report| report.content_length)
+            .sum()
+    }
+}
+
+/// selected unfinished large files of the request, from all pages of the listing
+async fn list_selected_files(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request: &CancelUnfinishedLargeFilesRequest<'_>,
+) -> Result<Vec<(FileId, FileInformation)>, GenericB2Error> {
+    // unwrap is safe, 100 is the maximum page size
+    let max_file_count = MaxUnfinishedLargeFileCount::try_from(100).unwrap();
+    let mut selected = Vec::new();
+    let mut start_file_id: Option<FileId> = None;
+    loop {
+        let list_request = ListUnfinishedLargeFilesRequest {
+            bucket_id: request.bucket_id,
+            name_prefix: request.name_prefix,
+            start_file_id: start_file_id.as_ref(),
+            max_file_count: Some(max_file_count),
+        };
+        let page =
+            b2_list_unfinished_large_files(api_url, authorization_token, &list_request).await?;
+        start_file_id = page.next_file_id().cloned();
+        for file in page.into_files() {
+            if let Some(file_id) = file.file_id().cloned() {
+                if request.is_selected(&file) {
+                    selected.push((file_id, file));
+                }
+            }
+        }
+        if start_file_id.is_none() {
+            return Ok(selected);
+        }
+    }
+}
+
+/// report of the uploaded parts of a file, from all pages of the listing
+async fn report_parts(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    file_id: &FileId,
+    file: FileInformation,
+) -> Result<UnfinishedLargeFileReport, GenericB2Error> {
+    // unwrap is safe, 1000 is the maximum page size
+    let max_part_count = MaxPartCount::try_from(1000).unwrap();
+    let mut report = UnfinishedLargeFileReport {
+        file,
+        part_count: 0,
+        content_length: 0,
+    };
+    let mut start_part_number = None;
+    loop {
+        let list_request = ListPartsRequest {
+            file_id,
+            start_part_number,
+            max_part_count: Some(max_part_count),
+        };
+        let page = b2_list_parts(api_url, authorization_token, &list_request).await?;
+        report.part_count += page.parts().len();
+        report.content_length += page
+            .parts()
+            .iter()
+            .map(|part| *part.content_length())
+            .sum::<u64>();
+        start_part_number = page.next_part_number().copied();
+        if start_part_number.is_none() {
+            return Ok(report);
+        }
+    }
+}
+
+/// Result of a single selected file
+enum HandledFile {
+    DryRun(Box<UnfinishedLargeFileReport>),
+    Canceled(CancelFileOk),
+}
+
+/// Cancels the unfinished large files selected by `request`, see the [module documentation](self)
+///
+/// An error is only returned, if the files could not be listed. Files, which could not be canceled, are reported
+/// in [CancelUnfinishedLargeFilesOk::failed].
+pub async fn b2_cancel_unfinished_large_files(
+    api_url: &ApiUrl,
+    authorization_token: &AuthorizationToken,
+    request: &CancelUnfinishedLargeFilesRequest<'_>,
+) -> Result<CancelUnfinishedLargeFilesOk, GenericB2Error> {
+    let files = list_selected_files(api_url, authorization_token, request).await?;
+    let dry_run = request.dry_run;
+    let mut results = stream::iter(files)
+        .map(|(file_id, file)| async move {
+            let result = if dry_run {
+                report_parts(api_url, authorization_token, &file_id, file)
+                    .await
+                    .map(|report| HandledFile::DryRun(Box::new(report)))
+            } else {
+                b2_cancel_large_file(api_url, authorization_token, &file_id)
+                    .await
+                    .map(HandledFile::Canceled)
+            };
+            (file_id, result)
+        })
+        .buffer_unordered(request.max_concurrency.get());
+
+    let mut ok = CancelUnfinishedLargeFilesOk::default();
+    while let Some((file_id, result)) = results.next().await {
+        match result {
+            Ok(HandledFile::DryRun(report)) => ok.dry_run.push(*report),
+            Ok(HandledFile::Canceled(canceled)) => ok.canceled.push(canceled),
+            Err(error) => ok.failed.push((file_id, error)),
+        }
+    }
+    Ok(ok)
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::v2::test::mock_server::{FAKE_ACCOUNT_ID, FAKE_AUTHORIZATION_TOKEN, FAKE_BUCKET_ID};
+
+    fn unfinished_file(file_id: &str, file_name: &str, started: i64) -> serde_json::Value {
+        json!({
+            "accountId": FAKE_ACCOUNT_ID,
+            "action": "start",
+            "bucketId": FAKE_BUCKET_ID,
+            "contentLength": 0,
+            "contentSha1": "none",
+            "contentType": "application/octet-stream",
+            "fileId": file_id,
+            "fileInfo": {},
+            "fileName": file_name,
+            "uploadTimestamp": started,
+        })
+    }
+
+    fn part(file_id: &str, part_number: u16) -> serde_json::Value {
+        json!({
+            "fileId": file_id,
+            "partNumber": part_number,
+            "contentLength": 100,
+            "contentSha1": "062685a84ab248d2488f02f6b01b948de2514ad8",
+            "uploadTimestamp": 1100,
+        })
+    }
+
+    async fn register_handlers(mock_server: &MockServer) {
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_unfinished_large_files"))
+            .and(body_json(
+                json!({"bucketId": FAKE_BUCKET_ID, "maxFileCount": 100}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "files": [
+                    unfinished_file("old_1", "backups/a.tar", 1000),
+                    unfinished_file("new_1", "backups/b.tar", 5000),
+                ],
+                "nextFileId": "old_2",
+            })))
+            .mount(mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_unfinished_large_files"))
+            .and(body_json(json!({
+                "bucketId": FAKE_BUCKET_ID,
+                "startFileId": "old_2",
+                "maxFileCount": 100,
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "files": [unfinished_file("old_2", "backups/c.tar", 2000)],
+                "nextFileId": null,
+            })))
+            .mount(mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_parts"))
+            .and(body_json(json!({"fileId": "old_1", "maxPartCount": 1000})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "parts": [part("old_1", 1), part("old_1", 2)],
+                "nextPartNumber": null,
+            })))
+            .mount(mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_list_parts"))
+            .and(body_json(json!({"fileId": "old_2", "maxPartCount": 1000})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "parts": [],
+                "nextPartNumber": null,
+            })))
+            .mount(mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_cancel_large_file"))
+            .and(body_json(json!({"fileId": "old_1"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "fileId": "old_1",
+                "accountId": FAKE_ACCOUNT_ID,
+                "bucketId": FAKE_BUCKET_ID,
+                "fileName": "backups/a.tar",
+            })))
+            .mount(mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_cancel_large_file"))
+            .and(body_json(json!({"fileId": "old_2"})))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "status": 400,
+                "code": "bad_request",
+                "message": "file is not an unfinished large file",
+            })))
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_cancel_unfinished_large_files() {
+        let mock_server = MockServer::start().await;
+        register_handlers(&mock_server).await;
+        let api_url = ApiUrl(mock_server.uri());
+        let authorization_token = AuthorizationToken(FAKE_AUTHORIZATION_TOKEN.into());
+        let bucket_id = FAKE_BUCKET_ID.to_owned().try_into().unwrap();
+
+        let request = CancelUnfinishedLargeFilesRequest::builder()
+            .bucket_id(&bucket_id)
+            .started_before(TimeStamp::from_millis(3000))
+            .dry_run(true)
+            .build();
+        let ok = b2_cancel_unfinished_large_files(&api_url, &authorization_token, &request)
+            .await
+            .unwrap();
+        assert!(ok.canceled().is_empty());
+        assert!(ok.failed().is_empty());
+        assert_eq!(2, ok.dry_run().len());
+        assert_eq!(2, ok.part_count());
+        assert_eq!(200, ok.content_length());
+
+        let filter = |file: &FileInformation| file.file_name().as_str() != "backups/c.tar";
+        let request = CancelUnfinishedLargeFilesRequest::builder()
+            .bucket_id(&bucket_id)
+            .started_before(TimeStamp::from_millis(3000))
+            .filter(&filter)
+            .build();
+        let ok = b2_cancel_unfinished_large_files(&api_url, &authorization_token, &request)
+            .await
+            .unwrap();
+        assert_eq!(1, ok.canceled().len());
+        assert_eq!("old_1", ok.canceled()[0].file_id().as_str());
+        assert!(ok.failed().is_empty());
+
+        let request = CancelUnfinishedLargeFilesRequest::builder()
+            .bucket_id(&bucket_id)
+            .started_before(TimeStamp::from_millis(3000))
+            .max_concurrency(NonZeroUsize::new(1).unwrap())
+            .build();
+        let ok = b2_cancel_unfinished_large_files(&api_url, &authorization_token, &request)
+            .await
+            .unwrap();
+        assert_eq!(1, ok.canceled().len());
+        assert_eq!(1, ok.failed().len());
+        assert_eq!("old_2", ok.failed()[0].0.as_str());
+        assert!(ok.dry_run().is_empty());
+    }
+}
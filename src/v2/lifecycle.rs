@@ -0,0 +1,295 @@
+//! Dry run of [LifeCycleRule]s against a listing of file versions, before the rules are set with
+//! [crate::v2::b2_update_bucket].
+//!
+//! The evaluation follows the [lifecycle rules documentation](https://www.backblaze.com/b2/docs/lifecycle_rules.html):
+//! - `daysFromUploadingToHiding` hides the current version of a file the given number of days after it was uploaded
+//! - `daysFromHidingToDeleting` deletes a version the given number of days after it stopped being the current
+//!   version, i.e. after a newer version was uploaded or the file was hidden. Hide markers are deleted together
+//!   with the versions they hid.
+//! - `daysFromStartingToCancelingUnfinishedLargeFiles` cancels large files, which were not finished the given number
+//!   of days after they were started
+//!
+//! B2 applies the rules once a day, so the actions are due at the reported time, but may happen up to a day later.
+use std::{collections::BTreeMap, num::NonZeroU64};
+
+use super::{FileAction, FileInformation, FileNamePrefix, LifeCycleRule, Period, TimeStamp};
+
+/// What a lifecycle rule does with a file version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleAction {
+    /// the file is hidden, i.e. a hide marker is added as new current version
+    Hide,
+    /// the file version is deleted
+    Delete,
+    /// the unfinished large file is canceled
+    Cancel,
+}
+
+/// An action a lifecycle rule would take on a file version, see [simulate_lifecycle_rules]
+#[derive(Debug, Clone, Copy)]
+pub struct SimulatedLifecycleAction<'a> {
+    file_version: &'a FileInformation,
+    action: LifecycleAction,
+    due: TimeStamp,
+    rule: &'a LifeCycleRule,
+}
+
+impl<'a> SimulatedLifecycleAction<'a> {
+    /// Get a reference to the simulated lifecycle action's file version.
+    pub fn file_version(&self) -> &'a FileInformation {
+        self.file_version
+    }
+
+    /// Get the simulated lifecycle action's action.
+    pub fn action(&self) -> LifecycleAction {
+        self.action
+    }
+
+    /// Get the simulated lifecycle action's due time.
+    pub fn due(&self) -> TimeStamp {
+        self.due
+    }
+
+    /// Get a reference to the rule causing the action.
+    pub fn rule(&self) -> &'a LifeCycleRule {
+        self.rule
+    }
+}
+
+/// Result of [simulate_lifecycle_rules]
+#[derive(Debug, Clone)]
+pub struct LifecycleSimulation<'a> {
+    actions: Vec<SimulatedLifecycleAction<'a>>,
+    overlapping_prefixes: Vec<(&'a FileNamePrefix, &'a FileNamePrefix)>,
+}
+
+impl<'a> LifecycleSimulation<'a> {
+    /// Get a reference to the lifecycle simulation's actions, ordered by file name and newest version first.
+    pub fn actions(&self) -> &[SimulatedLifecycleAction<'a>] {
+        &self.actions
+    }
+
+    /// file versions, which would be hidden
+    pub fn hidden(&self) -> impl Iterator<Item = &'a FileInformation> + '_ {
+        self.versions_with(LifecycleAction::Hide)
+    }
+
+    /// file versions (including hide markers), which would be deleted
+    pub fn deleted(&self) -> impl Iterator<Item = &'a FileInformation> + '_ {
+        self.versions_with(LifecycleAction::Delete)
+    }
+
+    /// unfinished large files, which would be canceled
+    pub fn canceled(&self) -> impl Iterator<Item = &'a FileInformation> + '_ {
+        self.versions_with(LifecycleAction::Cancel)
+    }
+
+    fn versions_with(
+        &self,
+        action: LifecycleAction,
+    ) -> impl Iterator<Item = &'a FileInformation> + '_ {
+        self.actions
+            .iter()
+            .filter(move |a| a.action == action)
+            .map(|a| a.file_version)
+    }
+
+    /// Pairs of rule prefixes, where one is a prefix of the other.
+    ///
+    /// B2 rejects such rule sets, the simulation applies the first matching rule to each file.
+    pub fn overlapping_prefixes(&self) -> &[(&'a FileNamePrefix, &'a FileNamePrefix)] {
+        &self.overlapping_prefixes
+    }
+}
+
+/// Pairs of prefixes of `rules`, where one is a prefix of the other (including duplicates)
+pub(crate) fn overlapping_prefixes(
+    rules: &[LifeCycleRule],
+) -> Vec<(&FileNamePrefix, &FileNamePrefix)> {
+    let mut overlapping = Vec::new();
+    for (i, rule) in rules.iter().enumerate() {
+        for other in &rules[i + 1..] {
+            let (a, b) = (rule.file_name_prefix(), other.file_name_prefix());
+            if a.as_str().starts_with(b.as_str()) || b.as_str().starts_with(a.as_str()) {
+                overlapping.push((a, b));
+            }
+        }
+    }
+    overlapping
+}
+
+fn days_after(timestamp: TimeStamp, days: NonZeroU64) -> TimeStamp {
+    timestamp + Period::days(days.get())
+}
+
+/// Evaluates `rules` against the file versions of a bucket (e.g. from [crate::v2::b2_list_file_versions]),
+/// returning the actions due at `now`.
+///
+/// Unfinished large files are only evaluated, if they are part of `versions` (e.g. from
+/// [crate::v2::b2_list_unfinished_large_files]), folders are ignored. `versions` has to contain all versions of the
+/// files to be evaluated, as the time a version was hidden is taken from the next newer version.
+pub fn simulate_lifecycle_rules<'a>(
+    rules: &'a [LifeCycleRule],
+    versions: &'a [FileInformation],
+    now: TimeStamp,
+) -> LifecycleSimulation<'a> {
+    let mut files: BTreeMap<&str, Vec<&FileInformation>> = BTreeMap::new();
+    for version in versions {
+        if matches!(
+            version.action(),
+            FileAction::Upload | FileAction::Copy | FileAction::Hide | FileAction::Start
+        ) {
+            files
+                .entry(version.file_name().as_str())
+                .or_default()
+                .push(version);
+        }
+    }
+
+    let mut actions = Vec::new();
+    for (file_name, mut versions) in files {
+        let rule = match rules
+            .iter()
+            .find(|rule| file_name.starts_with(rule.file_name_prefix().as_str()))
+        {
+            Some(rule) => rule,
+            None => continue,
+        };
+        versions.sort_by_key(|version| std::cmp::Reverse(*version.upload_timestamp()));
+        let mut action = |file_version, action, due: TimeStamp| {
+            if due <= now {
+                actions.push(SimulatedLifecycleAction {
+                    file_version,
+                    action,
+                    due,
+                    rule,
+                });
+            }
+        };
+
+        // upload time of the next newer version, i.e. when a version stopped being the current one
+        let mut newer_upload: Option<TimeStamp> = None;
+        for version in versions {
+            if let FileAction::Start = version.action() {
+                // unfinished large files are not versions of the file yet
+                if let Some(days) = rule.days_from_starting_to_canceling_unfinished_large_files() {
+                    let cancel_at = days_after(*version.upload_timestamp(), days);
+                    action(version, LifecycleAction::Cancel, cancel_at);
+                }
+                continue;
+            }
+            let hidden_at = match (newer_upload, version.action()) {
+                (Some(newer_upload), _) => Some(newer_upload),
+                (None, FileAction::Hide) => Some(*version.upload_timestamp()),
+                (None, _) => rule.days_from_uploading_to_hiding().map(|days| {
+                    let hide_at = days_after(*version.upload_timestamp(), days);
+                    action(version, LifecycleAction::Hide, hide_at);
+                    hide_at
+                }),
+            };
+            if let (Some(hidden_at), Some(days)) = (hidden_at, rule.days_from_hiding_to_deleting())
+            {
+                action(
+                    version,
+                    LifecycleAction::Delete,
+                    days_after(hidden_at, days),
+                );
+            }
+            newer_upload = Some(*version.upload_timestamp());
+        }
+    }
+
+    LifecycleSimulation {
+        actions,
+        overlapping_prefixes: overlapping_prefixes(rules),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::v2::timestamp::MILLIS_PER_DAY;
+
+    fn version(file_name: &str, action: &str, day: i64) -> FileInformation {
+        serde_json::from_value(json!({
+            "accountId": "ACCOUNT_ID",
+            "action": action,
+            "bucketId": "b2f6f21365e1d29f6c580f18",
+            "contentLength": 7,
+            "contentSha1": null,
+            "contentType": "text/plain",
+            "fileId": format!("{}_{}", file_name, day),
+            "fileInfo": {},
+            "fileName": file_name,
+            "uploadTimestamp": day * MILLIS_PER_DAY,
+        }))
+        .unwrap()
+    }
+
+    fn rule(prefix: &str, to_hiding: u64, to_deleting: u64) -> LifeCycleRule {
+        LifeCycleRule::new(
+            NonZeroU64::new(to_deleting),
+            NonZeroU64::new(to_hiding),
+            prefix.to_owned().try_into().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_simulate_lifecycle_rules() {
+        let rules = [rule("logs/", 30, 7), rule("backups/", 0, 1)];
+        let versions = [
+            version("logs/a.txt", "upload", 0),
+            version("logs/b.txt", "upload", 10),
+            version("backups/c.tar", "upload", 5),
+            version("backups/c.tar", "upload", 2),
+            version("backups/d.tar", "hide", 9),
+            version("backups/d.tar", "upload", 1),
+            version("other/e.txt", "upload", 0),
+        ];
+        let simulation = simulate_lifecycle_rules(
+            &rules,
+            &versions,
+            TimeStamp::from_millis(37 * MILLIS_PER_DAY),
+        );
+        let ids = |files: &mut dyn Iterator<Item = &FileInformation>| {
+            files
+                .map(|f| f.file_id().unwrap().as_str().to_owned())
+                .collect::<Vec<_>>()
+        };
+        // logs/b.txt is hidden on day 40, the old version of c.tar was deleted 1 day after day 5
+        assert_eq!(vec!["logs/a.txt_0"], ids(&mut simulation.hidden()));
+        assert_eq!(
+            vec![
+                "backups/c.tar_2",
+                "backups/d.tar_9",
+                "backups/d.tar_1",
+                "logs/a.txt_0"
+            ],
+            ids(&mut simulation.deleted())
+        );
+        assert!(simulation.overlapping_prefixes().is_empty());
+
+        let mut rule_with_cancel = rule("uploads/", 0, 0);
+        rule_with_cancel
+            .set_days_from_starting_to_canceling_unfinished_large_files(NonZeroU64::new(2));
+        let versions = [
+            version("uploads/big.iso", "start", 0),
+            version("uploads/big.iso", "start", 5),
+        ];
+        let rules = [rule_with_cancel];
+        let simulation = simulate_lifecycle_rules(
+            &rules,
+            &versions,
+            TimeStamp::from_millis(3 * MILLIS_PER_DAY),
+        );
+        assert_eq!(vec!["uploads/big.iso_0"], ids(&mut simulation.canceled()));
+
+        let rules = [rule("logs/", 30, 0), rule("logs/old/", 1, 0)];
+        let simulation = simulate_lifecycle_rules(&rules, &versions, TimeStamp::UNIX_EPOCH);
+        assert_eq!(1, simulation.overlapping_prefixes().len());
+    }
+}
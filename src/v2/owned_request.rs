@@ -0,0 +1,137 @@
+//! Owned counterparts of the request structs.
+//!
+//! The request structs borrow all of their values, which keeps the calls free of copies, but prevents
+//! storing requests (e.g. in a job queue) or moving them to other tasks. The owned requests generated by
+//! `owned_request!` own their values, are `Send + 'static`, can be serialized and deserialized, and can
+//! be converted from and into the borrowed requests.
+
+/// Generates an owned version of a request struct, has to be called in the module of the request.
+///
+/// Every field is given with its owned type and how it is converted into the field of the request:
+/// - `borrow`: the request borrows the value (`&T`, or `&[T]` for a `Vec<T>`)
+/// - `borrow_option`: like `borrow`, for an `Option`
+/// - `clone`: the request contains the value itself
+/// - `customer_key`: an `Option<OwnedServerSideEncryptionCustomerKey>`
+/// - `content_sha1`: an `OwnedContentSha1`
+///
+/// An optional cfg predicate after the conversion limits the field to a feature.
+macro_rules! owned_request {
+    (
+        $(#[$struct_attr:meta])*
+        $owned:ident => $request:ident {
+            $(
+                $(#[$field_attr:meta])*
+                $field:ident: $ty:ty [$conversion:ident $(, $cfg:meta)?],
+            )*
+        }
+    ) => {
+        $(#[$struct_attr])*
+        #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct $owned {
+            $(
+                $(#[cfg($cfg)])?
+                $(#[$field_attr])*
+                $field: $ty,
+            )*
+        }
+
+        impl $owned {
+            /// request borrowing the values of this owned request
+            pub fn as_request(&self) -> $request<'_> {
+                $request {
+                    $(
+                        $(#[cfg($cfg)])?
+                        $field: owned_request!(@as_request $conversion self.$field),
+                    )*
+                }
+            }
+        }
+
+        impl<'s> From<&$request<'s>> for $owned {
+            fn from(request: &$request<'s>) -> Self {
+                Self {
+                    $(
+                        $(#[cfg($cfg)])?
+                        $field: owned_request!(@to_owned $conversion request.$field),
+                    )*
+                }
+            }
+        }
+
+        impl<'s> From<$request<'s>> for $owned {
+            fn from(request: $request<'s>) -> Self {
+                Self::from(&request)
+            }
+        }
+    };
+
+    (@as_request borrow $value:expr) => {
+        ::std::borrow::Borrow::borrow(&$value)
+    };
+    (@as_request borrow_option $value:expr) => {
+        $value.as_ref().map(::std::borrow::Borrow::borrow)
+    };
+    (@as_request clone $value:expr) => {
+        $value.clone()
+    };
+    (@as_request customer_key $value:expr) => {
+        $value
+            .as_ref()
+            .map($crate::v2::OwnedServerSideEncryptionCustomerKey::as_customer_key)
+    };
+
+    (@as_request content_sha1 $value:expr) => {
+        $value.as_content_sha1()
+    };
+
+    (@to_owned borrow $value:expr) => {
+        ::std::borrow::ToOwned::to_owned($value)
+    };
+    (@to_owned borrow_option $value:expr) => {
+        $value.map(::std::borrow::ToOwned::to_owned)
+    };
+    (@to_owned clone $value:expr) => {
+        $value.clone()
+    };
+    (@to_owned customer_key $value:expr) => {
+        $value
+            .as_ref()
+            .map($crate::v2::OwnedServerSideEncryptionCustomerKey::from)
+    };
+    (@to_owned content_sha1 $value:expr) => {
+        $crate::v2::OwnedContentSha1::from($value)
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use crate::v2::{
+        ContentDisposition, ContentType, OwnedUploadFileParameters, Sha1Digest,
+        UploadFileParameters,
+    };
+
+    #[test]
+    fn test_owned_headers() {
+        let file_name = "photos/cat.jpg".to_owned().try_into().unwrap();
+        let content_type = ContentType::jpeg();
+        let content_disposition = ContentDisposition::inline();
+        let sha1 = Sha1Digest::new([1u8; 20]);
+        let request = UploadFileParameters::builder()
+            .file_name(&file_name)
+            .content_type(&content_type)
+            .content_length(42)
+            .content_sha1(&sha1)
+            .content_disposition(&content_disposition)
+            .build();
+        let owned = OwnedUploadFileParameters::from(&request);
+        let yaml = serde_yaml::to_string(&owned).unwrap();
+        let deserialized: OwnedUploadFileParameters = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(
+            format!("{:?}", request),
+            format!("{:?}", deserialized.as_request())
+        );
+    }
+}
@@ -0,0 +1,457 @@
+//! Checks whether an application key may call an endpoint, before the request is sent.
+//!
+//! The required capabilities are taken from the [official documentation](https://www.backblaze.com/b2/docs/application_keys.html),
+//! the server remains the authority, so a successful check does not guarantee a successful call.
+//!
+//! Calls run inside of [with_permission_check] are checked automatically and fail with
+//! [crate::v2::Error::PermissionDenied] instead of being sent, if the key is not allowed to make them.
+use std::{convert::TryInto, fmt::Display, future::Future, sync::Arc};
+
+use serde::Deserialize;
+use typed_builder::TypedBuilder;
+
+use super::{
+    AuthorizeAccountAllowed, BucketId, Capabilities, Capability, Endpoint, FileName, FileNamePrefix,
+};
+
+/// A call to be checked with [AuthorizeAccountAllowed::check_permission]
+#[derive(Debug, TypedBuilder)]
+pub struct PermissionCheck<'s> {
+    endpoint: Endpoint,
+    /// bucket accessed by the call, if not set bucket restrictions of the key are not checked
+    #[builder(default, setter(strip_option))]
+    bucket_id: Option<&'s BucketId>,
+    /// file accessed by the call, keys restricted to a name prefix require it (or `file_name_prefix`) on calls
+    /// listing or naming files
+    #[builder(default, setter(strip_option))]
+    file_name: Option<&'s FileName>,
+    /// prefix of files listed by the call, see `file_name`
+    #[builder(default, setter(strip_option))]
+    file_name_prefix: Option<&'s FileNamePrefix>,
+    /// set if governance mode retention is bypassed (e.g. on [crate::v2::b2_delete_file_version])
+    #[builder(default)]
+    bypass_governance: bool,
+}
+
+impl<'s> PermissionCheck<'s> {
+    /// capabilities required for this call
+    pub fn required_capabilities(&self) -> Capabilities {
+        let mut capabilities = self.endpoint.required_capabilities();
+        if self.bypass_governance {
+            capabilities |= Capability::BypassGovernance;
+        }
+        capabilities
+    }
+
+    /// `true` if the call lists files or addresses them by name, so B2 checks the name prefix of the key
+    fn names_files(&self) -> bool {
+        matches!(
+            self.endpoint,
+            Endpoint::CopyFile
+                | Endpoint::DeleteFileVersion
+                | Endpoint::DownloadFileByName
+                | Endpoint::GetDownloadAuthorization
+                | Endpoint::HideFile
+                | Endpoint::ListFileNames
+                | Endpoint::ListFileVersions
+                | Endpoint::ListUnfinishedLargeFiles
+                | Endpoint::StartLargeFile
+                | Endpoint::UpdateFileLegalHold
+                | Endpoint::UpdateFileRetention
+                | Endpoint::UploadFile
+        )
+    }
+}
+
+/// The application key is not allowed to make a call
+#[derive(Debug, PartialEq, Eq)]
+pub enum PermissionError {
+    MissingCapabilities {
+        endpoint: Endpoint,
+        missing: Capabilities,
+    },
+    BucketNotAllowed {
+        bucket_id: BucketId,
+    },
+    FileNameNotAllowed {
+        file_name: String,
+        allowed_prefix: FileNamePrefix,
+    },
+    /// The key is restricted to a name prefix, but the call does not name a file or prefix
+    FileNameMissing {
+        endpoint: Endpoint,
+        allowed_prefix: FileNamePrefix,
+    },
+}
+
+impl Display for PermissionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PermissionError::MissingCapabilities { endpoint, missing } => write!(
+                f,
+                "Missing capabilities for {:?}: {:?}",
+                endpoint,
+                missing.iter().collect::<Vec<_>>()
+            ),
+            PermissionError::BucketNotAllowed { bucket_id } => write!(
+                f,
+                "Application key is not allowed to access bucket {}",
+                bucket_id.as_str()
+            ),
+            PermissionError::FileNameNotAllowed {
+                file_name,
+                allowed_prefix,
+            } => write!(
+                f,
+                "Application key is restricted to files starting with {}, which does not match {}",
+                allowed_prefix.as_str(),
+                file_name
+            ),
+            PermissionError::FileNameMissing {
+                endpoint,
+                allowed_prefix,
+            } => write!(
+                f,
+                "Application key is restricted to files starting with {}, but {:?} does not name a file or prefix",
+                allowed_prefix.as_str(),
+                endpoint
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PermissionError {}
+
+impl AuthorizeAccountAllowed {
+    /// Checks capabilities, bucket and name prefix restrictions of the key for a call, without any network request.
+    pub fn check_permission(&self, check: &PermissionCheck) -> Result<(), PermissionError> {
+        let missing = check.required_capabilities() - self.capabilities;
+        if !missing.is_empty() {
+            return Err(PermissionError::MissingCapabilities {
+                endpoint: check.endpoint,
+                missing,
+            });
+        }
+        if let Some(bucket_id) = check.bucket_id {
+            if !self.is_bucket_allowed(bucket_id) {
+                return Err(PermissionError::BucketNotAllowed {
+                    bucket_id: bucket_id.clone(),
+                });
+            }
+        }
+        if let Some(allowed_prefix) = &self.name_prefix {
+            let mut names = check
+                .file_name
+                .map(FileName::as_str)
+                .into_iter()
+                .chain(check.file_name_prefix.map(FileNamePrefix::as_str))
+                .peekable();
+            if check.names_files() && names.peek().is_none() {
+                return Err(PermissionError::FileNameMissing {
+                    endpoint: check.endpoint,
+                    allowed_prefix: allowed_prefix.clone(),
+                });
+            }
+            for name in names {
+                if !name.starts_with(allowed_prefix.as_str()) {
+                    return Err(PermissionError::FileNameNotAllowed {
+                        file_name: name.to_owned(),
+                        allowed_prefix: allowed_prefix.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+tokio::task_local! {
+    static SCOPED_PERMISSIONS: Arc<AuthorizeAccountAllowed>;
+}
+
+/// Runs a future, checking every call made inside of it with [AuthorizeAccountAllowed::check_permission] before it is sent.
+///
+/// The bucket, file name and prefix of a call are taken from the request, calls addressing files only by id
+/// are checked for the required capabilities.
+pub async fn with_permission_check<F: Future>(
+    allowed: Arc<AuthorizeAccountAllowed>,
+    future: F,
+) -> F::Output {
+    SCOPED_PERMISSIONS.scope(allowed, future).await
+}
+
+/// fields of a json request body restricted by an application key
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RestrictedFields {
+    bucket_id: Option<String>,
+    file_name: Option<String>,
+    #[serde(alias = "namePrefix", alias = "fileNamePrefix")]
+    prefix: Option<String>,
+    #[serde(default)]
+    bypass_governance: bool,
+}
+
+/// checks a request before it is sent, if it runs inside of [with_permission_check]
+///
+/// Requests without an authorization token (e.g. downloads from public buckets) are not made with the key,
+/// so they are not checked.
+pub(crate) fn check_request(
+    endpoint: Endpoint,
+    path: &str,
+    headers: &http::HeaderMap,
+    body: Option<&[u8]>,
+) -> Result<(), PermissionError> {
+    let allowed = match SCOPED_PERMISSIONS.try_with(Arc::clone) {
+        Ok(allowed) => allowed,
+        Err(_) => return Ok(()),
+    };
+    if !headers.contains_key(http::header::AUTHORIZATION) {
+        return Ok(());
+    }
+    let mut fields = match endpoint {
+        // the prefix and bucket of key calls restrict the managed key, not the call
+        Endpoint::CreateKey | Endpoint::DeleteKey | Endpoint::ListKeys => {
+            RestrictedFields::default()
+        }
+        Endpoint::UploadFile | Endpoint::UploadPart => RestrictedFields {
+            file_name: headers
+                .get("X-Bz-File-Name")
+                .and_then(|name| name.to_str().ok())
+                .and_then(crate::header_serializer::url_decode),
+            ..Default::default()
+        },
+        Endpoint::DownloadFileByName => RestrictedFields {
+            file_name: path
+                .strip_prefix("/file/")
+                .and_then(|path| path.split_once('/'))
+                .and_then(|(_bucket_name, file_name)| {
+                    crate::header_serializer::url_decode(file_name)
+                }),
+            ..Default::default()
+        },
+        _ => body
+            .and_then(|body| serde_json::from_slice(body).ok())
+            .unwrap_or_default(),
+    };
+    let bucket_id: Option<BucketId> = fields.bucket_id.take().and_then(|id| id.try_into().ok());
+    let file_name: Option<FileName> = fields
+        .file_name
+        .take()
+        .and_then(|name| name.try_into().ok());
+    let prefix: Option<FileNamePrefix> = fields
+        .prefix
+        .take()
+        .and_then(|prefix| prefix.try_into().ok());
+    allowed.check_permission(&PermissionCheck {
+        endpoint,
+        bucket_id: bucket_id.as_ref(),
+        file_name: file_name.as_ref(),
+        file_name_prefix: prefix.as_ref(),
+        bypass_governance: fields.bypass_governance,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use super::*;
+
+    fn restricted_key() -> AuthorizeAccountAllowed {
+        serde_json::from_str(
+            r#"{
+                "bucketId": "bucket_a",
+                "bucketName": "a",
+                "capabilities": ["listFiles", "readFiles", "writeFiles", "deleteFiles"],
+                "namePrefix": "photos/"
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_allowed() {
+        let bucket_id: BucketId = "bucket_a".to_owned().try_into().unwrap();
+        let file_name: FileName = "photos/cat.jpg".to_owned().try_into().unwrap();
+        let check = PermissionCheck::builder()
+            .endpoint(Endpoint::UploadFile)
+            .bucket_id(&bucket_id)
+            .file_name(&file_name)
+            .build();
+        assert_eq!(Ok(()), restricted_key().check_permission(&check));
+    }
+
+    #[test]
+    fn test_missing_capability() {
+        let check = PermissionCheck::builder()
+            .endpoint(Endpoint::DeleteFileVersion)
+            .bypass_governance(true)
+            .build();
+        assert_eq!(
+            Err(PermissionError::MissingCapabilities {
+                endpoint: Endpoint::DeleteFileVersion,
+                missing: Capability::BypassGovernance.into(),
+            }),
+            restricted_key().check_permission(&check)
+        );
+    }
+
+    #[test]
+    fn test_restrictions() {
+        let key = restricted_key();
+        let other_bucket: BucketId = "bucket_b".to_owned().try_into().unwrap();
+        let check = PermissionCheck::builder()
+            .endpoint(Endpoint::ListFileNames)
+            .bucket_id(&other_bucket)
+            .build();
+        assert_eq!(
+            Err(PermissionError::BucketNotAllowed {
+                bucket_id: other_bucket.clone()
+            }),
+            key.check_permission(&check)
+        );
+
+        let prefix: FileNamePrefix = "videos/".to_owned().try_into().unwrap();
+        let check = PermissionCheck::builder()
+            .endpoint(Endpoint::ListFileNames)
+            .file_name_prefix(&prefix)
+            .build();
+        assert_eq!(
+            Err(PermissionError::FileNameNotAllowed {
+                file_name: "videos/".to_owned(),
+                allowed_prefix: "photos/".to_owned().try_into().unwrap(),
+            }),
+            key.check_permission(&check)
+        );
+
+        let bucket_id: BucketId = "bucket_a".to_owned().try_into().unwrap();
+        for endpoint in [Endpoint::ListFileNames, Endpoint::HideFile] {
+            let check = PermissionCheck::builder()
+                .endpoint(endpoint)
+                .bucket_id(&bucket_id)
+                .build();
+            assert_eq!(
+                Err(PermissionError::FileNameMissing {
+                    endpoint,
+                    allowed_prefix: "photos/".to_owned().try_into().unwrap(),
+                }),
+                key.check_permission(&check)
+            );
+        }
+        let check = PermissionCheck::builder()
+            .endpoint(Endpoint::GetUploadUrl)
+            .bucket_id(&bucket_id)
+            .build();
+        assert_eq!(Ok(()), key.check_permission(&check));
+    }
+
+    #[tokio::test]
+    async fn test_with_permission_check() {
+        use crate::v2::{
+            b2_list_file_names, errors::ListFileNamesError, ApiUrl, AuthorizationToken,
+            ListFileNamesRequest,
+        };
+
+        let other_bucket: BucketId = "bucket_b".to_owned().try_into().unwrap();
+        let request = ListFileNamesRequest::builder()
+            .bucket_id(&other_bucket)
+            .build();
+        // denied calls are not sent, so nothing listens on this url
+        let api_url = ApiUrl("http://localhost:1".to_owned());
+        let authorization_token = AuthorizationToken("token".to_owned());
+        let res = with_permission_check(
+            Arc::new(restricted_key()),
+            b2_list_file_names(&api_url, &authorization_token, &request),
+        )
+        .await;
+        match res {
+            Err(ListFileNamesError::Unexpected {
+                raw_error: crate::v2::Error::PermissionDenied(error),
+                retry: None,
+            }) => assert_eq!(
+                PermissionError::BucketNotAllowed {
+                    bucket_id: other_bucket.clone()
+                },
+                error
+            ),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_request() {
+        let mut authorized = http::HeaderMap::new();
+        authorized.insert(http::header::AUTHORIZATION, "token".parse().unwrap());
+        let mut upload_headers = authorized.clone();
+        upload_headers.insert("X-Bz-File-Name", "videos%2Fdog.mp4".parse().unwrap());
+        let check = SCOPED_PERMISSIONS.scope(Arc::new(restricted_key()), async {
+            (
+                check_request(
+                    Endpoint::UploadFile,
+                    "/b2api/v2/b2_upload_file/bucket_a/token",
+                    &upload_headers,
+                    None,
+                ),
+                check_request(
+                    Endpoint::DownloadFileByName,
+                    "/file/a/photos/cat.jpg",
+                    &authorized,
+                    None,
+                ),
+                check_request(
+                    Endpoint::DownloadFileByName,
+                    "/file/a/videos/dog.mp4",
+                    &authorized,
+                    None,
+                ),
+                check_request(
+                    Endpoint::DeleteFileVersion,
+                    "/b2api/v2/b2_delete_file_version",
+                    &authorized,
+                    Some(br#"{"fileName":"photos/cat.jpg","fileId":"id","bypassGovernance":true}"#),
+                ),
+            )
+        });
+        let (upload, download, other_download, delete) = check.await;
+        assert_eq!(
+            Err(PermissionError::FileNameNotAllowed {
+                file_name: "videos/dog.mp4".to_owned(),
+                allowed_prefix: "photos/".to_owned().try_into().unwrap(),
+            }),
+            upload
+        );
+        assert_eq!(Ok(()), download);
+        assert!(matches!(
+            other_download,
+            Err(PermissionError::FileNameNotAllowed { .. })
+        ));
+        assert_eq!(
+            Err(PermissionError::MissingCapabilities {
+                endpoint: Endpoint::DeleteFileVersion,
+                missing: Capability::BypassGovernance.into(),
+            }),
+            delete
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_request_without_authorization() {
+        // e.g. a download from a public bucket, which is not made with the key
+        let key: AuthorizeAccountAllowed = serde_json::from_str(
+            r#"{"bucketId": "bucket_a", "capabilities": ["listFiles"], "namePrefix": "photos/"}"#,
+        )
+        .unwrap();
+        let res = SCOPED_PERMISSIONS
+            .scope(Arc::new(key), async {
+                check_request(
+                    Endpoint::DownloadFileByName,
+                    "/file/public/videos/dog.mp4",
+                    &http::HeaderMap::new(),
+                    None,
+                )
+            })
+            .await;
+        assert_eq!(Ok(()), res);
+    }
+}
@@ -0,0 +1,322 @@
+//! Time limited download links for private buckets, combining [b2_get_download_authorization]
+//! and [get_b2_download_file_by_name_url]
+use typed_builder::TypedBuilder;
+
+use super::{
+    b2_get_download_authorization, errors::GetDownloadAuthorizationError,
+    get_b2_download_file_by_name_url, ApiUrl, AuthorizationToken, BucketId, BucketName,
+    ContentDispositionRef, ContentTypeRef, DownloadFileByNameUrlParameters,
+    DownloadOnlyAuthorizationToken, DownloadUrl, FileName, FileNamePrefix,
+    GetDownloadAuthorizationRequest, InvalidData, ValidDownloadAuthorizationDurationInSeconds,
+};
+
+#[derive(Debug, TypedBuilder)]
+pub struct PresignDownloadRequest<'s> {
+    bucket_id: &'s BucketId,
+    bucket_name: &'s BucketName,
+    file_name: &'s FileName,
+    valid_duration_in_seconds: ValidDownloadAuthorizationDurationInSeconds,
+
+    #[builder(default, setter(strip_option))]
+    b2_content_disposition: Option<ContentDispositionRef<'s>>,
+
+    #[builder(default, setter(strip_option))]
+    b2_content_type: Option<ContentTypeRef<'s>>,
+}
+
+/// Request for multiple presigned urls, which share a single download authorization for `file_name_prefix`
+#[derive(Debug, TypedBuilder)]
+pub struct PresignDownloadsRequest<'s> {
+    bucket_id: &'s BucketId,
+    bucket_name: &'s BucketName,
+    /// all `file_names` must start with this prefix, everything below it is accessible with the returned urls
+    file_name_prefix: &'s FileNamePrefix,
+    file_names: &'s [FileName],
+    valid_duration_in_seconds: ValidDownloadAuthorizationDurationInSeconds,
+
+    #[builder(default, setter(strip_option))]
+    b2_content_disposition: Option<ContentDispositionRef<'s>>,
+
+    #[builder(default, setter(strip_option))]
+    b2_content_type: Option<ContentTypeRef<'s>>,
+}
+
+/// appends the authorization token as `Authorization` query parameter to a download url
+fn presigned_url(
+    download_url: &DownloadUrl,
+    bucket_name: &BucketName,
+    file_name: &FileName,
+    params: &DownloadFileByNameUrlParameters,
+    authorization_token: &DownloadOnlyAuthorizationToken,
+) -> String {
+    let url = get_b2_download_file_by_name_url(download_url, bucket_name, file_name, params);
+    let separator = if url.ends_with('?') { "" } else { "&" };
+    format!(
+        "{}{}{}",
+        url,
+        separator,
+        // unwrap is safe, a single string pair can always be serialized
+        serde_urlencoded::to_string([("Authorization", authorization_token.as_str())]).unwrap()
+    )
+}
+
+/// Creates a download url for a file in a private bucket, that is valid for the given duration.
+///
+/// The `b2_content_disposition` and `b2_content_type` overrides are part of the download authorization,
+/// so they are always requested with the same values as used in the returned url.
+pub async fn presign_download(
+    api_url: &ApiUrl,
+    download_url: &DownloadUrl,
+    authorization_token: &AuthorizationToken,
+    request: &PresignDownloadRequest<'_>,
+) -> Result<String, GetDownloadAuthorizationError> {
+    let file_name_prefix = FileNamePrefix::from(request.file_name.clone());
+    let authorization = b2_get_download_authorization(
+        api_url,
+        authorization_token,
+        &GetDownloadAuthorizationRequest::with_content_overrides(
+            request.bucket_id,
+            &file_name_prefix,
+            request.valid_duration_in_seconds,
+            request.b2_content_disposition,
+            request.b2_content_type,
+        ),
+    )
+    .await?;
+    Ok(presigned_url(
+        download_url,
+        request.bucket_name,
+        request.file_name,
+        &DownloadFileByNameUrlParameters::with_content_overrides(
+            request.b2_content_disposition,
+            request.b2_content_type,
+        ),
+        authorization.authorization_token(),
+    ))
+}
+
+/// Creates download urls for multiple files sharing a prefix, using a single download authorization.
+///
+/// The urls are returned in the order of `file_names`. If a file name does not start with
+/// `file_name_prefix`, an error is returned without calling the server.
+pub async fn presign_downloads(
+    api_url: &ApiUrl,
+    download_url: &DownloadUrl,
+    authorization_token: &AuthorizationToken,
+    request: &PresignDownloadsRequest<'_>,
+) -> Result<Vec<String>, GetDownloadAuthorizationError> {
+    if let Some(file_name) = request
+        .file_names
+        .iter()
+        .find(|f| !f.as_str().starts_with(request.file_name_prefix.as_str()))
+    {
+        return Err(GetDownloadAuthorizationError::Unexpected {
+            raw_error: crate::v2::Error::InvalidData(InvalidData::new(format!(
+                "File name {} does not start with prefix {}",
+                file_name.as_str(),
+                request.file_name_prefix.as_str()
+            ))),
+            retry: None,
+        });
+    }
+    let authorization = b2_get_download_authorization(
+        api_url,
+        authorization_token,
+        &GetDownloadAuthorizationRequest::with_content_overrides(
+            request.bucket_id,
+            request.file_name_prefix,
+            request.valid_duration_in_seconds,
+            request.b2_content_disposition,
+            request.b2_content_type,
+        ),
+    )
+    .await?;
+    let params = DownloadFileByNameUrlParameters::with_content_overrides(
+        request.b2_content_disposition,
+        request.b2_content_type,
+    );
+    Ok(request
+        .file_names
+        .iter()
+        .map(|file_name| {
+            presigned_url(
+                download_url,
+                request.bucket_name,
+                file_name,
+                &params,
+                authorization.authorization_token(),
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::v2::test::mock_server::{FAKE_AUTHORIZATION_TOKEN, FAKE_BUCKET_ID};
+    use crate::v2::ContentType;
+
+    const DOWNLOAD_TOKEN: &str = "3_20160803004041_53982a92f631a8c7303e3266_d940c7f5ee17cd1de3758aaacf1024188bc0cd0b_000_20160804004041_0006_dnld";
+
+    async fn mock_download_authorization(
+        mock_server: &MockServer,
+        expected_input: serde_json::Value,
+    ) {
+        Mock::given(method("POST"))
+            .and(path("/b2api/v2/b2_get_download_authorization"))
+            .and(header("Authorization", FAKE_AUTHORIZATION_TOKEN))
+            .and(body_json(&expected_input))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "authorizationToken": DOWNLOAD_TOKEN,
+                "bucketId": FAKE_BUCKET_ID,
+                "fileNamePrefix": expected_input["fileNamePrefix"],
+            })))
+            .expect(1)
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_presign_download() {
+        let mock_server = MockServer::start().await;
+        mock_download_authorization(
+            &mock_server,
+            json!({
+                "bucketId": FAKE_BUCKET_ID,
+                "fileNamePrefix": "photos/cat.jpg",
+                "validDurationInSeconds": 3600,
+                "b2ContentType": "image/jpeg",
+            }),
+        )
+        .await;
+        let download_url: DownloadUrl =
+            serde_json::from_value(json!("https://f002.backblazeb2.com")).unwrap();
+        let content_type = ContentType::from(mime::IMAGE_JPEG);
+        let url = presign_download(
+            &ApiUrl(mock_server.uri()),
+            &download_url,
+            &AuthorizationToken(FAKE_AUTHORIZATION_TOKEN.to_owned()),
+            &PresignDownloadRequest::builder()
+                .bucket_id(&FAKE_BUCKET_ID.to_owned().try_into().unwrap())
+                .bucket_name(&"bucket".to_owned().try_into().unwrap())
+                .file_name(&"photos/cat.jpg".to_owned().try_into().unwrap())
+                .valid_duration_in_seconds(3600.try_into().unwrap())
+                .b2_content_type(&content_type)
+                .build(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            format!(
+                "https://f002.backblazeb2.com/file/bucket/photos/cat.jpg?b2ContentType=image%2Fjpeg&Authorization={}",
+                DOWNLOAD_TOKEN
+            ),
+            url
+        );
+    }
+
+    #[test]
+    fn test_presigned_url_encodes_file_name() {
+        let download_url: DownloadUrl =
+            serde_json::from_value(json!("https://f002.backblazeb2.com")).unwrap();
+        let token: DownloadOnlyAuthorizationToken =
+            serde_json::from_value(json!(DOWNLOAD_TOKEN)).unwrap();
+        let url = presigned_url(
+            &download_url,
+            &"bucket".to_owned().try_into().unwrap(),
+            &"photos/my cat #1?100%\u{fc}.jpg"
+                .to_owned()
+                .try_into()
+                .unwrap(),
+            &DownloadFileByNameUrlParameters::with_content_overrides(None, None),
+            &token,
+        );
+        assert_eq!(
+            format!(
+                "https://f002.backblazeb2.com/file/bucket/photos/my%20cat%20%231%3F100%25%C3%BC.jpg?Authorization={}",
+                DOWNLOAD_TOKEN
+            ),
+            url
+        );
+    }
+
+    #[tokio::test]
+    async fn test_presign_downloads() {
+        let mock_server = MockServer::start().await;
+        mock_download_authorization(
+            &mock_server,
+            json!({
+                "bucketId": FAKE_BUCKET_ID,
+                "fileNamePrefix": "photos/",
+                "validDurationInSeconds": 60,
+            }),
+        )
+        .await;
+        let download_url: DownloadUrl =
+            serde_json::from_value(json!("https://f002.backblazeb2.com")).unwrap();
+        let file_names: Vec<FileName> = vec![
+            "photos/cat.jpg".to_owned().try_into().unwrap(),
+            "photos/dog.jpg".to_owned().try_into().unwrap(),
+        ];
+        let prefix: FileNamePrefix = "photos/".to_owned().try_into().unwrap();
+        let bucket_id: BucketId = FAKE_BUCKET_ID.to_owned().try_into().unwrap();
+        let bucket_name: BucketName = "bucket".to_owned().try_into().unwrap();
+        let urls = presign_downloads(
+            &ApiUrl(mock_server.uri()),
+            &download_url,
+            &AuthorizationToken(FAKE_AUTHORIZATION_TOKEN.to_owned()),
+            &PresignDownloadsRequest::builder()
+                .bucket_id(&bucket_id)
+                .bucket_name(&bucket_name)
+                .file_name_prefix(&prefix)
+                .file_names(&file_names)
+                .valid_duration_in_seconds(60.try_into().unwrap())
+                .build(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            vec![
+                format!(
+                    "https://f002.backblazeb2.com/file/bucket/photos/cat.jpg?Authorization={}",
+                    DOWNLOAD_TOKEN
+                ),
+                format!(
+                    "https://f002.backblazeb2.com/file/bucket/photos/dog.jpg?Authorization={}",
+                    DOWNLOAD_TOKEN
+                ),
+            ],
+            urls
+        );
+
+        let other_file_names: Vec<FileName> = vec!["videos/cat.mp4".to_owned().try_into().unwrap()];
+        let res = presign_downloads(
+            &ApiUrl(mock_server.uri()),
+            &download_url,
+            &AuthorizationToken(FAKE_AUTHORIZATION_TOKEN.to_owned()),
+            &PresignDownloadsRequest::builder()
+                .bucket_id(&bucket_id)
+                .bucket_name(&bucket_name)
+                .file_name_prefix(&prefix)
+                .file_names(&other_file_names)
+                .valid_duration_in_seconds(60.try_into().unwrap())
+                .build(),
+        )
+        .await;
+        assert!(matches!(
+            res,
+            Err(GetDownloadAuthorizationError::Unexpected {
+                raw_error: crate::v2::Error::InvalidData(_),
+                retry: None,
+            })
+        ));
+    }
+}
@@ -1,11 +1,12 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
 use reqwest::RequestBuilder;
 use serde::{de, Deserialize, Serialize};
+use zeroize::Zeroize;
 
-use super::Md5DigestRef;
+use super::{Md5Digest, Md5DigestRef};
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ServerSideEncryption {
     None,
     SseB2,
@@ -111,9 +112,12 @@ impl<'de> Deserialize<'de> for ServerSideEncryption {
     }
 }
 
-const CUSTOMER_KEY_BYTES: usize = 32usize;
+pub(crate) const CUSTOMER_KEY_BYTES: usize = 32usize;
 
-#[derive(Debug, Serialize, Clone)]
+/// Key of the file info in which the [key id](ServerSideEncryptionCustomerKey::key_id) of files encrypted with SSE-C is recorded
+pub const SSE_C_KEY_ID_FILE_INFO: &str = "sse_c_key_id";
+
+#[derive(Serialize, Clone, Copy)]
 #[serde(into = "SerializableServerSideEncryptionCustomerKey")]
 pub enum ServerSideEncryptionCustomerKey<'s> {
     None,
@@ -124,6 +128,24 @@ pub enum ServerSideEncryptionCustomerKey<'s> {
     },
 }
 
+// the customer key is never printed, so it cannot end up in logs or traces
+impl<'s> std::fmt::Debug for ServerSideEncryptionCustomerKey<'s> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerSideEncryptionCustomerKey::None => write!(f, "None"),
+            ServerSideEncryptionCustomerKey::SseB2 => write!(f, "SseB2"),
+            ServerSideEncryptionCustomerKey::SseC {
+                customer_key: _,
+                customer_key_md5,
+            } => f
+                .debug_struct("SseC")
+                .field("customer_key", &"<redacted>")
+                .field("customer_key_md5", customer_key_md5)
+                .finish(),
+        }
+    }
+}
+
 impl<'s> ServerSideEncryptionCustomerKey<'s> {
     /// Adds these values to a RequestBuilder as headers
     pub(crate) fn add_to_request_as_header(&self, request: RequestBuilder) -> RequestBuilder {
@@ -147,6 +169,203 @@ impl<'s> ServerSideEncryptionCustomerKey<'s> {
                 ),
         }
     }
+
+    /// Id of an SSE-C key, the hex encoded md5 digest of the key. `None` for the other modes.
+    pub fn key_id(&self) -> Option<String> {
+        match self {
+            ServerSideEncryptionCustomerKey::SseC {
+                customer_key_md5, ..
+            } => Some((*customer_key_md5).clone().into()),
+            _ => None,
+        }
+    }
+
+    /// Records the key id of an SSE-C key in the file info headers of an upload, unless the file info of the
+    /// caller already contains it
+    pub(crate) fn add_key_id_to_request_as_file_info(
+        &self,
+        request: RequestBuilder,
+        file_info: Option<&HashMap<String, String>>,
+    ) -> RequestBuilder {
+        let recorded = file_info.is_some_and(|info| info.contains_key(SSE_C_KEY_ID_FILE_INFO));
+        match self.key_id() {
+            Some(key_id) if !recorded => {
+                request.header(format!("X-Bz-Info-{}", SSE_C_KEY_ID_FILE_INFO), key_id)
+            }
+            _ => request,
+        }
+    }
+}
+
+/// Json body of a request, with the key id of an SSE-C key recorded in its `fileInfo`, unless it already contains one
+pub(crate) fn json_with_key_id_in_file_info(
+    request: &impl Serialize,
+    sse: Option<&ServerSideEncryptionCustomerKey>,
+) -> serde_json::Value {
+    // unwrap is safe, the requests only contain values that can be represented in json
+    let mut body = serde_json::to_value(request).unwrap();
+    if let (Some(key_id), Some(fields)) = (sse.and_then(|sse| sse.key_id()), body.as_object_mut()) {
+        let file_info = fields
+            .entry("fileInfo")
+            .or_insert_with(|| serde_json::Value::Object(Default::default()));
+        if let Some(file_info) = file_info.as_object_mut() {
+            file_info
+                .entry(SSE_C_KEY_ID_FILE_INFO)
+                .or_insert_with(|| key_id.into());
+        }
+    }
+    body
+}
+
+// allows the request builders to take the key by reference as well as by value
+impl<'s> From<&ServerSideEncryptionCustomerKey<'s>> for ServerSideEncryptionCustomerKey<'s> {
+    fn from(sse_ck: &ServerSideEncryptionCustomerKey<'s>) -> Self {
+        *sse_ck
+    }
+}
+
+/// Owned version of [ServerSideEncryptionCustomerKey], used by the owned requests (e.g. [crate::v2::OwnedUploadFileParameters])
+///
+/// The serialized form contains the customer key itself, so it must be stored as securely as the key.
+/// The key is overwritten with zeros when dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub enum OwnedServerSideEncryptionCustomerKey {
+    None,
+    SseB2,
+    SseC {
+        customer_key: [u8; CUSTOMER_KEY_BYTES],
+        customer_key_md5: Md5Digest,
+    },
+}
+
+impl OwnedServerSideEncryptionCustomerKey {
+    /// borrowed form of this key, as used by the requests
+    pub fn as_customer_key(&self) -> ServerSideEncryptionCustomerKey<'_> {
+        match self {
+            Self::None => ServerSideEncryptionCustomerKey::None,
+            Self::SseB2 => ServerSideEncryptionCustomerKey::SseB2,
+            Self::SseC {
+                customer_key,
+                customer_key_md5,
+            } => ServerSideEncryptionCustomerKey::SseC {
+                customer_key,
+                customer_key_md5,
+            },
+        }
+    }
+}
+
+impl<'s> From<&ServerSideEncryptionCustomerKey<'s>> for OwnedServerSideEncryptionCustomerKey {
+    fn from(sse_ck: &ServerSideEncryptionCustomerKey<'s>) -> Self {
+        match sse_ck {
+            ServerSideEncryptionCustomerKey::None => Self::None,
+            ServerSideEncryptionCustomerKey::SseB2 => Self::SseB2,
+            ServerSideEncryptionCustomerKey::SseC {
+                customer_key,
+                customer_key_md5,
+            } => Self::SseC {
+                customer_key: **customer_key,
+                customer_key_md5: (*customer_key_md5).clone(),
+            },
+        }
+    }
+}
+
+impl Drop for OwnedServerSideEncryptionCustomerKey {
+    fn drop(&mut self) {
+        if let Self::SseC { customer_key, .. } = self {
+            customer_key.zeroize();
+        }
+    }
+}
+
+impl std::fmt::Debug for OwnedServerSideEncryptionCustomerKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_customer_key().fmt(f)
+    }
+}
+
+impl Serialize for OwnedServerSideEncryptionCustomerKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.as_customer_key().serialize(serializer)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeserializableServerSideEncryptionCustomerKey {
+    #[serde(default)]
+    algorithm: Option<String>,
+    #[serde(default)]
+    mode: Option<String>,
+    #[serde(default)]
+    customer_key: Option<String>, // base64 encoded
+    #[serde(default)]
+    customer_key_md5: Option<String>, // base64 encoded
+}
+
+/// decodes a base64 encoded value of a fixed length
+fn base64_decode_sse<E, const N: usize>(
+    field: &'static str,
+    value: Option<&str>,
+) -> Result<[u8; N], E>
+where
+    E: de::Error,
+{
+    let value = value.ok_or_else(|| de::Error::missing_field(field))?;
+    let mut bytes = base64::decode_config(value, BASE64_CONFIG).map_err(de::Error::custom)?;
+    let res = <[u8; N]>::try_from(bytes.as_slice())
+        .map_err(|_| de::Error::invalid_length(bytes.len(), &field));
+    bytes.zeroize();
+    res
+}
+
+impl<'de> Deserialize<'de> for OwnedServerSideEncryptionCustomerKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let sse = DeserializableServerSideEncryptionCustomerKey::deserialize(deserializer)?;
+        match sse.mode.as_deref() {
+            Some("SSE-B2") => {
+                is_aes_encryption_algorithm(sse.algorithm.as_deref()).map(|_| Self::SseB2)
+            }
+            Some("SSE-C") => {
+                is_aes_encryption_algorithm(sse.algorithm.as_deref())?;
+                Ok(Self::SseC {
+                    customer_key: base64_decode_sse("customerKey", sse.customer_key.as_deref())?,
+                    customer_key_md5: Md5Digest::new(base64_decode_sse(
+                        "customerKeyMd5",
+                        sse.customer_key_md5.as_deref(),
+                    )?),
+                })
+            }
+            None | Some("none") => {
+                if sse.algorithm.is_none() {
+                    Ok(Self::None)
+                } else {
+                    Err(de::Error::unknown_field("algorithm", &[]))
+                }
+            }
+            Some(mode) => Err(de::Error::unknown_variant(
+                mode,
+                &["none", "SSE-B2", "SSE-C"],
+            )),
+        }
+    }
+}
+
+/// decodes a base64 encoded customer key
+#[cfg(feature = "customer_keys")]
+pub(crate) fn base64_decode_key(value: &str) -> Result<[u8; CUSTOMER_KEY_BYTES], String> {
+    let mut bytes = base64::decode_config(value, BASE64_CONFIG).map_err(|e| e.to_string())?;
+    let key = <[u8; CUSTOMER_KEY_BYTES]>::try_from(bytes.as_slice())
+        .map_err(|_| format!("expected {} bytes, got {}", CUSTOMER_KEY_BYTES, bytes.len()));
+    bytes.zeroize();
+    key
 }
 
 const BASE64_CONFIG: base64::Config = base64::Config::new(base64::CharacterSet::Standard, false);
@@ -155,7 +374,7 @@ pub(crate) fn base64_encode_sse(s: impl AsRef<[u8]>) -> String {
     base64::encode_config(s, BASE64_CONFIG)
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SerializableServerSideEncryptionCustomerKey {
     algorithm: Option<&'static str>,
@@ -167,6 +386,20 @@ pub struct SerializableServerSideEncryptionCustomerKey {
     customer_key_md5: Option<String>, // base64 encoded
 }
 
+impl std::fmt::Debug for SerializableServerSideEncryptionCustomerKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SerializableServerSideEncryptionCustomerKey")
+            .field("algorithm", &self.algorithm)
+            .field("mode", &self.mode)
+            .field(
+                "customer_key",
+                &self.customer_key.as_ref().map(|_| "<redacted>"),
+            )
+            .field("customer_key_md5", &self.customer_key_md5)
+            .finish()
+    }
+}
+
 impl<'s> From<ServerSideEncryptionCustomerKey<'s>> for SerializableServerSideEncryptionCustomerKey {
     fn from(sse_ck: ServerSideEncryptionCustomerKey) -> Self {
         match sse_ck {
@@ -200,3 +433,100 @@ impl<'s> From<ServerSideEncryptionCustomerKey<'s>> for SerializableServerSideEnc
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::v2::{AuthorizationToken, Md5Digest};
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let customer_key = [0x42u8; CUSTOMER_KEY_BYTES];
+        let customer_key_md5 = Md5Digest::new([7u8; 16]);
+        let sse = ServerSideEncryptionCustomerKey::SseC {
+            customer_key: &customer_key,
+            customer_key_md5: &customer_key_md5,
+        };
+        let debug = format!("{:?}", sse);
+        assert!(debug.contains("<redacted>"));
+        assert!(!debug.contains("66, 66")); // 0x42
+        let serializable = format!(
+            "{:?}",
+            SerializableServerSideEncryptionCustomerKey::from(sse)
+        );
+        assert!(!serializable.contains(&base64_encode_sse(customer_key)));
+
+        let token = format!("{:?}", AuthorizationToken("secret_token".to_owned()));
+        assert!(!token.contains("secret_token"));
+    }
+
+    #[test]
+    fn test_owned_customer_key() {
+        let customer_key = [0x42u8; CUSTOMER_KEY_BYTES];
+        let customer_key_md5 = Md5Digest::new([7u8; 16]);
+        let sse = ServerSideEncryptionCustomerKey::SseC {
+            customer_key: &customer_key,
+            customer_key_md5: &customer_key_md5,
+        };
+        let owned = OwnedServerSideEncryptionCustomerKey::from(&sse);
+        assert!(!format!("{:?}", owned).contains("66, 66"));
+        let json = serde_json::to_string(&owned).unwrap();
+        assert_eq!(serde_json::to_string(&sse).unwrap(), json);
+        let deserialized: OwnedServerSideEncryptionCustomerKey =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(owned, deserialized);
+
+        for owned in [
+            OwnedServerSideEncryptionCustomerKey::None,
+            OwnedServerSideEncryptionCustomerKey::SseB2,
+        ] {
+            let json = serde_json::to_string(&owned).unwrap();
+            assert_eq!(owned, serde_json::from_str(&json).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_key_id_in_file_info() {
+        let customer_key = [0x42u8; CUSTOMER_KEY_BYTES];
+        let customer_key_md5 = Md5Digest::new([0xabu8; 16]);
+        let sse = ServerSideEncryptionCustomerKey::SseC {
+            customer_key: &customer_key,
+            customer_key_md5: &customer_key_md5,
+        };
+        assert_eq!(Some("ab".repeat(16)), sse.key_id());
+        assert_eq!(None, ServerSideEncryptionCustomerKey::SseB2.key_id());
+
+        let request = serde_json::json!({"fileName": "file", "fileInfo": {"author": "me"}});
+        assert_eq!(
+            serde_json::json!({
+                "fileName": "file",
+                "fileInfo": {"author": "me", "sse_c_key_id": "ab".repeat(16)},
+            }),
+            json_with_key_id_in_file_info(&request, Some(&sse))
+        );
+        let request = serde_json::json!({"fileName": "file"});
+        assert_eq!(
+            serde_json::json!({"fileName": "file", "fileInfo": {"sse_c_key_id": "ab".repeat(16)}}),
+            json_with_key_id_in_file_info(&request, Some(&sse))
+        );
+        assert_eq!(
+            request,
+            json_with_key_id_in_file_info(&request, Some(&ServerSideEncryptionCustomerKey::SseB2))
+        );
+        // a key id set by the caller is kept
+        let request = serde_json::json!({"fileName": "file", "fileInfo": {"sse_c_key_id": "mine"}});
+        assert_eq!(request, json_with_key_id_in_file_info(&request, Some(&sse)));
+
+        let header = |file_info| {
+            let request = reqwest::Client::new().post("http://localhost/");
+            let request = sse
+                .add_key_id_to_request_as_file_info(request, file_info)
+                .build()
+                .unwrap();
+            request.headers().get("X-Bz-Info-sse_c_key_id").cloned()
+        };
+        assert_eq!(Some("ab".repeat(16).parse().unwrap()), header(None));
+        let file_info = HashMap::from([(SSE_C_KEY_ID_FILE_INFO.to_owned(), "mine".to_owned())]);
+        assert_eq!(None, header(Some(&file_info)));
+    }
+}
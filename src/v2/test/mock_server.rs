@@ -19,6 +19,60 @@ pub const FAKE_APPLICATION_KEY: &str = "applicationKey_value";
 pub const FAKE_AUTHORIZATION_TOKEN: &str = "authorization_token";
 pub const FAKE_ACCOUNT_ID: &str = "a30f20426f0b1";
 pub const FAKE_BUCKET_ID: &str = "b2f6f21365e1d29f6c580f18";
+/// retain until timestamp of retentions, that are still active
+pub const FAR_FUTURE: i64 = 4102444800000;
+
+/// authorization of the fake account with the given capabilities, calling `api_url`
+pub fn fake_authorization(api_url: &str, capabilities: &[&str]) -> crate::v2::AuthorizeAccountOk {
+    serde_json::from_value(json!({
+        "absoluteMinimumPartSize": 5000000,
+        "accountId": FAKE_ACCOUNT_ID,
+        "allowed": {
+            "capabilities": capabilities,
+            "namePrefix": null,
+        },
+        "apiUrl": api_url,
+        "authorizationToken": FAKE_AUTHORIZATION_TOKEN,
+        "downloadUrl": api_url,
+        "recommendedPartSize": 100000000,
+    }))
+    .unwrap()
+}
+
+/// file version in the fake bucket, as returned by the listings of file versions,
+/// a retention is active until [FAR_FUTURE]
+pub fn file_version(
+    file_id: &str,
+    file_name: &str,
+    action: &str,
+    retention_mode: Option<&str>,
+    legal_hold: Option<&str>,
+) -> serde_json::Value {
+    json!({
+        "accountId": FAKE_ACCOUNT_ID,
+        "action": action,
+        "bucketId": FAKE_BUCKET_ID,
+        "contentLength": 7,
+        "contentSha1": "none",
+        "contentType": "text/plain",
+        "fileId": file_id,
+        "fileInfo": {},
+        "fileName": file_name,
+        "fileRetention": {
+            "isClientAuthorizedToRead": true,
+            "value": {
+                "mode": retention_mode,
+                "retainUntilTimestamp": retention_mode.map(|_| FAR_FUTURE),
+            },
+        },
+        "legalHold": {
+            "isClientAuthorizedToRead": true,
+            "value": legal_hold,
+        },
+        "serverSideEncryption": {"mode": null},
+        "uploadTimestamp": 1000,
+    })
+}
 
 struct AuthorizationHeaderMatch {
     username_expected: String,
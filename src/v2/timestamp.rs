@@ -0,0 +1,275 @@
+//! Points in time as used by the B2 api, in milliseconds since the epoch (1970-01-01T00:00:00Z).
+use std::{
+    fmt::Display,
+    num::ParseIntError,
+    ops::{Add, Sub},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::Period;
+
+pub(crate) const MILLIS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// Point in time in milliseconds since the epoch, e.g. the upload time of a file or the end of a retention
+///
+/// Adding a [Duration] or a [Period] saturates at the bounds of the timestamp instead of overflowing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TimeStamp(i64);
+
+impl TimeStamp {
+    /// the epoch, 1970-01-01T00:00:00Z
+    pub const UNIX_EPOCH: TimeStamp = TimeStamp(0);
+
+    pub const fn from_millis(millis: i64) -> Self {
+        Self(millis)
+    }
+
+    /// Get the time stamp's milliseconds since the epoch.
+    pub const fn as_millis(&self) -> i64 {
+        self.0
+    }
+
+    /// current system time
+    pub fn now() -> Self {
+        SystemTime::now().into()
+    }
+
+    /// Time elapsed from `earlier` to this time stamp, `None` if `earlier` is later.
+    pub fn duration_since(&self, earlier: TimeStamp) -> Option<Duration> {
+        let millis = self.0.checked_sub(earlier.0)?;
+        u64::try_from(millis).ok().map(Duration::from_millis)
+    }
+
+    fn add_millis(self, millis: i128) -> Self {
+        Self((self.0 as i128 + millis).clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    }
+}
+
+impl Display for TimeStamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for TimeStamp {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+impl From<SystemTime> for TimeStamp {
+    fn from(time: SystemTime) -> Self {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(after) => Self::UNIX_EPOCH + after,
+            Err(before) => Self::UNIX_EPOCH - before.duration(),
+        }
+    }
+}
+
+impl From<TimeStamp> for SystemTime {
+    fn from(timestamp: TimeStamp) -> Self {
+        let millis = Duration::from_millis(timestamp.0.unsigned_abs());
+        if timestamp.0 >= 0 {
+            UNIX_EPOCH + millis
+        } else {
+            UNIX_EPOCH - millis
+        }
+    }
+}
+
+impl Add<Duration> for TimeStamp {
+    type Output = TimeStamp;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        self.add_millis(rhs.as_millis().min(i64::MAX as u128) as i128)
+    }
+}
+
+impl Sub<Duration> for TimeStamp {
+    type Output = TimeStamp;
+
+    fn sub(self, rhs: Duration) -> Self::Output {
+        self.add_millis(-(rhs.as_millis().min(i64::MAX as u128) as i128))
+    }
+}
+
+/// End of the period starting at this time stamp, see [Period::duration_from] for the handling of years
+impl Add<Period> for TimeStamp {
+    type Output = TimeStamp;
+
+    fn add(self, rhs: Period) -> Self::Output {
+        match rhs {
+            Period::Days { value } => self.add_millis(value as i128 * MILLIS_PER_DAY as i128),
+            Period::Years { value } => {
+                let days = self.0.div_euclid(MILLIS_PER_DAY);
+                let millis_of_day = self.0.rem_euclid(MILLIS_PER_DAY);
+                let (year, month, day) = civil_from_days(days);
+                // time stamps span less than 300 million years, larger values saturate anyway
+                let year = year + value.min(300_000_000) as i64;
+                let day = if month == 2 && day == 29 && !is_leap_year(year) {
+                    28
+                } else {
+                    day
+                };
+                Self::UNIX_EPOCH
+                    .add_millis(days_from_civil(year, month, day) as i128 * MILLIS_PER_DAY as i128)
+                    .add_millis(millis_of_day as i128)
+            }
+        }
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+/// (year, month, day) of a number of days since 1970-01-01, in the proleptic gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// number of days since 1970-01-01 of a date in the proleptic gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for TimeStamp {
+    fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+        Self(time.timestamp_millis())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<TimeStamp> for chrono::DateTime<chrono::Utc> {
+    type Error = super::InvalidData;
+
+    fn try_from(timestamp: TimeStamp) -> Result<Self, Self::Error> {
+        chrono::DateTime::from_timestamp_millis(timestamp.0).ok_or_else(|| {
+            super::InvalidData::new(format!(
+                "Time stamp {} is out of the range of chrono::DateTime",
+                timestamp
+            ))
+        })
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for TimeStamp {
+    fn from(time: time::OffsetDateTime) -> Self {
+        let millis = time.unix_timestamp_nanos().div_euclid(1_000_000);
+        Self(millis.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<TimeStamp> for time::OffsetDateTime {
+    type Error = super::InvalidData;
+
+    fn try_from(timestamp: TimeStamp) -> Result<Self, Self::Error> {
+        time::OffsetDateTime::from_unix_timestamp_nanos(timestamp.0 as i128 * 1_000_000).map_err(
+            |_| {
+                super::InvalidData::new(format!(
+                    "Time stamp {} is out of the range of time::OffsetDateTime",
+                    timestamp
+                ))
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_add_period() {
+        // 2020-02-29T12:00:00Z
+        let leap_day = TimeStamp::from_millis(1582977600000);
+        assert_eq!(
+            1582977600000 + 3 * MILLIS_PER_DAY,
+            (leap_day + Period::days(3)).as_millis()
+        );
+        // 2021-02-28T12:00:00Z
+        assert_eq!(1614513600000, (leap_day + Period::years(1)).as_millis());
+        // 2024-02-29T12:00:00Z
+        assert_eq!(1709208000000, (leap_day + Period::years(4)).as_millis());
+        assert_eq!(
+            Duration::from_millis(366 * MILLIS_PER_DAY as u64),
+            Period::years(1).duration_from(TimeStamp::from_millis(1577836800000))
+        );
+        // 1969-12-31T00:00:00Z to 1970-12-31T00:00:00Z
+        assert_eq!(
+            364 * MILLIS_PER_DAY,
+            (TimeStamp::from_millis(-MILLIS_PER_DAY) + Period::years(1)).as_millis()
+        );
+    }
+
+    #[test]
+    fn test_system_time() {
+        let before_epoch = TimeStamp::from_millis(-1500);
+        assert_eq!(
+            UNIX_EPOCH - Duration::from_millis(1500),
+            SystemTime::from(before_epoch)
+        );
+        assert_eq!(
+            before_epoch,
+            TimeStamp::from(SystemTime::from(before_epoch))
+        );
+        let later = before_epoch + Duration::from_secs(2);
+        assert_eq!(500, later.as_millis());
+        assert_eq!(
+            Some(Duration::from_secs(2)),
+            later.duration_since(before_epoch)
+        );
+        assert_eq!(None, before_epoch.duration_since(later));
+        assert_eq!(
+            TimeStamp::from_millis(i64::MAX),
+            TimeStamp::from_millis(i64::MAX - 1) + Duration::from_secs(1)
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        let timestamp = TimeStamp::from_millis(1582977600123);
+        let time = chrono::DateTime::<chrono::Utc>::try_from(timestamp).unwrap();
+        assert_eq!("2020-02-29 12:00:00.123 UTC", time.to_string());
+        assert_eq!(timestamp, TimeStamp::from(time));
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time() {
+        let timestamp = TimeStamp::from_millis(-1);
+        let time = time::OffsetDateTime::try_from(timestamp).unwrap();
+        assert_eq!(1969, time.year());
+        assert_eq!(timestamp, TimeStamp::from(time));
+    }
+}